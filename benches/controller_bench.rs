@@ -0,0 +1,138 @@
+//! `PlaybackController::pre_read` latency under producer/command contention.
+//!
+//! A writer thread simulates the input callback, an optional command thread
+//! simulates the TUI (seeks / pause toggles), and the measured thread plays the
+//! output callback. Besides Criterion's summary, each scenario prints
+//! p50/p95/p99 of the individual `pre_read` calls.
+//!
+//! Run with `cargo bench --bench controller_bench`.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+
+// The crate is a binary, so pull the audio-path modules in by path.
+// (Their unit tests compile under `--all-targets` but don't run here.)
+#[allow(dead_code, unused_imports)]
+#[path = "../src/audio"]
+mod audio {
+    pub mod ring_buffer;
+    pub mod snapshot_ring;
+    pub mod source;
+}
+#[allow(dead_code, unused_imports)]
+#[path = "../src/platform.rs"]
+mod platform;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/playback"]
+mod playback {
+    pub mod analysis;
+    pub mod controller;
+    pub mod events;
+    pub mod loudness;
+    pub mod silence;
+    pub mod snap;
+    pub mod state;
+}
+
+use crate::audio::ring_buffer::AudioRingBuffer;
+use crate::playback::controller::PlaybackController;
+
+const SAMPLE_RATE: u32 = 48_000;
+const CHANNELS: u16 = 2;
+const FRAMES: usize = 256;
+/// Individual latencies kept for percentiles (bounds memory on long runs).
+const MAX_SAMPLES: usize = 1_000_000;
+
+#[derive(Clone, Copy)]
+enum Pattern {
+    Live,
+    Seeking,
+    Pausing,
+}
+
+fn run_scenario(c: &mut Criterion, name: &str, pattern: Pattern) {
+    let capacity = SAMPLE_RATE as usize * CHANNELS as usize * 60;
+    let ring = Arc::new(AudioRingBuffer::new(capacity));
+    let controller = Arc::new(PlaybackController::new(ring, CHANNELS, SAMPLE_RATE));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let writer = {
+        let ctrl = controller.clone();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let block = vec![0.25_f32; FRAMES * CHANNELS as usize];
+            while !stop.load(Ordering::Relaxed) {
+                ctrl.ring.write(&block);
+                thread::yield_now();
+            }
+        })
+    };
+
+    let commands = {
+        let ctrl = controller.clone();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut back = true;
+            while !stop.load(Ordering::Relaxed) {
+                match pattern {
+                    Pattern::Live => {}
+                    Pattern::Seeking => {
+                        ctrl.seek_ms(if back { 500.0 } else { -250.0 });
+                        back = !back;
+                    }
+                    Pattern::Pausing => ctrl.toggle_pause(),
+                }
+                thread::sleep(Duration::from_micros(200));
+            }
+        })
+    };
+
+    let mut output = vec![0.0_f32; FRAMES * CHANNELS as usize];
+    let mut latencies: Vec<Duration> = Vec::new();
+    c.bench_function(name, |b| {
+        b.iter_custom(|iters| {
+            let mut total = Duration::ZERO;
+            for _ in 0..iters {
+                let start = Instant::now();
+                black_box(controller.pre_read(FRAMES));
+                let elapsed = start.elapsed();
+                total += elapsed;
+                if latencies.len() < MAX_SAMPLES {
+                    latencies.push(elapsed);
+                }
+                controller.ring.read(&mut output);
+            }
+            total
+        })
+    });
+
+    stop.store(true, Ordering::Relaxed);
+    let _ = writer.join();
+    let _ = commands.join();
+
+    latencies.sort_unstable();
+    let pct = |p: f64| latencies[((latencies.len() - 1) as f64 * p) as usize];
+    if !latencies.is_empty() {
+        println!(
+            "{name}: p50 {:?}  p95 {:?}  p99 {:?}  ({} calls)",
+            pct(0.50),
+            pct(0.95),
+            pct(0.99),
+            latencies.len()
+        );
+    }
+}
+
+fn pre_read_benches(c: &mut Criterion) {
+    run_scenario(c, "pre_read/live", Pattern::Live);
+    run_scenario(c, "pre_read/seeking", Pattern::Seeking);
+    run_scenario(c, "pre_read/pausing", Pattern::Pausing);
+}
+
+criterion_group!(benches, pre_read_benches);
+criterion_main!(benches);
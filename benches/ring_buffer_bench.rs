@@ -0,0 +1,112 @@
+//! `AudioRingBuffer::write` throughput: the per-region atomic stores (wrap
+//! handled once per write) against the original loop with a modulo per
+//! sample, and against a plain `f32` memcpy, the cost of atomic storage,
+//! for typical and large input callbacks.
+//!
+//! After each write the read position catches up so the buffer never fills.
+//! The capacity isn't a multiple of any block size, so wrapping writes split.
+//!
+//! Run with `cargo bench --bench ring_buffer_bench`.
+
+use std::cell::UnsafeCell;
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+// The crate is a binary, so pull the ring buffer in by path.
+#[allow(dead_code, unused_imports)]
+#[path = "../src/audio"]
+mod audio {
+    pub mod ring_buffer;
+    pub mod source;
+}
+
+use crate::audio::ring_buffer::AudioRingBuffer;
+
+/// 60 seconds of stereo at 192 kHz.
+const CAPACITY: usize = 192_000 * 2 * 60 + 7;
+/// Interleaved samples per callback (stereo frames × 2).
+const BLOCK_SIZES: [usize; 3] = [512, 4096 * 2, 16_384 * 2];
+
+/// The write path before batch copies: one modulo and store per sample.
+struct LoopWriter {
+    buffer: Box<[UnsafeCell<f32>]>,
+    pos: usize,
+}
+
+impl LoopWriter {
+    fn new(capacity: usize) -> Self {
+        Self {
+            buffer: (0..capacity).map(|_| UnsafeCell::new(0.0)).collect(),
+            pos: 0,
+        }
+    }
+
+    fn write(&mut self, data: &[f32]) {
+        let capacity = self.buffer.len();
+        for (i, &sample) in data.iter().enumerate() {
+            let idx = (self.pos + i) % capacity;
+            // SAFETY: single-threaded; `idx` is in bounds.
+            unsafe {
+                *self.buffer[idx].get() = sample;
+            }
+        }
+        self.pos += data.len();
+    }
+}
+
+/// Plain `f32` storage with one memcpy per region: the fastest write, but its
+/// slots can't be shared with a reader without a data race.
+struct MemcpyWriter {
+    buffer: Box<[f32]>,
+    pos: usize,
+}
+
+impl MemcpyWriter {
+    fn new(capacity: usize) -> Self {
+        Self {
+            buffer: vec![0.0; capacity].into(),
+            pos: 0,
+        }
+    }
+
+    fn write(&mut self, data: &[f32]) {
+        let capacity = self.buffer.len();
+        let start = self.pos % capacity;
+        let first_len = data.len().min(capacity - start);
+        let (head, tail) = data.split_at(first_len);
+        self.buffer[start..start + first_len].copy_from_slice(head);
+        self.buffer[..tail.len()].copy_from_slice(tail);
+        self.pos += data.len();
+    }
+}
+
+fn write_benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ring_write");
+    for size in BLOCK_SIZES {
+        let block: Vec<f32> = (0..size).map(|i| i as f32 * 1e-6).collect();
+        group.throughput(Throughput::Elements(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("loop", size), &block, |b, block| {
+            let mut writer = LoopWriter::new(CAPACITY);
+            b.iter(|| writer.write(black_box(block)));
+        });
+
+        group.bench_with_input(BenchmarkId::new("memcpy", size), &block, |b, block| {
+            let mut writer = MemcpyWriter::new(CAPACITY);
+            b.iter(|| writer.write(black_box(block)));
+        });
+
+        group.bench_with_input(BenchmarkId::new("copy", size), &block, |b, block| {
+            let ring = AudioRingBuffer::new(CAPACITY);
+            b.iter(|| {
+                ring.write(black_box(block));
+                ring.set_read_position(ring.write_position());
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, write_benches);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+use anyhow::{Result, anyhow};
+
+/// A sub-device of an aggregate, as read from CoreAudio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubDevice {
+    pub uid: String,
+    pub name: String,
+    /// `kAudioDevicePropertyClockDomain`; 0 means unknown.
+    pub clock_domain: u32,
+    /// `kAudioSubDevicePropertyDriftCompensation`.
+    pub drift_compensation: bool,
+}
+
+/// Sub-devices that run on their own clock with no drift correction, so they
+/// slowly fall out of sync with the aggregate's main (clock source) device.
+/// If `main_uid` can't be found, the first sub-device is taken as the main one.
+pub fn unsynced<'a>(sub_devices: &'a [SubDevice], main_uid: Option<&str>) -> Vec<&'a SubDevice> {
+    let main = main_uid
+        .and_then(|uid| sub_devices.iter().find(|sub| sub.uid == uid))
+        .or(sub_devices.first());
+    let Some(main) = main else {
+        return Vec::new();
+    };
+    sub_devices
+        .iter()
+        .filter(|sub| sub.uid != main.uid && !sub.drift_compensation)
+        .filter(|sub| sub.clock_domain == 0 || sub.clock_domain != main.clock_domain)
+        .collect()
+}
+
+/// Refuses an aggregate whose sub-devices would drift apart.
+pub fn check_clock(name: &str, sub_devices: &[SubDevice], main_uid: Option<&str>) -> Result<()> {
+    let unsynced = unsynced(sub_devices, main_uid);
+    if unsynced.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<&str> = unsynced.iter().map(|sub| sub.name.as_str()).collect();
+    Err(anyhow!(
+        "Aggregate '{name}': {} not clocked with its main device and would drift.\n\
+         Fix: Open Audio MIDI Setup and enable Drift Correction for {}.",
+        names.join(", "),
+        if names.len() == 1 { "it" } else { "them" }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_clock_or_drift_correction_is_in_sync() {
+        let subs = [("a", 7, false), ("b", 7, false), ("c", 9, true)].map(
+            |(uid, clock_domain, drift_compensation)| SubDevice {
+                uid: uid.to_string(),
+                name: format!("{uid} speakers"),
+                clock_domain,
+                drift_compensation,
+            },
+        );
+        assert!(unsynced(&subs, Some("a")).is_empty());
+        assert!(check_clock("Zones", &subs, Some("a")).is_ok());
+    }
+
+    #[test]
+    fn free_running_sub_devices_are_refused() {
+        let subs = [("a", 7, true), ("b", 9, false), ("c", 0, false)].map(
+            |(uid, clock_domain, drift_compensation)| SubDevice {
+                uid: uid.to_string(),
+                name: format!("{uid} speakers"),
+                clock_domain,
+                drift_compensation,
+            },
+        );
+        // The main device needs no correction of its own.
+        let names: Vec<_> = unsynced(&subs, Some("b")).iter().map(|s| &s.uid).collect();
+        assert_eq!(names, ["c"]);
+        let error = check_clock("Zones", &subs, None).unwrap_err().to_string();
+        assert!(error.contains("b speakers, c speakers"), "{error}");
+        assert!(error.contains("Drift Correction"));
+    }
+}
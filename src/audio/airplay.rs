@@ -0,0 +1,97 @@
+/// Default `--airplay-margin-ms`: headroom on top of the reported latency
+/// for network hiccups.
+pub const DEFAULT_MARGIN_MS: u32 = 250;
+
+/// Live-mode cushion for an AirPlay output, in interleaved samples: the
+/// latency the device reports plus `margin_ms`, so playback stays far enough
+/// behind the input to ride out the device's large, variable buffering.
+pub fn live_floor_samples(
+    latency_frames: u32,
+    margin_ms: u32,
+    sample_rate: u32,
+    channels: u16,
+) -> usize {
+    let margin_frames = margin_ms as u64 * sample_rate as u64 / 1000;
+    let frames = latency_frames as u64 + margin_frames;
+    usize::try_from(frames.saturating_mul(channels.max(1) as u64)).unwrap_or(usize::MAX)
+}
+
+/// An AirPlay output's reported latency and the Live-mode cushion it needs,
+/// kept up to date as the device re-reports its latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AirPlayLatency {
+    latency_frames: u32,
+    margin_ms: u32,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl AirPlayLatency {
+    pub fn new(latency_frames: u32, margin_ms: u32, sample_rate: u32, channels: u16) -> Self {
+        Self {
+            latency_frames,
+            margin_ms,
+            sample_rate,
+            channels,
+        }
+    }
+
+    /// The cushion to hold playback behind live by.
+    pub fn floor_samples(&self) -> usize {
+        live_floor_samples(
+            self.latency_frames,
+            self.margin_ms,
+            self.sample_rate,
+            self.channels,
+        )
+    }
+
+    /// Takes a newly reported latency; the new cushion if it changed.
+    pub fn update(&mut self, latency_frames: u32) -> Option<usize> {
+        if latency_frames == self.latency_frames {
+            return None;
+        }
+        self.latency_frames = latency_frames;
+        Some(self.floor_samples())
+    }
+
+    /// The extra delay for the status line, e.g. "AirPlay +1.8s".
+    pub fn label(&self) -> String {
+        let frames = self.floor_samples() / self.channels.max(1) as usize;
+        let seconds = frames as f64 / self.sample_rate.max(1) as f64;
+        format!("AirPlay +{seconds:.1}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floor_covers_the_reported_latency_plus_the_margin() {
+        // 1.8s at 48 kHz plus the default 250ms, in stereo samples.
+        assert_eq!(
+            live_floor_samples(86_400, DEFAULT_MARGIN_MS, 48_000, 2),
+            (86_400 + 12_000) * 2
+        );
+        assert_eq!(live_floor_samples(44_100, 0, 44_100, 1), 44_100);
+        assert_eq!(live_floor_samples(0, 500, 48_000, 6), 24_000 * 6);
+        // Degenerate formats and huge values stay finite.
+        assert_eq!(live_floor_samples(100, 1_000, 0, 0), 100);
+        assert!(live_floor_samples(u32::MAX, u32::MAX, u32::MAX, u16::MAX) > 0);
+    }
+
+    #[test]
+    fn latency_changes_move_the_floor() {
+        let mut airplay = AirPlayLatency::new(86_400, 250, 48_000, 2);
+        assert_eq!(airplay.label(), "AirPlay +2.0s");
+        assert_eq!(airplay.update(86_400), None);
+
+        // A network hiccup makes the device buffer more.
+        assert_eq!(airplay.update(96_000), Some((96_000 + 12_000) * 2));
+        assert_eq!(airplay.floor_samples(), (96_000 + 12_000) * 2);
+        assert_eq!(airplay.label(), "AirPlay +2.2s");
+        assert_eq!(airplay.update(48_000), Some((48_000 + 12_000) * 2));
+        assert_eq!(airplay.label(), "AirPlay +1.2s");
+    }
+}
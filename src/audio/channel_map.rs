@@ -0,0 +1,127 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Error, Result, anyhow};
+
+/// Which source channel feeds each output channel, for outputs with a different
+/// layout than the input (e.g. an aggregate spanning several zones).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelMap {
+    /// 1-based source channel per output channel; 0 leaves it silent.
+    sources: Vec<u16>,
+}
+
+impl ChannelMap {
+    /// Repeats the source channels across `output_channels`: stereo over six
+    /// channels is L R L R L R, mono feeds every channel.
+    pub fn spread(source_channels: u16, output_channels: usize) -> Self {
+        let source_channels = source_channels.max(1);
+        Self {
+            sources: (0..output_channels)
+                .map(|i| (i % source_channels as usize) as u16 + 1)
+                .collect(),
+        }
+    }
+
+    /// Checks the map against the stream it will be applied to. A map shorter
+    /// than the output leaves the remaining channels silent.
+    pub fn validate(&self, source_channels: u16, output_channels: usize) -> Result<()> {
+        if self.sources.len() > output_channels {
+            return Err(anyhow!(
+                "channel map has {} entries, the output only {output_channels} channels",
+                self.sources.len()
+            ));
+        }
+        match self.sources.iter().find(|&&src| src > source_channels) {
+            Some(src) => Err(anyhow!(
+                "channel map uses input channel {src}, the input only has {source_channels}"
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes `source` (interleaved, `source_channels` wide) into `output`
+    /// (interleaved, `output_channels` wide) and silences what's left over.
+    pub fn apply(
+        &self,
+        source: &[f32],
+        source_channels: usize,
+        output: &mut [f32],
+        output_channels: usize,
+    ) {
+        let frames = source.len() / source_channels.max(1);
+        let mapped = (frames * output_channels).min(output.len());
+        for (out, frame) in output[..mapped]
+            .chunks_exact_mut(output_channels)
+            .zip(source.chunks_exact(source_channels))
+        {
+            for (ch, sample) in out.iter_mut().enumerate() {
+                *sample = match self.sources.get(ch) {
+                    Some(&src) if src > 0 => frame.get(src as usize - 1).copied().unwrap_or(0.0),
+                    _ => 0.0,
+                };
+            }
+        }
+        output[mapped..].fill(0.0);
+    }
+}
+
+/// Comma-separated source channels, one per output channel: "1,2,1,2",
+/// with 0 or "-" for a silent channel.
+impl FromStr for ChannelMap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let sources = s
+            .split(',')
+            .map(|entry| match entry.trim() {
+                "-" => Ok(0),
+                entry => entry
+                    .parse::<u16>()
+                    .map_err(|e| anyhow!("bad channel map entry '{entry}': {e}")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if sources.iter().all(|&src| src == 0) {
+            return Err(anyhow!("channel map '{s}' leaves every channel silent"));
+        }
+        Ok(Self { sources })
+    }
+}
+
+impl fmt::Display for ChannelMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries: Vec<String> = self.sources.iter().map(|src| src.to_string()).collect();
+        f.write_str(&entries.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spread_repeats_the_source_channels() {
+        assert_eq!(ChannelMap::spread(2, 6).to_string(), "1,2,1,2,1,2");
+        assert_eq!(ChannelMap::spread(1, 4).to_string(), "1,1,1,1");
+    }
+
+    #[test]
+    fn parses_and_validates_maps() {
+        let map: ChannelMap = "1,2,-,0,1".parse().unwrap();
+        assert_eq!(map.to_string(), "1,2,0,0,1");
+        assert!(map.validate(2, 8).is_ok());
+        assert!(map.validate(1, 8).is_err());
+        assert!(map.validate(2, 4).is_err());
+        assert!("1,x".parse::<ChannelMap>().is_err());
+        assert!("0,-".parse::<ChannelMap>().is_err());
+    }
+
+    #[test]
+    fn apply_maps_frames_and_silences_the_rest() {
+        let map: ChannelMap = "2,1,0".parse().unwrap();
+        let source = [0.1, 0.2, 0.3, 0.4];
+        let mut output = [9.0; 8];
+        map.apply(&source, 2, &mut output, 3);
+        assert_eq!(output, [0.2, 0.1, 0.0, 0.4, 0.3, 0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,81 @@
+use std::f32::consts::PI;
+
+/// High-pass corner of the DC blocker. Low enough to leave bass alone.
+pub const DC_BLOCK_CUTOFF_HZ: f32 = 10.0;
+
+/// One-pole DC blocker, `y[n] = x[n] - x[n-1] + r * y[n-1]`, with state per
+/// channel. Owned by the input callback; `process` never allocates.
+pub struct DcBlocker {
+    channels: usize,
+    /// Pole radius, from the cutoff and sample rate.
+    r: f32,
+    /// Previous input sample, per channel.
+    last_in: Box<[f32]>,
+    /// Previous output sample, per channel.
+    last_out: Box<[f32]>,
+}
+
+impl DcBlocker {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            channels,
+            r: 1.0 - 2.0 * PI * DC_BLOCK_CUTOFF_HZ / sample_rate.max(1) as f32,
+            last_in: vec![0.0; channels].into_boxed_slice(),
+            last_out: vec![0.0; channels].into_boxed_slice(),
+        }
+    }
+
+    /// Filters interleaved samples in place.
+    pub fn process(&mut self, data: &mut [f32]) {
+        for frame in data.chunks_mut(self.channels) {
+            for (ch, sample) in frame.iter_mut().enumerate() {
+                let out = *sample - self.last_in[ch] + self.r * self.last_out[ch];
+                self.last_in[ch] = *sample;
+                self.last_out[ch] = out;
+                *sample = out;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offset_converges_to_zero_mean() {
+        let rate = 48_000;
+        let mut blocker = DcBlocker::new(2, rate);
+        // Left: a 1 kHz tone riding on +0.3; right: a constant -0.5.
+        let mut data: Vec<f32> = (0..rate as usize)
+            .flat_map(|i| {
+                let tone = (2.0 * PI * 1000.0 * i as f32 / rate as f32).sin() * 0.2;
+                [0.3 + tone, -0.5]
+            })
+            .collect();
+        for block in data.chunks_mut(512) {
+            blocker.process(block);
+        }
+        // The last 100ms (4800 frames), well after the filter has settled.
+        let tail = &data[data.len() - 9600..];
+        let left: f32 = tail.iter().step_by(2).sum();
+        let right: f32 = tail.iter().skip(1).step_by(2).sum();
+        assert!((left / 4800.0).abs() < 1e-3);
+        assert!((right / 4800.0).abs() < 1e-3);
+        // The tone itself passes.
+        let peak = tail.iter().step_by(2).fold(0.0_f32, |m, s| m.max(s.abs()));
+        assert!(peak > 0.19);
+    }
+
+    #[test]
+    fn channels_keep_separate_state() {
+        let mut blocker = DcBlocker::new(2, 48_000);
+        let mut data = [1.0, 0.0, 1.0, 0.0];
+        blocker.process(&mut data);
+        assert_eq!(data[1], 0.0);
+        assert_eq!(data[3], 0.0);
+        assert_eq!(data[0], 1.0);
+        assert!(data[2] < 1.0);
+    }
+}
@@ -0,0 +1,87 @@
+/// Distance above which a name is not worth suggesting, as a fraction of the
+/// longer of the two names.
+const MAX_DISTANCE_RATIO: f32 = 0.6;
+
+/// The candidate name closest to `query`, for "did you mean" hints. A name
+/// sharing a word with the query wins, then the smallest edit distance
+/// (case-insensitive); `None` if nothing is close.
+pub fn closest<'a>(query: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let query = query.to_lowercase();
+    candidates
+        .iter()
+        .map(|&name| (name, score(&query, &name.to_lowercase())))
+        .filter(|&(_, (_, ratio))| ratio <= MAX_DISTANCE_RATIO)
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(name, _)| name)
+}
+
+/// (0 if a word is shared, else 1; edit distance / longer length), lower is closer.
+fn score(query: &str, name: &str) -> (u8, f32) {
+    let words = |s: &str| -> Vec<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.len() > 1)
+            .map(str::to_string)
+            .collect()
+    };
+    let name_words = words(name);
+    let shares_word = words(query).iter().any(|w| name_words.contains(w));
+    let longer = query.chars().count().max(name.chars().count()).max(1);
+    let ratio = edit_distance(query, name) as f32 / longer as f32;
+    // A shared word is close enough whatever the rest of the name is.
+    let ratio = if shares_word {
+        ratio.min(MAX_DISTANCE_RATIO)
+    } else {
+        ratio
+    };
+    (u8::from(!shares_word), ratio)
+}
+
+/// Levenshtein distance over chars.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_single_edits() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+
+    #[test]
+    fn shared_words_win_over_edit_distance() {
+        let inputs = ["MacBook Pro Microphone", "BlackHole 2ch", "BlackHole 16ch"];
+        assert_eq!(
+            closest("BlackHole+Speakers", &inputs),
+            Some("BlackHole 2ch")
+        );
+        assert_eq!(closest("blackhole 16", &inputs), Some("BlackHole 16ch"));
+    }
+
+    #[test]
+    fn typos_are_matched_and_unrelated_names_are_not() {
+        let outputs = ["MacBook Pro Speakers", "External Headphones"];
+        assert_eq!(closest("Headphnes", &outputs), Some("External Headphones"));
+        assert_eq!(
+            closest("Extrnal Headphone", &outputs),
+            Some("External Headphones")
+        );
+        assert_eq!(closest("zzz", &outputs), None);
+        assert_eq!(closest("anything", &[]), None);
+    }
+}
@@ -0,0 +1,124 @@
+mod libproc {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    unsafe extern "C" {
+        fn proc_name(pid: c_int, buffer: *mut c_void, buffersize: u32) -> c_int;
+    }
+
+    /// Short name of a running process, e.g. "Logic Pro X".
+    pub fn process_name(pid: i32) -> Option<String> {
+        let mut buf = [0 as c_char; 256];
+        // SAFETY: proc_name writes at most `buffersize` bytes into `buf` and
+        // returns the name length (0 on failure).
+        let len = unsafe { proc_name(pid, buf.as_mut_ptr() as *mut c_void, buf.len() as u32) };
+        if len <= 0 {
+            return None;
+        }
+        let bytes: Vec<u8> = buf[..len as usize].iter().map(|&c| c as u8).collect();
+        String::from_utf8(bytes).ok()
+    }
+}
+
+/// Whether another process is using a device, from its
+/// `kAudioDevicePropertyDeviceIsRunningSomewhere` and `kAudioDevicePropertyHogMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceUsage {
+    Idle,
+    /// IO is running (in this or another process); sharing is fine.
+    Running,
+    /// Another process has exclusive access; we can't open the device.
+    Hogged {
+        pid: i32,
+    },
+}
+
+impl DeviceUsage {
+    /// Interprets the raw property values (`None` if a query failed). The hog
+    /// mode property is the owning PID, or -1 if free; our own hog is no obstacle.
+    pub fn from_properties(running: Option<u32>, hog_mode: Option<u32>, own_pid: u32) -> Self {
+        let hog_pid = hog_mode.map(|v| v as i32);
+        match hog_pid {
+            Some(pid) if pid > 0 && pid as u32 != own_pid => Self::Hogged { pid },
+            _ if running.unwrap_or(0) != 0 => Self::Running,
+            _ => Self::Idle,
+        }
+    }
+
+    /// Name for the device listing JSON.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Running => "running",
+            Self::Hogged { .. } => "hogged",
+        }
+    }
+
+    /// Tag for the text device listing, `None` when idle.
+    pub fn tag(self) -> Option<String> {
+        match self {
+            Self::Idle => None,
+            Self::Running => Some("running".to_string()),
+            Self::Hogged { pid } => Some(format!("hogged by {}", owner(pid))),
+        }
+    }
+
+    pub fn hog_pid(self) -> Option<i32> {
+        match self {
+            Self::Hogged { pid } => Some(pid),
+            _ => None,
+        }
+    }
+}
+
+/// "Name (pid N)", or "pid N" if the name can't be looked up.
+pub fn owner(pid: i32) -> String {
+    match libproc::process_name(pid) {
+        Some(name) => format!("{name} (pid {pid})"),
+        None => format!("pid {pid}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWN_PID: u32 = 4242;
+
+    #[test]
+    fn free_devices_are_idle_or_running() {
+        let free = Some(u32::MAX); // -1
+        assert_eq!(
+            DeviceUsage::from_properties(Some(0), free, OWN_PID),
+            DeviceUsage::Idle
+        );
+        assert_eq!(
+            DeviceUsage::from_properties(Some(1), free, OWN_PID),
+            DeviceUsage::Running
+        );
+    }
+
+    #[test]
+    fn hog_by_another_process_wins() {
+        assert_eq!(
+            DeviceUsage::from_properties(Some(1), Some(77), OWN_PID),
+            DeviceUsage::Hogged { pid: 77 }
+        );
+        assert_eq!(
+            DeviceUsage::from_properties(Some(1), Some(77), OWN_PID).hog_pid(),
+            Some(77)
+        );
+    }
+
+    #[test]
+    fn own_hog_and_failed_queries_are_not_hogged() {
+        assert_eq!(
+            DeviceUsage::from_properties(Some(1), Some(OWN_PID), OWN_PID),
+            DeviceUsage::Running
+        );
+        assert_eq!(
+            DeviceUsage::from_properties(None, None, OWN_PID),
+            DeviceUsage::Idle
+        );
+        assert_eq!(DeviceUsage::Idle.tag(), None);
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::Path;
+
+/// Where CoreAudio loads third-party audio drivers (HAL plug-ins) from.
+pub const HAL_PLUGIN_DIR: &str = "/Library/Audio/Plug-Ins/HAL";
+
+/// Names of the BlackHole driver bundles in `dir` (e.g. "BlackHole2ch.driver"),
+/// sorted. Empty if there are none or `dir` can't be read.
+pub fn blackhole_drivers(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut drivers: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with("BlackHole") && name.ends_with(".driver"))
+        .collect();
+    drivers.sort();
+    drivers
+}
+
+/// Startup error when CoreAudio lists no virtual device, given the BlackHole
+/// drivers found on disk: installed but not loaded means coreaudiod hasn't
+/// picked it up yet.
+pub fn missing_device_message(installed: &[String]) -> String {
+    if installed.is_empty() {
+        return "No virtual audio driver found.\n\
+                Shifter captures a virtual device such as BlackHole: install it with\n\
+                `brew install blackhole-2ch` (see https://existential.audio/blackhole/),\n\
+                then set it as the system output. Use -l to list available devices."
+            .to_string();
+    }
+    format!(
+        "BlackHole is installed ({}) but not loaded.\n\
+         Restart the audio server with `sudo killall coreaudiod` (or reboot), then\n\
+         run Shifter again. Use -l to list available devices.",
+        installed.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_blackhole_bundles_and_picks_the_message() {
+        let dir = std::env::temp_dir().join(format!("shifter-test-{}-hal", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(blackhole_drivers(&dir).is_empty());
+        fs::create_dir_all(&dir).unwrap();
+        for name in [
+            "BlackHole16ch.driver",
+            "Other.driver",
+            "BlackHole2ch.driver",
+        ] {
+            fs::create_dir(dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("BlackHole.txt"), "").unwrap();
+        let found = blackhole_drivers(&dir);
+        assert_eq!(found, ["BlackHole16ch.driver", "BlackHole2ch.driver"]);
+        let _ = fs::remove_dir_all(&dir);
+
+        let loaded_later = missing_device_message(&found);
+        assert!(loaded_later.starts_with(
+            "BlackHole is installed (BlackHole16ch.driver, BlackHole2ch.driver) but not loaded."
+        ));
+        assert!(loaded_later.contains("sudo killall coreaudiod"));
+        let not_installed = missing_device_message(&[]);
+        assert!(not_installed.starts_with("No virtual audio driver found."));
+        assert!(not_installed.contains("brew install blackhole-2ch"));
+    }
+}
@@ -0,0 +1,213 @@
+use std::f64::consts::PI;
+
+/// Length of one BS.1770 gating block.
+const BLOCK_MS: usize = 400;
+/// Gating blocks overlap by 75%: a new one starts every 100ms.
+const BLOCK_STEP_MS: usize = 100;
+/// Blocks quieter than this are left out of the integrated loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Blocks this far below the absolute-gated loudness are left out too.
+const RELATIVE_GATE_LU: f64 = 10.0;
+
+/// Scales `data` so its loudest sample reaches 1.0 (0 dBFS). Returns the
+/// gain applied; silence is left as it is (gain 1.0).
+// Unused: the recorder streams each segment to disk as it plays and never
+// holds a whole one to normalize. Kept for an export step over finished files.
+#[allow(dead_code)]
+pub fn normalize_to_peak(data: &mut [f32]) -> f32 {
+    let peak = data.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+    if peak == 0.0 || !peak.is_finite() {
+        return 1.0;
+    }
+    let gain = 1.0 / peak;
+    apply_gain(data, gain);
+    gain
+}
+
+/// Scales interleaved `data` so its integrated loudness reaches
+/// `target_lufs`. Returns the gain applied; audio too short or too quiet to
+/// measure is left as it is (gain 1.0). Peaks are not limited, so a quiet
+/// recording raised to a loud target can exceed 0 dBFS.
+#[allow(dead_code)] // Unused for the same reason as `normalize_to_peak`.
+pub fn normalize_to_lufs(
+    data: &mut [f32],
+    target_lufs: f32,
+    sample_rate: u32,
+    channels: u16,
+) -> f32 {
+    let Some(loudness) = integrated_loudness(data, sample_rate, channels) else {
+        return 1.0;
+    };
+    let gain = 10f64.powf((target_lufs as f64 - loudness) / 20.0) as f32;
+    apply_gain(data, gain);
+    gain
+}
+
+fn apply_gain(data: &mut [f32], gain: f32) {
+    for sample in data {
+        *sample *= gain;
+    }
+}
+
+/// Integrated loudness of interleaved `data` per ITU-R BS.1770-4, in LUFS:
+/// K-weighted mean square over gated 400ms blocks. Every channel is
+/// weighted 1.0 (no surround weighting, no LFE exclusion). `None` if there
+/// is no full block or every block is gated out.
+pub fn integrated_loudness(data: &[f32], sample_rate: u32, channels: u16) -> Option<f64> {
+    let channels = channels.max(1) as usize;
+    let rate = sample_rate.max(1) as usize;
+    let frames = data.len() / channels;
+    let block = rate * BLOCK_MS / 1000;
+    let step = rate * BLOCK_STEP_MS / 1000;
+    if block == 0 || step == 0 || frames < block {
+        return None;
+    }
+
+    // K-weighted squares summed per step (all channels), so each block's
+    // energy is the sum of the four steps it spans.
+    let mut filters: Vec<KWeighting> = (0..channels)
+        .map(|_| KWeighting::new(sample_rate))
+        .collect();
+    let mut steps = vec![0.0; frames / step];
+    for (i, frame) in data
+        .chunks_exact(channels)
+        .take(steps.len() * step)
+        .enumerate()
+    {
+        let squares: f64 = frame
+            .iter()
+            .zip(&mut filters)
+            .map(|(&s, filter)| filter.process(s as f64).powi(2))
+            .sum();
+        steps[i / step] += squares;
+    }
+    let per_block = block / step;
+    let energies: Vec<f64> = steps
+        .windows(per_block)
+        .map(|w| w.iter().sum::<f64>() / (per_block * step) as f64)
+        .collect();
+
+    let gated_mean = |gate: f64| {
+        let kept: Vec<f64> = energies
+            .iter()
+            .copied()
+            .filter(|&e| loudness(e) > gate)
+            .collect();
+        (!kept.is_empty()).then(|| kept.iter().sum::<f64>() / kept.len() as f64)
+    };
+    let absolute = gated_mean(ABSOLUTE_GATE_LUFS)?;
+    gated_mean(loudness(absolute) - RELATIVE_GATE_LU).map(loudness)
+}
+
+/// Loudness of a K-weighted mean square summed over channels.
+fn loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// The BS.1770 K-weighting filter: a high shelf (the head's effect) into a
+/// high-pass (RLB), as two biquads with coefficients derived for any rate.
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        let k = (PI * 1_681.974_450_955_533 / fs).tan();
+        let q = 0.707_175_236_955_419_6;
+        let vh = 10f64.powf(3.999_843_853_973_347 / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let k = (PI * 38.135_470_876_024_44 / fs).tan();
+        let q = 0.500_327_037_323_877_3;
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        Self {
+            stages: [shelf, high_pass],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.stages.iter_mut().fold(x, |x, stage| stage.process(x))
+    }
+}
+
+/// Direct form II transposed biquad, `a0` normalized to 1.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z: [0.0; 2] }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    #[test]
+    fn peak_normalization_reaches_full_scale() {
+        let mut data = vec![0.1, -0.25, 0.2];
+        assert_eq!(normalize_to_peak(&mut data), 4.0);
+        assert_eq!(data, [0.4, -1.0, 0.8]);
+        let mut silence = vec![0.0; 4];
+        assert_eq!(normalize_to_peak(&mut silence), 1.0);
+        assert_eq!(normalize_to_peak(&mut []), 1.0);
+    }
+
+    #[test]
+    fn loudness_normalization_reaches_the_target() {
+        // `seconds` of a 1 kHz sine at -20 dBFS in both channels.
+        let stereo_sine = |seconds: f32| -> Vec<f32> {
+            (0..(RATE as f32 * seconds) as usize)
+                .flat_map(|i| {
+                    let s = (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / RATE as f32).sin();
+                    [s * 0.1; 2]
+                })
+                .collect()
+        };
+
+        // Which reads -20 LUFS.
+        let mut data = stereo_sine(5.0);
+        let measured = integrated_loudness(&data, RATE, 2).unwrap();
+        assert!((measured + 20.0).abs() < 0.1, "{measured}");
+
+        let gain = normalize_to_lufs(&mut data, -23.0, RATE, 2);
+        assert!((gain - 10f32.powf(-3.0 / 20.0)).abs() < 0.01, "{gain}");
+        let measured = integrated_loudness(&data, RATE, 2).unwrap();
+        assert!((measured + 23.0).abs() < 0.01, "{measured}");
+
+        // Shorter than a block, or below the absolute gate: left alone.
+        let mut short = stereo_sine(0.3);
+        assert_eq!(normalize_to_lufs(&mut short, -23.0, RATE, 2), 1.0);
+        let mut silence = vec![0.0; 96_000];
+        assert_eq!(normalize_to_lufs(&mut silence, -23.0, RATE, 2), 1.0);
+    }
+}
@@ -1,4 +1,6 @@
-use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{Result, anyhow};
 use coreaudio::audio_unit::audio_format::LinearPcmFlags;
@@ -6,17 +8,80 @@ use coreaudio::audio_unit::macos_helpers::audio_unit_from_device_id;
 use coreaudio::audio_unit::render_callback::{self, data};
 use coreaudio::audio_unit::{AudioUnit, Element, SampleFormat, Scope, StreamFormat};
 
+use crate::audio::aggregate;
+use crate::audio::airplay::AirPlayLatency;
+use crate::audio::channel_map::ChannelMap;
+use crate::audio::dc_block::DcBlocker;
+use crate::audio::latency::HardwareLatency;
+use crate::audio::native_format::NativeStreamFormat;
 use crate::audio::ring_buffer::AudioRingBuffer;
+use crate::audio::{device_match, device_usage};
 use crate::config::CliArgs;
-use crate::playback::controller::PlaybackController;
+use crate::console::{BannerFormat, Console, json_string};
+use crate::platform::{self, MemoryLock};
+use crate::playback::analysis;
+use crate::playback::controller::{MAX_CALLBACK_FRAMES, PlaybackController};
 use crate::playback::state::PlaybackState;
 
 mod coreaudio_device {
     use coreaudio_sys::*;
-    use std::os::raw::c_void;
+    use std::os::raw::{c_char, c_void};
+    use std::sync::mpsc::Sender;
+    use std::time::Duration;
+
+    use anyhow::{Result, anyhow};
+
+    use crate::audio::aggregate::SubDevice;
+    use crate::audio::device_usage::DeviceUsage;
+    use crate::audio::native_format::NativeStreamFormat;
 
     pub type AudioDeviceID = u32;
 
+    /// The CoreAudio and CoreFoundation calls the property helpers make, as a
+    /// table so tests can answer them from a fake device list.
+    #[derive(Clone, Copy)]
+    pub struct PropertyApi {
+        pub get_data: unsafe extern "C" fn(
+            AudioObjectID,
+            *const AudioObjectPropertyAddress,
+            u32,
+            *const c_void,
+            *mut u32,
+            *mut c_void,
+        ) -> OSStatus,
+        pub get_data_size: unsafe extern "C" fn(
+            AudioObjectID,
+            *const AudioObjectPropertyAddress,
+            u32,
+            *const c_void,
+            *mut u32,
+        ) -> OSStatus,
+        pub string_get_c_string:
+            unsafe extern "C" fn(CFStringRef, *mut c_char, CFIndex, CFStringEncoding) -> Boolean,
+        pub string_get_length: unsafe extern "C" fn(CFStringRef) -> CFIndex,
+        pub string_max_size: unsafe extern "C" fn(CFIndex, CFStringEncoding) -> CFIndex,
+        pub release: unsafe extern "C" fn(CFTypeRef),
+    }
+
+    const COREAUDIO: PropertyApi = PropertyApi {
+        get_data: AudioObjectGetPropertyData,
+        get_data_size: AudioObjectGetPropertyDataSize,
+        string_get_c_string: CFStringGetCString,
+        string_get_length: CFStringGetLength,
+        string_max_size: CFStringGetMaximumSizeForEncoding,
+        release: CFRelease,
+    };
+
+    #[cfg(not(test))]
+    fn api() -> PropertyApi {
+        COREAUDIO
+    }
+
+    #[cfg(test)]
+    fn api() -> PropertyApi {
+        tests::API.with(|api| api.get())
+    }
+
     fn get_device_id(selector: u32) -> Option<AudioDeviceID> {
         let address = AudioObjectPropertyAddress {
             mSelector: selector,
@@ -26,7 +91,7 @@ mod coreaudio_device {
         let mut device_id: AudioDeviceID = 0;
         let mut size = std::mem::size_of::<AudioDeviceID>() as u32;
         let status = unsafe {
-            AudioObjectGetPropertyData(
+            (api().get_data)(
                 kAudioObjectSystemObject,
                 &address,
                 0,
@@ -42,16 +107,25 @@ mod coreaudio_device {
         }
     }
 
-    fn get_device_name(device_id: AudioDeviceID) -> Option<String> {
+    pub fn get_device_name(device_id: AudioDeviceID) -> Option<String> {
+        get_string_property(device_id, kAudioObjectPropertyName)
+    }
+
+    /// Persistent unique ID of a device (stable across reboots, unlike its ID).
+    pub fn get_device_uid(device_id: AudioDeviceID) -> Option<String> {
+        get_string_property(device_id, kAudioDevicePropertyDeviceUID)
+    }
+
+    fn get_string_property(device_id: AudioDeviceID, selector: u32) -> Option<String> {
         let address = AudioObjectPropertyAddress {
-            mSelector: kAudioObjectPropertyName,
+            mSelector: selector,
             mScope: kAudioObjectPropertyScopeGlobal,
             mElement: kAudioObjectPropertyElementMain,
         };
         let mut name_ref: CFStringRef = std::ptr::null();
         let mut size = std::mem::size_of::<CFStringRef>() as u32;
         let status = unsafe {
-            AudioObjectGetPropertyData(
+            (api().get_data)(
                 device_id,
                 &address,
                 0,
@@ -63,22 +137,44 @@ mod coreaudio_device {
         if status != 0 || name_ref.is_null() {
             return None;
         }
-        let mut buf = [0i8; 256];
+        let string = c_string(name_ref);
+        // SAFETY: name_ref is a non-null CFString the property call handed
+        // us to own, released exactly once here.
+        unsafe { (api().release)(name_ref as *const c_void) };
+        string
+    }
+
+    /// Bytes `CFStringGetCString` needs for a string whose UTF-8 form takes
+    /// at most `max_size` bytes, NUL included. `None` for kCFNotFound (-1),
+    /// which CoreFoundation returns when the size would overflow.
+    fn c_string_capacity(max_size: CFIndex) -> Option<usize> {
+        usize::try_from(max_size).ok()?.checked_add(1)
+    }
+
+    /// A CFString as UTF-8, with a buffer sized for it: a fixed one cut long
+    /// multibyte names short and the conversion failed outright.
+    fn c_string(string: CFStringRef) -> Option<String> {
+        let api = api();
+        // SAFETY: string is a live CFString owned by the caller.
+        let length = unsafe { (api.string_get_length)(string) };
+        // SAFETY: pure size computation, no pointers involved.
+        let max_size = unsafe { (api.string_max_size)(length, kCFStringEncodingUTF8) };
+        let mut buf = vec![0 as c_char; c_string_capacity(max_size)?];
+        // SAFETY: buf is writable for buf.len() bytes.
         let ok = unsafe {
-            CFStringGetCString(
-                name_ref,
+            (api.string_get_c_string)(
+                string,
                 buf.as_mut_ptr(),
                 buf.len() as CFIndex,
                 kCFStringEncodingUTF8,
             )
         };
-        unsafe { CFRelease(name_ref as *const c_void) };
-        if ok != 0 {
-            let cstr = unsafe { std::ffi::CStr::from_ptr(buf.as_ptr()) };
-            cstr.to_str().ok().map(|s| s.to_owned())
-        } else {
-            None
+        if ok == 0 {
+            return None;
         }
+        // SAFETY: CFStringGetCString succeeded, so buf is NUL-terminated.
+        let cstr = unsafe { std::ffi::CStr::from_ptr(buf.as_ptr()) };
+        cstr.to_str().ok().map(|s| s.to_owned())
     }
 
     /// Returns the ID of the "system output" device (physical speakers).
@@ -86,12 +182,6 @@ mod coreaudio_device {
         get_device_id(kAudioHardwarePropertyDefaultSystemOutputDevice)
     }
 
-    /// Returns the name of the "system output" device (physical speakers).
-    pub fn system_output_device_name() -> Option<String> {
-        let id = system_output_device_id()?;
-        get_device_name(id)
-    }
-
     /// Returns (default_output_id, system_output_id) for device listing annotations.
     pub fn default_device_ids() -> (Option<AudioDeviceID>, Option<AudioDeviceID>) {
         let default = get_device_id(kAudioHardwarePropertyDefaultOutputDevice);
@@ -104,37 +194,86 @@ mod coreaudio_device {
     }
 
     fn get_all_device_ids() -> Vec<AudioDeviceID> {
+        get_object_ids(kAudioObjectSystemObject, kAudioHardwarePropertyDevices)
+    }
+
+    /// An array-of-object-IDs property, e.g. the system's devices.
+    fn get_object_ids(object_id: AudioObjectID, selector: u32) -> Vec<AudioObjectID> {
         let address = AudioObjectPropertyAddress {
-            mSelector: kAudioHardwarePropertyDevices,
+            mSelector: selector,
             mScope: kAudioObjectPropertyScopeGlobal,
             mElement: kAudioObjectPropertyElementMain,
         };
         let mut size: u32 = 0;
-        let status = unsafe {
-            AudioObjectGetPropertyDataSize(
-                kAudioObjectSystemObject,
-                &address,
-                0,
-                std::ptr::null(),
-                &mut size,
-            )
-        };
+        // SAFETY: address and size are live locals; no qualifier is passed.
+        let status =
+            unsafe { (api().get_data_size)(object_id, &address, 0, std::ptr::null(), &mut size) };
         if status != 0 || size == 0 {
             return Vec::new();
         }
-        let count = size as usize / std::mem::size_of::<AudioDeviceID>();
-        let mut device_ids = vec![0u32; count];
+        let count = size as usize / std::mem::size_of::<AudioObjectID>();
+        let mut ids = vec![0u32; count];
+        // Whole IDs only, so CoreAudio never writes past `ids`.
+        size = (count * std::mem::size_of::<AudioObjectID>()) as u32;
+        // SAFETY: `size` is the byte size of `ids`, which CoreAudio fills
+        // with at most that many bytes.
         let status = unsafe {
-            AudioObjectGetPropertyData(
-                kAudioObjectSystemObject,
+            (api().get_data)(
+                object_id,
                 &address,
                 0,
                 std::ptr::null(),
                 &mut size,
-                device_ids.as_mut_ptr() as *mut c_void,
+                ids.as_mut_ptr() as *mut c_void,
             )
         };
-        if status != 0 { Vec::new() } else { device_ids }
+        if status != 0 { Vec::new() } else { ids }
+    }
+
+    /// `kAudioAggregateDevicePropertyMainSubDevice` ('amst'), named
+    /// `...MasterSubDevice` in SDKs before macOS 12.
+    const AGGREGATE_MAIN_SUB_DEVICE: u32 = 0x616d7374;
+
+    pub fn is_airplay(device_id: AudioDeviceID) -> bool {
+        get_u32_property(
+            device_id,
+            kAudioDevicePropertyTransportType,
+            kAudioObjectPropertyScopeGlobal,
+        ) == Some(kAudioDeviceTransportTypeAirPlay)
+    }
+
+    pub fn is_aggregate(device_id: AudioDeviceID) -> bool {
+        get_u32_property(
+            device_id,
+            kAudioDevicePropertyTransportType,
+            kAudioObjectPropertyScopeGlobal,
+        ) == Some(kAudioDeviceTransportTypeAggregate)
+    }
+
+    /// The aggregate's active sub-devices and the UID of its clock source.
+    pub fn aggregate_sub_devices(device_id: AudioDeviceID) -> (Vec<SubDevice>, Option<String>) {
+        let global = kAudioObjectPropertyScopeGlobal;
+        let sub_devices =
+            get_object_ids(device_id, kAudioAggregateDevicePropertyActiveSubDeviceList)
+                .into_iter()
+                .map(|id| {
+                    let name = get_device_name(id).unwrap_or_else(|| format!("device {id}"));
+                    SubDevice {
+                        uid: get_device_uid(id).unwrap_or_else(|| name.clone()),
+                        name,
+                        clock_domain: get_u32_property(id, kAudioDevicePropertyClockDomain, global)
+                            .unwrap_or(0),
+                        drift_compensation: get_u32_property(
+                            id,
+                            kAudioSubDevicePropertyDriftCompensation,
+                            global,
+                        )
+                        .is_some_and(|on| on != 0),
+                    }
+                })
+                .collect();
+        let main_uid = get_string_property(device_id, AGGREGATE_MAIN_SUB_DEVICE);
+        (sub_devices, main_uid)
     }
 
     pub fn get_channel_count(device_id: AudioDeviceID, scope: u32) -> u32 {
@@ -144,15 +283,14 @@ mod coreaudio_device {
             mElement: kAudioObjectPropertyElementMain,
         };
         let mut size: u32 = 0;
-        let status = unsafe {
-            AudioObjectGetPropertyDataSize(device_id, &address, 0, std::ptr::null(), &mut size)
-        };
+        let status =
+            unsafe { (api().get_data_size)(device_id, &address, 0, std::ptr::null(), &mut size) };
         if status != 0 || size == 0 {
             return 0;
         }
         let mut buf = vec![0u8; size as usize];
         let status = unsafe {
-            AudioObjectGetPropertyData(
+            (api().get_data)(
                 device_id,
                 &address,
                 0,
@@ -175,6 +313,25 @@ mod coreaudio_device {
         channels
     }
 
+    /// Pause between channel count queries in [`wait_for_channels`].
+    const CHANNEL_RETRY_INTERVAL_MS: u64 = 100;
+
+    /// Channel count of `scope`, retrying every 100ms for up to `timeout_ms`:
+    /// some virtual devices report 0 channels until they become active.
+    pub fn wait_for_channels(device_id: AudioDeviceID, scope: u32, timeout_ms: u64) -> Result<u32> {
+        let retries = timeout_ms / CHANNEL_RETRY_INTERVAL_MS;
+        for attempt in 0..=retries {
+            if attempt > 0 {
+                std::thread::sleep(Duration::from_millis(CHANNEL_RETRY_INTERVAL_MS));
+            }
+            let channels = get_channel_count(device_id, scope);
+            if channels > 0 {
+                return Ok(channels);
+            }
+        }
+        Err(anyhow!("device reported no channels after {timeout_ms}ms"))
+    }
+
     pub fn get_sample_rate(device_id: AudioDeviceID) -> u32 {
         let address = AudioObjectPropertyAddress {
             mSelector: kAudioDevicePropertyNominalSampleRate,
@@ -184,7 +341,7 @@ mod coreaudio_device {
         let mut rate: f64 = 0.0;
         let mut size = std::mem::size_of::<f64>() as u32;
         let status = unsafe {
-            AudioObjectGetPropertyData(
+            (api().get_data)(
                 device_id,
                 &address,
                 0,
@@ -196,12 +353,122 @@ mod coreaudio_device {
         if status == 0 { rate as u32 } else { 0 }
     }
 
+    fn get_u32_property(device_id: AudioDeviceID, selector: u32, scope: u32) -> Option<u32> {
+        let address = AudioObjectPropertyAddress {
+            mSelector: selector,
+            mScope: scope,
+            mElement: kAudioObjectPropertyElementMain,
+        };
+        let mut value: u32 = 0;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        // SAFETY: `size` is the byte size of `value`, which CoreAudio fills
+        // with at most that many bytes.
+        let status = unsafe {
+            (api().get_data)(
+                device_id,
+                &address,
+                0,
+                std::ptr::null(),
+                &mut size,
+                &mut value as *mut _ as *mut c_void,
+            )
+        };
+        if status == 0 { Some(value) } else { None }
+    }
+
+    /// Format of the first stream of `scope` on the device, as the hardware
+    /// (or virtual driver) runs it.
+    pub fn get_stream_format(device_id: AudioDeviceID, scope: u32) -> Option<NativeStreamFormat> {
+        let streams = AudioObjectPropertyAddress {
+            mSelector: kAudioDevicePropertyStreams,
+            mScope: scope,
+            mElement: kAudioObjectPropertyElementMain,
+        };
+        let mut stream_id: AudioObjectID = 0;
+        // Room for one ID: only the first stream is read.
+        let mut size = std::mem::size_of::<AudioObjectID>() as u32;
+        // SAFETY: `size` is the byte size of `stream_id`, which CoreAudio
+        // fills with at most that many bytes.
+        let status = unsafe {
+            (api().get_data)(
+                device_id,
+                &streams,
+                0,
+                std::ptr::null(),
+                &mut size,
+                &mut stream_id as *mut _ as *mut c_void,
+            )
+        };
+        if status != 0 || size == 0 {
+            return None;
+        }
+
+        let format = AudioObjectPropertyAddress {
+            mSelector: kAudioStreamPropertyVirtualFormat,
+            mScope: kAudioObjectPropertyScopeGlobal,
+            mElement: kAudioObjectPropertyElementMain,
+        };
+        let mut asbd = AudioStreamBasicDescription::default();
+        let mut size = std::mem::size_of::<AudioStreamBasicDescription>() as u32;
+        // SAFETY: as above, `size` is the byte size of `asbd`.
+        let status = unsafe {
+            (api().get_data)(
+                stream_id,
+                &format,
+                0,
+                std::ptr::null(),
+                &mut size,
+                &mut asbd as *mut _ as *mut c_void,
+            )
+        };
+        (status == 0).then_some(NativeStreamFormat {
+            sample_rate: asbd.mSampleRate,
+            bits_per_channel: asbd.mBitsPerChannel,
+            channels_per_frame: asbd.mChannelsPerFrame,
+            format_id: asbd.mFormatID,
+            format_flags: asbd.mFormatFlags,
+        })
+    }
+
+    /// Device latency plus safety offset for the given scope, in frames.
+    pub fn get_latency_frames(device_id: AudioDeviceID, scope: u32) -> u32 {
+        let latency = get_u32_property(device_id, kAudioDevicePropertyLatency, scope);
+        let safety = get_u32_property(device_id, kAudioDevicePropertySafetyOffset, scope);
+        latency.unwrap_or(0) + safety.unwrap_or(0)
+    }
+
+    /// IO buffer size in frames.
+    pub fn get_buffer_frame_size(device_id: AudioDeviceID) -> u32 {
+        get_u32_property(
+            device_id,
+            kAudioDevicePropertyBufferFrameSize,
+            kAudioObjectPropertyScopeGlobal,
+        )
+        .unwrap_or(0)
+    }
+
+    /// Whether another process is running or hogging the device.
+    pub fn get_device_usage(device_id: AudioDeviceID) -> DeviceUsage {
+        let global = kAudioObjectPropertyScopeGlobal;
+        DeviceUsage::from_properties(
+            get_u32_property(
+                device_id,
+                kAudioDevicePropertyDeviceIsRunningSomewhere,
+                global,
+            ),
+            get_u32_property(device_id, kAudioDevicePropertyHogMode, global),
+            std::process::id(),
+        )
+    }
+
+    #[derive(Clone)]
     pub struct DeviceInfo {
         pub id: AudioDeviceID,
         pub name: String,
         pub input_channels: u32,
         pub output_channels: u32,
         pub sample_rate: u32,
+        pub usage: DeviceUsage,
     }
 
     pub fn all_devices() -> Vec<DeviceInfo> {
@@ -218,11 +485,129 @@ mod coreaudio_device {
                     input_channels,
                     output_channels,
                     sample_rate,
+                    usage: get_device_usage(id),
                 })
             })
             .collect()
     }
 
+    unsafe extern "C" fn on_system_output_changed(
+        _object_id: AudioObjectID,
+        _number_addresses: u32,
+        _addresses: *const AudioObjectPropertyAddress,
+        client_data: *mut c_void,
+    ) -> OSStatus {
+        if client_data.is_null() {
+            return 0;
+        }
+        // SAFETY: client_data is the leaked `Sender` from `watch_system_output`, never freed.
+        let tx = unsafe { &*(client_data as *const Sender<AudioDeviceID>) };
+        if let Some(id) = system_output_device_id() {
+            let _ = tx.send(id);
+        }
+        0
+    }
+
+    /// Sends the new device ID on `tx` whenever the system output device changes.
+    /// The listener stays registered for the life of the process.
+    pub fn watch_system_output(tx: Sender<AudioDeviceID>) -> bool {
+        let address = AudioObjectPropertyAddress {
+            mSelector: kAudioHardwarePropertyDefaultSystemOutputDevice,
+            mScope: kAudioObjectPropertyScopeGlobal,
+            mElement: kAudioObjectPropertyElementMain,
+        };
+        let client_data = Box::into_raw(Box::new(tx)) as *mut c_void;
+        // SAFETY: address outlives the call; client_data is leaked, so it
+        // stays valid for as long as the listener is registered.
+        let status = unsafe {
+            AudioObjectAddPropertyListener(
+                kAudioObjectSystemObject,
+                &address,
+                Some(on_system_output_changed),
+                client_data,
+            )
+        };
+        status == 0
+    }
+
+    unsafe extern "C" fn on_latency_changed(
+        object_id: AudioObjectID,
+        _number_addresses: u32,
+        _addresses: *const AudioObjectPropertyAddress,
+        client_data: *mut c_void,
+    ) -> OSStatus {
+        if client_data.is_null() {
+            return 0;
+        }
+        // SAFETY: client_data is the leaked `Sender` from `watch_latency`, never freed.
+        let tx = unsafe { &*(client_data as *const Sender<(AudioDeviceID, u32)>) };
+        let frames = get_latency_frames(object_id, kAudioObjectPropertyScopeOutput);
+        let _ = tx.send((object_id, frames));
+        0
+    }
+
+    /// Sends the device and its output latency (as `get_latency_frames`
+    /// reads it) on `tx` whenever the device reports a new latency. The
+    /// listener stays registered for the life of the process.
+    pub fn watch_latency(device_id: AudioDeviceID, tx: Sender<(AudioDeviceID, u32)>) -> bool {
+        let address = AudioObjectPropertyAddress {
+            mSelector: kAudioDevicePropertyLatency,
+            mScope: kAudioObjectPropertyScopeOutput,
+            mElement: kAudioObjectPropertyElementMain,
+        };
+        let client_data = Box::into_raw(Box::new(tx)) as *mut c_void;
+        // SAFETY: address outlives the call; client_data is leaked, so it
+        // stays valid for as long as the listener is registered.
+        let status = unsafe {
+            AudioObjectAddPropertyListener(
+                device_id,
+                &address,
+                Some(on_latency_changed),
+                client_data,
+            )
+        };
+        status == 0
+    }
+
+    unsafe extern "C" fn on_devices_changed(
+        _object_id: AudioObjectID,
+        _number_addresses: u32,
+        _addresses: *const AudioObjectPropertyAddress,
+        client_data: *mut c_void,
+    ) -> OSStatus {
+        if client_data.is_null() {
+            return 0;
+        }
+        // SAFETY: client_data is the leaked handler from `watch_devices`, never freed.
+        let on_change = unsafe { &*(client_data as *const DevicesChanged) };
+        on_change();
+        0
+    }
+
+    type DevicesChanged = Box<dyn Fn() + Send + Sync>;
+
+    /// Calls `on_change` whenever a device is added or removed. The listener
+    /// stays registered for the life of the process.
+    pub fn watch_devices(on_change: DevicesChanged) -> bool {
+        let address = AudioObjectPropertyAddress {
+            mSelector: kAudioHardwarePropertyDevices,
+            mScope: kAudioObjectPropertyScopeGlobal,
+            mElement: kAudioObjectPropertyElementMain,
+        };
+        let client_data = Box::into_raw(Box::new(on_change)) as *mut c_void;
+        // SAFETY: address outlives the call; client_data is leaked, so it
+        // stays valid for as long as the listener is registered.
+        let status = unsafe {
+            AudioObjectAddPropertyListener(
+                kAudioObjectSystemObject,
+                &address,
+                Some(on_devices_changed),
+                client_data,
+            )
+        };
+        status == 0
+    }
+
     /// Find a device ID by case-insensitive name substring.
     pub fn device_id_by_name(name: &str) -> Option<(AudioDeviceID, String)> {
         let lower = name.to_lowercase();
@@ -235,8 +620,360 @@ mod coreaudio_device {
         }
         None
     }
+
+    #[cfg(test)]
+    mod tests {
+        use std::cell::{Cell, RefCell};
+        use std::ffi::CString;
+
+        use super::*;
+
+        struct FakeDevice {
+            id: AudioDeviceID,
+            name: &'static str,
+            uid: &'static str,
+            input_channels: u32,
+            output_channels: u32,
+            sample_rate: f64,
+        }
+
+        thread_local! {
+            pub(super) static API: Cell<PropertyApi> = const { Cell::new(COREAUDIO) };
+            static DEVICES: RefCell<Vec<FakeDevice>> = const { RefCell::new(Vec::new()) };
+        }
+
+        /// Answers property calls from the thread's `DEVICES`.
+        const FAKE: PropertyApi = PropertyApi {
+            get_data: fake_get_data,
+            get_data_size: fake_get_data_size,
+            string_get_c_string: fake_get_c_string,
+            string_get_length: fake_get_length,
+            string_max_size: fake_max_size,
+            release: fake_release,
+        };
+
+        const UNKNOWN_PROPERTY: OSStatus = 0x77686f3f; // 'who?'
+
+        unsafe extern "C" fn fake_get_data_size(
+            object_id: AudioObjectID,
+            address: *const AudioObjectPropertyAddress,
+            _qualifier_size: u32,
+            _qualifier: *const c_void,
+            out_size: *mut u32,
+        ) -> OSStatus {
+            // SAFETY: forwards the caller's pointers; a null `out_data` only
+            // asks for the size.
+            unsafe {
+                fake_get_data(
+                    object_id,
+                    address,
+                    0,
+                    std::ptr::null(),
+                    out_size,
+                    std::ptr::null_mut(),
+                )
+            }
+        }
+
+        unsafe extern "C" fn fake_get_data(
+            object_id: AudioObjectID,
+            address: *const AudioObjectPropertyAddress,
+            _qualifier_size: u32,
+            _qualifier: *const c_void,
+            io_size: *mut u32,
+            out_data: *mut c_void,
+        ) -> OSStatus {
+            // SAFETY: callers pass a valid address and size pointer, as with CoreAudio.
+            let (address, io_size) = unsafe { (&*address, &mut *io_size) };
+            // The first device is the default and system output.
+            let value = DEVICES.with(|devices| {
+                let devices = devices.borrow();
+                if object_id == kAudioObjectSystemObject {
+                    let ids: Vec<u8> = match address.mSelector {
+                        s if s == kAudioHardwarePropertyDevices => {
+                            devices.iter().flat_map(|d| d.id.to_ne_bytes()).collect()
+                        }
+                        s if s == kAudioHardwarePropertyDefaultOutputDevice
+                            || s == kAudioHardwarePropertyDefaultSystemOutputDevice =>
+                        {
+                            devices.first()?.id.to_ne_bytes().to_vec()
+                        }
+                        _ => return None,
+                    };
+                    return Some(ids);
+                }
+                let device = devices.iter().find(|d| d.id == object_id)?;
+                let string = |value: &str| {
+                    // Freed by `fake_release`, as CFRelease would.
+                    let cf = Box::into_raw(Box::new(CString::new(value).ok()?)) as CFStringRef;
+                    Some((cf as usize).to_ne_bytes().to_vec())
+                };
+                match address.mSelector {
+                    s if s == kAudioObjectPropertyName => string(device.name),
+                    s if s == kAudioDevicePropertyDeviceUID => string(device.uid),
+                    s if s == kAudioDevicePropertyNominalSampleRate => {
+                        Some(device.sample_rate.to_ne_bytes().to_vec())
+                    }
+                    s if s == kAudioDevicePropertyStreamConfiguration => {
+                        let channels = if address.mScope == kAudioObjectPropertyScopeInput {
+                            device.input_channels
+                        } else {
+                            device.output_channels
+                        };
+                        let list = AudioBufferList {
+                            mNumberBuffers: u32::from(channels > 0),
+                            mBuffers: [AudioBuffer {
+                                mNumberChannels: channels,
+                                mDataByteSize: 0,
+                                mData: std::ptr::null_mut(),
+                            }],
+                        };
+                        let size = std::mem::size_of::<AudioBufferList>();
+                        // SAFETY: reads the bytes of a live, plain-data struct.
+                        let bytes = unsafe {
+                            std::slice::from_raw_parts(&list as *const _ as *const u8, size)
+                        };
+                        Some(bytes.to_vec())
+                    }
+                    _ => None,
+                }
+            });
+            let Some(bytes) = value else {
+                return UNKNOWN_PROPERTY;
+            };
+            if out_data.is_null() {
+                // Sizing a string property creates one; free it again.
+                if address.mSelector == kAudioObjectPropertyName
+                    || address.mSelector == kAudioDevicePropertyDeviceUID
+                {
+                    let mut cf = [0u8; std::mem::size_of::<usize>()];
+                    cf.copy_from_slice(&bytes[..std::mem::size_of::<usize>()]);
+                    // SAFETY: the string was just made above and isn't shared.
+                    unsafe { fake_release(usize::from_ne_bytes(cf) as CFTypeRef) };
+                }
+                *io_size = bytes.len() as u32;
+                return 0;
+            }
+            let len = bytes.len().min(*io_size as usize);
+            // SAFETY: `out_data` holds at least `*io_size` bytes, and `len`
+            // is within both that and `bytes`.
+            unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), out_data as *mut u8, len) };
+            *io_size = len as u32;
+            0
+        }
+
+        unsafe extern "C" fn fake_get_c_string(
+            string: CFStringRef,
+            buffer: *mut c_char,
+            buffer_size: CFIndex,
+            _encoding: CFStringEncoding,
+        ) -> Boolean {
+            // SAFETY: fake strings are `CString`s made by `fake_get_data`.
+            let bytes = unsafe { (*(string as *const CString)).as_bytes_with_nul() };
+            if bytes.len() > buffer_size as usize {
+                return 0;
+            }
+            // SAFETY: the buffer holds `buffer_size` bytes, checked above.
+            unsafe {
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer as *mut u8, bytes.len())
+            };
+            1
+        }
+
+        unsafe extern "C" fn fake_get_length(string: CFStringRef) -> CFIndex {
+            // SAFETY: fake strings are `CString`s made by `fake_get_data`.
+            let bytes = unsafe { (*(string as *const CString)).as_bytes() };
+            // CFString lengths count UTF-16 units.
+            String::from_utf8_lossy(bytes).encode_utf16().count() as CFIndex
+        }
+
+        /// CoreFoundation's UTF-8 worst case: three bytes per UTF-16 unit.
+        unsafe extern "C" fn fake_max_size(
+            length: CFIndex,
+            _encoding: CFStringEncoding,
+        ) -> CFIndex {
+            length.checked_mul(3).unwrap_or(-1)
+        }
+
+        unsafe extern "C" fn fake_release(cf: CFTypeRef) {
+            // SAFETY: only fake strings are released, each once.
+            drop(unsafe { Box::from_raw(cf as *mut CString) });
+        }
+
+        /// Longer in UTF-8 than the 256 bytes names used to be read into.
+        const LONG_NAME: &str = "Elgato Wave:3 (Digital Audio Interface) #2 \u{2014} \
+            Regieraum Mischpult f\u{fc}r Aufnahmen \u{00e0} l'\u{00e9}tage, \
+            \u{97f3}\u{58f0}\u{30a4}\u{30f3}\u{30bf}\u{30fc}\u{30d5}\u{30a7}\u{30fc}\u{30b9} \
+            \u{1f3a4}\u{1f3a7} Studio B Aggregate (Thunderbolt 4 Dock, Port 3) \
+            \u{2192} Monitor Mix f\u{fc}r Sprecherkabine \u{2014} Backup-Aufnahme \
+            \u{fc}ber USB-C Hub (Anker 7-in-1), Kan\u{e4}le 1\u{2013}2";
+
+        #[test]
+        fn all_devices_reads_every_property() {
+            // The speakers (91) are the default output.
+            let studio = vec![
+                FakeDevice {
+                    id: 91,
+                    name: "MacBook Pro Speakers",
+                    uid: "BuiltInSpeakerDevice",
+                    input_channels: 0,
+                    output_channels: 2,
+                    sample_rate: 48_000.0,
+                },
+                FakeDevice {
+                    id: 73,
+                    name: "BlackHole 2ch",
+                    uid: "BlackHole2ch_UID",
+                    input_channels: 2,
+                    output_channels: 2,
+                    sample_rate: 48_000.0,
+                },
+                FakeDevice {
+                    id: 80,
+                    name: "MacBook Pro Microphone",
+                    uid: "BuiltInMicrophoneDevice",
+                    input_channels: 1,
+                    output_channels: 0,
+                    sample_rate: 44_100.0,
+                },
+            ];
+            DEVICES.with(|d| *d.borrow_mut() = studio);
+            API.with(|api| api.set(FAKE));
+            let devices = all_devices();
+            let summary: Vec<_> = devices
+                .iter()
+                .map(|d| {
+                    (
+                        d.id,
+                        d.name.as_str(),
+                        d.input_channels,
+                        d.output_channels,
+                        d.sample_rate,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                summary,
+                [
+                    (91, "MacBook Pro Speakers", 0, 2, 48_000),
+                    (73, "BlackHole 2ch", 2, 2, 48_000),
+                    (80, "MacBook Pro Microphone", 1, 0, 44_100),
+                ]
+            );
+            // Hog mode and "running somewhere" aren't faked: the queries fail.
+            assert!(devices.iter().all(|d| d.usage == DeviceUsage::Idle));
+        }
+
+        #[test]
+        fn devices_are_found_by_case_insensitive_substring() {
+            // The speakers (91) are the default output.
+            let studio = vec![
+                FakeDevice {
+                    id: 91,
+                    name: "MacBook Pro Speakers",
+                    uid: "BuiltInSpeakerDevice",
+                    input_channels: 0,
+                    output_channels: 2,
+                    sample_rate: 48_000.0,
+                },
+                FakeDevice {
+                    id: 73,
+                    name: "BlackHole 2ch",
+                    uid: "BlackHole2ch_UID",
+                    input_channels: 2,
+                    output_channels: 2,
+                    sample_rate: 48_000.0,
+                },
+                FakeDevice {
+                    id: 80,
+                    name: "MacBook Pro Microphone",
+                    uid: "BuiltInMicrophoneDevice",
+                    input_channels: 1,
+                    output_channels: 0,
+                    sample_rate: 44_100.0,
+                },
+            ];
+            DEVICES.with(|d| *d.borrow_mut() = studio);
+            API.with(|api| api.set(FAKE));
+            assert_eq!(
+                device_id_by_name("blackhole"),
+                Some((73, "BlackHole 2ch".to_string()))
+            );
+            // The first match in device order wins.
+            assert_eq!(device_id_by_name("MacBook").map(|(id, _)| id), Some(91));
+            assert_eq!(device_id_by_name("Loopback"), None);
+            assert_eq!(get_device_uid(73).as_deref(), Some("BlackHole2ch_UID"));
+            assert_eq!(
+                system_output_device_id()
+                    .and_then(get_device_name)
+                    .as_deref(),
+                Some("MacBook Pro Speakers")
+            );
+        }
+
+        #[test]
+        fn long_multibyte_names_are_read_whole() {
+            assert!(LONG_NAME.len() > 256);
+            let blackhole = FakeDevice {
+                id: 73,
+                name: LONG_NAME,
+                uid: "BlackHole2ch_UID",
+                input_channels: 2,
+                output_channels: 2,
+                sample_rate: 48_000.0,
+            };
+            DEVICES.with(|d| *d.borrow_mut() = vec![blackhole]);
+            API.with(|api| api.set(FAKE));
+            assert_eq!(get_device_name(73).as_deref(), Some(LONG_NAME));
+            assert_eq!(c_string_capacity(0), Some(1));
+            assert_eq!(c_string_capacity(-1), None);
+        }
+
+        #[test]
+        fn channel_counts_follow_the_scope() {
+            // The speakers (91) are the default output.
+            let studio = vec![
+                FakeDevice {
+                    id: 91,
+                    name: "MacBook Pro Speakers",
+                    uid: "BuiltInSpeakerDevice",
+                    input_channels: 0,
+                    output_channels: 2,
+                    sample_rate: 48_000.0,
+                },
+                FakeDevice {
+                    id: 73,
+                    name: "BlackHole 2ch",
+                    uid: "BlackHole2ch_UID",
+                    input_channels: 2,
+                    output_channels: 2,
+                    sample_rate: 48_000.0,
+                },
+                FakeDevice {
+                    id: 80,
+                    name: "MacBook Pro Microphone",
+                    uid: "BuiltInMicrophoneDevice",
+                    input_channels: 1,
+                    output_channels: 0,
+                    sample_rate: 44_100.0,
+                },
+            ];
+            DEVICES.with(|d| *d.borrow_mut() = studio);
+            API.with(|api| api.set(FAKE));
+            assert_eq!(get_channel_count(73, kAudioObjectPropertyScopeInput), 2);
+            assert_eq!(get_channel_count(80, kAudioObjectPropertyScopeOutput), 0);
+            assert_eq!(get_channel_count(80, kAudioObjectPropertyScopeInput), 1);
+            assert_eq!(get_channel_count(999, kAudioObjectPropertyScopeInput), 0);
+            assert_eq!(get_sample_rate(80), 44_100);
+            assert!(wait_for_channels(91, kAudioObjectPropertyScopeInput, 0).is_err());
+        }
+    }
 }
 
+/// How long to wait for a freshly activated input device to report channels
+/// (three retries).
+const CHANNEL_WAIT_MS: u64 = 300;
+
 const VIRTUAL_DEVICE_NAMES: &[&str] = &["blackhole", "soundflower", "loopback"];
 
 fn is_virtual_device(name: &str) -> bool {
@@ -244,21 +981,252 @@ fn is_virtual_device(name: &str) -> bool {
     VIRTUAL_DEVICE_NAMES.iter().any(|v| lower.contains(v))
 }
 
+pub use coreaudio_device::{AudioDeviceID, DeviceInfo};
+
+/// The devices `resolve_output` chooses from: CoreAudio, or a fixed list in
+/// tests.
+pub trait DeviceRegistry {
+    /// Every device, in CoreAudio's order.
+    fn devices(&self) -> Vec<DeviceInfo>;
+    fn system_output_id(&self) -> Option<AudioDeviceID>;
+    fn default_output_id(&self) -> Option<AudioDeviceID>;
+}
+
+impl DeviceRegistry for Mutex<DeviceCache> {
+    fn devices(&self) -> Vec<DeviceInfo> {
+        all_devices(self)
+    }
+
+    fn system_output_id(&self) -> Option<AudioDeviceID> {
+        coreaudio_device::system_output_device_id()
+    }
+
+    fn default_output_id(&self) -> Option<AudioDeviceID> {
+        coreaudio_device::default_output_device_id()
+    }
+}
+
+/// Why `resolve_output` chose the output it did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionReason {
+    /// `-o` matched the device.
+    Requested { query: String },
+    /// No `-o`: the system output device, which macOS keeps physical even
+    /// when the default output is a virtual device.
+    SystemOutput {
+        /// The default output's name, if there is one.
+        default_output: Option<String>,
+    },
+    /// No `-o` and no system output device: the default output.
+    DefaultOutput,
+}
+
+impl SelectionReason {
+    /// The decision path, for the banner: "output not specified → system
+    /// output device 'LG TV' chosen; default output was 'BlackHole 2ch'
+    /// (virtual, skipped)".
+    pub fn describe(&self, chosen: &str) -> String {
+        match self {
+            Self::Requested { query } => format!("-o '{query}' matched '{chosen}'"),
+            Self::SystemOutput { default_output } => {
+                let default = match default_output.as_deref() {
+                    Some(name) if name == chosen => "; it is also the default output".to_string(),
+                    Some(name) if is_virtual_device(name) => {
+                        format!("; default output was '{name}' (virtual, skipped)")
+                    }
+                    Some(name) => format!("; default output was '{name}' (not used)"),
+                    None => "; there is no default output".to_string(),
+                };
+                format!(
+                    "output not specified \u{2192} system output device '{chosen}' chosen{default}"
+                )
+            }
+            Self::DefaultOutput => format!(
+                "output not specified \u{2192} no system output device; default output '{chosen}' chosen"
+            ),
+        }
+    }
+
+    /// A few words for the device details overlay: "-o 'lg'", "system
+    /// output (default 'BlackHole 2ch' is virtual)".
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Requested { query } => format!("-o '{query}'"),
+            Self::SystemOutput {
+                default_output: Some(name),
+            } if is_virtual_device(name) => {
+                format!("system output (default '{name}' is virtual)")
+            }
+            Self::SystemOutput { .. } => "system output".to_string(),
+            Self::DefaultOutput => "default output (no system output)".to_string(),
+        }
+    }
+}
+
+/// The output device for `args`: the `-o` match, else the system output
+/// device, else the default output. Virtual devices and the input device
+/// itself are refused.
+pub fn resolve_output(
+    args: &CliArgs,
+    input_id: AudioDeviceID,
+    registry: &impl DeviceRegistry,
+) -> Result<(DeviceInfo, SelectionReason)> {
+    let devices = registry.devices();
+    let by_id = |id: AudioDeviceID| devices.iter().find(|d| d.id == id).cloned();
+
+    if let Some(query) = &args.output_device {
+        let lower = query.to_lowercase();
+        let device = devices
+            .iter()
+            .find(|d| d.name.to_lowercase().contains(&lower))
+            .cloned()
+            .ok_or_else(|| anyhow!("No audio device found matching '{query}'"))?;
+        if is_virtual_device(&device.name) {
+            return Err(anyhow!(
+                "'{}' is a virtual audio device and cannot be used as output.\n\
+                 Use -l to list available output devices.",
+                device.name
+            ));
+        }
+        if device.id == input_id {
+            return Err(anyhow!(
+                "Input and output cannot be the same device ('{}').\n\
+                 Use -l to list available devices.",
+                device.name
+            ));
+        }
+        let reason = SelectionReason::Requested {
+            query: query.clone(),
+        };
+        return Ok((device, reason));
+    }
+
+    let default = registry.default_output_id().and_then(by_id);
+    // Try system output first (physical speakers even when default is virtual)
+    if let Some(device) = registry.system_output_id().and_then(by_id) {
+        let reason = SelectionReason::SystemOutput {
+            default_output: default.map(|d| d.name),
+        };
+        return Ok((device, reason));
+    }
+    match default {
+        Some(device) if is_virtual_device(&device.name) => Err(anyhow!(
+            "Default output device '{}' is a virtual device.\n\
+             Use -o to specify a physical output device. Use -l to list available devices.",
+            device.name
+        )),
+        Some(device) => Ok((device, SelectionReason::DefaultOutput)),
+        None => Err(anyhow!("No default output device")),
+    }
+}
+
+/// How long `DeviceCache` reuses a device list.
+pub const DEVICE_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// The last device list read from CoreAudio, reused for `ttl`: reading it is
+/// slow with many virtual devices, and startup needs it several times. It is
+/// dropped early when CoreAudio reports a device added or removed.
+pub struct DeviceCache {
+    ttl: Duration,
+    read: Option<(Instant, Vec<DeviceInfo>)>,
+}
+
+impl DeviceCache {
+    fn new(ttl: Duration) -> Self {
+        Self { ttl, read: None }
+    }
+
+    /// An empty cache, shared with the CoreAudio listener that invalidates it.
+    pub fn shared(ttl: Duration) -> Arc<Mutex<Self>> {
+        let cache = Arc::new(Mutex::new(Self::new(ttl)));
+        let listener = cache.clone();
+        // Without the listener, entries still expire after `ttl`.
+        coreaudio_device::watch_devices(Box::new(move || {
+            if let Ok(mut cache) = listener.lock() {
+                cache.invalidate();
+            }
+        }));
+        cache
+    }
+
+    /// The cached list if read less than `ttl` before `now`, else a new one
+    /// from `read`, which is kept.
+    fn get(&mut self, now: Instant, read: impl FnOnce() -> Vec<DeviceInfo>) -> Vec<DeviceInfo> {
+        if let Some((at, devices)) = &self.read
+            && now.saturating_duration_since(*at) < self.ttl
+        {
+            return devices.clone();
+        }
+        let devices = read();
+        self.read = Some((now, devices.clone()));
+        devices
+    }
+
+    fn invalidate(&mut self) {
+        self.read = None;
+    }
+}
+
+/// Every device, from `cache` while it is fresh.
+fn all_devices(cache: &Mutex<DeviceCache>) -> Vec<DeviceInfo> {
+    match cache.lock() {
+        Ok(mut cache) => cache.get(Instant::now(), coreaudio_device::all_devices),
+        Err(_) => coreaudio_device::all_devices(),
+    }
+}
+
+/// Devices that can be captured: virtual devices with input channels.
+pub fn virtual_input_devices(cache: &Mutex<DeviceCache>) -> Vec<DeviceInfo> {
+    all_devices(cache)
+        .into_iter()
+        .filter(|dev| dev.input_channels > 0 && is_virtual_device(&dev.name))
+        .collect()
+}
+
 pub struct AudioEngine {
     _input_unit: AudioUnit,
-    _output_unit: AudioUnit,
+    output_unit: AudioUnit,
+    stream_format: StreamFormat,
+    pub input_id: AudioDeviceID,
+    /// Input device UID, or its name if the UID can't be read.
+    pub input_uid: String,
+    pub output_id: AudioDeviceID,
     pub controller: Arc<PlaybackController>,
     pub input_device_name: String,
     pub output_device_name: String,
+    /// Why the output was chosen at startup.
+    pub output_reason: SelectionReason,
     pub sample_rate: u32,
     pub channels: u16,
+    /// The input device's own stream format, if it could be read.
+    pub native_format: Option<NativeStreamFormat>,
+    /// Whether the ring buffer is pinned in RAM.
+    pub memory_lock: MemoryLock,
+    pub hardware_latency: HardwareLatency,
+    /// `--channel-map`, applied to every output device.
+    channel_map: Option<ChannelMap>,
+    /// How the input channels reach the current output, if not one to one.
+    pub output_map: Option<ChannelMap>,
+    /// The current output's latency and live floor, if it is AirPlay.
+    pub airplay: Option<AirPlayLatency>,
+    /// `--airplay-margin-ms`.
+    airplay_margin_ms: u32,
+    /// Output latency changes reported by `watch_latency`.
+    latency_tx: Sender<(AudioDeviceID, u32)>,
+    latency_rx: Receiver<(AudioDeviceID, u32)>,
+    /// Outputs with a latency listener; each is registered once.
+    latency_watched: Vec<AudioDeviceID>,
 }
 
 impl AudioEngine {
-    pub fn new(args: &CliArgs) -> Result<Self> {
+    pub fn new(args: &CliArgs, devices: &Mutex<DeviceCache>) -> Result<Self> {
         // Find input device by name — must be a virtual device
-        let (input_id, input_name) = coreaudio_device::device_id_by_name(&args.input_device)
-            .ok_or_else(|| anyhow!("No audio device found matching '{}'", args.input_device))?;
+        let query = args
+            .input_device
+            .as_deref()
+            .ok_or_else(|| anyhow!("No input device given (use -i)"))?;
+        let (input_id, input_name) = coreaudio_device::device_id_by_name(query)
+            .ok_or_else(|| anyhow!("No audio device found matching '{query}'"))?;
 
         if !is_virtual_device(&input_name) {
             return Err(anyhow!(
@@ -266,61 +1234,36 @@ impl AudioEngine {
                  Use -l to list available input devices."
             ));
         }
+        let channels = coreaudio_device::wait_for_channels(
+            input_id,
+            coreaudio_sys::kAudioObjectPropertyScopeInput,
+            CHANNEL_WAIT_MS,
+        )
+        .map_err(|_| missing_direction(devices, input_id, &input_name, Direction::Input))?
+            as u16;
 
         // Find output device — must be a physical (non-virtual) device
-        let (output_id, output_name) = match &args.output_device {
-            Some(name) => {
-                let (id, dev_name) = coreaudio_device::device_id_by_name(name)
-                    .ok_or_else(|| anyhow!("No audio device found matching '{name}'"))?;
-                if is_virtual_device(&dev_name) {
-                    return Err(anyhow!(
-                        "'{dev_name}' is a virtual audio device and cannot be used as output.\n\
-                         Use -l to list available output devices."
-                    ));
-                }
-                if id == input_id {
-                    return Err(anyhow!(
-                        "Input and output cannot be the same device ('{dev_name}').\n\
-                         Use -l to list available devices."
-                    ));
-                }
-                (id, dev_name)
-            }
-            None => {
-                // Try system output first (physical speakers even when default is virtual)
-                if let Some(id) = coreaudio_device::system_output_device_id() {
-                    let name = coreaudio_device::system_output_device_name()
-                        .unwrap_or_else(|| "unknown".into());
-                    (id, name)
-                } else if let Some(id) = coreaudio_device::default_output_device_id() {
-                    let name = coreaudio_device::all_devices()
-                        .into_iter()
-                        .find(|d| d.id == id)
-                        .map(|d| d.name)
-                        .unwrap_or_else(|| "unknown".into());
-                    if is_virtual_device(&name) {
-                        return Err(anyhow!(
-                            "Default output device '{name}' is a virtual device.\n\
-                             Use -o to specify a physical output device. Use -l to list available devices."
-                        ));
-                    }
-                    (id, name)
-                } else {
-                    return Err(anyhow!("No default output device"));
-                }
-            }
-        };
+        let (output, output_reason) = resolve_output(args, input_id, devices)?;
+        let (output_id, output_name) = (output.id, output.name);
+
+        let output_channels = coreaudio_device::get_channel_count(
+            output_id,
+            coreaudio_sys::kAudioObjectPropertyScopeOutput,
+        );
+        if output_channels == 0 {
+            return Err(missing_direction(
+                devices,
+                output_id,
+                &output_name,
+                Direction::Output,
+            ));
+        }
 
         // Get device properties
         let sample_rate = coreaudio_device::get_sample_rate(input_id);
-        let channels = coreaudio_device::get_channel_count(
-            input_id,
-            coreaudio_sys::kAudioObjectPropertyScopeInput,
-        ) as u16;
-
-        if sample_rate == 0 || channels == 0 {
+        if sample_rate == 0 {
             return Err(anyhow!(
-                "Could not determine sample rate or channels for '{input_name}'"
+                "Could not determine sample rate for '{input_name}'"
             ));
         }
 
@@ -334,6 +1277,35 @@ impl AudioEngine {
             ));
         }
 
+        let hardware_latency = HardwareLatency {
+            input_device_frames: coreaudio_device::get_latency_frames(
+                input_id,
+                coreaudio_sys::kAudioObjectPropertyScopeInput,
+            ),
+            input_buffer_frames: coreaudio_device::get_buffer_frame_size(input_id),
+            output_device_frames: coreaudio_device::get_latency_frames(
+                output_id,
+                coreaudio_sys::kAudioObjectPropertyScopeOutput,
+            ),
+            output_buffer_frames: coreaudio_device::get_buffer_frame_size(output_id),
+        };
+
+        // The AudioUnits convert between the device's own sample format and
+        // the client format set here. The callbacks, ring and controller all
+        // work in f32, so the client side stays F32 whatever the device runs;
+        // only devices without a PCM sample format are refused.
+        let native_format = coreaudio_device::get_stream_format(
+            input_id,
+            coreaudio_sys::kAudioObjectPropertyScopeInput,
+        );
+        if let Some(native) = native_format
+            && native.sample_format().is_none()
+        {
+            return Err(anyhow!(
+                "'{input_name}' streams {}, which can't be captured",
+                native.label()
+            ));
+        }
         let stream_format = StreamFormat {
             sample_rate: sample_rate as f64,
             sample_format: SampleFormat::F32,
@@ -344,126 +1316,732 @@ impl AudioEngine {
         // Create ring buffer
         let capacity = sample_rate as usize * channels as usize * args.buffer_seconds as usize;
         let ring = Arc::new(AudioRingBuffer::new(capacity));
+        // Lives until exit, so it is never unlocked.
+        let (addr, len) = ring.allocation();
+        let memory_lock = platform::lock_memory(addr, len);
 
         // Create controller
-        let controller = Arc::new(PlaybackController::new(ring.clone(), channels, sample_rate));
+        let mut controller = PlaybackController::new(ring.clone(), channels, sample_rate);
+        if args.output_raw.is_some() || args.record_dir.is_some() {
+            controller = controller.with_sink();
+        }
+        let controller = Arc::new(controller);
+        if let Some(interval_s) = args.auto_bookmark_interval_s {
+            controller.set_auto_bookmark_interval_s(interval_s);
+        }
+        if let Some(ms) = args.capture_delay_ms {
+            controller.set_capture_delay_ms(ms);
+        }
+        if let Some(ms) = args.max_read_step_ms {
+            controller.set_max_read_step_ms(ms);
+        }
+        controller.set_snap(args.snap);
+        controller.set_input_gain_db(args.input_gain_db);
+        analysis::spawn(controller.clone(), channels)?;
 
         // Set up input AudioUnit (capture from virtual device)
-        let mut input_unit = audio_unit_from_device_id(input_id, true)
-            .map_err(|e| anyhow!("Failed to create input AudioUnit: {e}"))?;
+        let mut input_unit = audio_unit_from_device_id(input_id, true).map_err(|e| {
+            with_hog_hint(anyhow!("Failed to create input AudioUnit: {e}"), input_id)
+        })?;
         input_unit
             .set_stream_format(stream_format, Scope::Output, Element::Input)
             .map_err(|e| anyhow!("Failed to set input stream format: {e}"))?;
 
-        let ring_input = ring.clone();
+        let ctrl_input = controller.clone();
+        let mut dc_blocker = args.dc_block.then(|| DcBlocker::new(channels, sample_rate));
         type InputArgs = render_callback::Args<data::Interleaved<f32>>;
         input_unit
             .set_input_callback(move |args: InputArgs| {
-                ring_input.write(args.data.buffer);
+                // Oversized buffers are counted and handled in sane-sized pieces.
+                let limit = ctrl_input.callback_limit(args.data.buffer.len()).max(1);
+                for chunk in args.data.buffer.chunks_mut(limit) {
+                    if let Some(blocker) = dc_blocker.as_mut() {
+                        blocker.process(chunk);
+                    }
+                    ctrl_input.apply_input_gain(chunk);
+                    ctrl_input.capture_input(chunk);
+                }
                 Ok(())
             })
             .map_err(|e| anyhow!("Failed to set input callback: {e}"))?;
 
         // Set up output AudioUnit (play to speakers)
-        let mut output_unit = audio_unit_from_device_id(output_id, false)
-            .map_err(|e| anyhow!("Failed to create output AudioUnit: {e}"))?;
-        output_unit
-            .set_stream_format(stream_format, Scope::Input, Element::Output)
-            .map_err(|e| anyhow!("Failed to set output stream format: {e}"))?;
-
-        let ctrl_output = controller.clone();
-        let ch = channels;
-        type OutputArgs = render_callback::Args<data::Interleaved<f32>>;
-        output_unit
-            .set_render_callback(move |args: OutputArgs| {
-                let data = args.data.buffer;
-                let frame_count = data.len() / ch as usize;
-                let state = ctrl_output.pre_read(frame_count);
-
-                if state == PlaybackState::Paused {
-                    for s in data.iter_mut() {
-                        *s = 0.0;
-                    }
-                } else {
-                    ctrl_output.ring.read(data);
-                }
-
-                ctrl_output.apply_ramp(data);
-                ctrl_output.apply_volume(data);
-                ctrl_output.update_peaks(data);
-                Ok(())
-            })
-            .map_err(|e| anyhow!("Failed to set output callback: {e}"))?;
+        let output_map =
+            output_channel_map(output_id, &output_name, channels, args.channel_map.as_ref())?;
+        let mut output_unit =
+            build_output_unit(output_id, stream_format, &controller, output_map.clone())
+                .map_err(|e| with_hog_hint(e, output_id))?;
 
         // Start both audio units
-        input_unit
-            .start()
-            .map_err(|e| anyhow!("Failed to start input: {e}"))?;
         output_unit
             .start()
-            .map_err(|e| anyhow!("Failed to start output: {e}"))?;
+            .map_err(|e| with_hog_hint(anyhow!("Failed to start output: {e}"), output_id))?;
+        input_unit
+            .start()
+            .map_err(|e| with_hog_hint(anyhow!("Failed to start input: {e}"), input_id))?;
 
-        Ok(Self {
+        let (latency_tx, latency_rx) = mpsc::channel();
+        let mut engine = Self {
             _input_unit: input_unit,
-            _output_unit: output_unit,
+            output_unit,
+            stream_format,
+            input_id,
+            input_uid: coreaudio_device::get_device_uid(input_id)
+                .unwrap_or_else(|| input_name.clone()),
+            output_id,
             controller,
             input_device_name: input_name,
             output_device_name: output_name,
+            output_reason,
             sample_rate,
             channels,
-        })
+            native_format,
+            memory_lock,
+            hardware_latency,
+            channel_map: args.channel_map.clone(),
+            output_map,
+            airplay: None,
+            airplay_margin_ms: args.airplay_margin_ms,
+            latency_tx,
+            latency_rx,
+            latency_watched: Vec::new(),
+        };
+        engine.follow_airplay();
+        Ok(engine)
     }
-}
 
-pub fn list_all_devices(input_device: &str) -> Result<()> {
-    let devices = coreaudio_device::all_devices();
-    let (default_output_id, system_output_id) = coreaudio_device::default_device_ids();
-    let input_id = coreaudio_device::device_id_by_name(input_device).map(|(id, _)| id);
+    /// Holds Live playback behind by an AirPlay output's latency plus the
+    /// margin, watching for latency changes; no floor for other outputs.
+    fn follow_airplay(&mut self) {
+        self.airplay = coreaudio_device::is_airplay(self.output_id).then(|| {
+            AirPlayLatency::new(
+                self.hardware_latency.output_device_frames,
+                self.airplay_margin_ms,
+                self.sample_rate,
+                self.channels,
+            )
+        });
+        self.controller
+            .set_live_floor_samples(self.airplay.map_or(0, |airplay| airplay.floor_samples()));
+        if self.airplay.is_some() && !self.latency_watched.contains(&self.output_id) {
+            coreaudio_device::watch_latency(self.output_id, self.latency_tx.clone());
+            self.latency_watched.push(self.output_id);
+        }
+    }
 
-    println!("Available input devices (virtual):");
-    let mut found_virtual = false;
-    for dev in &devices {
-        if dev.input_channels == 0 {
-            continue;
+    /// Applies latency changes the current AirPlay output reported since the
+    /// last call. Returns whether the live floor moved.
+    pub fn poll_airplay_latency(&mut self) -> bool {
+        let mut moved = false;
+        for (id, frames) in self.latency_rx.try_iter() {
+            if id != self.output_id {
+                continue;
+            }
+            if let Some(airplay) = self.airplay.as_mut()
+                && let Some(floor) = airplay.update(frames)
+            {
+                self.hardware_latency.output_device_frames = frames;
+                self.controller.set_live_floor_samples(floor);
+                moved = true;
+            }
         }
-        if !is_virtual_device(&dev.name) {
-            continue;
+        moved
+    }
+
+    /// Replaces the output AudioUnit with one for `new_id`. The ring buffer and
+    /// controller are untouched, so playback continues at the same delay.
+    pub fn switch_output_device_by_id(&mut self, new_id: AudioDeviceID) -> Result<()> {
+        if new_id == self.output_id {
+            return Ok(());
         }
-        found_virtual = true;
-        println!(
-            "  {}  [{}ch {}Hz]",
-            dev.name, dev.input_channels, dev.sample_rate,
+        let name = coreaudio_device::get_device_name(new_id).unwrap_or_else(|| "unknown".into());
+        if new_id == self.input_id || is_virtual_device(&name) {
+            return Err(anyhow!(
+                "'{name}' is a virtual audio device; keeping current output"
+            ));
+        }
+        let sample_rate = coreaudio_device::get_sample_rate(new_id);
+        if sample_rate != self.sample_rate {
+            return Err(anyhow!(
+                "'{name}' runs at {sample_rate}Hz, input at {}Hz; keeping current output",
+                self.sample_rate
+            ));
+        }
+
+        let output_map =
+            output_channel_map(new_id, &name, self.channels, self.channel_map.as_ref())
+                .map_err(|e| anyhow!("{e}\nKeeping current output"))?;
+        let mut new_unit = build_output_unit(
+            new_id,
+            self.stream_format,
+            &self.controller,
+            output_map.clone(),
+        )?;
+        // Only one render callback may run at a time: both would read the ring
+        // and push to the single-producer snapshot rings.
+        self.output_unit
+            .stop()
+            .map_err(|e| anyhow!("Failed to stop output: {e}"))?;
+        if let Err(e) = new_unit.start() {
+            // Bring the old output back rather than leave audio dead.
+            let restarted = self.output_unit.start();
+            return Err(match restarted {
+                Ok(()) => anyhow!("Failed to start '{name}': {e}\nKeeping current output"),
+                Err(old) => anyhow!("Failed to start '{name}': {e}; restarting old output: {old}"),
+            });
+        }
+        self.output_unit = new_unit;
+
+        self.output_id = new_id;
+        self.output_device_name = name;
+        self.output_map = output_map;
+        self.hardware_latency.output_device_frames = coreaudio_device::get_latency_frames(
+            new_id,
+            coreaudio_sys::kAudioObjectPropertyScopeOutput,
         );
+        self.hardware_latency.output_buffer_frames =
+            coreaudio_device::get_buffer_frame_size(new_id);
+        self.follow_airplay();
+        Ok(())
     }
-    if !found_virtual {
-        println!("  (none found)");
+
+    /// Starts listening for system output device changes (e.g. headphones
+    /// plugged in). Each new device ID arrives on the returned receiver.
+    pub fn watch_system_output(&self) -> Result<Receiver<AudioDeviceID>> {
+        let (tx, rx) = mpsc::channel();
+        if !coreaudio_device::watch_system_output(tx) {
+            return Err(anyhow!("Failed to watch the system output device"));
+        }
+        Ok(rx)
     }
+}
 
-    println!("\nAvailable output devices:");
-    for dev in &devices {
-        if dev.output_channels == 0 {
-            continue;
+/// How the input channels should reach an output device: checks that an
+/// aggregate's sub-devices share a clock, and spreads the input across all of
+/// an aggregate's channels unless `requested` (`--channel-map`) says otherwise.
+/// `None` means one to one, the first input channels to the first outputs.
+fn output_channel_map(
+    device_id: AudioDeviceID,
+    name: &str,
+    channels: u16,
+    requested: Option<&ChannelMap>,
+) -> Result<Option<ChannelMap>> {
+    let is_aggregate = coreaudio_device::is_aggregate(device_id);
+    if is_aggregate {
+        let (sub_devices, main_uid) = coreaudio_device::aggregate_sub_devices(device_id);
+        aggregate::check_clock(name, &sub_devices, main_uid.as_deref())?;
+    }
+    let output_channels = coreaudio_device::get_channel_count(
+        device_id,
+        coreaudio_sys::kAudioObjectPropertyScopeOutput,
+    ) as usize;
+    match requested {
+        Some(map) => {
+            map.validate(channels, output_channels)
+                .map_err(|e| anyhow!("'{name}': {e}"))?;
+            Ok(Some(map.clone()))
         }
-        if Some(dev.id) == input_id {
-            continue;
+        None if is_aggregate && output_channels > channels as usize => {
+            Ok(Some(ChannelMap::spread(channels, output_channels)))
         }
-        let mut tags = Vec::new();
-        if Some(dev.id) == default_output_id {
-            tags.push("default");
+        None => Ok(None),
+    }
+}
+
+/// Creates an output AudioUnit for `device_id` that renders from `controller`,
+/// through `map` onto the device's channels if given. The caller starts it.
+fn build_output_unit(
+    device_id: AudioDeviceID,
+    stream_format: StreamFormat,
+    controller: &Arc<PlaybackController>,
+    map: Option<ChannelMap>,
+) -> Result<AudioUnit> {
+    let mut output_unit = audio_unit_from_device_id(device_id, false)
+        .map_err(|e| anyhow!("Failed to create output AudioUnit: {e}"))?;
+    let ch = stream_format.channels as usize;
+    let out_ch = match &map {
+        Some(_) => coreaudio_device::get_channel_count(
+            device_id,
+            coreaudio_sys::kAudioObjectPropertyScopeOutput,
+        ) as usize,
+        None => ch,
+    };
+    let device_format = StreamFormat {
+        channels: out_ch as u32,
+        ..stream_format
+    };
+    output_unit
+        .set_stream_format(device_format, Scope::Input, Element::Output)
+        .map_err(|e| anyhow!("Failed to set output stream format: {e}"))?;
+
+    let ctrl_output = controller.clone();
+    // Input-layout block for mapped outputs, allocated here rather than in the callback.
+    let mut scratch = vec![
+        0.0f32;
+        if map.is_some() {
+            MAX_CALLBACK_FRAMES * ch
+        } else {
+            0
         }
-        if Some(dev.id) == system_output_id && default_output_id != system_output_id {
-            tags.push("system output");
+    ];
+    type OutputArgs = render_callback::Args<data::Interleaved<f32>>;
+    output_unit
+        .set_render_callback(move |args: OutputArgs| {
+            let buffer = args.data.buffer;
+            match &map {
+                None => {
+                    // Silence what's past the limit of an oversized buffer.
+                    let limit = ctrl_output.callback_limit(buffer.len());
+                    let (data, excess) = buffer.split_at_mut(limit);
+                    excess.fill(0.0);
+                    render(&ctrl_output, data, ch);
+                }
+                Some(map) => {
+                    let frames = buffer.len() / out_ch.max(1);
+                    let limit = ctrl_output.callback_limit(frames * ch);
+                    let data = &mut scratch[..limit];
+                    render(&ctrl_output, data, ch);
+                    map.apply(data, ch, buffer, out_ch);
+                }
+            }
+            Ok(())
+        })
+        .map_err(|e| anyhow!("Failed to set output callback: {e}"))?;
+    Ok(output_unit)
+}
+
+/// Fills `data` (interleaved, `ch` channels) with the next block of playback.
+fn render(controller: &PlaybackController, data: &mut [f32], ch: usize) {
+    if data.is_empty() {
+        return;
+    }
+    let frame_count = data.len() / ch;
+    let state = controller.pre_read(frame_count);
+
+    if state == PlaybackState::Paused {
+        for s in data.iter_mut() {
+            *s = 0.0;
         }
+    } else {
+        controller.read_output(data);
+    }
+
+    controller.apply_solo(data);
+    controller.apply_ramp(data);
+    controller.apply_volume(data);
+    controller.output_blocks.push(data);
+    if let Some(sink) = &controller.sink_blocks {
+        sink.push(data);
+    }
+}
+
+/// Which way audio flows through a device the user picked.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Input,
+    Output,
+}
+
+/// Error for a device that matched by name but has no channels in the
+/// direction it was picked for, with its channel counts and the closest
+/// device that could be used instead.
+fn missing_direction(
+    cache: &Mutex<DeviceCache>,
+    device_id: AudioDeviceID,
+    name: &str,
+    direction: Direction,
+) -> anyhow::Error {
+    let devices = all_devices(cache);
+    let (inputs, outputs) = devices
+        .iter()
+        .find(|dev| dev.id == device_id)
+        .map_or((0, 0), |dev| (dev.input_channels, dev.output_channels));
+    let candidates: Vec<&str> = devices
+        .iter()
+        .filter(|dev| dev.id != device_id)
+        .filter(|dev| match direction {
+            Direction::Input => dev.input_channels > 0 && is_virtual_device(&dev.name),
+            Direction::Output => dev.output_channels > 0 && !is_virtual_device(&dev.name),
+        })
+        .map(|dev| dev.name.as_str())
+        .collect();
+    let (label, use_as) = match direction {
+        Direction::Input => ("input", "captured"),
+        Direction::Output => ("output", "played to"),
+    };
+    let hint = match device_match::closest(name, &candidates) {
+        Some(closest) => format!("Did you mean '{closest}'? "),
+        None => String::new(),
+    };
+    anyhow!(
+        "'{name}' has no {label} channels ({inputs} in, {outputs} out), so it can't be {use_as}.\n\
+         {hint}Use -l to list available {label} devices."
+    )
+}
+
+/// Adds who holds the device to `error` if another process hogs it.
+fn with_hog_hint(error: anyhow::Error, device_id: AudioDeviceID) -> anyhow::Error {
+    match coreaudio_device::get_device_usage(device_id).hog_pid() {
+        Some(pid) => anyhow!(
+            "{error}\nThe device is hogged (exclusive access) by {}; quit it or release the device.",
+            device_usage::owner(pid)
+        ),
+        None => error,
+    }
+}
+
+/// Lists input (virtual) and output devices, as text or as one JSON object:
+/// `{"inputs":[{"name","id","channels","sample_rate"}],"outputs":[{..., "tags":[..]}]}`.
+/// Without an input device name, none of the inputs are listed as outputs.
+pub fn list_all_devices(
+    input_device: Option<&str>,
+    console: &Console,
+    cache: &Mutex<DeviceCache>,
+) -> Result<()> {
+    let devices = all_devices(cache);
+    let (default_output_id, system_output_id) = coreaudio_device::default_device_ids();
+    let input_id = input_device
+        .and_then(coreaudio_device::device_id_by_name)
+        .map(|(id, _)| id);
+
+    let inputs: Vec<_> = devices
+        .iter()
+        .filter(|dev| dev.input_channels > 0 && is_virtual_device(&dev.name))
+        .collect();
+    let is_input = |id| match input_id {
+        Some(input_id) => id == input_id,
+        None => inputs.iter().any(|dev| dev.id == id),
+    };
+    let outputs: Vec<_> = devices
+        .iter()
+        .filter(|dev| dev.output_channels > 0 && !is_input(dev.id))
+        .map(|dev| {
+            let mut tags = Vec::new();
+            if Some(dev.id) == default_output_id {
+                tags.push("default");
+            }
+            if Some(dev.id) == system_output_id && default_output_id != system_output_id {
+                tags.push("system output");
+            }
+            (dev, tags)
+        })
+        .collect();
+
+    // Outputs each input can play to: the sample rates must match.
+    let compatible = |input: &DeviceInfo| -> Vec<&DeviceInfo> {
+        outputs
+            .iter()
+            .map(|(dev, _)| *dev)
+            .filter(|dev| rate_mismatch(dev.sample_rate, input.sample_rate).is_none())
+            .collect()
+    };
+
+    if console.format() == BannerFormat::Json {
+        // `extra` is appended to the object's fields.
+        let device_json = |dev: &DeviceInfo, channels, tags: &[&str], extra: &str| {
+            let tags: Vec<_> = tags.iter().map(|t| json_string(t)).collect();
+            let hog_pid = dev
+                .usage
+                .hog_pid()
+                .map_or_else(|| "null".to_string(), |pid| pid.to_string());
+            format!(
+                "{{\"name\":{},\"id\":{},\"channels\":{channels},\"sample_rate\":{},\"tags\":[{}],\"usage\":{},\"hog_pid\":{hog_pid}{extra}}}",
+                json_string(&dev.name),
+                dev.id,
+                dev.sample_rate,
+                tags.join(","),
+                json_string(dev.usage.label()),
+            )
+        };
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|dev| {
+                let ids: Vec<_> = compatible(dev)
+                    .iter()
+                    .map(|out| out.id.to_string())
+                    .collect();
+                let extra = format!(",\"compatible_outputs\":[{}]", ids.join(","));
+                device_json(dev, dev.input_channels, &[], &extra)
+            })
+            .collect();
+        let outputs: Vec<_> = outputs
+            .iter()
+            .map(|(dev, tags)| device_json(dev, dev.output_channels, tags, ""))
+            .collect();
+        console.out(&format!(
+            "{{\"inputs\":[{}],\"outputs\":[{}]}}",
+            inputs.join(","),
+            outputs.join(","),
+        ));
+        return Ok(());
+    }
+
+    console.out("Available input devices (virtual):");
+    for dev in &inputs {
+        let tag = dev
+            .usage
+            .tag()
+            .map_or_else(String::new, |tag| format!(" ({tag})"));
+        console.out(&format!(
+            "  {}  [{}ch {}Hz]{tag}",
+            dev.name, dev.input_channels, dev.sample_rate,
+        ));
+    }
+    if inputs.is_empty() {
+        console.out("  (none found)");
+    }
+
+    console.out("\nAvailable output devices:");
+    for (dev, tags) in &outputs {
+        let mut tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+        tags.extend(dev.usage.tag());
         let tag = if tags.is_empty() {
             String::new()
         } else {
             format!(" ({})", tags.join(", "))
         };
-        println!(
+        console.out(&format!(
             "  {}  [{}ch {}Hz]{tag}",
             dev.name, dev.output_channels, dev.sample_rate,
-        );
+        ));
+    }
+
+    if inputs.is_empty() || outputs.is_empty() {
+        return Ok(());
+    }
+    console.out("\nPairs (input and output sample rates must match):");
+    for input in &inputs {
+        let count = compatible(input).len();
+        console.out(&format!(
+            "  {}  ({count} of {} outputs)",
+            input.name,
+            outputs.len()
+        ));
+        for (dev, _) in &outputs {
+            let note = rate_mismatch(dev.sample_rate, input.sample_rate)
+                .map_or_else(String::new, |note| format!("  {note}"));
+            console.out(&format!("    {}{note}", dev.name));
+        }
     }
 
     Ok(())
 }
+
+/// `-l` note on an output an input can't be paired with, e.g. "[SR mismatch:
+/// 44100Hz \u{2260} 48000Hz]" (output rate first). `None` if the rates match.
+fn rate_mismatch(output_rate: u32, input_rate: u32) -> Option<String> {
+    (output_rate != input_rate)
+        .then(|| format!("[SR mismatch: {output_rate}Hz \u{2260} {input_rate}Hz]"))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn outputs_at_another_rate_are_marked() {
+        assert_eq!(rate_mismatch(48_000, 48_000), None);
+        assert_eq!(
+            rate_mismatch(44_100, 48_000).as_deref(),
+            Some("[SR mismatch: 44100Hz \u{2260} 48000Hz]")
+        );
+    }
+
+    #[derive(Clone)]
+    struct FakeRegistry {
+        devices: Vec<DeviceInfo>,
+        system_output: Option<AudioDeviceID>,
+        default_output: Option<AudioDeviceID>,
+    }
+
+    impl DeviceRegistry for FakeRegistry {
+        fn devices(&self) -> Vec<DeviceInfo> {
+            self.devices.clone()
+        }
+
+        fn system_output_id(&self) -> Option<AudioDeviceID> {
+            self.system_output
+        }
+
+        fn default_output_id(&self) -> Option<AudioDeviceID> {
+            self.default_output
+        }
+    }
+
+    #[test]
+    fn output_resolution_explains_each_branch() {
+        // BlackHole (73) as the default output, an LG TV (91) as the system
+        // output, and the built-in speakers (80).
+        let living_room = FakeRegistry {
+            devices: [
+                (73, "BlackHole 2ch"),
+                (91, "LG TV"),
+                (80, "MacBook Pro Speakers"),
+            ]
+            .map(|(id, name)| DeviceInfo {
+                id,
+                name: name.to_string(),
+                input_channels: 2,
+                output_channels: 2,
+                sample_rate: 48_000,
+                usage: crate::audio::device_usage::DeviceUsage::Idle,
+            })
+            .to_vec(),
+            system_output: Some(91),
+            default_output: Some(73),
+        };
+        // The chosen device's ID and why, with the input on BlackHole (73).
+        let resolve = |argv: &[&str], registry: &FakeRegistry| {
+            let args = CliArgs::try_parse_from([&["shifter"], argv].concat()).unwrap();
+            resolve_output(&args, 73, registry).map(|(device, reason)| (device.id, reason))
+        };
+        let registry = living_room.clone();
+        let (id, reason) = resolve(&[], &registry).unwrap();
+        assert_eq!(id, 91);
+        assert_eq!(
+            reason.describe("LG TV"),
+            "output not specified \u{2192} system output device 'LG TV' chosen; \
+             default output was 'BlackHole 2ch' (virtual, skipped)"
+        );
+        assert_eq!(
+            reason.summary(),
+            "system output (default 'BlackHole 2ch' is virtual)"
+        );
+
+        let (id, reason) = resolve(&["-o", "macbook"], &registry).unwrap();
+        assert_eq!(id, 80);
+        assert_eq!(
+            reason.describe("MacBook Pro Speakers"),
+            "-o 'macbook' matched 'MacBook Pro Speakers'"
+        );
+        assert_eq!(reason.summary(), "-o 'macbook'");
+
+        // No system output: the default output, if it is physical.
+        let registry = FakeRegistry {
+            system_output: None,
+            default_output: Some(80),
+            ..living_room.clone()
+        };
+        let (id, reason) = resolve(&[], &registry).unwrap();
+        assert_eq!((id, reason.clone()), (80, SelectionReason::DefaultOutput));
+        assert_eq!(
+            reason.describe("MacBook Pro Speakers"),
+            "output not specified \u{2192} no system output device; \
+             default output 'MacBook Pro Speakers' chosen"
+        );
+
+        // The system output is also the default output.
+        let registry = FakeRegistry {
+            default_output: Some(91),
+            ..living_room.clone()
+        };
+        let (_, reason) = resolve(&[], &registry).unwrap();
+        assert!(
+            reason
+                .describe("LG TV")
+                .ends_with("; it is also the default output")
+        );
+        assert_eq!(reason.summary(), "system output");
+    }
+
+    #[test]
+    fn output_resolution_refuses_unusable_devices() {
+        // BlackHole (73) as the default output, an LG TV (91) as the system
+        // output, and the built-in speakers (80).
+        let living_room = FakeRegistry {
+            devices: [
+                (73, "BlackHole 2ch"),
+                (91, "LG TV"),
+                (80, "MacBook Pro Speakers"),
+            ]
+            .map(|(id, name)| DeviceInfo {
+                id,
+                name: name.to_string(),
+                input_channels: 2,
+                output_channels: 2,
+                sample_rate: 48_000,
+                usage: crate::audio::device_usage::DeviceUsage::Idle,
+            })
+            .to_vec(),
+            system_output: Some(91),
+            default_output: Some(73),
+        };
+        let error = |argv: &[&str], registry: &FakeRegistry| {
+            let args = CliArgs::try_parse_from([&["shifter"], argv].concat()).unwrap();
+            resolve_output(&args, 73, registry)
+                .map(|(device, _)| device.id)
+                .unwrap_err()
+                .to_string()
+        };
+        let registry = living_room.clone();
+        assert_eq!(
+            error(&["-o", "Sonos"], &registry),
+            "No audio device found matching 'Sonos'"
+        );
+        assert!(error(&["-o", "blackhole"], &registry).starts_with("'BlackHole 2ch' is a virtual"));
+        let mut same = living_room.clone();
+        same.devices[0].name = "Studio Display".to_string();
+        assert!(error(&["-o", "studio"], &same).starts_with("Input and output cannot be the same"));
+
+        let registry = FakeRegistry {
+            system_output: None,
+            ..living_room.clone()
+        };
+        assert!(
+            error(&[], &registry).starts_with("Default output device 'BlackHole 2ch' is a virtual")
+        );
+        let registry = FakeRegistry {
+            system_output: None,
+            default_output: None,
+            ..living_room.clone()
+        };
+        assert_eq!(error(&[], &registry), "No default output device");
+        // An ID the device list doesn't have counts as no device.
+        let registry = FakeRegistry {
+            system_output: Some(999),
+            default_output: None,
+            ..living_room.clone()
+        };
+        assert_eq!(error(&[], &registry), "No default output device");
+    }
+
+    #[test]
+    fn device_cache_rereads_when_stale_or_invalidated() {
+        use std::cell::Cell;
+
+        use crate::audio::device_usage::DeviceUsage;
+
+        let reads = Cell::new(0);
+        let read = || {
+            reads.set(reads.get() + 1);
+            vec![DeviceInfo {
+                id: reads.get(),
+                name: format!("Device {}", reads.get()),
+                input_channels: 2,
+                output_channels: 0,
+                sample_rate: 48_000,
+                usage: DeviceUsage::Idle,
+            }]
+        };
+        let mut cache = DeviceCache::new(Duration::from_secs(1));
+        let start = Instant::now();
+        assert_eq!(cache.get(start, read)[0].name, "Device 1");
+        // Within the TTL, the first list is reused.
+        let fresh = cache.get(start + Duration::from_millis(999), read);
+        assert_eq!((fresh[0].name.as_str(), reads.get()), ("Device 1", 1));
+        // After it, the devices are read again.
+        assert_eq!(
+            cache.get(start + Duration::from_secs(1), read)[0].name,
+            "Device 2"
+        );
+        // A device notification drops the list before it expires.
+        cache.invalidate();
+        assert_eq!(
+            cache.get(start + Duration::from_secs(1), read)[0].name,
+            "Device 3"
+        );
+        assert_eq!(reads.get(), 3);
+    }
+}
@@ -0,0 +1,90 @@
+/// Fixed latencies reported by the input and output devices, in frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HardwareLatency {
+    /// Input device latency plus safety offset.
+    pub input_device_frames: u32,
+    /// Input IO buffer size.
+    pub input_buffer_frames: u32,
+    /// Output device latency plus safety offset.
+    pub output_device_frames: u32,
+    /// Output IO buffer size.
+    pub output_buffer_frames: u32,
+}
+
+/// Breakdown of the end-to-end delay into its components, in signal order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyBudget {
+    pub components: Vec<(&'static str, f64)>,
+}
+
+impl LatencyBudget {
+    /// `min_delay_ms` is the ring buffer's minimum offset (one output callback),
+    /// `user_delay_ms` the requested delay on top of it.
+    pub fn new(
+        hardware: &HardwareLatency,
+        sample_rate: u32,
+        min_delay_ms: f64,
+        user_delay_ms: f64,
+    ) -> Self {
+        let frames_ms = |frames: u32| match sample_rate {
+            0 => 0.0,
+            rate => frames as f64 / rate as f64 * 1000.0,
+        };
+        Self {
+            components: vec![
+                ("Input device", frames_ms(hardware.input_device_frames)),
+                ("Input buffer", frames_ms(hardware.input_buffer_frames)),
+                ("Minimum delay", min_delay_ms),
+                ("User delay", user_delay_ms),
+                ("Output buffer", frames_ms(hardware.output_buffer_frames)),
+                ("Output device", frames_ms(hardware.output_device_frames)),
+            ],
+        }
+    }
+
+    pub fn total_ms(&self) -> f64 {
+        self.components.iter().map(|(_, ms)| ms).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_frames_to_ms() {
+        let hw = HardwareLatency {
+            input_device_frames: 48,
+            input_buffer_frames: 512,
+            output_device_frames: 96,
+            output_buffer_frames: 512,
+        };
+        let budget = LatencyBudget::new(&hw, 48_000, 10.0, 250.0);
+        let ms: Vec<f64> = budget.components.iter().map(|(_, ms)| *ms).collect();
+        assert_eq!(ms[0], 1.0);
+        assert!((ms[1] - 10.6667).abs() < 1e-3);
+        assert_eq!(ms[5], 2.0);
+    }
+
+    #[test]
+    fn total_is_sum_of_components() {
+        let hw = HardwareLatency {
+            input_device_frames: 480,
+            input_buffer_frames: 480,
+            output_device_frames: 480,
+            output_buffer_frames: 480,
+        };
+        let budget = LatencyBudget::new(&hw, 48_000, 10.0, 1_000.0);
+        assert!((budget.total_ms() - 1_050.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_sample_rate_reports_only_software_delay() {
+        let hw = HardwareLatency {
+            input_device_frames: 480,
+            ..Default::default()
+        };
+        let budget = LatencyBudget::new(&hw, 0, 5.0, 0.0);
+        assert_eq!(budget.total_ms(), 5.0);
+    }
+}
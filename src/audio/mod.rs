@@ -1,2 +1,16 @@
+pub mod aggregate;
+pub mod airplay;
+pub mod channel_map;
+pub mod dc_block;
+pub mod device_match;
+pub mod device_usage;
+pub mod driver_check;
+pub mod dsp;
 pub mod engine;
+pub mod latency;
+pub mod native_format;
+pub mod raw_sink;
+pub mod recorder;
 pub mod ring_buffer;
+pub mod snapshot_ring;
+pub mod source;
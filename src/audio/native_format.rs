@@ -0,0 +1,105 @@
+use coreaudio::audio_unit::SampleFormat;
+use coreaudio_sys::{kAudioFormatFlagIsFloat, kAudioFormatLinearPCM};
+
+/// A device stream's own format (`kAudioStreamPropertyVirtualFormat`), i.e.
+/// what the AudioUnit's converter translates to and from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NativeStreamFormat {
+    pub sample_rate: f64,
+    pub bits_per_channel: u32,
+    pub channels_per_frame: u32,
+    /// Four-char code, `kAudioFormatLinearPCM` for every device Shifter can use.
+    pub format_id: u32,
+    pub format_flags: u32,
+}
+
+impl NativeStreamFormat {
+    pub fn is_linear_pcm(&self) -> bool {
+        self.format_id == kAudioFormatLinearPCM
+    }
+
+    pub fn is_float(&self) -> bool {
+        self.format_flags & kAudioFormatFlagIsFloat != 0
+    }
+
+    /// The matching `SampleFormat`, or `None` for non-PCM and odd sample sizes.
+    pub fn sample_format(&self) -> Option<SampleFormat> {
+        if !self.is_linear_pcm() {
+            return None;
+        }
+        match (self.is_float(), self.bits_per_channel) {
+            (true, 32) => Some(SampleFormat::F32),
+            (false, 32) => Some(SampleFormat::I32),
+            (false, 24) => Some(SampleFormat::I24),
+            (false, 16) => Some(SampleFormat::I16),
+            (false, 8) => Some(SampleFormat::I8),
+            _ => None,
+        }
+    }
+
+    /// Short description for the banner, e.g. "24-bit int" or "'aac ' (not PCM)".
+    pub fn label(&self) -> String {
+        if !self.is_linear_pcm() {
+            return format!("'{}' (not PCM)", four_char_code(self.format_id));
+        }
+        let kind = if self.is_float() { "float" } else { "int" };
+        format!("{}-bit {kind}", self.bits_per_channel)
+    }
+}
+
+/// `0x6c70636d` -> "lpcm". Unprintable bytes become '?'.
+fn four_char_code(code: u32) -> String {
+    code.to_be_bytes()
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcm_formats_map_to_sample_formats() {
+        let float = NativeStreamFormat {
+            sample_rate: 48_000.0,
+            bits_per_channel: 32,
+            channels_per_frame: 2,
+            format_id: kAudioFormatLinearPCM,
+            format_flags: kAudioFormatFlagIsFloat,
+        };
+        assert_eq!(float.sample_format(), Some(SampleFormat::F32));
+        assert_eq!(float.label(), "32-bit float");
+        let int24 = NativeStreamFormat {
+            bits_per_channel: 24,
+            format_flags: 0,
+            ..float
+        };
+        assert_eq!(int24.sample_format(), Some(SampleFormat::I24));
+        assert_eq!(int24.label(), "24-bit int");
+        let int20 = NativeStreamFormat {
+            bits_per_channel: 20,
+            ..int24
+        };
+        assert_eq!(int20.sample_format(), None);
+    }
+
+    #[test]
+    fn non_pcm_is_named_by_its_code() {
+        let aac = NativeStreamFormat {
+            sample_rate: 48_000.0,
+            bits_per_channel: 0,
+            channels_per_frame: 2,
+            format_id: 0x61616320,
+            format_flags: 0,
+        };
+        assert_eq!(aac.sample_format(), None);
+        assert_eq!(aac.label(), "'aac ' (not PCM)");
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+
+use crate::platform;
+use crate::playback::controller::PlaybackController;
+
+/// How often the writer thread drains the sink ring.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Sample encoding for `--output-raw`. All formats are interleaved and
+/// little-endian, with no header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RawFormat {
+    /// 32-bit IEEE float, 4 bytes per sample, nominal range -1.0..=1.0.
+    #[value(name = "f32le")]
+    F32,
+    /// 16-bit signed integer, 2 bytes per sample, TPDF-dithered.
+    #[value(name = "s16le")]
+    S16,
+    /// 24-bit signed integer, 3 packed bytes per sample, TPDF-dithered.
+    #[value(name = "s24le")]
+    S24,
+}
+
+impl RawFormat {
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            RawFormat::F32 => 4,
+            RawFormat::S16 => 2,
+            RawFormat::S24 => 3,
+        }
+    }
+
+    /// The format name, which is also ffmpeg's `-f` name.
+    pub fn name(self) -> &'static str {
+        match self {
+            RawFormat::F32 => "f32le",
+            RawFormat::S16 => "s16le",
+            RawFormat::S24 => "s24le",
+        }
+    }
+}
+
+/// Converts sink blocks to raw PCM bytes.
+///
+/// Samples dropped because the writer fell behind are written as silence, so
+/// the byte stream stays in step with the output clock.
+pub struct RawEncoder {
+    format: RawFormat,
+    /// Stream position of the next sample to encode.
+    next_position: usize,
+    /// xorshift32 state for the dither noise.
+    rng: u32,
+}
+
+impl RawEncoder {
+    pub fn new(format: RawFormat) -> Self {
+        Self {
+            format,
+            next_position: 0,
+            rng: 0x9E37_79B9,
+        }
+    }
+
+    /// Appends the encoding of a block starting at stream `position` to `out`.
+    pub fn encode(&mut self, position: usize, samples: &[f32], out: &mut Vec<u8>) {
+        let gap = position.saturating_sub(self.next_position);
+        out.resize(out.len() + gap * self.format.bytes_per_sample(), 0);
+        for &s in samples {
+            self.encode_sample(s, out);
+        }
+        self.next_position = position + samples.len();
+    }
+
+    fn encode_sample(&mut self, s: f32, out: &mut Vec<u8>) {
+        match self.format {
+            RawFormat::F32 => out.extend_from_slice(&s.to_le_bytes()),
+            RawFormat::S16 => {
+                let v = self.quantize(s, i16::MAX as f64) as i16;
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            RawFormat::S24 => {
+                let v = self.quantize(s, 8_388_607.0);
+                out.extend_from_slice(&v.to_le_bytes()[..3]);
+            }
+        }
+    }
+
+    /// Scales to `full_scale`, adds triangular (TPDF) dither of ±1 LSB, rounds
+    /// and clamps.
+    fn quantize(&mut self, s: f32, full_scale: f64) -> i32 {
+        let dither = self.uniform() - self.uniform();
+        let v = (s as f64 * full_scale + dither).round();
+        v.clamp(-full_scale - 1.0, full_scale) as i32
+    }
+
+    /// Uniform noise in 0.0..1.0.
+    fn uniform(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng as f64 / (u32::MAX as f64 + 1.0)
+    }
+}
+
+/// Opens `path` and starts the writer thread, which drains the controller's
+/// sink ring into it. Write errors stop the sink and are reported on `notices`.
+pub fn spawn(
+    controller: Arc<PlaybackController>,
+    path: &Path,
+    format: RawFormat,
+    notices: Sender<String>,
+) -> Result<()> {
+    let file = File::create(path)
+        .map_err(|e| anyhow!("Failed to open raw output '{}': {e}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    std::thread::Builder::new()
+        .name("shifter-raw-sink".into())
+        .spawn(move || {
+            platform::raise_thread_qos();
+            let Some(sink) = controller.sink_blocks.as_ref() else {
+                return;
+            };
+            let mut encoder = RawEncoder::new(format);
+            let mut bytes = Vec::new();
+            loop {
+                bytes.clear();
+                sink.drain(|block| encoder.encode(block.position, block.samples(), &mut bytes));
+                if let Err(e) = writer.write_all(&bytes).and_then(|_| writer.flush()) {
+                    let _ = notices.send(format!("Raw output stopped: {e}"));
+                    return;
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start raw output thread: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f32le_is_the_raw_sample_bytes() {
+        let mut encoder = RawEncoder::new(RawFormat::F32);
+        let mut out = Vec::new();
+        encoder.encode(0, &[0.5, -1.0], &mut out);
+        assert_eq!(out, [0, 0, 0, 0x3F, 0, 0, 0x80, 0xBF]);
+    }
+
+    #[test]
+    fn s16le_clamps_full_scale() {
+        let mut encoder = RawEncoder::new(RawFormat::S16);
+        let mut out = Vec::new();
+        encoder.encode(0, &[2.0, -2.0], &mut out);
+        assert_eq!(out, [0xFF, 0x7F, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn s24le_packs_three_bytes_per_sample() {
+        let mut encoder = RawEncoder::new(RawFormat::S24);
+        let mut out = Vec::new();
+        encoder.encode(0, &[2.0, -2.0], &mut out);
+        assert_eq!(out, [0xFF, 0xFF, 0x7F, 0x00, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn dither_stays_within_one_lsb() {
+        let mut encoder = RawEncoder::new(RawFormat::S16);
+        let mut out = Vec::new();
+        encoder.encode(0, &[0.25; 1000], &mut out);
+        let exact = 0.25 * i16::MAX as f32;
+        for pair in out.chunks(2) {
+            let v = i16::from_le_bytes([pair[0], pair[1]]) as f32;
+            assert!((v - exact).abs() <= 1.5, "{v}");
+        }
+    }
+
+    #[test]
+    fn dropped_samples_are_written_as_silence() {
+        let mut encoder = RawEncoder::new(RawFormat::F32);
+        let mut out = Vec::new();
+        encoder.encode(0, &[1.0], &mut out);
+        encoder.encode(3, &[1.0], &mut out);
+        let samples: Vec<f32> = out
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(samples, [1.0, 0.0, 0.0, 1.0]);
+    }
+}
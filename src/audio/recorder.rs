@@ -0,0 +1,491 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use crate::control::wallclock;
+use crate::platform;
+use crate::playback::controller::PlaybackController;
+use crate::playback::events::ShifterEvent;
+use crate::playback::state::PlaybackState;
+use crate::wav::{WavSpec, WavWriter};
+
+/// How often the recorder thread drains the sink ring.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Extension of a segment until it is finished and renamed into place.
+const PART_EXTENSION: &str = "wav.part";
+
+/// What splits the recording besides the split key and command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SplitTriggers {
+    /// `--split-on-bookmark`: every bookmark, manual or automatic.
+    pub on_bookmark: bool,
+    /// `--split-after-pause-s`: playback paused this long. The pause itself
+    /// is left out of both files.
+    pub after_pause: Option<Duration>,
+}
+
+/// One file of the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// 1-based, in recording order.
+    pub index: usize,
+    /// Where the file ends up once finished.
+    pub path: PathBuf,
+    pub frames: usize,
+    /// Whether the header is final and the file renamed into place.
+    pub finished: bool,
+}
+
+/// Writes the recording as consecutive WAV files in a directory, named
+/// `segment-<index>-<HH-MM-SS>.wav` after when they were started. A segment
+/// is written as `.wav.part` and renamed once its header is final, so a
+/// `.wav` in the directory is always complete.
+pub struct SegmentWriter {
+    dir: PathBuf,
+    spec: WavSpec,
+    /// The open segment and its `.part` path.
+    current: Option<(WavWriter, PathBuf)>,
+    segments: Arc<Mutex<Vec<Segment>>>,
+}
+
+impl SegmentWriter {
+    /// Creates `dir` if missing. No file is opened before the first write.
+    pub fn new(dir: &Path, spec: WavSpec) -> Result<Self> {
+        fs::create_dir_all(dir).map_err(|e| anyhow!("Failed to create {}: {e}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            spec,
+            current: None,
+            segments: Arc::default(),
+        })
+    }
+
+    /// Appends interleaved samples to the open segment, starting one if
+    /// there is none.
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        if self.current.is_none() {
+            self.open()?;
+        }
+        if let Some((writer, _)) = &mut self.current {
+            writer.write(samples)?;
+        }
+        let frames = samples.len() / self.spec.channels.max(1) as usize;
+        if let Ok(mut segments) = self.segments.lock()
+            && let Some(segment) = segments.last_mut()
+        {
+            segment.frames += frames;
+        }
+        Ok(())
+    }
+
+    /// Appends `len` samples of silence.
+    pub fn write_silence(&mut self, mut len: usize) -> Result<()> {
+        const ZEROS: [f32; 1024] = [0.0; 1024];
+        while len > 0 {
+            let chunk = len.min(ZEROS.len());
+            self.write(&ZEROS[..chunk])?;
+            len -= chunk;
+        }
+        Ok(())
+    }
+
+    /// Finishes the open segment: its header is written before the next
+    /// write opens another. Nothing happens if no segment is open.
+    pub fn split(&mut self) -> Result<()> {
+        let Some((writer, part)) = self.current.take() else {
+            return Ok(());
+        };
+        writer.finish()?;
+        let mut segments = self
+            .segments
+            .lock()
+            .map_err(|_| anyhow!("Segment list poisoned"))?;
+        if let Some(segment) = segments.last_mut() {
+            fs::rename(&part, &segment.path)
+                .map_err(|e| anyhow!("Failed to rename {}: {e}", part.display()))?;
+            segment.finished = true;
+        }
+        Ok(())
+    }
+
+    /// The segments so far, shared with whoever shows them.
+    pub fn segments(&self) -> Arc<Mutex<Vec<Segment>>> {
+        self.segments.clone()
+    }
+
+    fn open(&mut self) -> Result<()> {
+        let mut segments = self
+            .segments
+            .lock()
+            .map_err(|_| anyhow!("Segment list poisoned"))?;
+        let index = segments.len() + 1;
+        let name = format!(
+            "segment-{index:03}-{}.wav",
+            time_label(wallclock::local_seconds_of_day())
+        );
+        let path = self.dir.join(name);
+        let part = path.with_extension(PART_EXTENSION);
+        self.current = Some((WavWriter::create(&part, self.spec)?, part));
+        segments.push(Segment {
+            index,
+            path,
+            frames: 0,
+            finished: false,
+        });
+        Ok(())
+    }
+}
+
+/// "14-30-05" for seconds since midnight, for file names.
+fn time_label(seconds: f64) -> String {
+    let s = seconds.max(0.0) as u64;
+    format!("{:02}-{:02}-{:02}", s / 3600 % 24, s % 3600 / 60, s % 60)
+}
+
+/// The `--record-dir` recorder: a thread draining the controller's sink
+/// ring into `SegmentWriter` files, splitting on request and on the
+/// configured triggers. Dropping it stops the thread and finishes the last
+/// file.
+pub struct Recorder {
+    segments: Arc<Mutex<Vec<Segment>>>,
+    spec: WavSpec,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Recorder {
+    /// Starts recording what plays into `dir`. Write errors stop the
+    /// recording and are reported on `notices`.
+    pub fn spawn(
+        controller: Arc<PlaybackController>,
+        dir: &Path,
+        spec: WavSpec,
+        triggers: SplitTriggers,
+        notices: Sender<String>,
+    ) -> Result<Self> {
+        let mut writer = SegmentWriter::new(dir, spec)?;
+        let segments = writer.segments();
+        let stop = Arc::new(AtomicBool::new(false));
+        // Subscribed here so no bookmark is missed while the thread starts.
+        let events = controller.events.subscribe();
+        controller.set_recording(true);
+        let thread_stop = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("shifter-recorder".into())
+            .spawn(move || {
+                platform::raise_thread_qos();
+                let mut state = RecorderState::new(&controller, triggers);
+                let mut result = Ok(());
+                while result.is_ok() {
+                    // Drain once more after a stop so the end isn't lost.
+                    let stopping = thread_stop.load(Ordering::Acquire);
+                    let bookmarked = events
+                        .try_iter()
+                        .filter(|event| matches!(event, ShifterEvent::Bookmarked { .. }))
+                        .count()
+                        > 0;
+                    result = state.step(&controller, &mut writer, bookmarked, Instant::now());
+                    if stopping {
+                        break;
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                controller.set_recording(false);
+                if let Err(e) = result.and_then(|_| writer.split()) {
+                    let _ = notices.send(format!("Recording stopped: {e}"));
+                }
+            })
+            .map_err(|e| anyhow!("Failed to start recorder thread: {e}"))?;
+        Ok(Self {
+            segments,
+            spec,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The files recorded so far, the last one possibly still open.
+    pub fn segments(&self) -> Vec<Segment> {
+        self.segments
+            .lock()
+            .map(|segments| segments.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.spec.sample_rate
+    }
+
+    /// Samples (frames times channels) written across every file.
+    pub fn samples_written(&self) -> u64 {
+        let frames: usize = self.segments().iter().map(|s| s.frames).sum();
+        frames as u64 * self.spec.channels as u64
+    }
+
+    /// Audio bytes written across every file, headers left out.
+    pub fn bytes_written(&self) -> u64 {
+        self.samples_written() * self.spec.format.bytes_per_sample() as u64
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// What the recorder thread keeps between polls.
+struct RecorderState {
+    triggers: SplitTriggers,
+    /// `split_requests` already handled.
+    splits_seen: usize,
+    /// Sink position the next block should start at; a later one means
+    /// blocks were dropped, written as silence to keep the timing.
+    next_position: Option<usize>,
+    /// When playback was seen paused, while it stays paused.
+    paused_since: Option<Instant>,
+    /// Silent samples held back during a pause, written if playback resumes
+    /// before `after_pause` and dropped if it doesn't.
+    held: usize,
+    /// The pause outlasted `after_pause`: its silence is dropped.
+    pause_split: bool,
+}
+
+impl RecorderState {
+    fn new(controller: &PlaybackController, triggers: SplitTriggers) -> Self {
+        Self {
+            triggers,
+            splits_seen: controller.split_requests(),
+            next_position: None,
+            paused_since: None,
+            held: 0,
+            pause_split: false,
+        }
+    }
+
+    /// One poll: handles split requests and triggers, then writes the
+    /// pending sink blocks.
+    fn step(
+        &mut self,
+        controller: &PlaybackController,
+        writer: &mut SegmentWriter,
+        bookmarked: bool,
+        now: Instant,
+    ) -> Result<()> {
+        let splits = controller.split_requests();
+        if splits != self.splits_seen || (bookmarked && self.triggers.on_bookmark) {
+            self.splits_seen = splits;
+            writer.split()?;
+        }
+
+        if controller.state() == PlaybackState::Paused {
+            let since = *self.paused_since.get_or_insert(now);
+            if let Some(limit) = self.triggers.after_pause
+                && !self.pause_split
+                && now.saturating_duration_since(since) >= limit
+            {
+                self.pause_split = true;
+                self.held = 0;
+                writer.split()?;
+            }
+        } else {
+            self.paused_since = None;
+            self.pause_split = false;
+        }
+
+        let Some(sink) = controller.sink_blocks.as_ref() else {
+            return Ok(());
+        };
+        let hold_silence = self.triggers.after_pause.is_some() && self.paused_since.is_some();
+        let mut result = Ok(());
+        sink.drain(|block| {
+            if result.is_ok() {
+                result = self.block(writer, block.position, block.samples(), hold_silence);
+            }
+        });
+        result
+    }
+
+    fn block(
+        &mut self,
+        writer: &mut SegmentWriter,
+        position: usize,
+        samples: &[f32],
+        hold_silence: bool,
+    ) -> Result<()> {
+        let gap = self
+            .next_position
+            .map_or(0, |next| position.saturating_sub(next));
+        self.next_position = Some(position + samples.len());
+        if hold_silence && samples.iter().all(|&s| s == 0.0) {
+            if !self.pause_split {
+                self.held += gap + samples.len();
+            }
+            return Ok(());
+        }
+        writer.write_silence(std::mem::take(&mut self.held) + gap)?;
+        writer.write(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::audio::raw_sink::RawFormat;
+    use crate::audio::ring_buffer::AudioRingBuffer;
+    use crate::wav::WavReader;
+
+    #[test]
+    fn splitting_during_writes_is_gapless() {
+        let dir =
+            std::env::temp_dir().join(format!("shifter-test-{}-record-split", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 1000,
+            format: RawFormat::F32,
+        };
+        let ring = Arc::new(AudioRingBuffer::new(4_000));
+        let ctrl = Arc::new(PlaybackController::new(ring, 2, 1000).with_sink());
+        let (notice_tx, notices) = mpsc::channel();
+        let recorder = Recorder::spawn(
+            ctrl.clone(),
+            &dir,
+            spec,
+            SplitTriggers::default(),
+            notice_tx,
+        )
+        .unwrap();
+        let sink = ctrl.sink_blocks.as_ref().unwrap();
+
+        // A ramp, so any lost or repeated sample shows at the boundary.
+        let ramp: Vec<f32> = (0..6_000).map(|i| i as f32 / 6_000.0).collect();
+        for (i, block) in ramp.chunks(100).enumerate() {
+            sink.push(block);
+            if i == 20 {
+                for _ in 0..500 {
+                    if recorder.segments().iter().map(|s| s.frames).sum::<usize>() >= 1_000 {
+                        break;
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                assert!(ctrl.request_split());
+            }
+            if i % 10 == 0 {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        // Wait for the recorder to catch up with everything pushed.
+        for _ in 0..500 {
+            if recorder.segments().iter().map(|s| s.frames).sum::<usize>() >= 3_000 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let segments = recorder.segments();
+        drop(recorder);
+        assert!(notices.try_recv().is_err());
+        assert!(!ctrl.request_split());
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].index, 1);
+        assert!(segments[0].finished);
+        let name = segments[1].path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("segment-002-") && name.ends_with(".wav"));
+        let mut reader = WavReader::open(&segments[0].path).unwrap();
+        let mut first = vec![0.0; reader.frames() as usize * 2];
+        reader.read(&mut first).unwrap();
+        let mut reader = WavReader::open(&segments[1].path).unwrap();
+        let mut second = vec![0.0; reader.frames() as usize * 2];
+        reader.read(&mut second).unwrap();
+        assert!(first.len() >= 2_000 && !second.is_empty());
+        assert_eq!(first.len() + second.len(), ramp.len());
+        assert_eq!([first, second].concat(), ramp);
+        assert_eq!(
+            fs::read_dir(&dir).unwrap().count(),
+            2,
+            "no .part files left"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn long_pauses_and_bookmarks_split_short_pauses_do_not() {
+        let dir =
+            std::env::temp_dir().join(format!("shifter-test-{}-record-pause", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 1000,
+            format: RawFormat::F32,
+        };
+        let ring = Arc::new(AudioRingBuffer::new(4_000));
+        let ctrl = PlaybackController::new(ring, 2, 1000).with_sink();
+        let mut writer = SegmentWriter::new(&dir, spec).unwrap();
+        let triggers = SplitTriggers {
+            on_bookmark: true,
+            after_pause: Some(Duration::from_secs(5)),
+        };
+        let mut state = RecorderState::new(&ctrl, triggers);
+        let sink = ctrl.sink_blocks.as_ref().unwrap();
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        let mut step = |state: &mut RecorderState, block: &[f32], s: u64, marked: bool| {
+            sink.push(block);
+            state.step(&ctrl, &mut writer, marked, at(s)).unwrap();
+        };
+
+        step(&mut state, &[0.5; 10], 0, false);
+        // A 2s pause: its silence stays in the file.
+        ctrl.toggle_pause();
+        step(&mut state, &[0.0; 10], 1, false);
+        step(&mut state, &[0.0; 10], 3, false);
+        ctrl.toggle_pause();
+        step(&mut state, &[0.5; 10], 3, false);
+        // A 6s pause: the file ends before it, the next starts on resume.
+        ctrl.toggle_pause();
+        step(&mut state, &[0.0; 10], 4, false);
+        step(&mut state, &[0.0; 10], 10, false);
+        step(&mut state, &[0.0; 10], 11, false);
+        ctrl.toggle_pause();
+        step(&mut state, &[0.25; 10], 12, false);
+        step(&mut state, &[0.25; 10], 13, true);
+        writer.split().unwrap();
+
+        let segments = writer.segments().lock().unwrap().clone();
+        let frames: Vec<usize> = segments.iter().map(|s| s.frames).collect();
+        assert_eq!(frames, [20, 5, 5]);
+        assert!(segments.iter().all(|s| s.finished));
+        let mut reader = WavReader::open(&segments[0].path).unwrap();
+        let mut first = vec![0.0; reader.frames() as usize * 2];
+        reader.read(&mut first).unwrap();
+        assert_eq!(first[..10], [0.5; 10]);
+        assert_eq!(first[10..30], [0.0; 20]);
+        let mut reader = WavReader::open(&segments[1].path).unwrap();
+        let mut second = vec![0.0; reader.frames() as usize * 2];
+        reader.read(&mut second).unwrap();
+        assert_eq!(second, [0.25; 10]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_names_carry_the_time_of_day() {
+        assert_eq!(time_label(52_205.9), "14-30-05");
+        assert_eq!(time_label(-1.0), "00-00-00");
+    }
+}
@@ -1,5 +1,13 @@
-use std::cell::UnsafeCell;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+use crate::audio::source::AudioSource;
+
+/// Most holds taken at once; `hold` fails past it.
+pub const MAX_HOLDS: usize = 8;
+
+/// A free `holds` slot.
+const NO_HOLD: usize = usize::MAX;
 
 /// Result of a read operation on the ring buffer.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,8 +28,17 @@ pub enum ReadResult {
 ///
 /// Positions are absolute sample counts (monotonically increasing).
 /// Physical index = `absolute_position % capacity`.
+///
+/// Samples are stored as `f32` bits in `AtomicU32`s, so the buffer is safe to
+/// share. Accesses are `Relaxed`: the producer's `Release` store of
+/// `write_pos` publishes them, and the capacity check keeps the producer out
+/// of the region being read.
+///
+/// Other consumers (exports, disk spill) can take a `RingHold` on the oldest
+/// position they still need. The writer treats holds like the read position:
+/// a write that would overwrite one is dropped.
 pub struct AudioRingBuffer {
-    buffer: Box<[UnsafeCell<f32>]>,
+    buffer: Box<[AtomicU32]>,
     capacity: usize,
     /// Absolute write position (total interleaved samples written since start).
     write_pos: AtomicUsize,
@@ -29,51 +46,137 @@ pub struct AudioRingBuffer {
     read_pos: AtomicUsize,
     /// Whether the input stream has started writing data.
     active: AtomicBool,
+    /// Held positions (`NO_HOLD` when free), see `hold`.
+    holds: [AtomicUsize; MAX_HOLDS],
+    /// End of the write in progress (or the last one), announced before the
+    /// writer checks the holds.
+    write_claim: AtomicUsize,
+    /// Samples in the last write: how far the writer moves at once.
+    last_write_len: AtomicUsize,
+}
+
+/// Keeps the writer from overwriting samples from `position` on. Released
+/// on drop.
+pub struct RingHold {
+    ring: Arc<AudioRingBuffer>,
+    slot: usize,
 }
 
-// SAFETY: The producer (input callback) and consumer (output callback)
-// access different regions of the buffer. The producer writes ahead of the
-// consumer, and the buffer is sized to ensure they never overlap.
-unsafe impl Send for AudioRingBuffer {}
-unsafe impl Sync for AudioRingBuffer {}
+impl RingHold {
+    /// Moves the hold forward to `position`; it never moves back.
+    #[allow(dead_code)] // For exports and disk spill; nothing takes a hold yet.
+    pub fn advance(&self, position: usize) {
+        self.ring.holds[self.slot].fetch_max(position, Ordering::SeqCst);
+    }
+}
+
+impl Drop for RingHold {
+    fn drop(&mut self) {
+        self.ring.holds[self.slot].store(NO_HOLD, Ordering::SeqCst);
+    }
+}
 
 impl AudioRingBuffer {
     /// Create a new ring buffer with the given capacity in interleaved samples.
     pub fn new(capacity: usize) -> Self {
-        let mut buf = Vec::with_capacity(capacity);
-        for _ in 0..capacity {
-            buf.push(UnsafeCell::new(0.0));
-        }
         Self {
-            buffer: buf.into_boxed_slice(),
+            // 0 is the bit pattern of 0.0.
+            buffer: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
             capacity,
             write_pos: AtomicUsize::new(0),
             read_pos: AtomicUsize::new(0),
             active: AtomicBool::new(false),
+            holds: std::array::from_fn(|_| AtomicUsize::new(NO_HOLD)),
+            write_claim: AtomicUsize::new(0),
+            last_write_len: AtomicUsize::new(0),
         }
     }
 
+    /// Start and byte length of the sample storage, for pinning it in RAM.
+    pub fn allocation(&self) -> (*const u8, usize) {
+        (
+            self.buffer.as_ptr().cast(),
+            std::mem::size_of_val(&*self.buffer),
+        )
+    }
+
     /// Called by the input callback. Writes interleaved samples into the buffer.
     /// If the buffer is full (write would overwrite unread data), the write is
     /// dropped to preserve buffered audio — correct behavior for a DVR on pause.
     pub fn write(&self, data: &[f32]) {
+        let Some(wp) = self.claim(data.len()) else {
+            return; // buffer full — drop incoming samples
+        };
+        // `data.len() <= capacity` (checked above), so the region wraps at most once.
+        let start = wp % self.capacity;
+        let first_len = data.len().min(self.capacity - start);
+        let (head, tail) = data.split_at(first_len);
+        store(&self.buffer[start..start + first_len], head);
+        store(&self.buffer[..tail.len()], tail);
+        self.commit(wp, data.len());
+    }
+
+    /// Announces a write of `len` samples and returns where it starts, or
+    /// `None` if it would overwrite unread or held samples. The claim is
+    /// stored before the holds are loaded and `hold` does the reverse, so
+    /// either the writer sees a new hold or `hold` sees the claim.
+    fn claim(&self, len: usize) -> Option<usize> {
         let wp = self.write_pos.load(Ordering::Relaxed);
+        self.write_claim.store(wp + len, Ordering::SeqCst);
         let rp = self.read_pos.load(Ordering::Acquire);
-        if wp + data.len() > rp + self.capacity {
-            return; // buffer full — drop incoming samples
-        }
-        for (i, &sample) in data.iter().enumerate() {
-            let idx = (wp + i) % self.capacity;
-            // SAFETY: only the producer writes; consumer reads at a different
-            // region guaranteed by the capacity constraint.
-            unsafe {
-                *self.buffer[idx].get() = sample;
-            }
+        if wp + len > self.floor(rp) + self.capacity {
+            self.write_claim.store(wp, Ordering::SeqCst);
+            return None;
         }
-        self.write_pos.store(wp + data.len(), Ordering::Release);
+        Some(wp)
+    }
+
+    /// Publishes a write of `len` samples from `wp`.
+    fn commit(&self, wp: usize, len: usize) {
+        self.write_pos.store(wp + len, Ordering::Release);
+        self.last_write_len.store(len, Ordering::Relaxed);
         self.active.store(true, Ordering::Relaxed);
     }
 
+    /// Oldest position the writer must keep: the read position or a hold.
+    fn floor(&self, rp: usize) -> usize {
+        self.holds
+            .iter()
+            .map(|hold| hold.load(Ordering::SeqCst))
+            .fold(rp, usize::min)
+    }
+
+    /// Holds the samples from `position` on until the returned hold is
+    /// dropped or advanced; while held, writes that would overwrite them are
+    /// dropped. `None` if `position` is already overwritten (or about to be)
+    /// or `MAX_HOLDS` are taken.
+    #[allow(dead_code)] // For exports and disk spill; nothing takes a hold yet.
+    pub fn hold(self: &Arc<Self>, position: usize) -> Option<RingHold> {
+        let slot = self.holds.iter().position(|hold| {
+            hold.compare_exchange(NO_HOLD, position, Ordering::SeqCst, Ordering::Relaxed)
+                .is_ok()
+        })?;
+        let hold = RingHold {
+            ring: self.clone(),
+            slot,
+        };
+        // Dropping `hold` releases the slot.
+        (position + self.capacity >= self.write_claim.load(Ordering::SeqCst)).then_some(hold)
+    }
+
+    /// Oldest position a reader without a hold can start copying from: the
+    /// writer may overwrite the oldest samples with its next write, so that
+    /// much is left out, unless the read position or a hold keeps it back.
+    pub fn safe_oldest(&self) -> usize {
+        let wp = self.write_pos.load(Ordering::Acquire);
+        let next_end = wp + self.last_write_len.load(Ordering::Relaxed);
+        let floor = self.floor(self.read_pos.load(Ordering::Acquire));
+        next_end
+            .min(floor.saturating_add(self.capacity))
+            .saturating_sub(self.capacity)
+            .clamp(wp.saturating_sub(self.capacity), wp)
+    }
+
     /// Called by the output callback. Reads `output.len()` samples starting
     /// at the current `read_pos` and advances `read_pos`.
     pub fn read(&self, output: &mut [f32]) -> ReadResult {
@@ -105,22 +208,47 @@ impl AudioRingBuffer {
             return ReadResult::Underrun;
         }
 
-        for (i, sample) in output.iter_mut().enumerate() {
-            let idx = (rp + i) % self.capacity;
-            // SAFETY: producer writes ahead; this region is stable.
-            unsafe {
-                *sample = *self.buffer[idx].get();
+        if !self.read_at(rp, output) {
+            for s in output.iter_mut() {
+                *s = 0.0;
             }
+            return ReadResult::Underrun;
         }
         self.read_pos.store(rp + output.len(), Ordering::Release);
         ReadResult::Ok
     }
 
+    /// Copies the samples `[abs_pos, abs_pos + output.len())` into `output`
+    /// without touching the read position.
+    ///
+    /// Returns false (leaving `output` as it was) if any part of the region
+    /// hasn't been written yet or has already been overwritten. The producer
+    /// overwrites a region once it laps it (`capacity` samples later), so
+    /// regions near the oldest end can change while being copied.
+    pub fn read_at(&self, abs_pos: usize, output: &mut [f32]) -> bool {
+        let len = output.len();
+        let wp = self.write_pos.load(Ordering::Acquire);
+        if len > self.capacity || abs_pos + len > wp || abs_pos < wp.saturating_sub(self.capacity) {
+            return false;
+        }
+        let start = abs_pos % self.capacity;
+        let first_len = len.min(self.capacity - start);
+        let (head, tail) = output.split_at_mut(first_len);
+        load(&self.buffer[start..start + first_len], head);
+        load(&self.buffer[..tail.len()], tail);
+        true
+    }
+
     /// Returns the current absolute write position.
     pub fn write_position(&self) -> usize {
         self.write_pos.load(Ordering::Acquire)
     }
 
+    /// Returns the current absolute read position.
+    pub fn read_position(&self) -> usize {
+        self.read_pos.load(Ordering::Acquire)
+    }
+
     /// Sets the read position. Called by the controller on seek/jump-to-live.
     pub fn set_read_position(&self, pos: usize) {
         self.read_pos.store(pos, Ordering::Release);
@@ -143,6 +271,84 @@ impl AudioRingBuffer {
     pub fn capacity(&self) -> usize {
         self.capacity
     }
+
+    /// Memory the samples take, in bytes.
+    pub fn capacity_bytes(&self) -> usize {
+        self.capacity * std::mem::size_of::<f32>()
+    }
+
+    /// Memory the samples take, in MB (MiB), for display.
+    pub fn capacity_mb(&self) -> f64 {
+        self.capacity_bytes() as f64 / (1024.0 * 1024.0)
+    }
+}
+
+// Delegates to the inherent methods, so callers holding the concrete type
+// need no import.
+impl AudioSource for AudioRingBuffer {
+    fn write(&self, data: &[f32]) {
+        AudioRingBuffer::write(self, data);
+    }
+
+    fn read(&self, output: &mut [f32]) -> ReadResult {
+        AudioRingBuffer::read(self, output)
+    }
+
+    fn read_at(&self, position: usize, output: &mut [f32]) -> bool {
+        AudioRingBuffer::read_at(self, position, output)
+    }
+
+    fn write_position(&self) -> usize {
+        AudioRingBuffer::write_position(self)
+    }
+
+    fn read_position(&self) -> usize {
+        AudioRingBuffer::read_position(self)
+    }
+
+    fn set_read_position(&self, position: usize) {
+        AudioRingBuffer::set_read_position(self, position);
+    }
+
+    fn delay_samples(&self) -> usize {
+        AudioRingBuffer::delay_samples(self)
+    }
+
+    fn usage_fraction(&self) -> f64 {
+        AudioRingBuffer::usage_fraction(self)
+    }
+
+    fn capacity(&self) -> usize {
+        AudioRingBuffer::capacity(self)
+    }
+
+    fn capacity_mb(&self) -> f64 {
+        AudioRingBuffer::capacity_mb(self)
+    }
+
+    fn safe_oldest(&self) -> usize {
+        AudioRingBuffer::safe_oldest(self)
+    }
+}
+
+/// Stores `samples` into equally long `cells`.
+///
+/// LLVM never vectorizes atomic stores, so this runs one store per sample, at
+/// 1.5-2.5x the cost of a memcpy into plain `f32`s (`ring_buffer_bench`). That
+/// is about 1ns per sample, 1µs of a 10ms callback, and buys a ring whose
+/// slots can be read while written without a data race. A non-atomic bulk
+/// copy would bring the race back.
+fn store(cells: &[AtomicU32], samples: &[f32]) {
+    for (cell, &sample) in cells.iter().zip(samples) {
+        cell.store(sample.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Loads equally long `cells` into `samples`.
+fn load(cells: &[AtomicU32], samples: &mut [f32]) {
+    for (cell, sample) in cells.iter().zip(samples) {
+        *sample = f32::from_bits(cell.load(Ordering::Relaxed));
+    }
 }
 
 #[cfg(test)]
@@ -161,6 +367,14 @@ mod tests {
         assert_eq!(output, [1.0, 2.0, 3.0, 4.0]);
     }
 
+    #[test]
+    fn capacity_in_bytes_and_megabytes() {
+        // 60s of 48kHz stereo.
+        let rb = AudioRingBuffer::new(5_760_000);
+        assert_eq!(rb.capacity_bytes(), 23_040_000);
+        assert!((rb.capacity_mb() - 21.972_656_25).abs() < 1e-9);
+    }
+
     #[test]
     fn underrun_before_write() {
         let rb = AudioRingBuffer::new(1024);
@@ -190,6 +404,113 @@ mod tests {
         assert_eq!(output2, [7.0, 8.0, 9.0, 10.0]);
     }
 
+    #[test]
+    fn single_write_across_the_end() {
+        let rb = AudioRingBuffer::new(8);
+        rb.write(&[0.0; 5]);
+        let mut out = [0.0_f32; 5];
+        rb.read(&mut out);
+
+        // Starts at index 5: three samples at the end, five at the front.
+        let input: Vec<f32> = (1..=8).map(|i| i as f32).collect();
+        rb.write(&input);
+        let mut out = [0.0_f32; 8];
+        assert_eq!(rb.read(&mut out), ReadResult::Ok);
+        assert_eq!(out.to_vec(), input);
+    }
+
+    #[test]
+    fn holds_keep_the_writer_back_until_released() {
+        let rb = Arc::new(AudioRingBuffer::new(8));
+        rb.write(&[0.0; 6]);
+        rb.set_read_position(6);
+        let hold = rb.hold(2).unwrap();
+        rb.write(&[1.0; 4]);
+        assert_eq!(rb.write_position(), 10);
+        // Would overwrite position 2.
+        rb.write(&[2.0; 2]);
+        assert_eq!(rb.write_position(), 10);
+        assert_eq!(rb.safe_oldest(), 2);
+
+        hold.advance(4);
+        hold.advance(3);
+        rb.write(&[2.0; 2]);
+        assert_eq!(rb.write_position(), 12);
+        drop(hold);
+        // Unheld and live: the next write may lap two more samples.
+        rb.set_read_position(12);
+        assert_eq!(rb.safe_oldest(), 6);
+
+        // Overwritten already, or as far as the writer has claimed.
+        assert!(rb.hold(3).is_none());
+        assert!(rb.hold(4).is_some());
+        let holds: Vec<RingHold> = (0..MAX_HOLDS).map_while(|_| rb.hold(10)).collect();
+        assert_eq!(holds.len(), MAX_HOLDS);
+        assert!(rb.hold(10).is_none());
+    }
+
+    #[test]
+    fn held_regions_stay_intact_while_the_writer_laps_the_buffer() {
+        const CAPACITY: usize = 1_000;
+        const READERS: usize = 4;
+        const WINDOW: usize = 100;
+        let rb = Arc::new(AudioRingBuffer::new(CAPACITY));
+        let done = Arc::new(AtomicUsize::new(0));
+        let writer = {
+            let (rb, done) = (rb.clone(), done.clone());
+            std::thread::spawn(move || {
+                // Each sample holds its position (exact in f32 below 2^24);
+                // playing along live.
+                while done.load(Ordering::Relaxed) < READERS && rb.write_position() < 1 << 24 {
+                    let wp = rb.write_position();
+                    let block: Vec<f32> = (wp..wp + 64).map(|p| p as f32).collect();
+                    rb.set_read_position(wp);
+                    rb.write(&block);
+                    if rb.write_position() == wp {
+                        std::thread::yield_now();
+                    }
+                }
+            })
+        };
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                let (rb, done) = (rb.clone(), done.clone());
+                std::thread::spawn(move || {
+                    // Rounds, each a hold advanced across three windows from
+                    // the oldest safe sample, until the writer lapped it 10 times.
+                    let mut rounds = 0;
+                    while rounds < 20 || rb.write_position() < 10 * CAPACITY {
+                        let start = rb.safe_oldest();
+                        let Some(hold) = rb.hold(start) else {
+                            std::thread::yield_now();
+                            continue;
+                        };
+                        let mut window = [0.0_f32; WINDOW];
+                        for from in (start..).step_by(WINDOW).take(3) {
+                            // Slow enough for an unchecked writer to lap it.
+                            std::thread::sleep(std::time::Duration::from_millis(1));
+                            while rb.write_position() < from + WINDOW {
+                                std::thread::yield_now();
+                            }
+                            assert!(rb.read_at(from, &mut window));
+                            let expected: Vec<f32> =
+                                (from..from + WINDOW).map(|p| p as f32).collect();
+                            assert_eq!(window.to_vec(), expected);
+                            hold.advance(from + WINDOW);
+                        }
+                        rounds += 1;
+                    }
+                    done.fetch_add(1, Ordering::Relaxed);
+                })
+            })
+            .collect();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        writer.join().unwrap();
+        assert!(rb.holds.iter().all(|h| h.load(Ordering::SeqCst) == NO_HOLD));
+    }
+
     #[test]
     fn seek_position() {
         let rb = AudioRingBuffer::new(1024);
@@ -203,6 +524,46 @@ mod tests {
         assert_eq!(output, [50.0, 51.0, 52.0, 53.0]);
     }
 
+    #[test]
+    fn read_at_contiguous() {
+        let rb = AudioRingBuffer::new(8);
+        rb.write(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let mut out = [0.0_f32; 3];
+        assert!(rb.read_at(1, &mut out));
+        assert_eq!(out, [2.0, 3.0, 4.0]);
+        assert_eq!(rb.read_position(), 0);
+    }
+
+    #[test]
+    fn read_at_wrapped() {
+        let rb = AudioRingBuffer::new(8);
+        rb.write(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let mut out = [0.0_f32; 6];
+        rb.read(&mut out);
+        rb.write(&[7.0, 8.0, 9.0, 10.0]);
+
+        let mut out = [0.0_f32; 5];
+        assert!(rb.read_at(5, &mut out));
+        assert_eq!(out, [6.0, 7.0, 8.0, 9.0, 10.0]);
+    }
+
+    #[test]
+    fn read_at_rejects_unwritten_and_overwritten() {
+        let rb = AudioRingBuffer::new(8);
+        rb.write(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert!(!rb.read_at(4, &mut [0.0; 3]));
+        assert!(!rb.read_at(0, &mut [0.0; 9]));
+
+        let mut out = [0.0_f32; 6];
+        rb.read(&mut out);
+        rb.write(&[7.0, 8.0, 9.0, 10.0]);
+        // Positions 0 and 1 have been overwritten by 8.0 and 9.0.
+        let mut out = [-1.0_f32; 2];
+        assert!(!rb.read_at(1, &mut out));
+        assert_eq!(out, [-1.0; 2]);
+        assert!(rb.read_at(2, &mut [0.0; 8]));
+    }
+
     #[test]
     fn delay_samples_tracking() {
         let rb = AudioRingBuffer::new(1024);
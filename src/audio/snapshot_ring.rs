@@ -0,0 +1,175 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Samples per block. One callback fits in a single block up to 2048 stereo frames;
+/// larger callbacks are split.
+pub const BLOCK_LEN: usize = 4096;
+
+/// A copy of (part of) one callback buffer.
+pub struct Block {
+    samples: [f32; BLOCK_LEN],
+    len: usize,
+    /// Stream position of the first sample (interleaved samples pushed before it,
+    /// including dropped ones).
+    pub position: usize,
+}
+
+impl Block {
+    const EMPTY: Block = Block {
+        samples: [0.0; BLOCK_LEN],
+        len: 0,
+        position: 0,
+    };
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples[..self.len]
+    }
+}
+
+/// A lock-free single-producer/single-consumer ring of fixed-size sample blocks.
+///
+/// The audio callback (producer) copies its buffer in with `push`, and the
+/// analysis thread (consumer) processes pending blocks with `drain`. The
+/// producer never blocks or allocates: when the consumer falls behind, new
+/// blocks are dropped and counted.
+pub struct SnapshotRing {
+    slots: Box<[UnsafeCell<Block>]>,
+    /// Blocks pushed (absolute count). Written by the producer only.
+    head: AtomicUsize,
+    /// Blocks consumed (absolute count). Written by the consumer only.
+    tail: AtomicUsize,
+    /// Stream position of the next pushed sample. Producer only.
+    next_position: AtomicUsize,
+    /// Blocks dropped because the ring was full.
+    dropped: AtomicUsize,
+}
+
+// SAFETY: slots in `tail..head` belong to the consumer and all others to the
+// producer; ownership is handed over by the Release/Acquire on head and tail.
+unsafe impl Send for SnapshotRing {}
+unsafe impl Sync for SnapshotRing {}
+
+impl SnapshotRing {
+    /// Create a ring holding up to `blocks` pending blocks.
+    pub fn new(blocks: usize) -> Self {
+        let mut slots = Vec::with_capacity(blocks);
+        for _ in 0..blocks {
+            slots.push(UnsafeCell::new(Block::EMPTY));
+        }
+        Self {
+            slots: slots.into_boxed_slice(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            next_position: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// Called by an audio callback. Copies `data` into the ring, splitting it
+    /// into `BLOCK_LEN` blocks. Drops blocks the consumer has no room for.
+    pub fn push(&self, data: &[f32]) {
+        for chunk in data.chunks(BLOCK_LEN) {
+            let position = self.next_position.load(Ordering::Relaxed);
+            self.next_position
+                .store(position + chunk.len(), Ordering::Relaxed);
+
+            let head = self.head.load(Ordering::Relaxed);
+            let tail = self.tail.load(Ordering::Acquire);
+            if head - tail >= self.slots.len() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            // SAFETY: the slot at `head` is outside `tail..head`, so the consumer
+            // isn't reading it, and only this (single) producer writes slots.
+            let block = unsafe { &mut *self.slots[head % self.slots.len()].get() };
+            block.samples[..chunk.len()].copy_from_slice(chunk);
+            block.len = chunk.len();
+            block.position = position;
+            self.head.store(head + 1, Ordering::Release);
+        }
+    }
+
+    /// Called by the consumer. Passes each pending block to `f`, oldest first,
+    /// then releases them to the producer. Returns the number of blocks.
+    pub fn drain(&self, mut f: impl FnMut(&Block)) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        for i in tail..head {
+            // SAFETY: slots in `tail..head` are fully written (Acquire on head)
+            // and the producer won't touch them until `tail` moves past them.
+            let block = unsafe { &*self.slots[i % self.slots.len()].get() };
+            f(block);
+        }
+        self.tail.store(head, Ordering::Release);
+        head - tail
+    }
+
+    /// Blocks dropped so far because the consumer fell behind.
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Zeroes the dropped-block count (a drop racing with this may be lost).
+    pub fn reset_dropped(&self) {
+        self.dropped.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_and_drain_in_order() {
+        let ring = SnapshotRing::new(4);
+        ring.push(&[1.0, 2.0]);
+        ring.push(&[3.0]);
+        let mut blocks = Vec::new();
+        ring.drain(|b| blocks.push((b.position, b.samples().to_vec())));
+        assert_eq!(blocks, vec![(0, vec![1.0, 2.0]), (2, vec![3.0])]);
+        let mut blocks = Vec::new();
+        ring.drain(|b| blocks.push((b.position, b.samples().to_vec())));
+        assert!(blocks.is_empty());
+    }
+
+    #[test]
+    fn large_buffers_are_split_into_blocks() {
+        let ring = SnapshotRing::new(4);
+        ring.push(&vec![0.5; BLOCK_LEN + 10]);
+        let mut blocks = Vec::new();
+        ring.drain(|b| blocks.push((b.position, b.samples().to_vec())));
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].1.len(), BLOCK_LEN);
+        assert_eq!(blocks[1], (BLOCK_LEN, vec![0.5; 10]));
+    }
+
+    #[test]
+    fn full_ring_drops_and_counts() {
+        let ring = SnapshotRing::new(2);
+        ring.push(&[1.0]);
+        ring.push(&[2.0]);
+        ring.push(&[3.0]);
+        assert_eq!(ring.dropped(), 1);
+        let mut blocks = Vec::new();
+        ring.drain(|b| blocks.push((b.position, b.samples().to_vec())));
+        assert_eq!(blocks, vec![(0, vec![1.0]), (1, vec![2.0])]);
+
+        // Positions keep counting dropped samples, so gaps are visible.
+        ring.push(&[4.0]);
+        let mut blocks = Vec::new();
+        ring.drain(|b| blocks.push((b.position, b.samples().to_vec())));
+        assert_eq!(blocks, vec![(3, vec![4.0])]);
+    }
+
+    #[test]
+    fn slots_are_reused_after_drain() {
+        let ring = SnapshotRing::new(2);
+        for i in 0..10 {
+            ring.push(&[i as f32]);
+            let mut blocks = Vec::new();
+            ring.drain(|b| blocks.push((b.position, b.samples().to_vec())));
+            assert_eq!(blocks, vec![(i, vec![i as f32])]);
+        }
+        assert_eq!(ring.dropped(), 0);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::audio::ring_buffer::ReadResult;
+
+/// The audio a `PlaybackController` plays from: a buffer of interleaved
+/// samples addressed by absolute position, written at one end and read at a
+/// position the controller moves. Live, this is the `AudioRingBuffer`; the
+/// trait keeps the controller testable and lets other sources (e.g. a file
+/// reader for offline processing) stand in.
+///
+/// `read` and `write` are called from the audio callbacks, so they must not
+/// block or allocate.
+pub trait AudioSource: Send + Sync {
+    /// Appends interleaved samples at the write position.
+    fn write(&self, data: &[f32]);
+
+    /// Reads `output.len()` samples at the read position and advances it.
+    fn read(&self, output: &mut [f32]) -> ReadResult;
+
+    /// Copies the samples from absolute position `position` without moving
+    /// the read position. False if any of them isn't available.
+    fn read_at(&self, position: usize, output: &mut [f32]) -> bool;
+
+    /// Absolute position of the next sample written.
+    fn write_position(&self) -> usize;
+
+    /// Absolute position of the next sample read.
+    fn read_position(&self) -> usize;
+
+    fn set_read_position(&self, position: usize);
+
+    /// `write_position - read_position`.
+    fn delay_samples(&self) -> usize;
+
+    /// How much of the capacity the delay takes (0.0 - 1.0).
+    fn usage_fraction(&self) -> f64;
+
+    /// Samples held, at most.
+    fn capacity(&self) -> usize;
+
+    /// Memory the samples take, in MB, for display.
+    fn capacity_mb(&self) -> f64;
+
+    /// Oldest position `read_at` can safely start from.
+    fn safe_oldest(&self) -> usize;
+}
@@ -1,21 +1,435 @@
-use clap::Parser;
+use std::path::PathBuf;
 
+use anyhow::{Result, anyhow};
+use clap::{Parser, Subcommand};
+
+use crate::audio::airplay;
+use crate::audio::channel_map::ChannelMap;
+use crate::audio::raw_sink::RawFormat;
+use crate::console::BannerFormat;
+use crate::control::command;
+use crate::control::status_file::StatusFormat;
+use crate::playback::snap::Snap;
+use crate::tui::keymap::{ConfirmQuit, NumberKeys};
+
+/// Shortest buffer `--buffer-seconds` accepts: room to seek back at all.
+pub const MIN_BUFFER_SECONDS: u32 = 2;
+
+/// Longest buffer `--buffer-seconds` accepts: an hour, about 1.4 GB at
+/// 48 kHz stereo. Far longer ones can't be allocated at all.
+pub const MAX_BUFFER_SECONDS: u32 = 3600;
+
+/// Every option except `-l` can also be set with a `SHIFTER_*` environment
+/// variable (shown in `--help`); a flag on the command line takes precedence.
+/// Boolean flags take `true` or `false`.
 #[derive(Parser, Debug)]
 #[command(name = "shifter", version, about = "TUI audio time-shift tool")]
 pub struct CliArgs {
-    /// Input device name or substring (e.g. "BlackHole")
-    #[arg(short, long, default_value = "BlackHole")]
-    pub input_device: String,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    /// Input device name or substring; shorthand for -i, and wins over it
+    #[arg(value_name = "DEVICE")]
+    pub device: Option<String>,
+
+    /// Input device name or substring (e.g. "BlackHole"). Default: the only
+    /// virtual input device, or one picked from a list if there are several
+    #[arg(short, long, env = "SHIFTER_INPUT")]
+    pub input_device: Option<String>,
 
     /// Output device name or substring (default: system default)
-    #[arg(short, long)]
+    #[arg(short, long, env = "SHIFTER_OUTPUT")]
     pub output_device: Option<String>,
 
-    /// Buffer duration in seconds
-    #[arg(short, long, default_value_t = 60)]
+    /// Input channel for each output channel, e.g. "1,2,1,2" (0 or - = silent).
+    /// Aggregate outputs repeat the input across all their channels by default
+    #[arg(long, value_name = "MAP", env = "SHIFTER_CHANNEL_MAP")]
+    pub channel_map: Option<ChannelMap>,
+
+    /// Name of this instance, to run several side by side (default: derived
+    /// from the input device UID). Shown in the title and `shifter status`
+    #[arg(long, value_name = "NAME", env = "SHIFTER_INSTANCE_NAME")]
+    pub instance_name: Option<String>,
+
+    /// Buffer duration in seconds (2 to 3600)
+    #[arg(
+        short,
+        long,
+        default_value = "60",
+        value_parser = parse_buffer_seconds,
+        env = "SHIFTER_BUFFER_SECONDS"
+    )]
     pub buffer_seconds: u32,
 
-    /// List available audio devices and exit
+    /// Bookmark the captured audio automatically every N seconds (e.g. 60)
+    #[arg(long, value_name = "SECONDS", env = "SHIFTER_AUTO_BOOKMARK_INTERVAL_S")]
+    pub auto_bookmark_interval_s: Option<u32>,
+
+    /// Discard the first N ms of input, e.g. while switching the system output
+    /// to the virtual device; the buffer starts after that
+    #[arg(long, value_name = "MS", env = "SHIFTER_CAPTURE_DELAY_MS")]
+    pub capture_delay_ms: Option<u32>,
+
+    /// Limit how far playback may jump per output callback when a slow
+    /// callback left it behind (ms, e.g. 5); the gap closes over several callbacks
+    #[arg(long, value_name = "MS", env = "SHIFTER_MAX_READ_STEP_MS")]
+    pub max_read_step_ms: Option<u32>,
+
+    /// With an AirPlay output, hold Live playback behind by the device's
+    /// reported latency plus this margin (ms), so network hiccups don't
+    /// starve it
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = airplay::DEFAULT_MARGIN_MS,
+        env = "SHIFTER_AIRPLAY_MARGIN_MS"
+    )]
+    pub airplay_margin_ms: u32,
+
+    /// Snap seeks to a grid of delays ("1s", "500ms"), to bookmarks within
+    /// a second ("marks"), or not at all ("off"); S toggles it
+    #[arg(
+        long,
+        value_name = "SNAP",
+        default_value = "off",
+        value_parser = command::parse_snap,
+        env = "SHIFTER_SNAP"
+    )]
+    pub snap: Snap,
+
+    /// Gain applied to the input before it is buffered, in dB (-24 to 24);
+    /// affects meters and --output-raw, unlike the output volume
+    #[arg(
+        long,
+        value_name = "DB",
+        default_value_t = 0.0,
+        allow_negative_numbers = true,
+        value_parser = command::parse_gain_db,
+        env = "SHIFTER_INPUT_GAIN_DB"
+    )]
+    pub input_gain_db: f32,
+
+    /// Remove DC offset from the input (a 10 Hz high-pass) before it is buffered
+    #[arg(long, env = "SHIFTER_DC_BLOCK")]
+    pub dc_block: bool,
+
+    /// Also write the processed output as headerless raw PCM to this file (or FIFO)
+    #[arg(long, value_name = "PATH", env = "SHIFTER_OUTPUT_RAW")]
+    pub output_raw: Option<PathBuf>,
+
+    /// Log marks, pauses and seeks as CSV to this file, with stream positions
+    /// (convert with `shifter events-to-labels`)
+    #[arg(long, value_name = "PATH", env = "SHIFTER_EVENT_LOG")]
+    pub event_log: Option<PathBuf>,
+
+    /// Record the processed output as WAV files in this directory, one per
+    /// segment; R starts the next file, Shift+R lists them
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "output_raw",
+        env = "SHIFTER_RECORD_DIR"
+    )]
+    pub record_dir: Option<PathBuf>,
+
+    /// Also start the next --record-dir file at every bookmark
+    #[arg(long, requires = "record_dir", env = "SHIFTER_SPLIT_ON_BOOKMARK")]
+    pub split_on_bookmark: bool,
+
+    /// Also start the next --record-dir file when playback stays paused N
+    /// seconds; the pause is left out
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        requires = "record_dir",
+        env = "SHIFTER_SPLIT_AFTER_PAUSE_S"
+    )]
+    pub split_after_pause_s: Option<u32>,
+
+    /// Sample format for --output-raw and --record-dir (interleaved, little-endian)
+    #[arg(long, value_enum, default_value = "f32le", env = "SHIFTER_RAW_FORMAT")]
+    pub raw_format: RawFormat,
+
+    /// What the number keys 1-9 do: select the seek step, jump to bookmark N
+    /// (oldest first), or set volume presets (20%..100%)
+    #[arg(
+        long,
+        value_enum,
+        default_value = "seek-scale",
+        env = "SHIFTER_NUMBER_KEYS"
+    )]
+    pub number_keys: NumberKeys,
+
+    /// Start with the debug overlay (raw read/write positions) open; Ctrl+D toggles it
+    #[arg(long, env = "SHIFTER_DEBUG")]
+    pub debug: bool,
+
+    /// Don't speed up held seek keys (×2, ×4, ×8 the step while repeating)
+    #[arg(long, env = "SHIFTER_NO_SEEK_ACCELERATION")]
+    pub no_seek_acceleration: bool,
+
+    /// Ask before quitting: never, on Q only (Ctrl+C still quits at once),
+    /// or always
+    #[arg(
+        long,
+        value_enum,
+        default_value = "never",
+        env = "SHIFTER_CONFIRM_QUIT"
+    )]
+    pub confirm_quit: ConfirmQuit,
+
+    /// Resume playback on unlock if it was paused automatically on lock/sleep
+    #[arg(long, env = "SHIFTER_AUTO_RESUME_ON_UNLOCK")]
+    pub auto_resume_on_unlock: bool,
+
+    /// Use a colorblind-friendly blue/orange/white palette for meters and gauges
+    #[arg(long, env = "SHIFTER_COLORBLIND")]
+    pub colorblind: bool,
+
+    /// Dim the screen and stop redrawing the meters after N seconds without
+    /// a key press; any key, an xrun or a full buffer wakes it (I dims at once)
+    #[arg(long, value_name = "SECONDS", env = "SHIFTER_IDLE_TIMEOUT_S")]
+    pub idle_timeout_s: Option<u64>,
+
+    /// Smoothing time constant for the meters' dB numbers in ms (0 = off)
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 250,
+        env = "SHIFTER_METER_SMOOTHING_MS"
+    )]
+    pub meter_smoothing_ms: u64,
+
+    /// Level the meters show for a reference tone when calibrated with Shift+C, in dB
+    #[arg(
+        long,
+        value_name = "DB",
+        default_value_t = -18.0,
+        allow_negative_numbers = true,
+        env = "SHIFTER_METER_REFERENCE_DB"
+    )]
+    pub meter_reference_db: f32,
+
+    /// Don't load the automation script (~/.config/shifter/script.rhai)
+    #[arg(long, env = "SHIFTER_NO_SCRIPT")]
+    pub no_script: bool,
+
+    /// Startup banner format on stderr; `json` is a single object with a stable schema
+    #[arg(
+        long,
+        value_enum,
+        default_value = "text",
+        env = "SHIFTER_BANNER_FORMAT"
+    )]
+    pub banner_format: BannerFormat,
+
+    /// Don't print the startup banner; other messages still show (see --quiet)
+    #[arg(long, env = "SHIFTER_NO_STARTUP_SUMMARY")]
+    pub no_startup_summary: bool,
+
+    /// Don't print the startup banner or other informational messages
+    #[arg(short, long, alias = "no-banner", env = "SHIFTER_QUIET")]
+    pub quiet: bool,
+
+    /// List available audio devices and exit (as JSON with --banner-format json)
     #[arg(short, long)]
     pub list_devices: bool,
+
+    /// Read commands (as for --distributed-notifications, one per line) from
+    /// this FIFO, created if missing: `echo "rewind 30s" > PATH`
+    #[arg(long, value_name = "PATH", env = "SHIFTER_COMMAND_FIFO")]
+    pub command_fifo: Option<PathBuf>,
+
+    /// Write a one-line status to this FIFO, created if missing, once per
+    /// second while it is read (e.g. `head -n1 PATH` in a tmux status bar)
+    #[arg(long, value_name = "PATH", env = "SHIFTER_STATUS_FIFO")]
+    pub status_fifo: Option<PathBuf>,
+
+    /// Write the status (state, delay, buffer, volume, devices) to this file
+    /// once per second, replacing it whole, for tmux or SwiftBar to read;
+    /// ~/.shifter/status.json without =PATH. Removed on exit
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        env = "SHIFTER_STATUS_FILE"
+    )]
+    pub status_file: Option<Option<PathBuf>>,
+
+    /// What --status-file contains: a JSON object or the --status-fifo line
+    #[arg(
+        long,
+        value_enum,
+        default_value = "json",
+        env = "SHIFTER_STATUS_FILE_FORMAT"
+    )]
+    pub status_file_format: StatusFormat,
+
+    /// Accept commands via macOS distributed notifications (for Shortcuts automations)
+    ///
+    /// Observes `com.xesco.shifter.command` notifications whose object is a command
+    /// string (e.g. "rewind 30s", "pause", "live", "volume 80") and posts
+    /// `com.xesco.shifter.status` once per second with a JSON status object.
+    ///
+    /// From a Shortcuts "Run Shell Script" action:
+    ///
+    ///   osascript -l JavaScript -e 'ObjC.import("Foundation");
+    ///     $.NSDistributedNotificationCenter.defaultCenter
+    ///       .postNotificationNameObjectUserInfoDeliverImmediately(
+    ///         "com.xesco.shifter.command", "rewind 30s", $(), true)'
+    #[arg(long, verbatim_doc_comment, env = "SHIFTER_DISTRIBUTED_NOTIFICATIONS")]
+    pub distributed_notifications: bool,
+}
+
+impl CliArgs {
+    /// Parses the command line. The input device is the positional `DEVICE`,
+    /// else `-i` / `SHIFTER_INPUT`, else `None` (picked at startup).
+    pub fn parse_args() -> Self {
+        Self::parse().with_positional_device()
+    }
+
+    fn with_positional_device(mut self) -> Self {
+        if let Some(device) = self.device.take() {
+            self.input_device = Some(device);
+        }
+        self
+    }
+}
+
+/// The `--buffer-seconds` value parser: a whole number of seconds, at least
+/// `MIN_BUFFER_SECONDS` (a zero-length ring has nothing to play back) and at
+/// most `MAX_BUFFER_SECONDS`.
+fn parse_buffer_seconds(value: &str) -> Result<u32> {
+    let seconds: u32 = value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid buffer length '{value}' (whole seconds)"))?;
+    if seconds < MIN_BUFFER_SECONDS {
+        return Err(anyhow!(
+            "Buffer must be at least {MIN_BUFFER_SECONDS} seconds, got {seconds}"
+        ));
+    }
+    if seconds > MAX_BUFFER_SECONDS {
+        return Err(anyhow!(
+            "Buffer must be at most {MAX_BUFFER_SECONDS} seconds, got {seconds}"
+        ));
+    }
+    Ok(seconds)
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// List the running instances and exit
+    Status,
+    /// Convert an --event-log CSV to an Audacity label track and exit
+    EventsToLabels {
+        /// Event log written with --event-log
+        input: PathBuf,
+        /// Write the labels here instead of printing them
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Apply the processing chain to a WAV file as fast as possible and exit
+    Render {
+        /// WAV file to process (16/24-bit PCM or 32-bit float)
+        #[arg(long = "in", value_name = "PATH")]
+        input: PathBuf,
+        /// Where to write the processed WAV file
+        #[arg(long = "out", value_name = "PATH")]
+        output: PathBuf,
+        /// Sample format of the output (default: the input's)
+        #[arg(long, value_enum)]
+        format: Option<RawFormat>,
+        /// Remove DC offset first, as --dc-block
+        #[arg(long)]
+        dc_block: bool,
+        /// Gain in dB, as --input-gain-db
+        #[arg(
+            long,
+            value_name = "DB",
+            default_value_t = 0.0,
+            allow_negative_numbers = true,
+            value_parser = command::parse_gain_db
+        )]
+        input_gain_db: f32,
+        /// Output volume in percent (0 to 150), as the `volume` command
+        #[arg(long, value_name = "PERCENT", default_value = "100", value_parser = command::parse_volume)]
+        volume: f32,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positional_device_wins_over_the_flag() {
+        let input_device = |argv: &[&str]| {
+            CliArgs::try_parse_from(argv)
+                .unwrap()
+                .with_positional_device()
+                .input_device
+        };
+        assert_eq!(input_device(&["shifter"]), None);
+        assert_eq!(
+            input_device(&["shifter", "-i", "Loopback"]).as_deref(),
+            Some("Loopback")
+        );
+        assert_eq!(
+            input_device(&["shifter", "Soundflower"]).as_deref(),
+            Some("Soundflower")
+        );
+        assert_eq!(
+            input_device(&["shifter", "-i", "Loopback", "Soundflower"]).as_deref(),
+            Some("Soundflower")
+        );
+    }
+
+    #[test]
+    fn status_file_path_is_optional_and_never_the_device() {
+        let status_file = |argv: &[&str]| CliArgs::try_parse_from(argv).unwrap().status_file;
+        assert_eq!(status_file(&["shifter"]), None);
+        assert_eq!(status_file(&["shifter", "--status-file"]), Some(None));
+        assert_eq!(
+            status_file(&["shifter", "--status-file=/tmp/s.json"]),
+            Some(Some(PathBuf::from("/tmp/s.json")))
+        );
+        let args = CliArgs::try_parse_from(["shifter", "--status-file", "Soundflower"]).unwrap();
+        assert_eq!(
+            args.with_positional_device().input_device.as_deref(),
+            Some("Soundflower")
+        );
+    }
+
+    #[test]
+    fn subcommands_are_not_device_names() {
+        let args = CliArgs::try_parse_from(["shifter", "status"]).unwrap();
+        assert!(matches!(args.command, Some(CliCommand::Status)));
+        assert_eq!(args.device, None);
+    }
+
+    #[test]
+    fn buffers_outside_two_seconds_to_an_hour_are_refused() {
+        let buffer = |argv: &[&str]| {
+            CliArgs::try_parse_from(argv)
+                .map(|args| args.buffer_seconds)
+                .map_err(|e| e.to_string())
+        };
+        assert_eq!(buffer(&["shifter"]), Ok(60));
+        assert_eq!(buffer(&["shifter", "-b", "2"]), Ok(2));
+        assert_eq!(buffer(&["shifter", "-b", "3600"]), Ok(3600));
+        for (value, message) in [
+            ("0", "Buffer must be at least 2 seconds, got 0"),
+            ("1", "Buffer must be at least 2 seconds, got 1"),
+            ("3601", "Buffer must be at most 3600 seconds, got 3601"),
+            ("4000000000", "at most 3600 seconds, got 4000000000"),
+            ("-5", "Invalid buffer length '-5' (whole seconds)"),
+            ("1.5", "Invalid buffer length '1.5' (whole seconds)"),
+        ] {
+            let flag = format!("--buffer-seconds={value}");
+            let error = buffer(&["shifter", &flag]).unwrap_err();
+            assert!(error.contains(message), "{error}");
+        }
+    }
 }
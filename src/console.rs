@@ -0,0 +1,416 @@
+use clap::ValueEnum;
+
+/// Format of the startup banner on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BannerFormat {
+    /// Human-readable lines; wording may change between versions.
+    Text,
+    /// One JSON object on a single line (stable schema, see `Banner::json`).
+    Json,
+}
+
+/// All non-TUI user-facing output goes through here, so `--quiet` and
+/// `--banner-format json` apply everywhere.
+///
+/// Command results (e.g. the device list) go to stdout and are never
+/// suppressed; in JSON mode they are JSON too. The banner goes to stderr.
+pub struct Console {
+    quiet: bool,
+    format: BannerFormat,
+}
+
+impl Console {
+    pub fn new(quiet: bool, format: BannerFormat) -> Self {
+        Self { quiet, format }
+    }
+
+    pub fn format(&self) -> BannerFormat {
+        self.format
+    }
+
+    /// Prints the startup banner, unless quiet.
+    pub fn banner(&self, banner: &Banner) {
+        if self.quiet {
+            return;
+        }
+        match self.format {
+            BannerFormat::Text => {
+                for line in banner.text() {
+                    eprintln!("{line}");
+                }
+            }
+            BannerFormat::Json => eprintln!("{}", banner.json()),
+        }
+    }
+
+    /// An informational line (e.g. progress) on stderr, unless quiet.
+    pub fn info(&self, line: &str) {
+        if !self.quiet {
+            eprintln!("{line}");
+        }
+    }
+
+    /// A line of command output on stdout.
+    pub fn out(&self, line: &str) {
+        println!("{line}");
+    }
+}
+
+/// Startup information: devices, stream format and effective configuration.
+pub struct Banner {
+    /// Instance name (`--instance-name` or derived from the input device).
+    pub instance: String,
+    pub input_device: String,
+    pub input_id: u32,
+    pub output_device: String,
+    pub output_id: u32,
+    /// Whether the output follows the system output device (no `-o`).
+    pub follow_system_output: bool,
+    /// How the output was chosen (`SelectionReason::describe`).
+    pub output_reason: String,
+    /// Input channel per output channel, e.g. "1,2,1,2", if not one to one.
+    pub channel_map: Option<String>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// The input device's own sample format, e.g. "24-bit int", if known.
+    pub native_format: Option<String>,
+    pub buffer_seconds: u32,
+    /// Ring buffer size in samples (all channels).
+    pub capacity_samples: usize,
+    /// Estimated end-to-end delay while live: device latencies, IO buffers
+    /// and the one-callback minimum.
+    pub initial_delay_ms: f64,
+    /// Input and output IO buffer sizes in frames.
+    pub io_buffer_frames: (u32, u32),
+    /// How fast the meter peaks fall, in dB per second (negative).
+    pub peak_decay_db_per_s: f32,
+    pub auto_bookmark_interval_s: Option<u32>,
+    pub auto_resume_on_unlock: bool,
+    pub distributed_notifications: bool,
+    pub script: bool,
+    pub colorblind: bool,
+    /// `--output-raw` path and format name.
+    pub raw_output: Option<(String, &'static str)>,
+    /// `--record-dir` directory and format name.
+    pub record_dir: Option<(String, &'static str)>,
+}
+
+impl Banner {
+    /// Version of the JSON schema. Bumped only when a field is removed or
+    /// changes meaning; new fields may be added without a bump.
+    pub const SCHEMA: u32 = 1;
+
+    fn is_recording(&self) -> bool {
+        self.raw_output.is_some() || self.record_dir.is_some()
+    }
+
+    /// Ring buffer size in MB of f32 samples.
+    fn capacity_mb(&self) -> f64 {
+        (self.capacity_samples * size_of::<f32>()) as f64 / (1024.0 * 1024.0)
+    }
+
+    pub fn text(&self) -> Vec<String> {
+        let native = self
+            .native_format
+            .as_ref()
+            .map_or_else(String::new, |format| format!(", device {format}"));
+        let mut lines = vec![format!(
+            "Audio [{}]: {} -> {} ({}ch {}Hz{native}, {}s buffer)",
+            self.instance,
+            self.input_device,
+            self.output_device,
+            self.channels,
+            self.sample_rate,
+            self.buffer_seconds,
+        )];
+        lines.push(format!(
+            "Buffer: {} samples ({:.1} MB), live delay ~{:.1}ms, IO buffers {}/{} frames, \
+             meter decay {:.0} dB/s, {}",
+            self.capacity_samples,
+            self.capacity_mb(),
+            self.initial_delay_ms,
+            self.io_buffer_frames.0,
+            self.io_buffer_frames.1,
+            self.peak_decay_db_per_s,
+            if self.is_recording() {
+                "recording"
+            } else {
+                "not recording"
+            },
+        ));
+        lines.push(format!("Output: {}", self.output_reason));
+        if let Some(map) = &self.channel_map {
+            lines.push(format!(
+                "Output channels: {map} (input channel per output channel)"
+            ));
+        }
+        if let Some((path, format)) = &self.raw_output {
+            lines.push(format!(
+                "Raw output: {path} ({format}, {}ch {}Hz)",
+                self.channels, self.sample_rate,
+            ));
+        }
+        if let Some((dir, format)) = &self.record_dir {
+            lines.push(format!(
+                "Recording to: {dir} (WAV segments, {format}, {}ch {}Hz)",
+                self.channels, self.sample_rate,
+            ));
+        }
+        lines
+    }
+
+    /// The banner as a single-line JSON object:
+    ///
+    /// ```text
+    /// {"schema":1,"version":"0.1.0","instance":"blackhole2ch_uid",
+    ///  "input":{"name":"BlackHole 2ch","id":73},
+    ///  "output":{"name":"MacBook Pro Speakers","id":91,"follow_system":true,
+    ///            "channel_map":null,"reason":"output not specified → system output ..."},
+    ///  "format":{"sample_rate":48000,"channels":2,"sample":"f32","native":"24-bit int"},
+    ///  "buffer_seconds":60,"capacity":{"samples":5760000,"mb":22.0},
+    ///  "latency":{"initial_delay_ms":23.5,"io_buffer_frames":{"input":512,"output":512}},
+    ///  "meters":{"peak_decay_db_per_s":-120.0},
+    ///  "config":{"auto_bookmark_interval_s":null,"auto_resume_on_unlock":false,
+    ///            "distributed_notifications":false,"script":true,"colorblind":false,
+    ///            "recording":true,"raw_output":{"path":"/tmp/out.pcm","format":"s16le"},
+    ///            "record_dir":null}}
+    /// ```
+    ///
+    /// `raw_output` and `record_dir` are `null` when not enabled, `native`
+    /// when the input device's format couldn't be read, `channel_map` (e.g.
+    /// "1,2,1,2") when input channels go one to one to the output's first
+    /// channels.
+    pub fn json(&self) -> String {
+        let sink = |sink: &Option<(String, &str)>| match sink {
+            Some((path, format)) => format!(
+                "{{\"path\":{},\"format\":{}}}",
+                json_string(path),
+                json_string(format),
+            ),
+            None => "null".to_string(),
+        };
+        let native = self
+            .native_format
+            .as_deref()
+            .map_or_else(|| "null".to_string(), json_string);
+        let channel_map = self
+            .channel_map
+            .as_deref()
+            .map_or_else(|| "null".to_string(), json_string);
+        let auto_bookmark = self
+            .auto_bookmark_interval_s
+            .map_or_else(|| "null".to_string(), |s| s.to_string());
+        format!(
+            "{{\"schema\":{},\"version\":{},\"instance\":{},\
+             \"input\":{{\"name\":{},\"id\":{}}},\
+             \"output\":{{\"name\":{},\"id\":{},\"follow_system\":{},\"channel_map\":{},\
+             \"reason\":{}}},\
+             \"format\":{{\"sample_rate\":{},\"channels\":{},\"sample\":\"f32\",\"native\":{}}},\
+             \"buffer_seconds\":{},\"capacity\":{{\"samples\":{},\"mb\":{:.1}}},\
+             \"latency\":{{\"initial_delay_ms\":{:.1},\
+             \"io_buffer_frames\":{{\"input\":{},\"output\":{}}}}},\
+             \"meters\":{{\"peak_decay_db_per_s\":{:.1}}},\
+             \"config\":{{\"auto_bookmark_interval_s\":{},\"auto_resume_on_unlock\":{},\
+             \"distributed_notifications\":{},\"script\":{},\"colorblind\":{},\
+             \"recording\":{},\"raw_output\":{},\"record_dir\":{}}}}}",
+            Self::SCHEMA,
+            json_string(env!("CARGO_PKG_VERSION")),
+            json_string(&self.instance),
+            json_string(&self.input_device),
+            self.input_id,
+            json_string(&self.output_device),
+            self.output_id,
+            self.follow_system_output,
+            channel_map,
+            json_string(&self.output_reason),
+            self.sample_rate,
+            self.channels,
+            native,
+            self.buffer_seconds,
+            self.capacity_samples,
+            self.capacity_mb(),
+            self.initial_delay_ms,
+            self.io_buffer_frames.0,
+            self.io_buffer_frames.1,
+            self.peak_decay_db_per_s,
+            auto_bookmark,
+            self.auto_resume_on_unlock,
+            self.distributed_notifications,
+            self.script,
+            self.colorblind,
+            self.is_recording(),
+            sink(&self.raw_output),
+            sink(&self.record_dir),
+        )
+    }
+}
+
+/// Quotes and escapes `s` as a JSON string.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_banner_parses_with_stable_schema() {
+        let banner = Banner {
+            instance: "blackhole2ch_uid".into(),
+            input_device: "BlackHole 2ch".into(),
+            input_id: 73,
+            output_device: "Bob's \"Desk\" Speakers".into(),
+            output_id: 91,
+            follow_system_output: true,
+            output_reason: "output not specified \u{2192} system output device chosen".into(),
+            channel_map: Some("1,2,1,2".into()),
+            sample_rate: 48_000,
+            channels: 2,
+            native_format: Some("24-bit int".into()),
+            buffer_seconds: 60,
+            capacity_samples: 5_760_000,
+            initial_delay_ms: 23.5,
+            io_buffer_frames: (512, 256),
+            peak_decay_db_per_s: -120.0,
+            auto_bookmark_interval_s: None,
+            auto_resume_on_unlock: false,
+            distributed_notifications: true,
+            script: true,
+            colorblind: false,
+            raw_output: Some(("/tmp/out.pcm".into(), "s16le")),
+            record_dir: None,
+        };
+        let json = banner.json();
+        assert!(!json.contains('\n'));
+        let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(v["schema"], 1);
+        assert_eq!(v["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(v["instance"], "blackhole2ch_uid");
+        assert_eq!(v["input"]["name"], "BlackHole 2ch");
+        assert_eq!(v["input"]["id"], 73);
+        assert_eq!(v["output"]["name"], "Bob's \"Desk\" Speakers");
+        assert_eq!(v["output"]["id"], 91);
+        assert_eq!(v["output"]["follow_system"], true);
+        assert_eq!(v["output"]["channel_map"], "1,2,1,2");
+        assert_eq!(
+            v["output"]["reason"],
+            "output not specified \u{2192} system output device chosen"
+        );
+        assert_eq!(v["format"]["sample_rate"], 48_000);
+        assert_eq!(v["format"]["channels"], 2);
+        assert_eq!(v["format"]["sample"], "f32");
+        assert_eq!(v["format"]["native"], "24-bit int");
+        assert_eq!(v["buffer_seconds"], 60);
+        assert_eq!(v["capacity"]["samples"], 5_760_000);
+        assert_eq!(v["capacity"]["mb"], 22.0);
+        assert_eq!(v["latency"]["initial_delay_ms"], 23.5);
+        assert_eq!(v["latency"]["io_buffer_frames"]["output"], 256);
+        assert_eq!(v["meters"]["peak_decay_db_per_s"], -120.0);
+        assert_eq!(v["config"]["recording"], true);
+        assert_eq!(
+            v["config"]["auto_bookmark_interval_s"],
+            serde_json::Value::Null
+        );
+        assert_eq!(v["config"]["distributed_notifications"], true);
+        assert_eq!(v["config"]["raw_output"]["format"], "s16le");
+    }
+
+    #[test]
+    fn json_banner_without_raw_output_is_null() {
+        let mut b = Banner {
+            instance: "blackhole2ch_uid".into(),
+            input_device: "BlackHole 2ch".into(),
+            input_id: 73,
+            output_device: "Bob's \"Desk\" Speakers".into(),
+            output_id: 91,
+            follow_system_output: true,
+            output_reason: "output not specified \u{2192} system output device chosen".into(),
+            channel_map: Some("1,2,1,2".into()),
+            sample_rate: 48_000,
+            channels: 2,
+            native_format: Some("24-bit int".into()),
+            buffer_seconds: 60,
+            capacity_samples: 5_760_000,
+            initial_delay_ms: 23.5,
+            io_buffer_frames: (512, 256),
+            peak_decay_db_per_s: -120.0,
+            auto_bookmark_interval_s: None,
+            auto_resume_on_unlock: false,
+            distributed_notifications: true,
+            script: true,
+            colorblind: false,
+            raw_output: Some(("/tmp/out.pcm".into(), "s16le")),
+            record_dir: None,
+        };
+        b.raw_output = None;
+        b.auto_bookmark_interval_s = Some(30);
+        b.native_format = None;
+        b.channel_map = None;
+        let v: serde_json::Value = serde_json::from_str(&b.json()).unwrap();
+        assert_eq!(v["config"]["raw_output"], serde_json::Value::Null);
+        assert_eq!(v["config"]["record_dir"], serde_json::Value::Null);
+        assert_eq!(v["config"]["recording"], false);
+        assert_eq!(v["config"]["auto_bookmark_interval_s"], 30);
+        assert_eq!(v["format"]["native"], serde_json::Value::Null);
+        assert_eq!(v["output"]["channel_map"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn text_banner_sums_up_the_buffer() {
+        let banner = Banner {
+            instance: "blackhole2ch_uid".into(),
+            input_device: "BlackHole 2ch".into(),
+            input_id: 73,
+            output_device: "Bob's \"Desk\" Speakers".into(),
+            output_id: 91,
+            follow_system_output: true,
+            output_reason: "output not specified \u{2192} system output device chosen".into(),
+            channel_map: Some("1,2,1,2".into()),
+            sample_rate: 48_000,
+            channels: 2,
+            native_format: Some("24-bit int".into()),
+            buffer_seconds: 60,
+            capacity_samples: 5_760_000,
+            initial_delay_ms: 23.5,
+            io_buffer_frames: (512, 256),
+            peak_decay_db_per_s: -120.0,
+            auto_bookmark_interval_s: None,
+            auto_resume_on_unlock: false,
+            distributed_notifications: true,
+            script: true,
+            colorblind: false,
+            raw_output: Some(("/tmp/out.pcm".into(), "s16le")),
+            record_dir: None,
+        };
+        let lines = banner.text();
+        assert_eq!(
+            lines[1],
+            "Buffer: 5760000 samples (22.0 MB), live delay ~23.5ms, IO buffers 512/256 frames, \
+             meter decay -120 dB/s, recording"
+        );
+        assert_eq!(
+            lines[2],
+            "Output: output not specified \u{2192} system output device chosen"
+        );
+    }
+
+    #[test]
+    fn json_string_escapes_control_characters() {
+        assert_eq!(json_string("a\"b\\c\n\u{1}"), "\"a\\\"b\\\\c\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,403 @@
+use anyhow::{Result, anyhow};
+
+use crate::control::wallclock;
+use crate::playback::controller::{INPUT_GAIN_MAX_DB, PlaybackController};
+use crate::playback::loudness;
+use crate::playback::snap::Snap;
+use crate::playback::state::PlaybackState;
+
+/// A remote control command, parsed from a single line of text.
+///
+/// Grammar (case-insensitive, one command per line):
+///
+/// ```text
+/// pause              pause playback (no-op if already paused)
+/// resume             resume playback (no-op unless paused)
+/// toggle             toggle pause / resume
+/// live               jump to live
+/// mute               toggle mute
+/// rewind <duration>  seek backward (increase delay), e.g. "rewind 30s"
+/// forward <duration> seek forward (toward live), e.g. "forward 500ms"
+/// volume <percent>   set output volume (0-150)
+/// gain <dB>          set input gain before the buffer (-24 to 24), e.g. "gain -6"
+/// at <time>          seek to the audio captured at a local wall-clock time,
+///                    e.g. "at 14:30" or "at 14:30:05" (the last 24 hours)
+/// autovol [<dBFS>]   set the volume so the loud parts of the session so far
+///                    (95th percentile level) sit at -14 dBFS, or the given level
+/// state <state>      set the playback state: 0 / live, 1 / paused or
+///                    2 / time-shifted (resume only; see `set_state`)
+/// snap <snap>        snap seeks to a grid ("1s"), to bookmarks ("marks"),
+///                    or not at all ("off")
+/// solo <channel|off> copy one input channel (1-based) to every output channel
+/// split              start the next --record-dir file
+/// ```
+///
+/// Durations are milliseconds by default, or suffixed with `ms` or `s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Pause,
+    Resume,
+    TogglePause,
+    Live,
+    ToggleMute,
+    /// Seek by a signed delta in milliseconds. Positive increases the delay.
+    Seek(f64),
+    /// Set volume as a fraction (1.0 = 100%).
+    Volume(f32),
+    /// Set the input gain in dB.
+    InputGain(f32),
+    /// Seek to the audio captured at a local time, in seconds since midnight.
+    At(f64),
+    /// Set the volume from the session's level distribution, with the target in dBFS.
+    AutoVolume(f32),
+    /// Set the playback state (validated by `PlaybackController::set_state`).
+    SetState(PlaybackState),
+    /// Set where seeks land.
+    Snap(Snap),
+    /// Solo a channel (1-based), or `None` to turn solo off.
+    Solo(Option<u16>),
+    /// Start the next recording segment.
+    Split,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let verb = words
+            .next()
+            .ok_or_else(|| anyhow!("Empty command"))?
+            .to_lowercase();
+        let arg = words.next();
+        if let Some(extra) = words.next() {
+            return Err(anyhow!(
+                "Unexpected argument '{extra}' in '{}'",
+                line.trim()
+            ));
+        }
+
+        let command = match verb.as_str() {
+            "pause" => Self::Pause,
+            "resume" => Self::Resume,
+            "toggle" => Self::TogglePause,
+            "live" => Self::Live,
+            "mute" => Self::ToggleMute,
+            "split" => Self::Split,
+            "rewind" => Self::Seek(parse_duration_ms(require_arg(&verb, arg)?)?),
+            "forward" => Self::Seek(-parse_duration_ms(require_arg(&verb, arg)?)?),
+            "volume" => Self::Volume(parse_volume(require_arg(&verb, arg)?)?),
+            "gain" => Self::InputGain(parse_gain_db(require_arg(&verb, arg)?)?),
+            "at" => Self::At(wallclock::parse_time_of_day(require_arg(&verb, arg)?)?),
+            "autovol" => Self::AutoVolume(match arg {
+                Some(value) => value
+                    .trim_end_matches("dBFS")
+                    .trim_end_matches("dbfs")
+                    .parse()
+                    .ok()
+                    .filter(|db: &f32| (-60.0..=0.0).contains(db))
+                    .ok_or_else(|| anyhow!("Invalid auto volume target '{value}'"))?,
+                None => loudness::AUTOVOL_TARGET_DB,
+            }),
+            "state" => Self::SetState(require_arg(&verb, arg)?.parse()?),
+            "snap" => Self::Snap(parse_snap(require_arg(&verb, arg)?)?),
+            "solo" => {
+                let value = require_arg(&verb, arg)?;
+                Self::Solo(match value.to_lowercase().as_str() {
+                    "off" => None,
+                    channel => Some(
+                        channel
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid channel '{value}'"))?,
+                    ),
+                })
+            }
+            _ => return Err(anyhow!("Unknown command '{verb}'")),
+        };
+
+        if arg.is_some() && !command.takes_argument() {
+            return Err(anyhow!("'{verb}' takes no argument"));
+        }
+        Ok(command)
+    }
+
+    fn takes_argument(self) -> bool {
+        matches!(
+            self,
+            Self::Seek(_)
+                | Self::Volume(_)
+                | Self::InputGain(_)
+                | Self::At(_)
+                | Self::AutoVolume(_)
+                | Self::SetState(_)
+                | Self::Snap(_)
+                | Self::Solo(_)
+        )
+    }
+
+    /// Executes the command against the controller. Fails if an `at` time is
+    /// outside the buffer window, a state transition is invalid, `autovol`
+    /// has too little audio to go on, a solo channel doesn't exist or `split`
+    /// finds nothing recording.
+    pub fn apply(self, controller: &PlaybackController) -> Result<()> {
+        match self {
+            Self::Pause => {
+                if controller.state() != PlaybackState::Paused {
+                    controller.toggle_pause();
+                }
+            }
+            Self::Resume => {
+                if controller.state() == PlaybackState::Paused {
+                    controller.toggle_pause();
+                }
+            }
+            Self::TogglePause => controller.toggle_pause(),
+            Self::Live => controller.jump_to_live(),
+            Self::ToggleMute => controller.toggle_mute(),
+            Self::Seek(delta_ms) => {
+                controller.seek_ms(delta_ms);
+            }
+            Self::Volume(volume) => controller.set_volume(volume),
+            Self::InputGain(db) => controller.set_input_gain_db(db),
+            Self::At(time_of_day) => {
+                let ago = wallclock::seconds_ago(wallclock::local_seconds_of_day(), time_of_day);
+                if !controller.seek_to_delay_ms(ago * 1000.0) {
+                    return Err(anyhow!("Outside buffer window"));
+                }
+            }
+            Self::AutoVolume(target_db) => {
+                controller.auto_volume(target_db)?;
+            }
+            Self::SetState(state) => controller.set_state(state)?,
+            Self::Snap(snap) => controller.set_snap(snap),
+            Self::Solo(channel) => controller.set_solo(channel)?,
+            Self::Split => {
+                if !controller.request_split() {
+                    return Err(anyhow!("Not recording (see --record-dir)"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn require_arg<'a>(verb: &str, arg: Option<&'a str>) -> Result<&'a str> {
+    arg.ok_or_else(|| anyhow!("'{verb}' requires an argument"))
+}
+
+/// Parses a volume percentage ("80", "150%") into a fraction. Also the
+/// `render --volume` value parser.
+pub fn parse_volume(value: &str) -> Result<f32> {
+    let percent: f32 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| anyhow!("Invalid volume '{value}'"))?;
+    if !(0.0..=150.0).contains(&percent) {
+        return Err(anyhow!("Volume must be between 0 and 150, got {percent}"));
+    }
+    Ok(percent / 100.0)
+}
+
+/// Parses an input gain in dB ("-6", "3.5dB"). Also the `--input-gain-db`
+/// value parser.
+pub fn parse_gain_db(value: &str) -> Result<f32> {
+    let db: f32 = value
+        .trim_end_matches("dB")
+        .trim_end_matches("db")
+        .parse()
+        .map_err(|_| anyhow!("Invalid gain '{value}'"))?;
+    if !(-INPUT_GAIN_MAX_DB..=INPUT_GAIN_MAX_DB).contains(&db) {
+        return Err(anyhow!(
+            "Gain must be between -{INPUT_GAIN_MAX_DB} and {INPUT_GAIN_MAX_DB} dB, got {db}"
+        ));
+    }
+    Ok(db)
+}
+
+/// Parses a snap mode: "off", "marks" or a grid duration ("1s", "500ms").
+/// Also the `--snap` value parser.
+pub fn parse_snap(value: &str) -> Result<Snap> {
+    match value.trim().to_lowercase().as_str() {
+        "off" => Ok(Snap::Off),
+        "marks" | "bookmarks" => Ok(Snap::Bookmarks),
+        grid => {
+            let ms = parse_duration_ms(grid)?.round();
+            if !(1.0..=u32::MAX as f64).contains(&ms) {
+                return Err(anyhow!("Invalid snap grid '{value}'"));
+            }
+            Ok(Snap::Grid(ms as u32))
+        }
+    }
+}
+
+/// Longest duration a command takes: one day, far past any buffer.
+const MAX_DURATION_MS: f64 = 86_400_000.0;
+
+/// Parses "250", "250ms" or "1.5s" into milliseconds, up to one day.
+fn parse_duration_ms(value: &str) -> Result<f64> {
+    let lower = value.to_lowercase();
+    let (number, scale) = if let Some(n) = lower.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = lower.strip_suffix('s') {
+        (n, 1000.0)
+    } else {
+        (lower.as_str(), 1.0)
+    };
+    let ms: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration '{value}'"))?;
+    if !ms.is_finite() || ms < 0.0 {
+        return Err(anyhow!("Invalid duration '{value}'"));
+    }
+    let ms = ms * scale;
+    if ms > MAX_DURATION_MS {
+        return Err(anyhow!(
+            "Duration must be at most {}s (one day), got '{value}'",
+            MAX_DURATION_MS / 1000.0
+        ));
+    }
+    Ok(ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_simple_commands() {
+        assert_eq!(Command::parse("pause").unwrap(), Command::Pause);
+        assert_eq!(Command::parse("resume").unwrap(), Command::Resume);
+        assert_eq!(Command::parse("toggle").unwrap(), Command::TogglePause);
+        assert_eq!(Command::parse("live").unwrap(), Command::Live);
+        assert_eq!(Command::parse("mute").unwrap(), Command::ToggleMute);
+        assert_eq!(Command::parse("split").unwrap(), Command::Split);
+    }
+
+    #[test]
+    fn parsing_ignores_case_and_whitespace() {
+        assert_eq!(Command::parse("  LIVE \n").unwrap(), Command::Live);
+        assert_eq!(
+            Command::parse("Rewind   30S").unwrap(),
+            Command::Seek(30_000.0)
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(Command::parse("rewind 250").unwrap(), Command::Seek(250.0));
+        assert_eq!(
+            Command::parse("rewind 250ms").unwrap(),
+            Command::Seek(250.0)
+        );
+        assert_eq!(
+            Command::parse("rewind 1.5s").unwrap(),
+            Command::Seek(1500.0)
+        );
+        assert_eq!(
+            Command::parse("forward 30s").unwrap(),
+            Command::Seek(-30_000.0)
+        );
+        assert_eq!(
+            Command::parse("rewind 86400s").unwrap(),
+            Command::Seek(86_400_000.0)
+        );
+        let err = Command::parse("rewind 1e300").unwrap_err();
+        assert!(err.to_string().contains("at most 86400s"), "{err}");
+        assert!(Command::parse("forward 86401s").is_err());
+    }
+
+    #[test]
+    fn parses_volume_percent() {
+        assert_eq!(Command::parse("volume 80").unwrap(), Command::Volume(0.8));
+        assert_eq!(Command::parse("volume 150%").unwrap(), Command::Volume(1.5));
+    }
+
+    #[test]
+    fn parses_wall_clock_time() {
+        assert_eq!(Command::parse("at 14:30").unwrap(), Command::At(52_200.0));
+        assert_eq!(
+            Command::parse("AT 14:30:05").unwrap(),
+            Command::At(52_205.0)
+        );
+    }
+
+    #[test]
+    fn parses_auto_volume_target() {
+        assert_eq!(
+            Command::parse("autovol").unwrap(),
+            Command::AutoVolume(loudness::AUTOVOL_TARGET_DB)
+        );
+        assert_eq!(
+            Command::parse("autovol -16").unwrap(),
+            Command::AutoVolume(-16.0)
+        );
+        assert_eq!(
+            Command::parse("autovol -18dBFS").unwrap(),
+            Command::AutoVolume(-18.0)
+        );
+        assert!(Command::parse("autovol 3").is_err());
+        assert!(Command::parse("autovol loud").is_err());
+    }
+
+    #[test]
+    fn parses_states_by_number_or_label() {
+        assert_eq!(
+            Command::parse("state 0").unwrap(),
+            Command::SetState(PlaybackState::Live)
+        );
+        assert_eq!(
+            Command::parse("state Paused").unwrap(),
+            Command::SetState(PlaybackState::Paused)
+        );
+        assert_eq!(
+            Command::parse("state 2").unwrap(),
+            Command::SetState(PlaybackState::TimeShifted)
+        );
+        assert!(Command::parse("state 3").is_err());
+        assert!(Command::parse("state").is_err());
+    }
+
+    #[test]
+    fn parses_input_gain() {
+        assert_eq!(Command::parse("gain -6").unwrap(), Command::InputGain(-6.0));
+        assert_eq!(parse_gain_db("3.5dB").unwrap(), 3.5);
+        assert!(parse_gain_db("30").is_err());
+        assert!(Command::parse("gain loud").is_err());
+    }
+
+    #[test]
+    fn parses_solo() {
+        assert_eq!(Command::parse("solo 3").unwrap(), Command::Solo(Some(3)));
+        assert_eq!(Command::parse("SOLO off").unwrap(), Command::Solo(None));
+        assert!(Command::parse("solo").is_err());
+        assert!(Command::parse("solo left").is_err());
+    }
+
+    #[test]
+    fn parses_snap_modes() {
+        assert_eq!(
+            Command::parse("snap off").unwrap(),
+            Command::Snap(Snap::Off)
+        );
+        assert_eq!(
+            Command::parse("snap marks").unwrap(),
+            Command::Snap(Snap::Bookmarks)
+        );
+        assert_eq!(parse_snap("1s").unwrap(), Snap::Grid(1000));
+        assert_eq!(parse_snap("250ms").unwrap(), Snap::Grid(250));
+        assert!(parse_snap("0").is_err());
+        assert!(parse_snap("soon").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_commands() {
+        assert!(Command::parse("").is_err());
+        assert!(Command::parse("dance").is_err());
+        assert!(Command::parse("rewind").is_err());
+        assert!(Command::parse("rewind soon").is_err());
+        assert!(Command::parse("rewind -5s").is_err());
+        assert!(Command::parse("volume 200").is_err());
+        assert!(Command::parse("live now").is_err());
+        assert!(Command::parse("rewind 5s 10s").is_err());
+        assert!(Command::parse("at").is_err());
+        assert!(Command::parse("at 25:00").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::ops::ControlFlow;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+use crate::control::command::Command;
+use crate::playback::controller::PlaybackController;
+use crate::tui::format::format_duration_ms;
+
+/// How often a status line is written while a reader has `--status-fifo` open.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+mod sys {
+    use std::ffi::CString;
+    use std::os::raw::c_char;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    unsafe extern "C" {
+        fn mkfifo(path: *const c_char, mode: u16) -> i32;
+    }
+
+    /// Creates a FIFO at `path`, readable and writable by the user only.
+    pub fn make_fifo(path: &Path) -> std::io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        // SAFETY: path is a NUL-terminated string that outlives the call.
+        if unsafe { mkfifo(path.as_ptr(), 0o600) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+}
+
+/// The FIFOs this run created; removed on drop. FIFOs that already existed
+/// are left in place.
+#[derive(Debug, Default)]
+pub struct Fifos {
+    created: Vec<PathBuf>,
+}
+
+impl Drop for Fifos {
+    fn drop(&mut self) {
+        for path in &self.created {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Starts `--command-fifo` (commands read line by line, malformed ones shown
+/// as toasts) and `--status-fifo` (a status line per second while a reader
+/// has it open), each on its own thread. Missing FIFOs are created.
+pub fn start(
+    controller: Arc<PlaybackController>,
+    command_path: Option<&Path>,
+    status_path: Option<&Path>,
+    notices: Sender<String>,
+) -> Result<Fifos> {
+    let mut fifos = Fifos::default();
+    if let Some(path) = command_path {
+        if ensure_fifo(path)? {
+            fifos.created.push(path.to_path_buf());
+        }
+        let ctrl = controller.clone();
+        let path = path.to_path_buf();
+        std::thread::Builder::new()
+            .name("shifter-command-fifo".into())
+            .spawn(move || {
+                let e = read_commands(&path, |line| {
+                    let result = Command::parse(line).and_then(|command| command.apply(&ctrl));
+                    if let Err(e) = result {
+                        let _ = notices.send(format!("Command FIFO: {e}"));
+                    }
+                    ControlFlow::Continue(())
+                });
+                if let Some(e) = e {
+                    let _ = notices.send(format!("Command FIFO stopped: {e}"));
+                }
+            })
+            .map_err(|e| anyhow!("Failed to start command FIFO thread: {e}"))?;
+    }
+    if let Some(path) = status_path {
+        if ensure_fifo(path)? {
+            fifos.created.push(path.to_path_buf());
+        }
+        let path = path.to_path_buf();
+        std::thread::Builder::new()
+            .name("shifter-status-fifo".into())
+            .spawn(move || write_status(&path, &controller))
+            .map_err(|e| anyhow!("Failed to start status FIFO thread: {e}"))?;
+    }
+    Ok(fifos)
+}
+
+/// Makes sure `path` is a FIFO, creating it (and its directory) if missing.
+/// Returns whether it was created.
+fn ensure_fifo(path: &Path) -> Result<bool> {
+    match fs::metadata(path) {
+        Ok(meta) if meta.file_type().is_fifo() => Ok(false),
+        Ok(_) => Err(anyhow!(
+            "{} exists and is not a FIFO.\n\
+             Remove it or choose another path.",
+            path.display()
+        )),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)
+                    .map_err(|e| anyhow!("Failed to create {}: {e}", dir.display()))?;
+            }
+            sys::make_fifo(path)
+                .map_err(|e| anyhow!("Failed to create FIFO {}: {e}", path.display()))?;
+            Ok(true)
+        }
+        Err(e) => Err(anyhow!("Failed to read {}: {e}", path.display())),
+    }
+}
+
+/// Hands each non-empty line written to the FIFO at `path` to `handle`, until
+/// it breaks. Returns the error that stopped it.
+///
+/// The FIFO is opened for writing too, so it never reads EOF between writers:
+/// closing and reopening it there would throw away what a writer that came
+/// in meanwhile had already written.
+fn read_commands(
+    path: &Path,
+    mut handle: impl FnMut(&str) -> ControlFlow<()>,
+) -> Option<std::io::Error> {
+    let file = match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => file,
+        Err(e) => return Some(e),
+    };
+    // Blocks until a writer sends a line.
+    for line in BufReader::new(file).split(b'\n') {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(e),
+        };
+        let line = String::from_utf8_lossy(&line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        if handle(&line).is_break() {
+            return None;
+        }
+    }
+    None
+}
+
+/// Writes a status line to the FIFO at `path` every `STATUS_INTERVAL` while
+/// a reader has it open, waiting for the next reader when it goes away.
+fn write_status(path: &Path, controller: &PlaybackController) {
+    loop {
+        // Blocks until a reader opens the FIFO.
+        let Ok(mut file) = OpenOptions::new().write(true).open(path) else {
+            return;
+        };
+        // Writing fails (EPIPE) once the reader has closed it.
+        while writeln!(file, "{}", status_line(controller)).is_ok() {
+            std::thread::sleep(STATUS_INTERVAL);
+        }
+    }
+}
+
+/// One-line status for `--status-fifo`, e.g. "TIME-SHIFTED -30s buffer 42%
+/// volume 80%" (or "muted" for the volume, as in the status bar).
+pub fn status_line(controller: &PlaybackController) -> String {
+    let volume = if controller.is_muted() {
+        "muted".to_string()
+    } else {
+        format!("volume {:.0}%", controller.volume() * 100.0)
+    };
+    format!(
+        "{} -{} buffer {:.0}% {volume}",
+        controller.state().label(),
+        format_duration_ms(controller.delay_ms()),
+        controller.buffer_usage() * 100.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::audio::ring_buffer::AudioRingBuffer;
+    use crate::playback::state::PlaybackState;
+
+    #[test]
+    fn commands_are_read_across_writers_and_fifos_removed() {
+        let dir = std::env::temp_dir().join(format!("shifter-test-{}-fifo", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("cmd");
+        let ring = Arc::new(AudioRingBuffer::new(4_000));
+        let ctrl = Arc::new(PlaybackController::new(ring, 1, 1000));
+        let (notice_tx, notices) = mpsc::channel();
+        let fifos = start(ctrl.clone(), Some(&path), None, notice_tx).unwrap();
+        assert!(fs::metadata(&path).unwrap().file_type().is_fifo());
+        // A second run reuses the FIFO without claiming it.
+        assert!(!ensure_fifo(&path).unwrap());
+
+        // Two writers one after the other, as two `echo ... > cmd` would.
+        fs::write(&path, "pause\n").unwrap();
+        fs::write(&path, "dance\nmute\n").unwrap();
+        let notice = notices.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(notice, "Command FIFO: Unknown command 'dance'");
+        for _ in 0..500 {
+            if ctrl.is_muted() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(ctrl.state(), PlaybackState::Paused);
+        assert!(ctrl.is_muted());
+
+        drop(fifos);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reading_stops_when_the_handler_breaks() {
+        let dir =
+            std::env::temp_dir().join(format!("shifter-test-{}-fifo-read", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("cmd");
+        assert!(ensure_fifo(&path).unwrap());
+        let reader_path = path.clone();
+        let reader = std::thread::spawn(move || {
+            let mut lines = vec![];
+            let e = read_commands(&reader_path, |line| {
+                lines.push(line.to_string());
+                if line == "live" {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+            (lines, e.is_none())
+        });
+        fs::write(&path, "rewind 30s\n\n").unwrap();
+        fs::write(&path, "live\n").unwrap();
+        assert_eq!(
+            reader.join().unwrap(),
+            (vec!["rewind 30s".to_string(), "live".to_string()], true)
+        );
+
+        fs::write(dir.join("plain"), "").unwrap();
+        assert!(ensure_fifo(&dir.join("plain")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn status_line_reads_like_the_status_bar() {
+        let ring = Arc::new(AudioRingBuffer::new(4_000));
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.set_volume(0.8);
+        assert_eq!(status_line(&ctrl), "LIVE -0ms buffer 0% volume 80%");
+        ctrl.toggle_mute();
+        assert_eq!(status_line(&ctrl), "LIVE -0ms buffer 0% muted");
+    }
+}
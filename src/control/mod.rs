@@ -0,0 +1,7 @@
+pub mod command;
+pub mod fifo;
+pub mod notifications;
+pub mod power;
+pub mod script;
+pub mod status_file;
+pub mod wallclock;
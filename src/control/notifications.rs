@@ -0,0 +1,244 @@
+use std::sync::Arc;
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+use crate::console::json_string;
+use crate::control::command::Command;
+use crate::playback::controller::PlaybackController;
+
+/// Distributed notification observed for incoming commands.
+/// The notification object carries the command string.
+pub const COMMAND_NOTIFICATION: &str = "com.xesco.shifter.command";
+
+/// Distributed notification posted once per second with a JSON status payload
+/// as the notification object.
+pub const STATUS_NOTIFICATION: &str = "com.xesco.shifter.status";
+
+pub mod cf_notification {
+    use std::os::raw::{c_char, c_void};
+
+    type CFIndex = isize;
+    type CFTypeID = usize;
+    type CFStringRef = *const c_void;
+    type CFNotificationCenterRef = *mut c_void;
+    type CFNotificationCallback = extern "C" fn(
+        center: CFNotificationCenterRef,
+        observer: *mut c_void,
+        name: CFStringRef,
+        object: *const c_void,
+        user_info: *const c_void,
+    );
+
+    const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+    const K_CF_NOTIFICATION_DELIVER_IMMEDIATELY: CFIndex = 4;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        fn CFNotificationCenterGetDistributedCenter() -> CFNotificationCenterRef;
+        fn CFNotificationCenterAddObserver(
+            center: CFNotificationCenterRef,
+            observer: *const c_void,
+            call_back: CFNotificationCallback,
+            name: CFStringRef,
+            object: *const c_void,
+            suspension_behavior: CFIndex,
+        );
+        fn CFNotificationCenterPostNotification(
+            center: CFNotificationCenterRef,
+            name: CFStringRef,
+            object: *const c_void,
+            user_info: *const c_void,
+            deliver_immediately: u8,
+        );
+        fn CFStringCreateWithBytes(
+            alloc: *const c_void,
+            bytes: *const u8,
+            num_bytes: CFIndex,
+            encoding: u32,
+            is_external_representation: u8,
+        ) -> CFStringRef;
+        fn CFStringGetCString(
+            string: CFStringRef,
+            buffer: *mut c_char,
+            buffer_size: CFIndex,
+            encoding: u32,
+        ) -> u8;
+        fn CFStringGetTypeID() -> CFTypeID;
+        fn CFGetTypeID(cf: *const c_void) -> CFTypeID;
+        fn CFRelease(cf: *const c_void);
+        fn CFRunLoopRun();
+    }
+
+    type Handler = Box<dyn Fn(&str, Option<String>) + Send>;
+
+    /// Owned CFString, released on drop.
+    struct CfString(CFStringRef);
+
+    impl CfString {
+        fn new(s: &str) -> Option<Self> {
+            // SAFETY: bytes/len describe a valid UTF-8 buffer for the duration of the call.
+            let r = unsafe {
+                CFStringCreateWithBytes(
+                    std::ptr::null(),
+                    s.as_ptr(),
+                    s.len() as CFIndex,
+                    K_CF_STRING_ENCODING_UTF8,
+                    0,
+                )
+            };
+            if r.is_null() { None } else { Some(Self(r)) }
+        }
+    }
+
+    impl Drop for CfString {
+        fn drop(&mut self) {
+            // SAFETY: we own the reference returned by a Create function.
+            unsafe { CFRelease(self.0) };
+        }
+    }
+
+    fn to_string(cf: *const c_void) -> Option<String> {
+        if cf.is_null() {
+            return None;
+        }
+        // SAFETY: cf is a non-null CF object handed to us by CoreFoundation.
+        if unsafe { CFGetTypeID(cf) != CFStringGetTypeID() } {
+            return None;
+        }
+        let mut buf = [0 as c_char; 1024];
+        // SAFETY: cf is a CFString; buf is writable for buf.len() bytes.
+        let ok = unsafe {
+            CFStringGetCString(
+                cf,
+                buf.as_mut_ptr(),
+                buf.len() as CFIndex,
+                K_CF_STRING_ENCODING_UTF8,
+            )
+        };
+        if ok == 0 {
+            return None;
+        }
+        // SAFETY: CFStringGetCString succeeded, so buf is NUL-terminated.
+        let cstr = unsafe { std::ffi::CStr::from_ptr(buf.as_ptr()) };
+        cstr.to_str().ok().map(|s| s.to_owned())
+    }
+
+    extern "C" fn on_notification(
+        _center: CFNotificationCenterRef,
+        observer: *mut c_void,
+        name: CFStringRef,
+        object: *const c_void,
+        _user_info: *const c_void,
+    ) {
+        if observer.is_null() {
+            return;
+        }
+        // SAFETY: observer is the leaked `Handler` registered in `observe`, never freed.
+        let handler = unsafe { &*(observer as *const Handler) };
+        let name = to_string(name).unwrap_or_default();
+        handler(&name, to_string(object));
+    }
+
+    /// Registers `handler` for each notification name and runs the current
+    /// thread's run loop forever. Call from a dedicated thread.
+    pub fn observe(names: &[&str], handler: Handler) {
+        let observer = Box::into_raw(Box::new(handler)) as *const c_void;
+        // SAFETY: the distributed center is a process-wide singleton.
+        let center = unsafe { CFNotificationCenterGetDistributedCenter() };
+        for name in names {
+            let Some(cf_name) = CfString::new(name) else {
+                continue;
+            };
+            // SAFETY: the center retains the name; observer lives for the process lifetime.
+            unsafe {
+                CFNotificationCenterAddObserver(
+                    center,
+                    observer,
+                    on_notification,
+                    cf_name.0,
+                    std::ptr::null(),
+                    K_CF_NOTIFICATION_DELIVER_IMMEDIATELY,
+                );
+            }
+        }
+        // SAFETY: runs this thread's run loop, which delivers the callbacks above.
+        unsafe { CFRunLoopRun() };
+    }
+
+    /// Posts a distributed notification with a string object.
+    pub fn post(name: &str, object: &str) {
+        let (Some(cf_name), Some(cf_object)) = (CfString::new(name), CfString::new(object)) else {
+            return;
+        };
+        // SAFETY: both strings are valid CFStrings for the duration of the call.
+        unsafe {
+            CFNotificationCenterPostNotification(
+                CFNotificationCenterGetDistributedCenter(),
+                cf_name.0,
+                cf_object.0,
+                std::ptr::null(),
+                1,
+            );
+        }
+    }
+}
+
+/// Starts observing `com.xesco.shifter.command` notifications on a dedicated
+/// run-loop thread (malformed or failing ones shown as toasts), and posting
+/// `com.xesco.shifter.status` once per second and on every controller event.
+pub fn start(
+    controller: Arc<PlaybackController>,
+    instance: String,
+    notices: Sender<String>,
+) -> Result<()> {
+    let ctrl = controller.clone();
+    std::thread::Builder::new()
+        .name("shifter-notifications".into())
+        .spawn(move || {
+            cf_notification::observe(
+                &[COMMAND_NOTIFICATION],
+                Box::new(move |_name, object| {
+                    let result = Command::parse(object.as_deref().unwrap_or_default())
+                        .and_then(|command| command.apply(&ctrl));
+                    if let Err(e) = result {
+                        let _ = notices.send(format!("Command notification: {e}"));
+                    }
+                }),
+            );
+        })
+        .map_err(|e| anyhow!("Failed to start notification thread: {e}"))?;
+
+    // Post once per second, and right away when something changes.
+    let events = controller.events.subscribe();
+    std::thread::Builder::new()
+        .name("shifter-status".into())
+        .spawn(move || {
+            loop {
+                cf_notification::post(STATUS_NOTIFICATION, &status_json(&controller, &instance));
+                if let Err(RecvTimeoutError::Disconnected) =
+                    events.recv_timeout(Duration::from_secs(1))
+                {
+                    break;
+                }
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start status thread: {e}"))?;
+
+    Ok(())
+}
+
+/// Small JSON status payload: instance name, state, delay, buffer usage,
+/// volume and mute.
+pub fn status_json(controller: &PlaybackController, instance: &str) -> String {
+    format!(
+        "{{\"instance\":{},\"state\":\"{}\",\"delay_ms\":{:.1},\"buffer_usage\":{:.3},\"volume\":{:.2},\"muted\":{}}}",
+        json_string(instance),
+        controller.state().label(),
+        controller.delay_ms(),
+        controller.buffer_usage(),
+        controller.volume(),
+        controller.is_muted(),
+    )
+}
@@ -0,0 +1,349 @@
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Result, anyhow};
+
+use crate::control::notifications::cf_notification;
+use crate::playback::controller::PlaybackController;
+use crate::playback::state::PlaybackState;
+
+/// Distributed notifications posted by loginwindow when the screen locks/unlocks.
+pub const SCREEN_LOCKED_NOTIFICATION: &str = "com.apple.screenIsLocked";
+pub const SCREEN_UNLOCKED_NOTIFICATION: &str = "com.apple.screenIsUnlocked";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemEvent {
+    ScreenLocked,
+    ScreenUnlocked,
+    WillSleep,
+    DidWake,
+}
+
+impl SystemEvent {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ScreenLocked => "Screen locked",
+            Self::ScreenUnlocked => "Screen unlocked",
+            Self::WillSleep => "System sleeping",
+            Self::DidWake => "System woke",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reaction {
+    Pause,
+    Resume,
+}
+
+/// Decides when lock/sleep events should pause or resume playback.
+///
+/// Only pauses while playing, so a manual pause is never doubled up, and only
+/// resumes a pause it initiated itself once the screen is unlocked and the
+/// machine is awake.
+#[derive(Debug)]
+pub struct AutoPause {
+    auto_resume: bool,
+    locked: bool,
+    asleep: bool,
+    /// Whether the current pause was started by a lock/sleep event.
+    paused_by_us: bool,
+}
+
+impl AutoPause {
+    pub fn new(auto_resume: bool) -> Self {
+        Self {
+            auto_resume,
+            locked: false,
+            asleep: false,
+            paused_by_us: false,
+        }
+    }
+
+    pub fn on_event(&mut self, event: SystemEvent, state: PlaybackState) -> Option<Reaction> {
+        match event {
+            SystemEvent::ScreenLocked => self.locked = true,
+            SystemEvent::WillSleep => self.asleep = true,
+            SystemEvent::ScreenUnlocked => self.locked = false,
+            SystemEvent::DidWake => self.asleep = false,
+        }
+
+        match event {
+            SystemEvent::ScreenLocked | SystemEvent::WillSleep => {
+                if state == PlaybackState::Paused {
+                    return None;
+                }
+                self.paused_by_us = true;
+                Some(Reaction::Pause)
+            }
+            SystemEvent::ScreenUnlocked | SystemEvent::DidWake => {
+                if self.locked || self.asleep || !self.paused_by_us {
+                    return None;
+                }
+                self.paused_by_us = false;
+                // The user may have resumed by hand while locked; leave that alone.
+                (self.auto_resume && state == PlaybackState::Paused).then_some(Reaction::Resume)
+            }
+        }
+    }
+}
+
+mod io_power {
+    use std::os::raw::c_void;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use anyhow::{Result, anyhow};
+
+    use crate::control::power::SystemEvent;
+
+    type IoConnect = u32;
+    type IoObject = u32;
+    type IONotificationPortRef = *mut c_void;
+    type CFRunLoopRef = *mut c_void;
+    type CFRunLoopSourceRef = *mut c_void;
+    type CFStringRef = *const c_void;
+    type IOServiceInterestCallback = extern "C" fn(
+        refcon: *mut c_void,
+        service: IoObject,
+        message_type: u32,
+        message_argument: *mut c_void,
+    );
+
+    const K_IO_MESSAGE_CAN_SYSTEM_SLEEP: u32 = 0xE000_0270;
+    const K_IO_MESSAGE_SYSTEM_WILL_SLEEP: u32 = 0xE000_0280;
+    const K_IO_MESSAGE_SYSTEM_HAS_POWERED_ON: u32 = 0xE000_0300;
+
+    #[link(name = "IOKit", kind = "framework")]
+    unsafe extern "C" {
+        fn IORegisterForSystemPower(
+            refcon: *mut c_void,
+            the_port_ref: *mut IONotificationPortRef,
+            callback: IOServiceInterestCallback,
+            notifier: *mut IoObject,
+        ) -> IoConnect;
+        fn IONotificationPortGetRunLoopSource(notify: IONotificationPortRef) -> CFRunLoopSourceRef;
+        fn IOAllowPowerChange(kernel_port: IoConnect, notification_id: isize) -> i32;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        fn CFRunLoopGetCurrent() -> CFRunLoopRef;
+        fn CFRunLoopAddSource(rl: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+        static kCFRunLoopDefaultMode: CFStringRef;
+    }
+
+    type Handler = Box<dyn Fn(SystemEvent) + Send>;
+
+    struct Observer {
+        /// Root power domain connection, needed to acknowledge sleep messages.
+        root_port: AtomicU32,
+        handler: Handler,
+    }
+
+    impl Observer {
+        fn allow(&self, message_argument: *mut c_void) {
+            // SAFETY: root_port is the connection returned by IORegisterForSystemPower;
+            // the notification id is the message argument we were handed.
+            unsafe {
+                IOAllowPowerChange(
+                    self.root_port.load(Ordering::Acquire),
+                    message_argument as isize,
+                );
+            }
+        }
+    }
+
+    extern "C" fn on_power(
+        refcon: *mut c_void,
+        _service: IoObject,
+        message_type: u32,
+        message_argument: *mut c_void,
+    ) {
+        if refcon.is_null() {
+            return;
+        }
+        // SAFETY: refcon is the leaked `Observer` registered in `register`, never freed.
+        let observer = unsafe { &*(refcon as *const Observer) };
+        match message_type {
+            K_IO_MESSAGE_CAN_SYSTEM_SLEEP => observer.allow(message_argument),
+            K_IO_MESSAGE_SYSTEM_WILL_SLEEP => {
+                (observer.handler)(SystemEvent::WillSleep);
+                observer.allow(message_argument);
+            }
+            K_IO_MESSAGE_SYSTEM_HAS_POWERED_ON => (observer.handler)(SystemEvent::DidWake),
+            _ => {}
+        }
+    }
+
+    /// Registers `handler` for sleep/wake on the current thread's run loop.
+    /// The caller must run that run loop for callbacks to be delivered.
+    pub fn register(handler: Handler) -> Result<()> {
+        let observer = Box::into_raw(Box::new(Observer {
+            root_port: AtomicU32::new(0),
+            handler,
+        }));
+        let mut port: IONotificationPortRef = std::ptr::null_mut();
+        let mut notifier: IoObject = 0;
+        // SAFETY: out-pointers are valid locals; observer lives for the process lifetime.
+        let root_port = unsafe {
+            IORegisterForSystemPower(observer as *mut c_void, &mut port, on_power, &mut notifier)
+        };
+        if root_port == 0 || port.is_null() {
+            // SAFETY: registration failed, so IOKit holds no reference to observer.
+            drop(unsafe { Box::from_raw(observer) });
+            return Err(anyhow!("IORegisterForSystemPower failed"));
+        }
+        // SAFETY: observer was leaked above and is only read by the callback.
+        unsafe { (*observer).root_port.store(root_port, Ordering::Release) };
+        // SAFETY: port is a valid notification port; the current run loop outlives the source.
+        unsafe {
+            CFRunLoopAddSource(
+                CFRunLoopGetCurrent(),
+                IONotificationPortGetRunLoopSource(port),
+                kCFRunLoopDefaultMode,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Watches screen lock/unlock and sleep/wake on a dedicated run-loop thread,
+/// pausing playback on lock/sleep and optionally resuming on unlock.
+/// A short description of each event and action is sent to `notices`.
+pub fn start(
+    controller: Arc<PlaybackController>,
+    auto_resume: bool,
+    notices: Sender<String>,
+) -> Result<()> {
+    let auto_pause = Mutex::new(AutoPause::new(auto_resume));
+    let react = Arc::new(move |event: SystemEvent| {
+        if event == SystemEvent::DidWake {
+            controller.record_wake();
+        }
+        let Ok(mut auto_pause) = auto_pause.lock() else {
+            return;
+        };
+        let notice = match auto_pause.on_event(event, controller.state()) {
+            Some(Reaction::Pause) => {
+                controller.toggle_pause();
+                format!("{} \u{2014} paused", event.label())
+            }
+            Some(Reaction::Resume) => {
+                controller.toggle_pause();
+                format!("{} \u{2014} resumed", event.label())
+            }
+            None => event.label().to_string(),
+        };
+        let _ = notices.send(notice);
+    });
+
+    std::thread::Builder::new()
+        .name("shifter-power".into())
+        .spawn(move || {
+            let on_power = react.clone();
+            // Lock/unlock still works if sleep notifications can't be registered.
+            let _ = io_power::register(Box::new(move |event| on_power(event)));
+            cf_notification::observe(
+                &[SCREEN_LOCKED_NOTIFICATION, SCREEN_UNLOCKED_NOTIFICATION],
+                Box::new(move |name, _object| {
+                    if name == SCREEN_LOCKED_NOTIFICATION {
+                        react(SystemEvent::ScreenLocked);
+                    } else if name == SCREEN_UNLOCKED_NOTIFICATION {
+                        react(SystemEvent::ScreenUnlocked);
+                    }
+                }),
+            );
+        })
+        .map_err(|e| anyhow!("Failed to start power notification thread: {e}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_pauses_while_playing() {
+        let mut ap = AutoPause::new(false);
+        assert_eq!(
+            ap.on_event(SystemEvent::ScreenLocked, PlaybackState::Live),
+            Some(Reaction::Pause)
+        );
+        let mut ap = AutoPause::new(false);
+        assert_eq!(
+            ap.on_event(SystemEvent::WillSleep, PlaybackState::TimeShifted),
+            Some(Reaction::Pause)
+        );
+    }
+
+    #[test]
+    fn manual_pause_is_left_alone() {
+        let mut ap = AutoPause::new(true);
+        assert_eq!(
+            ap.on_event(SystemEvent::ScreenLocked, PlaybackState::Paused),
+            None
+        );
+        assert_eq!(
+            ap.on_event(SystemEvent::ScreenUnlocked, PlaybackState::Paused),
+            None
+        );
+    }
+
+    #[test]
+    fn unlock_resumes_only_with_auto_resume() {
+        let mut ap = AutoPause::new(false);
+        ap.on_event(SystemEvent::ScreenLocked, PlaybackState::Live);
+        assert_eq!(
+            ap.on_event(SystemEvent::ScreenUnlocked, PlaybackState::Paused),
+            None
+        );
+
+        let mut ap = AutoPause::new(true);
+        ap.on_event(SystemEvent::ScreenLocked, PlaybackState::Live);
+        assert_eq!(
+            ap.on_event(SystemEvent::ScreenUnlocked, PlaybackState::Paused),
+            Some(Reaction::Resume)
+        );
+    }
+
+    #[test]
+    fn lock_then_sleep_pauses_once() {
+        let mut ap = AutoPause::new(true);
+        ap.on_event(SystemEvent::ScreenLocked, PlaybackState::Live);
+        assert_eq!(
+            ap.on_event(SystemEvent::WillSleep, PlaybackState::Paused),
+            None
+        );
+    }
+
+    #[test]
+    fn wake_waits_for_unlock() {
+        let mut ap = AutoPause::new(true);
+        ap.on_event(SystemEvent::ScreenLocked, PlaybackState::Live);
+        ap.on_event(SystemEvent::WillSleep, PlaybackState::Paused);
+        assert_eq!(
+            ap.on_event(SystemEvent::DidWake, PlaybackState::Paused),
+            None
+        );
+        assert_eq!(
+            ap.on_event(SystemEvent::ScreenUnlocked, PlaybackState::Paused),
+            Some(Reaction::Resume)
+        );
+    }
+
+    #[test]
+    fn manual_resume_while_locked_is_not_resumed_again() {
+        let mut ap = AutoPause::new(true);
+        ap.on_event(SystemEvent::ScreenLocked, PlaybackState::Live);
+        assert_eq!(
+            ap.on_event(SystemEvent::ScreenUnlocked, PlaybackState::Live),
+            None
+        );
+        // The auto-pause is forgotten, so a later manual pause isn't resumed.
+        assert_eq!(
+            ap.on_event(SystemEvent::DidWake, PlaybackState::Paused),
+            None
+        );
+    }
+}
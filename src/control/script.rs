@@ -0,0 +1,401 @@
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use crossterm::event::{KeyCode, KeyModifiers};
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, Scope};
+
+use crate::control::command::Command;
+use crate::playback::controller::PlaybackController;
+
+/// How often `on_tick()` is called.
+pub const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Execution time budget for a single script call. Longer calls are aborted.
+const CALL_BUDGET: Duration = Duration::from_millis(10);
+
+/// Read-only status exposed to scripts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Status {
+    pub state: &'static str,
+    pub delay_ms: f64,
+    pub peak_left: f32,
+    pub peak_right: f32,
+    pub buffer_usage: f64,
+    /// Volume as a fraction (1.0 = 100%).
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Status {
+    pub fn from_controller(controller: &PlaybackController) -> Self {
+        let (peak_left, peak_right) = controller.peak_levels();
+        Self {
+            state: controller.state().label(),
+            delay_ms: controller.delay_ms(),
+            peak_left,
+            peak_right,
+            buffer_usage: controller.buffer_usage(),
+            volume: controller.volume(),
+            muted: controller.is_muted(),
+        }
+    }
+}
+
+/// Something a script asked for. Applied by the caller after the call returns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    Command(Command),
+    Mark,
+    Toast(String),
+}
+
+/// State shared between the registered API functions and `Script`.
+#[derive(Default)]
+struct Shared {
+    status: RefCell<Status>,
+    actions: RefCell<Vec<ScriptAction>>,
+    /// When the current call must finish by.
+    deadline: Cell<Option<Instant>>,
+}
+
+impl Shared {
+    fn push(&self, action: ScriptAction) {
+        self.actions.borrow_mut().push(action);
+    }
+}
+
+/// A user script with optional `on_tick()` and `on_key(key)` callbacks.
+///
+/// Scripts only see a `Status` snapshot and queue `ScriptAction`s; they never
+/// touch the controller directly.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    shared: Rc<Shared>,
+    has_on_tick: bool,
+    has_on_key: bool,
+    last_tick: Instant,
+}
+
+impl Script {
+    /// `~/.config/shifter/script.rhai`, if `$HOME` is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".config/shifter/script.rhai"))
+    }
+
+    /// Loads and runs the script at `path`. Returns `Ok(None)` if it doesn't exist.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+        Self::compile(&source).map(Some)
+    }
+
+    /// Compiles `source` and runs its top-level statements once.
+    pub fn compile(source: &str) -> Result<Self> {
+        let shared = Rc::new(Shared::default());
+        let mut engine = Engine::new();
+        register_api(&mut engine, &shared);
+
+        let budget = shared.clone();
+        engine.on_progress(move |_ops| {
+            let expired = budget
+                .deadline
+                .get()
+                .is_some_and(|deadline| Instant::now() > deadline);
+            expired.then(|| Dynamic::from("time budget exceeded"))
+        });
+
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow!("Script error: {e}"))?;
+        let has_fn = |name: &str, arity: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == arity)
+        };
+        let has_on_tick = has_fn("on_tick", 0);
+        let has_on_key = has_fn("on_key", 1);
+
+        let mut script = Self {
+            engine,
+            ast,
+            scope: Scope::new(),
+            shared,
+            has_on_tick,
+            has_on_key,
+            last_tick: Instant::now(),
+        };
+        script.with_budget(|s| s.engine.run_ast_with_scope(&mut s.scope, &s.ast))?;
+        Ok(script)
+    }
+
+    /// Calls `on_tick()` if a tick is due. Called every TUI frame.
+    pub fn tick(&mut self, status: impl FnOnce() -> Status) -> Result<Vec<ScriptAction>> {
+        if !self.has_on_tick || self.last_tick.elapsed() < TICK_INTERVAL {
+            return Ok(Vec::new());
+        }
+        self.last_tick = Instant::now();
+        self.on_tick(status())
+    }
+
+    /// Calls `on_tick()` unconditionally.
+    pub fn on_tick(&mut self, status: Status) -> Result<Vec<ScriptAction>> {
+        if !self.has_on_tick {
+            return Ok(Vec::new());
+        }
+        self.shared.status.replace(status);
+        self.call("on_tick", ()).map(|_| self.shared.actions.take())
+    }
+
+    /// Offers a key to `on_key(key)` before the keymap sees it.
+    /// Returns whether the script consumed the key, and its queued actions.
+    pub fn on_key(&mut self, key: &str, status: Status) -> Result<(bool, Vec<ScriptAction>)> {
+        if !self.has_on_key {
+            return Ok((false, Vec::new()));
+        }
+        self.shared.status.replace(status);
+        let result = self.call("on_key", (key.to_string(),));
+        let actions = self.shared.actions.take();
+        let consumed = result?.as_bool().unwrap_or(false);
+        Ok((consumed, actions))
+    }
+
+    fn call(&mut self, name: &str, args: impl rhai::FuncArgs) -> Result<Dynamic> {
+        self.with_budget(|s| {
+            let options = CallFnOptions::new().eval_ast(false);
+            s.engine
+                .call_fn_with_options::<Dynamic>(options, &mut s.scope, &s.ast, name, args)
+        })
+        .map_err(|e| anyhow!("{name}: {e}"))
+    }
+
+    /// Runs `f` with the per-call time budget armed.
+    fn with_budget<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, Box<EvalAltResult>>,
+    ) -> Result<T> {
+        self.shared.actions.borrow_mut().clear();
+        self.shared.deadline.set(Some(Instant::now() + CALL_BUDGET));
+        let result = f(self);
+        self.shared.deadline.set(None);
+        result.map_err(|e| match *e {
+            EvalAltResult::ErrorTerminated(..) => anyhow!(
+                "Script aborted: exceeded {}ms budget",
+                CALL_BUDGET.as_millis()
+            ),
+            e => anyhow!("Script error: {e}"),
+        })
+    }
+}
+
+/// Registers the script API: status getters and queued commands.
+fn register_api(engine: &mut Engine, shared: &Rc<Shared>) {
+    let s = shared.clone();
+    engine.register_fn("state", move || s.status.borrow().state.to_string());
+    let s = shared.clone();
+    engine.register_fn("delay_ms", move || s.status.borrow().delay_ms);
+    let s = shared.clone();
+    engine.register_fn("peak_left", move || s.status.borrow().peak_left as f64);
+    let s = shared.clone();
+    engine.register_fn("peak_right", move || s.status.borrow().peak_right as f64);
+    let s = shared.clone();
+    engine.register_fn("peak", move || {
+        let status = s.status.borrow();
+        status.peak_left.max(status.peak_right) as f64
+    });
+    let s = shared.clone();
+    engine.register_fn("buffer_usage", move || s.status.borrow().buffer_usage);
+    let s = shared.clone();
+    engine.register_fn("volume", move || s.status.borrow().volume as f64 * 100.0);
+    let s = shared.clone();
+    engine.register_fn("muted", move || s.status.borrow().muted);
+
+    let s = shared.clone();
+    engine.register_fn("seek", move |ms: f64| {
+        s.push(ScriptAction::Command(Command::Seek(ms)))
+    });
+    let s = shared.clone();
+    engine.register_fn("seek", move |ms: i64| {
+        s.push(ScriptAction::Command(Command::Seek(ms as f64)))
+    });
+    let s = shared.clone();
+    engine.register_fn("volume", move |percent: f64| {
+        let volume = (percent / 100.0).clamp(0.0, 1.5) as f32;
+        s.push(ScriptAction::Command(Command::Volume(volume)))
+    });
+    let s = shared.clone();
+    engine.register_fn("volume", move |percent: i64| {
+        let volume = (percent as f64 / 100.0).clamp(0.0, 1.5) as f32;
+        s.push(ScriptAction::Command(Command::Volume(volume)))
+    });
+    for (name, command) in [
+        ("pause", Command::Pause),
+        ("resume", Command::Resume),
+        ("toggle", Command::TogglePause),
+        ("live", Command::Live),
+        ("mute", Command::ToggleMute),
+    ] {
+        let s = shared.clone();
+        engine.register_fn(name, move || s.push(ScriptAction::Command(command)));
+    }
+    let s = shared.clone();
+    engine.register_fn("mark", move || s.push(ScriptAction::Mark));
+    let s = shared.clone();
+    engine.register_fn("toast", move |message: &str| {
+        s.push(ScriptAction::Toast(message.to_string()))
+    });
+}
+
+/// Name passed to `on_key`, e.g. "x", "space", "left", "ctrl+k".
+pub fn key_name(code: KeyCode, modifiers: KeyModifiers) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c) => c.to_lowercase().to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::BackTab => "backtab".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::F(n) => format!("f{n}"),
+        _ => return None,
+    };
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        Some(format!("ctrl+{name}"))
+    } else {
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn on_tick_marks_when_level_exceeds_threshold() {
+        let mut script = Script::compile(
+            r#"
+            fn on_tick() {
+                if peak() > 0.5 { mark(); toast("loud"); }
+            }
+            "#,
+        )
+        .unwrap();
+        let quiet = Status {
+            peak_left: 0.2,
+            ..Status::default()
+        };
+        let loud = Status {
+            peak_right: 0.9,
+            ..Status::default()
+        };
+        assert!(script.on_tick(quiet).unwrap().is_empty());
+        assert_eq!(
+            script.on_tick(loud).unwrap(),
+            vec![ScriptAction::Mark, ScriptAction::Toast("loud".into())]
+        );
+    }
+
+    #[test]
+    fn on_key_can_consume_keys_and_issue_commands() {
+        let mut script = Script::compile(
+            r#"
+            fn on_key(key) {
+                if key == "x" { seek(-5000); volume(80); return true; }
+                false
+            }
+            "#,
+        )
+        .unwrap();
+        let (consumed, actions) = script.on_key("x", Status::default()).unwrap();
+        assert!(consumed);
+        assert_eq!(
+            actions,
+            vec![
+                ScriptAction::Command(Command::Seek(-5000.0)),
+                ScriptAction::Command(Command::Volume(0.8)),
+            ]
+        );
+        assert_eq!(
+            script.on_key("q", Status::default()).unwrap(),
+            (false, vec![])
+        );
+    }
+
+    #[test]
+    fn status_getters_reflect_snapshot() {
+        let mut script = Script::compile(
+            r#"
+            fn on_tick() {
+                if state() == "LIVE" && volume() == 100.0 && !muted() { pause(); }
+            }
+            "#,
+        )
+        .unwrap();
+        let status = Status {
+            state: "LIVE",
+            volume: 1.0,
+            ..Status::default()
+        };
+        assert_eq!(
+            script.on_tick(status).unwrap(),
+            vec![ScriptAction::Command(Command::Pause)]
+        );
+    }
+
+    #[test]
+    fn missing_callbacks_are_no_ops() {
+        let mut script = Script::compile("let x = 1;").unwrap();
+        assert!(script.on_tick(Status::default()).unwrap().is_empty());
+        assert_eq!(
+            script.on_key("x", Status::default()).unwrap(),
+            (false, vec![])
+        );
+    }
+
+    #[test]
+    fn runaway_script_is_aborted() {
+        let mut script = Script::compile("fn on_tick() { loop { } }").unwrap();
+        let err = script.on_tick(Status::default()).unwrap_err();
+        assert!(err.to_string().contains("budget"));
+    }
+
+    #[test]
+    fn syntax_errors_are_reported() {
+        assert!(Script::compile("fn on_tick( {").is_err());
+    }
+
+    #[test]
+    fn runtime_errors_are_reported() {
+        let mut script = Script::compile("fn on_tick() { undefined_fn(); }").unwrap();
+        assert!(script.on_tick(Status::default()).is_err());
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(
+            key_name(KeyCode::Char(' '), KeyModifiers::NONE).as_deref(),
+            Some("space")
+        );
+        assert_eq!(
+            key_name(KeyCode::Char('K'), KeyModifiers::SHIFT).as_deref(),
+            Some("k")
+        );
+        assert_eq!(
+            key_name(KeyCode::Char('c'), KeyModifiers::CONTROL).as_deref(),
+            Some("ctrl+c")
+        );
+        assert_eq!(key_name(KeyCode::Home, KeyModifiers::NONE), None);
+    }
+}
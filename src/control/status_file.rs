@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+
+use crate::console::json_string;
+use crate::control::fifo;
+use crate::playback::controller::PlaybackController;
+
+/// How often the status file is rewritten.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What `--status-file` contains, chosen with `--status-file-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum StatusFormat {
+    /// One JSON object (see `StatusFile::json`).
+    #[default]
+    Json,
+    /// One line, as `--status-fifo` writes it, plus the devices.
+    Plain,
+}
+
+/// `--status-file`: the status, rewritten once per second for tmux status
+/// lines and menu bar plugins to read. Each write goes to a temp file that is
+/// then renamed over the status file, so readers never see half of one. The
+/// temp file has a fixed name, so a crash leaves at most one behind, and the
+/// next run removes it. The status file is removed on drop.
+pub struct StatusFile {
+    path: PathBuf,
+    temp: PathBuf,
+    format: StatusFormat,
+    last_write: Option<Instant>,
+}
+
+impl StatusFile {
+    /// Version of the JSON schema; bumped only when a field is removed or
+    /// changes meaning.
+    pub const SCHEMA: u32 = 1;
+
+    /// `~/.shifter/status.json`, where `--status-file` without a path writes.
+    pub fn default_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".shifter/status.json"))
+    }
+
+    /// Creates the file's directory if missing and removes a temp file left
+    /// by a crash. Nothing is written before the first `tick`.
+    pub fn create(path: &Path, format: StatusFormat) -> Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("Status file path {} has no file name", path.display()))?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .map_err(|e| anyhow!("Failed to create {}: {e}", dir.display()))?;
+        }
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(".tmp");
+        let temp = path.with_file_name(temp_name);
+        let _ = fs::remove_file(&temp);
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp,
+            format,
+            last_write: None,
+        })
+    }
+
+    /// Rewrites the file if `STATUS_INTERVAL` has passed since the last write.
+    pub fn tick(
+        &mut self,
+        now: Instant,
+        controller: &PlaybackController,
+        input: &str,
+        output: &str,
+    ) -> Result<()> {
+        if self
+            .last_write
+            .is_some_and(|last| now.saturating_duration_since(last) < STATUS_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_write = Some(now);
+        let contents = match self.format {
+            StatusFormat::Json => Self::json(controller, input, output),
+            StatusFormat::Plain => Self::plain(controller, input, output),
+        };
+        self.write(&contents)
+    }
+
+    /// The JSON status, one line:
+    ///
+    /// ```text
+    /// {"schema":1,"state":"TIME-SHIFTED","delay_s":30.0,"buffer_usage":0.420,
+    ///  "volume":0.80,"muted":false,"input":"BlackHole 2ch","output":"MacBook Pro Speakers"}
+    /// ```
+    pub fn json(controller: &PlaybackController, input: &str, output: &str) -> String {
+        format!(
+            "{{\"schema\":{},\"state\":\"{}\",\"delay_s\":{:.1},\"buffer_usage\":{:.3},\
+             \"volume\":{:.2},\"muted\":{},\"input\":{},\"output\":{}}}\n",
+            Self::SCHEMA,
+            controller.state().label(),
+            controller.delay_ms() / 1000.0,
+            controller.buffer_usage(),
+            controller.volume(),
+            controller.is_muted(),
+            json_string(input),
+            json_string(output),
+        )
+    }
+
+    /// The `--status-fifo` line followed by the devices, e.g. "LIVE -0ms
+    /// buffer 42% volume 80% BlackHole 2ch -> MacBook Pro Speakers".
+    pub fn plain(controller: &PlaybackController, input: &str, output: &str) -> String {
+        format!("{} {input} -> {output}\n", fifo::status_line(controller))
+    }
+
+    fn write(&self, contents: &str) -> Result<()> {
+        fs::write(&self.temp, contents)
+            .and_then(|_| fs::rename(&self.temp, &self.path))
+            .map_err(|e| anyhow!("Status file {} stopped: {e}", self.path.display()))
+    }
+}
+
+impl Drop for StatusFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.temp);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::audio::ring_buffer::AudioRingBuffer;
+
+    #[test]
+    fn writes_replace_the_file_whole_and_leave_no_temp_files() {
+        let dir =
+            std::env::temp_dir().join(format!("shifter-test-{}-status-file", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("status.json");
+        // A temp file left by a crash is cleaned up.
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".status.json.tmp"), "{\"sta").unwrap();
+        let mut file = StatusFile::create(&path, StatusFormat::Json).unwrap();
+        let names = || -> Vec<String> {
+            fs::read_dir(&dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect()
+        };
+        assert!(names().is_empty());
+
+        let ring = Arc::new(AudioRingBuffer::new(4_000));
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        let start = Instant::now();
+        file.tick(start, &ctrl, "In", "Out").unwrap();
+        assert_eq!(names(), ["status.json"]);
+        let first = fs::read_to_string(&path).unwrap();
+        assert!(first.contains("\"volume\":1.00"));
+
+        // Within the interval nothing is written; after it, the new status.
+        ctrl.set_volume(0.5);
+        file.tick(start + Duration::from_millis(500), &ctrl, "In", "Out")
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), first);
+        file.tick(start + Duration::from_secs(1), &ctrl, "In", "Out")
+            .unwrap();
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .contains("\"volume\":0.50")
+        );
+        assert_eq!(names(), ["status.json"]);
+
+        drop(file);
+        assert!(names().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn json_status_has_a_stable_schema() {
+        let ring = Arc::new(AudioRingBuffer::new(4_000));
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.set_volume(0.8);
+        ctrl.toggle_pause();
+        let json = StatusFile::json(&ctrl, "BlackHole 2ch", "Bob's \"Desk\"");
+        assert_eq!(json.lines().count(), 1);
+        let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(v["schema"], StatusFile::SCHEMA);
+        assert_eq!(v["state"], "PAUSED");
+        assert_eq!(v["delay_s"], 0.0);
+        assert_eq!(v["buffer_usage"], 0.0);
+        assert_eq!(v["volume"], 0.8);
+        assert_eq!(v["muted"], false);
+        assert_eq!(v["input"], "BlackHole 2ch");
+        assert_eq!(v["output"], "Bob's \"Desk\"");
+
+        assert_eq!(
+            StatusFile::plain(&ctrl, "In", "Out"),
+            "PAUSED -0ms buffer 0% volume 80% In -> Out\n"
+        );
+    }
+}
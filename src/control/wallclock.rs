@@ -0,0 +1,105 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+mod cf_time {
+    use std::os::raw::c_void;
+
+    type CFTimeZoneRef = *const c_void;
+    type CFAbsoluteTime = f64;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        fn CFTimeZoneCopySystem() -> CFTimeZoneRef;
+        fn CFTimeZoneGetSecondsFromGMT(tz: CFTimeZoneRef, at: CFAbsoluteTime) -> f64;
+        fn CFAbsoluteTimeGetCurrent() -> CFAbsoluteTime;
+        fn CFRelease(cf: *const c_void);
+    }
+
+    /// Current offset of the system time zone from UTC in seconds (DST included).
+    pub fn utc_offset_seconds() -> f64 {
+        // SAFETY: CFTimeZoneCopySystem returns an owned reference (or null),
+        // released below; the other calls take plain values.
+        unsafe {
+            let tz = CFTimeZoneCopySystem();
+            if tz.is_null() {
+                return 0.0;
+            }
+            let offset = CFTimeZoneGetSecondsFromGMT(tz, CFAbsoluteTimeGetCurrent());
+            CFRelease(tz);
+            offset
+        }
+    }
+}
+
+/// Parses a wall-clock time "HH:MM", "HH:MM:SS" or "HH:MM:SS.sss" (24-hour)
+/// into seconds since midnight.
+pub fn parse_time_of_day(value: &str) -> Result<f64> {
+    let invalid = || anyhow!("Invalid time '{value}' (expected HH:MM or HH:MM:SS)");
+    let parts: Vec<&str> = value.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return Err(invalid());
+    }
+    let hours: u32 = parts[0].parse().map_err(|_| invalid())?;
+    let minutes: u32 = parts[1].parse().map_err(|_| invalid())?;
+    let seconds: f64 = match parts.get(2) {
+        Some(s) => s.parse().map_err(|_| invalid())?,
+        None => 0.0,
+    };
+    if hours > 23 || minutes > 59 || !(0.0..60.0).contains(&seconds) {
+        return Err(invalid());
+    }
+    Ok(hours as f64 * 3600.0 + minutes as f64 * 60.0 + seconds)
+}
+
+/// How long ago `target` was, both in seconds since local midnight. A target
+/// later than `now` means yesterday.
+pub fn seconds_ago(now: f64, target: f64) -> f64 {
+    (now - target).rem_euclid(SECONDS_PER_DAY)
+}
+
+/// Seconds since local midnight, now.
+pub fn local_seconds_of_day() -> f64 {
+    let utc = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64());
+    (utc + cf_time::utc_offset_seconds()).rem_euclid(SECONDS_PER_DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hours_minutes_and_seconds() {
+        assert_eq!(parse_time_of_day("14:30").unwrap(), 52_200.0);
+        assert_eq!(parse_time_of_day("14:30:05").unwrap(), 52_205.0);
+        assert_eq!(parse_time_of_day("00:00:00.5").unwrap(), 0.5);
+        assert_eq!(parse_time_of_day("9:05").unwrap(), 32_700.0);
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        assert!(parse_time_of_day("").is_err());
+        assert!(parse_time_of_day("14").is_err());
+        assert!(parse_time_of_day("24:00").is_err());
+        assert!(parse_time_of_day("12:60").is_err());
+        assert!(parse_time_of_day("12:30:60").is_err());
+        assert!(parse_time_of_day("12:30:00:00").is_err());
+        assert!(parse_time_of_day("noon").is_err());
+    }
+
+    #[test]
+    fn earlier_today_is_seconds_ago() {
+        assert_eq!(seconds_ago(52_230.0, 52_200.0), 30.0);
+        assert_eq!(seconds_ago(52_200.0, 52_200.0), 0.0);
+    }
+
+    #[test]
+    fn later_time_means_yesterday() {
+        // At 00:00:10, "23:59:50" was 20 seconds ago.
+        assert_eq!(seconds_ago(10.0, 86_390.0), 20.0);
+    }
+}
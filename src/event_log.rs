@@ -0,0 +1,370 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+
+use crate::playback::events::ShifterEvent;
+use crate::playback::state::PlaybackState;
+
+/// First line of the CSV, after the `#` metadata line.
+const HEADER: &str = "wall_clock,position,event,to_position";
+
+/// What the user did. Written as the `event` column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Mark,
+    Pause,
+    Resume,
+    Seek,
+    Live,
+}
+
+impl EventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mark => "mark",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Seek => "seek",
+            Self::Live => "live",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Mark,
+            Self::Pause,
+            Self::Resume,
+            Self::Seek,
+            Self::Live,
+        ]
+        .into_iter()
+        .find(|kind| kind.name() == name)
+    }
+}
+
+/// One row. Positions are stream frames since capture started (the ring's
+/// write position divided by the channel count), so they line up with a
+/// recording of the input that starts with the session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    /// Local wall-clock time, seconds since midnight.
+    pub wall_seconds: f64,
+    /// Frame playing when the event happened.
+    pub position: u64,
+    pub kind: EventKind,
+    /// Frame playing after a seek or jump to live.
+    pub to_position: Option<u64>,
+}
+
+impl Event {
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            format_wall_clock(self.wall_seconds),
+            self.position,
+            self.kind.name(),
+            self.to_position.map_or_else(String::new, |p| p.to_string()),
+        )
+    }
+
+    pub fn parse_csv(line: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid event log row '{line}'");
+        let fields: Vec<&str> = line.split(',').collect();
+        let [wall_clock, position, kind, to_position] = fields[..] else {
+            return Err(invalid());
+        };
+        Ok(Self {
+            wall_seconds: parse_wall_clock(wall_clock).ok_or_else(invalid)?,
+            position: position.parse().map_err(|_| invalid())?,
+            kind: EventKind::from_name(kind).ok_or_else(invalid)?,
+            to_position: match to_position {
+                "" => None,
+                p => Some(p.parse().map_err(|_| invalid())?),
+            },
+        })
+    }
+}
+
+/// `--event-log` writer. Each row is flushed as it is written, so the file is
+/// complete even if Shifter is killed.
+pub struct EventLog {
+    writer: BufWriter<File>,
+    channels: u16,
+}
+
+impl EventLog {
+    /// Creates (or truncates) `path` and writes the metadata and header lines.
+    pub fn create(path: &Path, sample_rate: u32, channels: u16) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| anyhow!("Failed to create event log {}: {e}", path.display()))?;
+        let mut log = Self {
+            writer: BufWriter::new(file),
+            channels: channels.max(1),
+        };
+        log.write_line(&format!(
+            "# shifter event log, sample_rate={sample_rate}, channels={channels}"
+        ))?;
+        log.write_line(HEADER)?;
+        Ok(log)
+    }
+
+    pub fn write(&mut self, event: &Event) -> Result<()> {
+        self.write_line(&event.csv_row())
+    }
+
+    /// Logs a controller event: bookmarks, pause/resume and seeks, whatever
+    /// caused them (keys, script, notifications, screen lock). Other events
+    /// are not logged.
+    pub fn record(&mut self, event: &ShifterEvent, wall_seconds: f64) -> Result<()> {
+        let frames = |samples: usize| (samples / self.channels as usize) as u64;
+        let (position, kind, to_position) = match *event {
+            ShifterEvent::Bookmarked { position } => (position, EventKind::Mark, None),
+            ShifterEvent::StateChanged {
+                to: PlaybackState::Paused,
+                position,
+                ..
+            } => (position, EventKind::Pause, None),
+            ShifterEvent::StateChanged {
+                from: PlaybackState::Paused,
+                position,
+                ..
+            } => (position, EventKind::Resume, None),
+            ShifterEvent::Seeked {
+                to_ms,
+                position,
+                to_position,
+                ..
+            } => {
+                // The read head gets to `to_position` on the next output callback.
+                let kind = if to_ms == 0.0 {
+                    EventKind::Live
+                } else {
+                    EventKind::Seek
+                };
+                (position, kind, Some(to_position))
+            }
+            _ => return Ok(()),
+        };
+        self.write(&Event {
+            wall_seconds,
+            position: frames(position),
+            kind,
+            to_position: to_position.map(frames),
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        writeln!(self.writer, "{line}")
+            .and_then(|()| self.writer.flush())
+            .map_err(|e| anyhow!("Failed to write event log: {e}"))
+    }
+}
+
+/// Sample rate from the `# ... sample_rate=N` metadata line.
+fn sample_rate_of(csv: &str) -> Option<u32> {
+    csv.lines()
+        .filter_map(|line| line.strip_prefix('#'))
+        .flat_map(|meta| meta.split(',').map(str::trim))
+        .find_map(|field| field.strip_prefix("sample_rate=")?.parse().ok())
+}
+
+/// Converts an event log to an Audacity label track (`start\tend\tlabel`,
+/// seconds from the start of the stream). Marks, seeks and jumps to live are
+/// point labels; a pause becomes a point label with how long it lasted.
+pub fn labels_from_csv(csv: &str) -> Result<String> {
+    let sample_rate = sample_rate_of(csv)
+        .filter(|&rate| rate > 0)
+        .ok_or_else(|| anyhow!("Event log has no sample_rate line"))?;
+    let seconds = |frames: u64| frames as f64 / sample_rate as f64;
+    let events = csv
+        .lines()
+        .filter(|line| !line.starts_with('#') && *line != HEADER && !line.trim().is_empty())
+        .map(Event::parse_csv)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut labels = String::new();
+    let mut paused: Option<&Event> = None;
+    for event in &events {
+        let text = match event.kind {
+            EventKind::Mark => "mark".to_string(),
+            EventKind::Seek | EventKind::Live => match event.to_position {
+                Some(to) => format!("{} to {:.3}", event.kind.name(), seconds(to)),
+                None => event.kind.name().to_string(),
+            },
+            EventKind::Pause => {
+                paused = Some(event);
+                continue;
+            }
+            EventKind::Resume => match paused.take() {
+                Some(pause) => {
+                    let wall = (event.wall_seconds - pause.wall_seconds).rem_euclid(86_400.0);
+                    push_label(
+                        &mut labels,
+                        seconds(pause.position),
+                        &format!("paused {wall:.1}s"),
+                    );
+                    continue;
+                }
+                None => "resume".to_string(),
+            },
+        };
+        push_label(&mut labels, seconds(event.position), &text);
+    }
+    // Still paused when the log ended.
+    if let Some(pause) = paused {
+        push_label(&mut labels, seconds(pause.position), "paused");
+    }
+    Ok(labels)
+}
+
+fn push_label(labels: &mut String, at: f64, text: &str) {
+    labels.push_str(&format!("{at:.6}\t{at:.6}\t{text}\n"));
+}
+
+/// Reads an event log and writes its Audacity labels to `output`, or returns
+/// them for printing.
+pub fn convert_to_labels(input: &Path, output: Option<&Path>) -> Result<Option<String>> {
+    let csv = fs::read_to_string(input)
+        .map_err(|e| anyhow!("Failed to read event log {}: {e}", input.display()))?;
+    let labels = labels_from_csv(&csv)?;
+    match output {
+        Some(path) => {
+            fs::write(path, labels)
+                .map_err(|e| anyhow!("Failed to write labels {}: {e}", path.display()))?;
+            Ok(None)
+        }
+        None => Ok(Some(labels)),
+    }
+}
+
+/// "HH:MM:SS.mmm" for seconds since midnight.
+fn format_wall_clock(seconds: f64) -> String {
+    let ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000 % 24,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn parse_wall_clock(value: &str) -> Option<f64> {
+    let mut parts = value.split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_round_trip() {
+        let seek = Event {
+            wall_seconds: 52_205.25,
+            position: 96_000,
+            kind: EventKind::Seek,
+            to_position: Some(48_000),
+        };
+        assert_eq!(seek.csv_row(), "14:30:05.250,96000,seek,48000");
+        assert_eq!(Event::parse_csv(&seek.csv_row()).unwrap(), seek);
+        let mark = Event {
+            wall_seconds: 0.0,
+            position: 10,
+            kind: EventKind::Mark,
+            to_position: None,
+        };
+        assert_eq!(mark.csv_row(), "00:00:00.000,10,mark,");
+        assert_eq!(Event::parse_csv(&mark.csv_row()).unwrap(), mark);
+        assert!(Event::parse_csv("14:30,1,jump,").is_err());
+    }
+
+    #[test]
+    fn record_logs_seeks_pauses_and_marks_in_frames() {
+        use std::sync::Arc;
+
+        use crate::audio::ring_buffer::AudioRingBuffer;
+        use crate::playback::controller::PlaybackController;
+
+        let path = std::env::temp_dir().join(format!("shifter-events-{}.csv", std::process::id()));
+        let ring = Arc::new(AudioRingBuffer::new(2 * 1000 * 10));
+        // 5s of stereo at 1 kHz.
+        ring.write(&vec![0.0; 2 * 1000 * 5]);
+        let ctrl = PlaybackController::new(ring, 2, 1000);
+        let events = ctrl.events.subscribe();
+        let mut log = EventLog::create(&path, 1000, 2).unwrap();
+        let mut record_all = |wall_seconds| {
+            for event in events.try_iter() {
+                log.record(&event, wall_seconds).unwrap();
+            }
+        };
+
+        ctrl.seek_ms(2_000.0);
+        record_all(11.0);
+        ctrl.pre_read(10);
+        ctrl.add_bookmark();
+        ctrl.toggle_pause();
+        record_all(12.0);
+        ctrl.set_volume(0.5);
+        ctrl.jump_to_live();
+        record_all(13.0);
+
+        let rows: Vec<String> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .skip(2)
+            .map(str::to_string)
+            .collect();
+        let _ = fs::remove_file(&path);
+        assert_eq!(
+            rows,
+            [
+                "00:00:11.000,5000,seek,3000",
+                "00:00:12.000,3000,mark,",
+                "00:00:12.000,3000,pause,",
+                "00:00:13.000,3000,resume,",
+                "00:00:13.000,3000,live,5000",
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_are_in_stream_seconds() {
+        let csv = format!(
+            "# shifter event log, sample_rate=48000, channels=2\n{HEADER}\n\
+             00:01:40.000,48000,mark,\n\
+             00:01:41.000,96000,pause,\n\
+             00:01:53.500,96000,resume,\n\
+             00:02:00.000,120000,seek,24000\n\
+             00:02:01.000,72000,pause,\n"
+        );
+        let labels = labels_from_csv(&csv).unwrap();
+        let lines: Vec<&str> = labels.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "1.000000\t1.000000\tmark",
+                "2.000000\t2.000000\tpaused 12.5s",
+                "2.500000\t2.500000\tseek to 0.500",
+                "1.500000\t1.500000\tpaused",
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_need_the_sample_rate() {
+        assert!(labels_from_csv(&format!("{HEADER}\n")).is_err());
+        assert_eq!(
+            sample_rate_of("# shifter event log, sample_rate=44100, channels=1"),
+            Some(44_100)
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow};
+
+use crate::console::{BannerFormat, Console, json_string};
+use crate::tui::format::format_clock;
+
+/// Extension of the lock files in `lock_dir()`, one per running instance.
+const LOCK_EXTENSION: &str = "lock";
+
+mod process {
+    unsafe extern "C" {
+        fn kill(pid: i32, sig: i32) -> i32;
+    }
+
+    const EPERM: i32 = 1;
+
+    /// Whether a process with this PID exists (possibly owned by another user).
+    pub fn is_alive(pid: u32) -> bool {
+        let Ok(pid) = i32::try_from(pid) else {
+            return false;
+        };
+        if pid <= 0 {
+            return false;
+        }
+        // SAFETY: signal 0 performs only the existence and permission checks;
+        // nothing is delivered. pid > 0 addresses a single process.
+        let result = unsafe { kill(pid, 0) };
+        result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(EPERM)
+    }
+}
+
+/// Directory holding the instance lock files (per user: `$TMPDIR` on macOS).
+pub fn lock_dir() -> PathBuf {
+    std::env::temp_dir().join("shifter")
+}
+
+/// Instance name: `--instance-name` if given, otherwise derived from the input
+/// device UID. Reduced to `[a-z0-9_-]` so it is safe in file names.
+pub fn instance_name(explicit: Option<&str>, input_uid: &str) -> String {
+    let mut name = String::new();
+    for c in explicit.unwrap_or(input_uid).chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name = name.trim_end_matches('-');
+    if name.is_empty() {
+        "default".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Per-instance file path in `dir`, e.g. `<dir>/<name>.lock`. Other
+/// per-instance files (sockets, metrics) derive their paths the same way.
+pub fn instance_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    dir.join(format!("{name}.{extension}"))
+}
+
+/// What an instance records in its lock file, for `shifter status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstanceInfo {
+    pub pid: u32,
+    pub name: String,
+    pub input_uid: String,
+    pub input_device: String,
+    pub output_device: String,
+    /// Start time, seconds since the Unix epoch.
+    pub started: u64,
+}
+
+impl InstanceInfo {
+    /// Info for this process.
+    pub fn current(
+        name: String,
+        input_uid: String,
+        input_device: String,
+        output_device: String,
+    ) -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            pid: std::process::id(),
+            name,
+            input_uid,
+            input_device,
+            output_device,
+            started,
+        }
+    }
+
+    /// Lock file contents: one `key=value` per line.
+    fn to_lock_file(&self) -> String {
+        format!(
+            "pid={}\nname={}\ninput_uid={}\ninput={}\noutput={}\nstarted={}\n",
+            self.pid,
+            self.name,
+            self.input_uid,
+            self.input_device,
+            self.output_device,
+            self.started,
+        )
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let field = |key: &str| {
+            contents.lines().find_map(|line| {
+                line.strip_prefix(key)
+                    .and_then(|rest| rest.strip_prefix('='))
+                    .map(str::to_string)
+            })
+        };
+        Some(Self {
+            pid: field("pid")?.parse().ok()?,
+            name: field("name")?,
+            input_uid: field("input_uid")?,
+            input_device: field("input")?,
+            output_device: field("output")?,
+            started: field("started")?.parse().ok()?,
+        })
+    }
+
+    /// Seconds since the instance started.
+    pub fn uptime_seconds(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+            .saturating_sub(self.started)
+    }
+}
+
+/// Held for the lifetime of an instance; removes its lock file on drop.
+#[derive(Debug)]
+pub struct InstanceLock {
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Claims the instance `info.name` in `dir`.
+    ///
+    /// Fails if a running instance already captures the same input device or
+    /// uses the same name. Lock files left by instances that are no longer
+    /// running are removed first.
+    pub fn acquire(dir: &Path, info: &InstanceInfo) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .map_err(|e| anyhow!("Failed to create lock directory {}: {e}", dir.display()))?;
+
+        if let Some(other) = running_instances(dir)?
+            .into_iter()
+            .find(|other| other.input_uid == info.input_uid)
+        {
+            return Err(anyhow!(
+                "'{}' is already in use by shifter instance '{}' (pid {})",
+                info.input_device,
+                other.name,
+                other.pid,
+            ));
+        }
+
+        let path = instance_path(dir, &info.name, LOCK_EXTENSION);
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                return Err(anyhow!(
+                    "A shifter instance named '{}' is already running (use --instance-name)",
+                    info.name,
+                ));
+            }
+            Err(e) => return Err(anyhow!("Failed to create {}: {e}", path.display())),
+        };
+        let lock = Self { path };
+        file.write_all(info.to_lock_file().as_bytes())
+            .map_err(|e| anyhow!("Failed to write {}: {e}", lock.path.display()))?;
+        Ok(lock)
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Instances with a lock file in `dir` whose process is still running, sorted
+/// by name. Stale and unreadable lock files are removed.
+pub fn running_instances(dir: &Path) -> Result<Vec<InstanceInfo>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow!("Failed to read {}: {e}", dir.display())),
+    };
+    let mut instances = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != LOCK_EXTENSION) {
+            continue;
+        }
+        let info = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| InstanceInfo::parse(&contents));
+        match info {
+            Some(info) if process::is_alive(info.pid) => instances.push(info),
+            _ => {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    instances.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(instances)
+}
+
+/// `shifter status`: prints one summary per running instance (a JSON array
+/// with `--banner-format json`).
+pub fn print_status(console: &Console) -> Result<()> {
+    let instances = running_instances(&lock_dir())?;
+    match console.format() {
+        BannerFormat::Text => {
+            if instances.is_empty() {
+                console.out("No shifter instances running");
+            }
+            for info in &instances {
+                console.out(&format!(
+                    "{}  pid {}  {} -> {}  up {}",
+                    info.name,
+                    info.pid,
+                    info.input_device,
+                    info.output_device,
+                    format_clock(info.uptime_seconds() as f64),
+                ));
+            }
+        }
+        BannerFormat::Json => {
+            let items: Vec<String> = instances
+                .iter()
+                .map(|info| {
+                    format!(
+                        "{{\"name\":{},\"pid\":{},\"input\":{},\"output\":{},\"uptime_s\":{}}}",
+                        json_string(&info.name),
+                        info.pid,
+                        json_string(&info.input_device),
+                        json_string(&info.output_device),
+                        info.uptime_seconds(),
+                    )
+                })
+                .collect();
+            console.out(&format!("[{}]", items.join(",")));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_derive_from_uid_or_flag() {
+        assert_eq!(instance_name(None, "BlackHole2ch_UID"), "blackhole2ch_uid");
+        assert_eq!(
+            instance_name(None, "com.rogue:Loopback 1"),
+            "com-rogue-loopback-1"
+        );
+        assert_eq!(instance_name(Some("Podcast B"), "ignored"), "podcast-b");
+        assert_eq!(instance_name(Some("../"), "ignored"), "default");
+        let dir = Path::new("/tmp/shifter");
+        assert_eq!(
+            instance_path(dir, "podcast-b", "lock"),
+            Path::new("/tmp/shifter/podcast-b.lock")
+        );
+    }
+
+    #[test]
+    fn lock_is_exclusive_and_released_on_drop() {
+        let dir =
+            std::env::temp_dir().join(format!("shifter-test-{}-exclusive", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let a1 = InstanceInfo::current("a".into(), "uid-1".into(), "In".into(), "Out".into());
+        let b1 = InstanceInfo::current("b".into(), "uid-1".into(), "In".into(), "Out".into());
+        let a2 = InstanceInfo::current("a".into(), "uid-2".into(), "In".into(), "Out".into());
+        let b2 = InstanceInfo::current("b".into(), "uid-2".into(), "In".into(), "Out".into());
+        let lock = InstanceLock::acquire(&dir, &a1).unwrap();
+        assert!(lock.path.exists());
+
+        // Same input device under another name, or same name on another device.
+        assert!(InstanceLock::acquire(&dir, &b1).is_err());
+        assert!(InstanceLock::acquire(&dir, &a2).is_err());
+        let other = InstanceLock::acquire(&dir, &b2).unwrap();
+
+        let names: Vec<String> = running_instances(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+
+        let path = lock.path.clone();
+        drop(lock);
+        assert!(!path.exists());
+        drop(other);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stale_locks_are_recovered() {
+        let dir = std::env::temp_dir().join(format!("shifter-test-{}-stale", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let info = InstanceInfo::current("a".into(), "uid-1".into(), "In".into(), "Out".into());
+        let stale = InstanceInfo {
+            pid: i32::MAX as u32,
+            ..info.clone()
+        };
+        fs::write(
+            instance_path(&dir, "a", LOCK_EXTENSION),
+            stale.to_lock_file(),
+        )
+        .unwrap();
+        fs::write(instance_path(&dir, "junk", LOCK_EXTENSION), "not a lock").unwrap();
+
+        let lock = InstanceLock::acquire(&dir, &info).unwrap();
+        let running = running_instances(&dir).unwrap();
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].pid, std::process::id());
+        assert!(!instance_path(&dir, "junk", LOCK_EXTENSION).exists());
+        drop(lock);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn lock_file_round_trips() {
+        let info = InstanceInfo::current(
+            "a".into(),
+            "uid-1".into(),
+            "BlackHole 2ch".into(),
+            "Speakers".into(),
+        );
+        assert_eq!(InstanceInfo::parse(&info.to_lock_file()), Some(info));
+        assert_eq!(InstanceInfo::parse("pid=1\n"), None);
+    }
+}
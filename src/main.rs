@@ -1,33 +1,233 @@
 mod audio;
 mod config;
+mod console;
+mod control;
+mod event_log;
+mod instance;
+mod platform;
 mod playback;
+mod prefs;
+mod render;
 mod tui;
+mod wav;
 
-use anyhow::Result;
-use clap::Parser;
+use std::sync::mpsc;
+use std::time::Duration;
 
-use crate::audio::engine::{AudioEngine, list_all_devices};
-use crate::config::CliArgs;
+use anyhow::{Result, anyhow};
+
+use crate::audio::engine::{AudioEngine, DEVICE_CACHE_TTL, DeviceCache, list_all_devices};
+use crate::audio::latency::LatencyBudget;
+use crate::audio::raw_sink;
+use crate::audio::recorder::{Recorder, SplitTriggers};
+use crate::config::{CliArgs, CliCommand};
+use crate::console::{Banner, Console};
+use crate::control::script::Script;
+use crate::control::status_file::StatusFile;
+use crate::control::{fifo, notifications, power};
+use crate::event_log::EventLog;
+use crate::instance::{InstanceInfo, InstanceLock};
+use crate::playback::analysis::PEAK_DECAY_PER_SECOND;
+use crate::prefs::Prefs;
+use crate::render::RenderSettings;
 use crate::tui::app::App;
+use crate::tui::device_picker;
+use crate::tui::theme::Palette;
+use crate::wav::WavSpec;
 
 fn main() -> Result<()> {
-    let args = CliArgs::parse();
+    let mut args = CliArgs::parse_args();
+    let console = Console::new(args.quiet, args.banner_format);
+
+    match &args.command {
+        Some(CliCommand::Status) => return instance::print_status(&console),
+        Some(CliCommand::EventsToLabels { input, output }) => {
+            if let Some(labels) = event_log::convert_to_labels(input, output.as_deref())? {
+                console.out(labels.trim_end());
+            }
+            return Ok(());
+        }
+        Some(CliCommand::Render {
+            input,
+            output,
+            format,
+            dc_block,
+            input_gain_db,
+            volume,
+        }) => {
+            let settings = RenderSettings {
+                dc_block: *dc_block,
+                input_gain_db: *input_gain_db,
+                volume: *volume,
+            };
+            return render::run(input, output, *format, settings, &console);
+        }
+        None => {}
+    }
+
+    // Device lists read during startup, reused while fresh
+    let devices = DeviceCache::shared(DEVICE_CACHE_TTL);
 
     if args.list_devices {
-        return list_all_devices(&args.input_device);
+        return list_all_devices(args.input_device.as_deref(), &console, &devices);
+    }
+
+    let palette = if args.colorblind {
+        Palette::colorblind()
+    } else {
+        Palette::standard()
+    };
+
+    // Without a device name, use the only virtual input or ask which one
+    if args.input_device.is_none() {
+        match device_picker::choose_input(&palette, &devices)? {
+            Some(name) => args.input_device = Some(name),
+            None => return Ok(()),
+        }
     }
 
     // Initialize audio engine
-    let engine = AudioEngine::new(&args)?;
-
-    eprintln!(
-        "Audio: {} -> {} ({}ch {}Hz, {}s buffer)",
-        engine.input_device_name,
-        engine.output_device_name,
-        engine.channels,
-        engine.sample_rate,
-        args.buffer_seconds,
-    );
+    let engine = AudioEngine::new(&args, &devices)?;
+
+    // One instance per input device; held (and removed on exit) until main returns
+    let instance_name = instance::instance_name(args.instance_name.as_deref(), &engine.input_uid);
+    let instance_lock = InstanceLock::acquire(
+        &instance::lock_dir(),
+        &InstanceInfo::current(
+            instance_name.clone(),
+            engine.input_uid.clone(),
+            engine.input_device_name.clone(),
+            engine.output_device_name.clone(),
+        ),
+    )?;
+
+    // Background events and remote command errors are shown as toasts in the TUI
+    let (notice_tx, notice_rx) = mpsc::channel();
+    if args.distributed_notifications {
+        notifications::start(
+            engine.controller.clone(),
+            instance_name.clone(),
+            notice_tx.clone(),
+        )?;
+    }
+
+    // Pause on screen lock / sleep
+    power::start(
+        engine.controller.clone(),
+        args.auto_resume_on_unlock,
+        notice_tx.clone(),
+    )?;
+
+    // Created FIFOs are removed when this is dropped, after the TUI exits
+    let fifos = fifo::start(
+        engine.controller.clone(),
+        args.command_fifo.as_deref(),
+        args.status_fifo.as_deref(),
+        notice_tx.clone(),
+    )?;
+
+    if let Some(path) = &args.output_raw {
+        raw_sink::spawn(
+            engine.controller.clone(),
+            path,
+            args.raw_format,
+            notice_tx.clone(),
+        )?;
+    }
+    let recorder = args
+        .record_dir
+        .as_deref()
+        .map(|dir| {
+            let controller = &engine.controller;
+            let spec = WavSpec {
+                channels: controller.channels(),
+                sample_rate: controller.sample_rate(),
+                format: args.raw_format,
+            };
+            let triggers = SplitTriggers {
+                on_bookmark: args.split_on_bookmark,
+                after_pause: args
+                    .split_after_pause_s
+                    .map(|s| Duration::from_secs(s as u64)),
+            };
+            Recorder::spawn(controller.clone(), dir, spec, triggers, notice_tx)
+        })
+        .transpose()?;
+
+    let status_file = args
+        .status_file
+        .as_ref()
+        .map(|path| {
+            let path = path
+                .clone()
+                .or_else(StatusFile::default_path)
+                .ok_or_else(|| anyhow!("HOME is not set: give --status-file a path"))?;
+            StatusFile::create(&path, args.status_file_format)
+        })
+        .transpose()?;
+
+    let event_log = args
+        .event_log
+        .as_deref()
+        .map(|path| {
+            let controller = &engine.controller;
+            EventLog::create(path, controller.sample_rate(), controller.channels())
+        })
+        .transpose()?;
+
+    // Script errors don't stop startup; they're shown as a toast
+    let script = match Script::default_path() {
+        Some(path) if !args.no_script => Script::load(&path),
+        _ => Ok(None),
+    };
+
+    // Without -o, follow the system output device when it changes
+    let system_output = if args.output_device.is_none() {
+        Some(engine.watch_system_output()?)
+    } else {
+        None
+    };
+
+    // Live, the read head trails the write head by one output callback, plus
+    // the floor for an AirPlay output.
+    let latency = engine.hardware_latency;
+    let callback_ms = latency.output_buffer_frames as f64 * 1000.0 / engine.sample_rate as f64
+        + engine.controller.live_floor_ms();
+    let banner = Banner {
+        instance: instance_name.clone(),
+        input_device: engine.input_device_name.clone(),
+        input_id: engine.input_id,
+        output_device: engine.output_device_name.clone(),
+        output_id: engine.output_id,
+        follow_system_output: system_output.is_some(),
+        output_reason: engine.output_reason.describe(&engine.output_device_name),
+        channel_map: engine.output_map.as_ref().map(|map| map.to_string()),
+        sample_rate: engine.sample_rate,
+        channels: engine.channels,
+        native_format: engine.native_format.map(|format| format.label()),
+        buffer_seconds: args.buffer_seconds,
+        capacity_samples: engine.controller.ring.capacity(),
+        initial_delay_ms: LatencyBudget::new(&latency, engine.sample_rate, callback_ms, 0.0)
+            .total_ms(),
+        io_buffer_frames: (latency.input_buffer_frames, latency.output_buffer_frames),
+        peak_decay_db_per_s: 20.0 * PEAK_DECAY_PER_SECOND.log10(),
+        auto_bookmark_interval_s: args.auto_bookmark_interval_s,
+        auto_resume_on_unlock: args.auto_resume_on_unlock,
+        distributed_notifications: args.distributed_notifications,
+        script: matches!(script, Ok(Some(_))),
+        colorblind: args.colorblind,
+        raw_output: args
+            .output_raw
+            .as_ref()
+            .map(|path| (path.display().to_string(), args.raw_format.name())),
+        record_dir: args
+            .record_dir
+            .as_ref()
+            .map(|dir| (dir.display().to_string(), args.raw_format.name())),
+    };
+    if !args.no_startup_summary {
+        console.banner(&banner);
+    }
 
     // Set up panic hook to restore terminal
     let default_hook = std::panic::take_hook();
@@ -44,12 +244,32 @@ fn main() -> Result<()> {
         engine.input_device_name.clone(),
         engine.output_device_name.clone(),
         args.buffer_seconds,
-    );
+        engine.hardware_latency,
+        palette,
+        notice_rx,
+    )
+    .with_instance_name(instance_name)
+    .with_output_reason(engine.output_reason.summary())
+    .with_script(script)
+    .with_number_keys(args.number_keys)
+    .with_confirm_quit(args.confirm_quit)
+    .with_debug(args.debug)
+    .with_event_log(event_log)
+    .with_status_file(status_file)
+    .with_recorder(recorder)
+    .with_seek_acceleration(!args.no_seek_acceleration)
+    .with_meter_smoothing(Duration::from_millis(args.meter_smoothing_ms))
+    .with_idle_timeout(args.idle_timeout_s.map(Duration::from_secs))
+    .with_meter_reference(args.meter_reference_db)
+    .with_prefs(Prefs::default_path())
+    .with_engine(engine, system_output);
 
     let result = app.run(&mut terminal);
 
     // Restore terminal
     ratatui::restore();
+    drop(fifos);
+    drop(instance_lock);
 
     result
 }
@@ -0,0 +1,155 @@
+use std::io;
+
+/// Darwin's `QOS_CLASS_USER_INTERACTIVE`.
+const QOS_CLASS_USER_INTERACTIVE: u32 = 0x21;
+
+/// Darwin's `RLIMIT_MEMLOCK`.
+const RLIMIT_MEMLOCK: i32 = 6;
+
+mod ffi {
+    use std::os::raw::{c_int, c_void};
+
+    #[repr(C)]
+    pub struct RLimit {
+        pub rlim_cur: u64,
+        pub rlim_max: u64,
+    }
+
+    unsafe extern "C" {
+        pub fn mlock(addr: *const c_void, len: usize) -> c_int;
+        pub fn getrlimit(resource: c_int, rlp: *mut RLimit) -> c_int;
+        pub fn pthread_set_qos_class_self_np(qos_class: u32, relative_priority: c_int) -> c_int;
+    }
+}
+
+/// Outcome of pinning the ring buffer in RAM, shown in the diagnostics overlay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryLock {
+    Locked {
+        bytes: usize,
+    },
+    /// `mlock` failed, typically because the allocation exceeds `RLIMIT_MEMLOCK`
+    /// (`limit`, if it could be read). The buffer still works but may be paged
+    /// out during long pauses.
+    Failed {
+        bytes: usize,
+        errno: i32,
+        limit: Option<u64>,
+    },
+}
+
+impl MemoryLock {
+    /// Interprets an `mlock` return value and the errno / limit read after it.
+    fn from_result(result: i32, bytes: usize, errno: i32, limit: Option<u64>) -> Self {
+        if result == 0 {
+            Self::Locked { bytes }
+        } else {
+            Self::Failed {
+                bytes,
+                errno,
+                limit,
+            }
+        }
+    }
+
+    /// One line for the diagnostics overlay.
+    pub fn describe(self) -> String {
+        match self {
+            Self::Locked { bytes } => format!("locked ({})", format_bytes(bytes as u64)),
+            Self::Failed {
+                bytes,
+                errno,
+                limit,
+            } => {
+                let reason = match limit {
+                    Some(limit) if limit < bytes as u64 => {
+                        format!("RLIMIT_MEMLOCK {}", format_bytes(limit))
+                    }
+                    _ => io::Error::from_raw_os_error(errno).to_string(),
+                };
+                format!("not locked ({}: {reason})", format_bytes(bytes as u64))
+            }
+        }
+    }
+}
+
+/// Pins `len` bytes at `addr` in RAM so a long-paused buffer isn't paged out
+/// and then faulted back in on resume. Failure is reported, not fatal.
+pub fn lock_memory(addr: *const u8, len: usize) -> MemoryLock {
+    // SAFETY: mlock only changes the paging of the given range; callers pass
+    // a live allocation, and an invalid range just fails with ENOMEM.
+    let result = unsafe { ffi::mlock(addr.cast(), len) };
+    let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+    let limit = (result != 0).then(memlock_limit).flatten();
+    MemoryLock::from_result(result, len, errno, limit)
+}
+
+/// Soft `RLIMIT_MEMLOCK` in bytes.
+fn memlock_limit() -> Option<u64> {
+    let mut limit = ffi::RLimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit fills the struct it is given.
+    let result = unsafe { ffi::getrlimit(RLIMIT_MEMLOCK, &mut limit) };
+    (result == 0).then_some(limit.rlim_cur)
+}
+
+/// Raises the calling thread to `QOS_CLASS_USER_INTERACTIVE`, so the analysis
+/// and writer threads keep up on a loaded system. Returns false if the call
+/// failed; the thread then keeps its default class.
+pub fn raise_thread_qos() -> bool {
+    // SAFETY: only affects the scheduling of the calling thread.
+    qos_applied(unsafe { ffi::pthread_set_qos_class_self_np(QOS_CLASS_USER_INTERACTIVE, 0) })
+}
+
+fn qos_applied(result: i32) -> bool {
+    result == 0
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const MB: u64 = 1024 * 1024;
+    if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else {
+        format!("{} KB", bytes.div_ceil(1024))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENOMEM: i32 = 12;
+    const EPERM: i32 = 1;
+
+    #[test]
+    fn successful_lock_reports_the_size() {
+        let lock = MemoryLock::from_result(0, 23_040_000, 0, None);
+        assert_eq!(lock, MemoryLock::Locked { bytes: 23_040_000 });
+        assert_eq!(lock.describe(), "locked (22.0 MB)");
+    }
+
+    #[test]
+    fn lock_over_the_limit_names_the_limit() {
+        let lock = MemoryLock::from_result(-1, 23_040_000, ENOMEM, Some(64 * 1024));
+        assert_eq!(
+            lock.describe(),
+            "not locked (22.0 MB: RLIMIT_MEMLOCK 64 KB)"
+        );
+    }
+
+    #[test]
+    fn other_lock_failures_name_the_error() {
+        let lock = MemoryLock::from_result(-1, 4096, EPERM, Some(u64::MAX));
+        assert!(matches!(lock, MemoryLock::Failed { errno: EPERM, .. }));
+        assert!(lock.describe().starts_with("not locked (4 KB: "));
+        assert!(!lock.describe().contains("RLIMIT"));
+    }
+
+    #[test]
+    fn qos_result_is_checked() {
+        assert!(qos_applied(0));
+        assert!(!qos_applied(22));
+    }
+}
@@ -0,0 +1,375 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering, fence};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use crate::platform;
+use crate::playback::controller::PlaybackController;
+
+/// How often the analysis thread drains the snapshot rings.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Fraction of the displayed peak left after one second, for smooth meter
+/// movement: -120 dB/s, close to the old 0.85 per 512-frame block at 48 kHz.
+/// Applied by elapsed time, so slow or batched drains don't change the speed.
+pub const PEAK_DECAY_PER_SECOND: f32 = 1e-6;
+
+/// Analysis results published by the analysis thread for the TUI.
+///
+/// Peaks are published as a versioned snapshot: the version is odd while an
+/// update is in progress, so readers retry instead of seeing a torn pair.
+/// Values are stored as value * 1000.
+pub struct Meters {
+    version: AtomicUsize,
+    peak_left: AtomicUsize,
+    peak_right: AtomicUsize,
+    /// Max absolute input sample since the TUI last took it.
+    input_envelope: AtomicUsize,
+    /// Max absolute output sample since the TUI last took it.
+    output_envelope: AtomicUsize,
+    /// Highest undecayed output peak per channel this session (until reset).
+    session_peak_left: AtomicUsize,
+    session_peak_right: AtomicUsize,
+}
+
+impl Meters {
+    pub fn new() -> Self {
+        Self {
+            version: AtomicUsize::new(0),
+            peak_left: AtomicUsize::new(0),
+            peak_right: AtomicUsize::new(0),
+            input_envelope: AtomicUsize::new(0),
+            output_envelope: AtomicUsize::new(0),
+            session_peak_left: AtomicUsize::new(0),
+            session_peak_right: AtomicUsize::new(0),
+        }
+    }
+
+    fn publish_peaks(&self, left: f32, right: f32) {
+        let v = self.version.load(Ordering::Relaxed);
+        self.version.store(v + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        self.peak_left
+            .store((left * 1000.0) as usize, Ordering::Relaxed);
+        self.peak_right
+            .store((right * 1000.0) as usize, Ordering::Relaxed);
+        self.version.store(v + 2, Ordering::Release);
+    }
+
+    /// Latest (left, right) peak levels.
+    pub fn peaks(&self) -> (f32, f32) {
+        loop {
+            let before = self.version.load(Ordering::Acquire);
+            if before & 1 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let l = self.peak_left.load(Ordering::Relaxed);
+            let r = self.peak_right.load(Ordering::Relaxed);
+            fence(Ordering::Acquire);
+            if self.version.load(Ordering::Relaxed) == before {
+                return (l as f32 / 1000.0, r as f32 / 1000.0);
+            }
+        }
+    }
+
+    fn raise_session_peaks(&self, left: f32, right: f32) {
+        self.session_peak_left
+            .fetch_max((left * 1000.0) as usize, Ordering::Relaxed);
+        self.session_peak_right
+            .fetch_max((right * 1000.0) as usize, Ordering::Relaxed);
+    }
+
+    /// Highest (left, right) peaks since startup or the last reset.
+    pub fn session_peaks(&self) -> (f32, f32) {
+        (
+            self.session_peak_left.load(Ordering::Relaxed) as f32 / 1000.0,
+            self.session_peak_right.load(Ordering::Relaxed) as f32 / 1000.0,
+        )
+    }
+
+    pub fn reset_session_peaks(&self) {
+        self.session_peak_left.store(0, Ordering::Relaxed);
+        self.session_peak_right.store(0, Ordering::Relaxed);
+    }
+
+    fn raise_envelopes(&self, input: f32, output: f32) {
+        self.input_envelope
+            .fetch_max((input * 1000.0) as usize, Ordering::Relaxed);
+        self.output_envelope
+            .fetch_max((output * 1000.0) as usize, Ordering::Relaxed);
+    }
+
+    /// Returns and resets the (input, output) envelope maxima since the last call.
+    /// Both are taken together so they cover the same wall-clock interval.
+    pub fn take_envelopes(&self) -> (f32, f32) {
+        let input = self.input_envelope.swap(0, Ordering::Relaxed) as f32 / 1000.0;
+        let output = self.output_envelope.swap(0, Ordering::Relaxed) as f32 / 1000.0;
+        (input, output)
+    }
+}
+
+/// Consumes the controller's snapshot rings and publishes `Meters`.
+pub struct Analyzer {
+    channels: usize,
+    peak_left: f32,
+    peak_right: f32,
+    /// When the peaks were last decayed.
+    last_peak_update: Option<Instant>,
+}
+
+impl Analyzer {
+    pub fn new(channels: u16) -> Self {
+        Self {
+            channels: channels as usize,
+            peak_left: 0.0,
+            peak_right: 0.0,
+            last_peak_update: None,
+        }
+    }
+
+    /// Processes all pending input/output blocks and publishes the results.
+    /// Returns the number of blocks processed.
+    pub fn drain(&mut self, controller: &PlaybackController) -> usize {
+        self.drain_at(controller, Instant::now())
+    }
+
+    fn drain_at(&mut self, controller: &PlaybackController, now: Instant) -> usize {
+        let mut input_peak: f32 = 0.0;
+        let mut output_peak: f32 = 0.0;
+        let (mut block_left, mut block_right) = (0.0f32, 0.0f32);
+
+        let mut count = controller.input_blocks.drain(|block| {
+            controller
+                .peak_history
+                .record(block.position, block.samples());
+            controller.levels.record(block.samples());
+            input_peak = block
+                .samples()
+                .iter()
+                .fold(input_peak, |m, s| m.max(s.abs()));
+        });
+        let outputs = controller.output_blocks.drain(|block| {
+            let (l, r) = self.block_peaks(block.samples());
+            output_peak = output_peak.max(l.max(r));
+            controller.meters.raise_session_peaks(l, r);
+            block_left = block_left.max(l);
+            block_right = block_right.max(r);
+        });
+        if outputs > 0 {
+            let elapsed = self.last_peak_update.map_or(0.0, |last| {
+                now.saturating_duration_since(last).as_secs_f32()
+            });
+            let decay = PEAK_DECAY_PER_SECOND.powf(elapsed);
+            self.peak_left = block_left.max(self.peak_left * decay);
+            self.peak_right = block_right.max(self.peak_right * decay);
+            self.last_peak_update = Some(now);
+        }
+        count += outputs;
+
+        if count > 0 {
+            controller.meters.raise_envelopes(input_peak, output_peak);
+            controller
+                .meters
+                .publish_peaks(self.peak_left, self.peak_right);
+        }
+        count
+    }
+
+    /// Per-channel (left, right) peak of one interleaved block.
+    fn block_peaks(&self, data: &[f32]) -> (f32, f32) {
+        if self.channels == 0 {
+            return (0.0, 0.0);
+        }
+        let mut peak_l: f32 = 0.0;
+        let mut peak_r: f32 = 0.0;
+        for frame in data.chunks(self.channels) {
+            if let Some(&l) = frame.first() {
+                peak_l = peak_l.max(l.abs());
+            }
+            if let Some(&r) = frame.get(1) {
+                peak_r = peak_r.max(r.abs());
+            }
+        }
+        (peak_l, peak_r)
+    }
+}
+
+/// Starts the analysis thread, which keeps all metering off the audio callbacks.
+pub fn spawn(controller: Arc<PlaybackController>, channels: u16) -> Result<()> {
+    std::thread::Builder::new()
+        .name("shifter-analysis".into())
+        .spawn(move || {
+            platform::raise_thread_qos();
+            let mut analyzer = Analyzer::new(channels);
+            loop {
+                analyzer.drain(&controller);
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start analysis thread: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::time::Instant;
+
+    use super::*;
+    use crate::audio::ring_buffer::AudioRingBuffer;
+
+    const SAMPLE_RATE: u32 = 48_000;
+    const FRAMES: usize = 128;
+
+    #[test]
+    fn peaks_decay_with_elapsed_time() {
+        let ctrl = PlaybackController::new(Arc::new(AudioRingBuffer::new(48_000)), 2, SAMPLE_RATE);
+        let mut analyzer = Analyzer::new(2);
+        let start = Instant::now();
+        ctrl.output_blocks.push(&[0.5, -0.8, 0.1, 0.2]);
+        ctrl.output_blocks.push(&[0.0, 0.0]);
+        analyzer.drain_at(&ctrl, start);
+        assert_eq!(ctrl.meters.peaks(), (0.5, 0.8));
+
+        ctrl.output_blocks.push(&[0.0, 0.0]);
+        analyzer.drain_at(&ctrl, start + Duration::from_millis(100));
+        let decay = PEAK_DECAY_PER_SECOND.powf(0.1);
+        let (l, r) = ctrl.meters.peaks();
+        assert!((l - 0.5 * decay).abs() < 0.002);
+        assert!((r - 0.8 * decay).abs() < 0.002);
+    }
+
+    #[test]
+    fn decay_does_not_depend_on_how_often_peaks_are_drained() {
+        let start = Instant::now();
+        let run = |steps: u64| {
+            let ctrl =
+                PlaybackController::new(Arc::new(AudioRingBuffer::new(48_000)), 2, SAMPLE_RATE);
+            let mut analyzer = Analyzer::new(2);
+            ctrl.output_blocks.push(&[0.8, 0.8]);
+            analyzer.drain_at(&ctrl, start);
+            for step in 1..=steps {
+                ctrl.output_blocks.push(&[0.0, 0.0]);
+                analyzer.drain_at(&ctrl, start + Duration::from_millis(100 * step / steps));
+            }
+            ctrl.meters.peaks().0
+        };
+        // 1 drain (a stalled thread) vs 20 (the normal pace) over 100ms.
+        assert!((run(1) - run(20)).abs() < 0.002);
+    }
+
+    #[test]
+    fn mono_has_no_right_peak() {
+        let ctrl = PlaybackController::new(Arc::new(AudioRingBuffer::new(1024)), 1, SAMPLE_RATE);
+        let mut analyzer = Analyzer::new(1);
+        ctrl.output_blocks.push(&[0.3, -0.6]);
+        analyzer.drain(&ctrl);
+        let (l, r) = ctrl.meters.peaks();
+        assert!((l - 0.6).abs() < 0.002);
+        assert_eq!(r, 0.0);
+    }
+
+    #[test]
+    fn envelopes_accumulate_until_taken() {
+        let ctrl = PlaybackController::new(Arc::new(AudioRingBuffer::new(48_000)), 2, SAMPLE_RATE);
+        let mut analyzer = Analyzer::new(2);
+        ctrl.input_blocks.push(&[0.4, -0.9]);
+        ctrl.output_blocks.push(&[0.2, 0.1]);
+        analyzer.drain(&ctrl);
+        ctrl.input_blocks.push(&[0.1, 0.1]);
+        analyzer.drain(&ctrl);
+        let (input, output) = ctrl.meters.take_envelopes();
+        assert!((input - 0.9).abs() < 0.002);
+        assert!((output - 0.2).abs() < 0.002);
+        assert_eq!(ctrl.meters.take_envelopes(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn session_peaks_only_rise_until_reset() {
+        let ctrl = PlaybackController::new(Arc::new(AudioRingBuffer::new(48_000)), 2, SAMPLE_RATE);
+        let mut analyzer = Analyzer::new(2);
+        let start = Instant::now();
+        ctrl.output_blocks.push(&[0.5, -0.8]);
+        ctrl.output_blocks.push(&[0.1, 0.1]);
+        analyzer.drain_at(&ctrl, start);
+        for i in 1..=50 {
+            ctrl.output_blocks.push(&[0.0, 0.0]);
+            analyzer.drain_at(&ctrl, start + Duration::from_millis(10 * i));
+        }
+        // The meter peaks decayed; the session peaks did not.
+        assert!(ctrl.meters.peaks().0 < 0.01);
+        let (l, r) = ctrl.meters.session_peaks();
+        assert!((l - 0.5).abs() < 0.002 && (r - 0.8).abs() < 0.002);
+        ctrl.meters.reset_session_peaks();
+        assert_eq!(ctrl.meters.session_peaks(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn nothing_pending_publishes_nothing() {
+        let ctrl = PlaybackController::new(Arc::new(AudioRingBuffer::new(48_000)), 2, SAMPLE_RATE);
+        let mut analyzer = Analyzer::new(2);
+        assert_eq!(analyzer.drain(&ctrl), 0);
+        assert_eq!(ctrl.meters.peaks(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn analysis_is_much_faster_than_realtime() {
+        // 10 seconds of 48 kHz stereo audio in 128-frame callbacks, both sides.
+        let ctrl = PlaybackController::new(Arc::new(AudioRingBuffer::new(48_000)), 2, SAMPLE_RATE);
+        let mut analyzer = Analyzer::new(2);
+        let block = vec![0.25_f32; FRAMES * 2];
+        let callbacks = SAMPLE_RATE as usize * 10 / FRAMES;
+        let start = Instant::now();
+        for _ in 0..callbacks {
+            ctrl.input_blocks.push(&block);
+            ctrl.output_blocks.push(&block);
+            analyzer.drain(&ctrl);
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn analysis_thread_keeps_up_at_48khz_stereo() {
+        let ctrl = Arc::new(PlaybackController::new(
+            Arc::new(AudioRingBuffer::new(48_000)),
+            2,
+            SAMPLE_RATE,
+        ));
+        let stop = Arc::new(AtomicBool::new(false));
+        let consumer = {
+            let ctrl = ctrl.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut analyzer = Analyzer::new(2);
+                let mut processed = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    processed += analyzer.drain(&ctrl);
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                processed + analyzer.drain(&ctrl)
+            })
+        };
+
+        // ~0.5s of callbacks paced at the real 128-frame period (2.67ms).
+        let block = vec![0.25_f32; FRAMES * 2];
+        let period = Duration::from_secs_f64(FRAMES as f64 / SAMPLE_RATE as f64);
+        let callbacks = 200;
+        let start = Instant::now();
+        for i in 0..callbacks {
+            ctrl.input_blocks.push(&block);
+            ctrl.output_blocks.push(&block);
+            let due = period * (i + 1);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+        let processed = consumer.join().unwrap();
+
+        assert_eq!(ctrl.input_blocks.dropped(), 0);
+        assert_eq!(ctrl.output_blocks.dropped(), 0);
+        assert_eq!(processed, callbacks as usize * 2);
+    }
+}
@@ -0,0 +1,207 @@
+use std::time::{Duration, Instant};
+
+use crate::playback::controller::PlaybackController;
+
+/// How often the frame counters are sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Clocks further apart than this are drifting.
+pub const DRIFT_THRESHOLD_PPM: f64 = 10.0;
+/// Drift beyond this is warned about once.
+pub const DRIFT_WARNING_PPM: f64 = 100.0;
+/// Span measured before a drift is shown. The counters advance a callback at
+/// a time, so a short span can't resolve a few ppm.
+const MIN_SPAN: Duration = Duration::from_secs(60);
+/// Span measured before a drift is warned about (one 512-frame callback is
+/// about 18ppm of ten minutes at 48kHz).
+const WARNING_SPAN: Duration = Duration::from_secs(600);
+/// A one-second rate this far (as a fraction) from the nominal rate means a
+/// stall or a device change, and the measurement starts over.
+const MAX_STEP_DEVIATION: f64 = 0.01;
+
+/// Frames counted against one device clock, sampled over a span.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateTracker {
+    /// First and latest sample of the span, as (time, frames).
+    first: Option<(Instant, usize)>,
+    last: Option<(Instant, usize)>,
+}
+
+impl RateTracker {
+    /// Records `frames` at `now`. Returns the rate since the previous sample.
+    fn sample(&mut self, now: Instant, frames: usize) -> Option<f64> {
+        let step = self.last.and_then(|last| rate(last, (now, frames)));
+        self.first.get_or_insert((now, frames));
+        self.last = Some((now, frames));
+        step
+    }
+
+    /// Average frames per second over the span.
+    pub fn rate_hz(&self) -> Option<f64> {
+        rate(self.first?, self.last?)
+    }
+
+    fn span(&self) -> Duration {
+        match (self.first, self.last) {
+            (Some((from, _)), Some((to, _))) => to.saturating_duration_since(from),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+fn rate((from, from_frames): (Instant, usize), (to, to_frames): (Instant, usize)) -> Option<f64> {
+    let seconds = to.saturating_duration_since(from).as_secs_f64();
+    (seconds > 0.0 && to_frames >= from_frames).then(|| (to_frames - from_frames) as f64 / seconds)
+}
+
+/// Input vs. output device clock. Both run at the nominal rate, but two
+/// devices without a shared clock never agree exactly, and the difference
+/// keeps moving the buffer level. Sleep, stalls and device changes start
+/// the measurement over.
+#[derive(Debug)]
+pub struct ClockDrift {
+    sample_rate: f64,
+    /// Frames the input callback received (`write_rate_hz`).
+    write: RateTracker,
+    /// Frames the output callback asked for (`read_rate_hz`).
+    read: RateTracker,
+    next_sample: Option<Instant>,
+    wakes_seen: usize,
+    warned: bool,
+}
+
+impl ClockDrift {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f64,
+            write: RateTracker::default(),
+            read: RateTracker::default(),
+            next_sample: None,
+            wakes_seen: 0,
+            warned: false,
+        }
+    }
+
+    /// Samples the controller's frame counters once a second. True the first
+    /// time the drift passes `DRIFT_WARNING_PPM`.
+    pub fn tick(&mut self, controller: &PlaybackController) -> bool {
+        self.sample(
+            Instant::now(),
+            controller.input_frames(),
+            controller.output_frames(),
+            controller.wake_count(),
+        )
+    }
+
+    fn sample(&mut self, now: Instant, written: usize, played: usize, wakes: usize) -> bool {
+        if self.next_sample.is_some_and(|next| now < next) {
+            return false;
+        }
+        self.next_sample = Some(now + SAMPLE_INTERVAL);
+        if wakes != self.wakes_seen {
+            self.wakes_seen = wakes;
+            self.restart();
+        }
+        let steps = [
+            self.write.sample(now, written),
+            self.read.sample(now, played),
+        ];
+        let off_rate = |step: Option<f64>| {
+            step.is_some_and(|hz| (hz / self.sample_rate - 1.0).abs() > MAX_STEP_DEVIATION)
+        };
+        if steps.into_iter().any(off_rate) {
+            self.restart();
+            self.write.sample(now, written);
+            self.read.sample(now, played);
+            return false;
+        }
+        if self.warned || self.write.span() < WARNING_SPAN {
+            return false;
+        }
+        self.warned = self.ppm().is_some_and(|ppm| ppm.abs() > DRIFT_WARNING_PPM);
+        self.warned
+    }
+
+    fn restart(&mut self) {
+        self.write = RateTracker::default();
+        self.read = RateTracker::default();
+    }
+
+    /// How much faster the output clock runs than the input clock, in parts
+    /// per million. `None` until `MIN_SPAN` has been measured.
+    pub fn ppm(&self) -> Option<f64> {
+        if self.write.span() < MIN_SPAN {
+            return None;
+        }
+        let (write_hz, read_hz) = (self.write.rate_hz()?, self.read.rate_hz()?);
+        (write_hz > 0.0).then(|| (read_hz - write_hz) / write_hz * 1e6)
+    }
+
+    /// Whether the clocks differ by more than `DRIFT_THRESHOLD_PPM`.
+    pub fn is_drifting(&self) -> bool {
+        self.ppm()
+            .is_some_and(|ppm| ppm.abs() > DRIFT_THRESHOLD_PPM)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: usize = 48_000;
+
+    #[test]
+    fn drift_is_shown_after_a_minute_and_warned_once_after_ten() {
+        let start = Instant::now();
+        // Feeds `seconds` of one sample per second, the output clock running
+        // `ppm` fast. Returns how many times it warned.
+        let run = |drift: &mut ClockDrift, seconds: u64, ppm: f64| {
+            (0..=seconds)
+                .filter(|&s| {
+                    let written = RATE * s as usize;
+                    let played = (written as f64 * (1.0 + ppm / 1e6)) as usize;
+                    drift.sample(start + Duration::from_secs(s), written, played, 0)
+                })
+                .count()
+        };
+        let mut drift = ClockDrift::new(RATE as u32);
+        run(&mut drift, 59, 3.0);
+        assert_eq!(drift.ppm(), None);
+        run(&mut drift, 60, 3.0);
+        assert!((drift.ppm().unwrap() - 3.0).abs() < 0.5);
+        assert!(!drift.is_drifting());
+
+        let mut drift = ClockDrift::new(RATE as u32);
+        assert_eq!(run(&mut drift, 3600, -150.0), 1);
+        assert!((drift.ppm().unwrap() + 150.0).abs() < 0.5);
+        assert!(drift.is_drifting());
+        assert_eq!(drift.write.rate_hz(), Some(RATE as f64));
+    }
+
+    #[test]
+    fn stalls_and_wakes_start_the_measurement_over() {
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        // Two minutes of matched clocks, one sample per second.
+        let run = |drift: &mut ClockDrift| {
+            for s in 0..=120 {
+                drift.sample(at(s), RATE * s as usize, RATE * s as usize, 0);
+            }
+        };
+        let mut drift = ClockDrift::new(RATE as u32);
+        run(&mut drift);
+        assert_eq!(drift.ppm(), Some(0.0));
+
+        // Input stalled for a second.
+        drift.sample(at(121), RATE * 120, RATE * 121, 0);
+        assert_eq!(drift.ppm(), None);
+        assert_eq!(drift.write.span(), Duration::ZERO);
+
+        let mut drift = ClockDrift::new(RATE as u32);
+        run(&mut drift);
+        drift.sample(at(121), RATE * 121, RATE * 121, 1);
+        assert_eq!(drift.ppm(), None);
+        // Samples come at most once a second.
+        assert!(!drift.sample(at(121) + Duration::from_millis(500), 0, 0, 2));
+        assert_eq!(drift.wakes_seen, 1);
+    }
+}
@@ -1,12 +1,87 @@
-use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU8, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::audio::ring_buffer::AudioRingBuffer;
+use anyhow::{Result, anyhow};
+
+use crate::audio::ring_buffer::ReadResult;
+use crate::audio::snapshot_ring::SnapshotRing;
+use crate::audio::source::AudioSource;
+use crate::playback::analysis::Meters;
+use crate::playback::events::{EventBus, ShifterEvent, XRunKind};
+use crate::playback::loudness::{self, LevelHistogram};
+use crate::playback::silence::PeakHistory;
+use crate::playback::snap::{self, Snap, Toward};
 use crate::playback::state::PlaybackState;
 
 /// Number of samples for the anti-click fade-in ramp after seeking.
 const RAMP_LENGTH: usize = 256;
 
+/// `ramp_position` before the first ramp (past the end: none running).
+const RAMP_IDLE: usize = usize::MAX;
+
+/// Pending blocks per snapshot ring (~170ms of 128-frame callbacks).
+const SNAPSHOT_BLOCKS: usize = 64;
+
+/// Pending blocks in the raw output sink ring (~700ms of 128-frame callbacks),
+/// larger than `SNAPSHOT_BLOCKS` to ride out file I/O stalls.
+const SINK_BLOCKS: usize = 256;
+
+/// A read position move no seek asked for, larger than this (beyond the normal
+/// per-callback advance), counts as a read jump.
+pub const READ_JUMP_THRESHOLD_MS: usize = 50;
+
+/// Largest callback buffer processed, in frames (CoreAudio rarely exceeds
+/// 4096). Larger output buffers are clamped and counted; see `callback_limit`.
+pub const MAX_CALLBACK_FRAMES: usize = 8192;
+
+/// Input gain limit either way, in dB.
+pub const INPUT_GAIN_MAX_DB: f32 = 24.0;
+
+/// Buffer fill levels (percent) announced with `ShifterEvent::BufferThreshold`.
+pub const BUFFER_THRESHOLDS: [u8; 2] = [90, 100];
+
+/// `snap_mode` value for snapping to bookmarks.
+const SNAP_BOOKMARKS: usize = usize::MAX;
+
+/// The range edge a clamped seek ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekLimit {
+    /// Can't seek forward past live.
+    Live,
+    /// Can't seek back past the oldest buffered audio.
+    BufferStart,
+}
+
+/// Raw seek/read state for the debug overlay. Positions and delays are in
+/// interleaved samples, as the ring counts them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugSnapshot {
+    pub write_position: usize,
+    pub read_position: usize,
+    pub target_delay_samples: usize,
+    pub display_delay_samples: usize,
+    /// Frames in the last output callback.
+    pub callback_frames: usize,
+    pub capacity: usize,
+    /// Furthest back a seek can go (`min(write_position, capacity)`).
+    pub max_delay_samples: usize,
+    /// Read delay the output callback aims for: one callback plus the live
+    /// floor and the target, clamped to `max_delay_samples`.
+    pub clamped_delay_samples: usize,
+    /// `output_underruns`.
+    pub output_underruns: usize,
+    pub channels: u16,
+}
+
+/// Transport state before a change, to tell what it did.
+#[derive(Debug, Clone, Copy)]
+struct Transport {
+    state: PlaybackState,
+    target_delay: usize,
+    position: usize,
+}
+
 /// Shared state bridge between the TUI thread and the audio callbacks.
 ///
 /// Seeking model: the TUI sets a `target_delay_samples` and the output callback
@@ -17,43 +92,208 @@ const RAMP_LENGTH: usize = 256;
 /// - TimeShifted: target_delay > 0 (the user-requested extra delay)
 /// - Paused:      read head frozen, write continues
 pub struct PlaybackController {
-    pub ring: Arc<AudioRingBuffer>,
+    /// What plays: the ring buffer the input callback fills, live.
+    pub ring: Arc<dyn AudioSource>,
     state: AtomicU8,
     channels: u16,
     sample_rate: u32,
     /// The user-requested delay beyond the minimum callback buffer.
     /// In Live mode this is 0. Seek adds/subtracts from this.
     target_delay_samples: AtomicUsize,
-    /// Remaining samples in the anti-click fade-in ramp.
-    ramp_remaining: AtomicUsize,
-    /// Peak level for left channel, stored as value * 1000.
-    peak_left: AtomicUsize,
-    /// Peak level for right channel, stored as value * 1000.
-    peak_right: AtomicUsize,
+    /// Extra minimum delay under the target, for outputs with large,
+    /// variable latency (AirPlay). 0 for most outputs.
+    live_floor_samples: AtomicUsize,
+    /// Samples into the anti-click fade-in ramp; none runs once it reaches
+    /// `RAMP_LENGTH` frames.
+    ramp_position: AtomicUsize,
     /// Output volume as value * 1000 (1000 = 100%).
     volume: AtomicUsize,
     /// Saved volume before mute (0 = not muted).
     muted_volume: AtomicUsize,
+    /// `volume` is exactly 1000, so `apply_volume` can skip the buffer.
+    volume_is_unity: AtomicBool,
+    /// Input gain in dB as value * 10, applied before the ring buffer.
+    input_gain_tenth_db: AtomicIsize,
+    /// Linear input gain reached by the last input callback (f32 bits); the
+    /// callback ramps from it to the target to avoid zipper noise.
+    input_gain_applied: AtomicU32,
     /// Delay in samples as last computed by the output callback.
     /// Single atomic — no read/write race, so the TUI gets a stable value.
     display_delay_samples: AtomicUsize,
+    /// Frames in the last output callback (the minimum delay is one callback).
+    callback_frames: AtomicUsize,
+    /// Frames received by the input callbacks, discarded ones included: the
+    /// input device clock, for `ClockDrift`.
+    input_frames: AtomicUsize,
+    /// Frames asked for by the output callbacks: the output device clock.
+    output_frames: AtomicUsize,
+    /// Target delay seen by the last `pre_read` (`usize::MAX` after a pause),
+    /// to tell requested read position moves from unrequested ones.
+    last_target_delay: AtomicUsize,
+    /// Callbacks larger than `MAX_CALLBACK_FRAMES`.
+    oversized_callbacks: AtomicUsize,
+    /// Output reads that caught up with the input while playing, so the
+    /// callback played silence.
+    output_underrun_count: AtomicUsize,
+    /// Unrequested read position jumps detected by `pre_read`.
+    read_jumps: AtomicUsize,
+    /// Size of the last read jump in samples.
+    last_read_jump: AtomicUsize,
+    /// Max unrequested read position move per callback in samples (0 = no limit).
+    max_read_step_samples: AtomicUsize,
+    /// Whether a limited move is still closing the gap (counted once).
+    catching_up: AtomicBool,
+    /// Input samples still to be discarded before capture starts (`--capture-delay-ms`).
+    capture_discard_samples: AtomicUsize,
+    /// Copies of the input callback buffers, consumed by the analysis thread.
+    pub input_blocks: SnapshotRing,
+    /// Copies of the output callback buffers, consumed by the analysis thread.
+    pub output_blocks: SnapshotRing,
+    /// Meter values published by the analysis thread.
+    pub meters: Meters,
+    /// Per-segment input peaks, recorded by the analysis thread.
+    pub peak_history: PeakHistory,
+    /// Session distribution of short-term input levels, for `autovol`.
+    pub levels: LevelHistogram,
+    /// Write position at the last `publish_pending`.
+    checked_write_position: AtomicUsize,
+    /// Read jumps, oversized callbacks and auto bookmarks already published.
+    published_read_jumps: AtomicUsize,
+    published_oversized: AtomicUsize,
+    published_auto_bookmarks: AtomicUsize,
+    /// Transport, volume and callback events for subscribers.
+    pub events: EventBus,
+    /// System wakes seen so far. Capture stops during sleep, so the TUI rebases
+    /// its captured-vs-wall-time comparison when this changes.
+    wakes: AtomicUsize,
+    /// Copies of the output callback buffers for `--output-raw` and
+    /// `--record-dir`, if enabled.
+    pub sink_blocks: Option<SnapshotRing>,
+    /// Whether a `--record-dir` recorder is draining `sink_blocks`.
+    recording: AtomicBool,
+    /// Segment splits requested so far; the recorder splits when it changes.
+    split_requests: AtomicUsize,
+    /// Configured snap mode: 0 = off, `SNAP_BOOKMARKS`, otherwise the grid in ms.
+    snap_mode: AtomicUsize,
+    /// Whether snapping is on (toggled from the TUI).
+    snap_enabled: AtomicBool,
+    /// Source channel (0-based) copied to every output channel, or -1 if off.
+    solo_channel: AtomicIsize,
+    /// Auto-bookmark interval in samples (0 = disabled).
+    auto_bookmark_interval_samples: AtomicUsize,
+    /// Number of intervals already bookmarked (`write_pos / interval`).
+    last_auto_bookmark: AtomicUsize,
+    /// Absolute write positions of bookmarks, oldest first.
+    /// The one exception to the lock-free rule: it changes at most once per
+    /// interval, and the output callback only ever `try_lock`s it.
+    bookmarks: Mutex<Vec<usize>>,
+    /// Debug builds only: range-checked `SeqCst` loads, see `load_checked`.
+    #[cfg(debug_assertions)]
+    unsafe_check_mode: bool,
 }
 
 impl PlaybackController {
-    pub fn new(ring: Arc<AudioRingBuffer>, channels: u16, sample_rate: u32) -> Self {
+    /// Zero channels or a zero sample rate, which the engine never passes,
+    /// are taken as 1, so no position-to-time conversion divides by zero.
+    pub fn new(ring: Arc<dyn AudioSource>, channels: u16, sample_rate: u32) -> Self {
+        let channels = channels.max(1);
+        let sample_rate = sample_rate.max(1);
         Self {
+            peak_history: PeakHistory::new(ring.capacity(), channels, sample_rate),
+            levels: LevelHistogram::new(channels, sample_rate),
             ring,
             state: AtomicU8::new(PlaybackState::Live as u8),
             channels,
             sample_rate,
             target_delay_samples: AtomicUsize::new(0),
-            ramp_remaining: AtomicUsize::new(0),
-            peak_left: AtomicUsize::new(0),
-            peak_right: AtomicUsize::new(0),
+            live_floor_samples: AtomicUsize::new(0),
+            ramp_position: AtomicUsize::new(RAMP_IDLE),
             volume: AtomicUsize::new(1000),
             muted_volume: AtomicUsize::new(0),
+            volume_is_unity: AtomicBool::new(true),
+            input_gain_tenth_db: AtomicIsize::new(0),
+            input_gain_applied: AtomicU32::new(1.0_f32.to_bits()),
             display_delay_samples: AtomicUsize::new(0),
+            callback_frames: AtomicUsize::new(0),
+            input_frames: AtomicUsize::new(0),
+            output_frames: AtomicUsize::new(0),
+            last_target_delay: AtomicUsize::new(usize::MAX),
+            oversized_callbacks: AtomicUsize::new(0),
+            output_underrun_count: AtomicUsize::new(0),
+            read_jumps: AtomicUsize::new(0),
+            last_read_jump: AtomicUsize::new(0),
+            max_read_step_samples: AtomicUsize::new(0),
+            catching_up: AtomicBool::new(false),
+            capture_discard_samples: AtomicUsize::new(0),
+            input_blocks: SnapshotRing::new(SNAPSHOT_BLOCKS),
+            output_blocks: SnapshotRing::new(SNAPSHOT_BLOCKS),
+            meters: Meters::new(),
+            checked_write_position: AtomicUsize::new(0),
+            published_read_jumps: AtomicUsize::new(0),
+            published_oversized: AtomicUsize::new(0),
+            published_auto_bookmarks: AtomicUsize::new(0),
+            events: EventBus::default(),
+            wakes: AtomicUsize::new(0),
+            sink_blocks: None,
+            recording: AtomicBool::new(false),
+            split_requests: AtomicUsize::new(0),
+            snap_mode: AtomicUsize::new(0),
+            snap_enabled: AtomicBool::new(false),
+            solo_channel: AtomicIsize::new(-1),
+            auto_bookmark_interval_samples: AtomicUsize::new(0),
+            last_auto_bookmark: AtomicUsize::new(0),
+            bookmarks: Mutex::new(Vec::new()),
+            #[cfg(debug_assertions)]
+            unsafe_check_mode: false,
+        }
+    }
+
+    /// Also copies output callback buffers into `sink_blocks` for the raw sink
+    /// or the recorder.
+    pub fn with_sink(mut self) -> Self {
+        self.sink_blocks = Some(SnapshotRing::new(SINK_BLOCKS));
+        self
+    }
+
+    /// Debug builds only: loads the delay and volume atomics with `SeqCst`
+    /// and panics on values no valid store could have left, to catch
+    /// unsynchronized access early in tests.
+    #[cfg(debug_assertions)]
+    #[allow(dead_code)] // Only tests turn it on.
+    pub fn with_unsafe_check_mode(mut self) -> Self {
+        self.unsafe_check_mode = true;
+        self
+    }
+
+    /// Loads `value` (`Relaxed`). With `with_unsafe_check_mode` the load is
+    /// `SeqCst` and a value outside `valid` panics, naming `what`.
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+    fn load_checked(&self, value: &AtomicUsize, valid: RangeInclusive<usize>, what: &str) -> usize {
+        #[cfg(debug_assertions)]
+        if self.unsafe_check_mode {
+            let loaded = value.load(Ordering::SeqCst);
+            assert!(
+                valid.contains(&loaded),
+                "{what} is {loaded}, outside {valid:?}"
+            );
+            return loaded;
         }
+        value.load(Ordering::Relaxed)
+    }
+
+    fn load_target_delay(&self) -> usize {
+        let capacity = self.ring.capacity();
+        self.load_checked(&self.target_delay_samples, 0..=capacity, "target delay")
+    }
+
+    fn load_display_delay(&self) -> usize {
+        let capacity = self.ring.capacity();
+        self.load_checked(&self.display_delay_samples, 0..=capacity, "display delay")
+    }
+
+    /// Volume as value * 1000.
+    fn load_volume(&self) -> usize {
+        self.load_checked(&self.volume, 0..=1500, "volume")
     }
 
     // -- State queries (called by TUI) --
@@ -63,32 +303,361 @@ impl PlaybackController {
     }
 
     pub fn delay_ms(&self) -> f64 {
-        let delay_samples = self.display_delay_samples.load(Ordering::Relaxed);
-        let frames = delay_samples / self.channels as usize;
+        self.samples_to_ms(self.load_display_delay())
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Duration of the last output callback, i.e. the minimum delay.
+    pub fn callback_ms(&self) -> f64 {
+        let frames = self.callback_frames.load(Ordering::Relaxed);
         frames as f64 / self.sample_rate as f64 * 1000.0
     }
 
+    /// Holds playback at least `samples` behind the input on top of one
+    /// callback, in Live mode and under any seek (clamped to the buffer).
+    pub fn set_live_floor_samples(&self, samples: usize) {
+        self.live_floor_samples
+            .store(samples.min(self.ring.capacity()), Ordering::Relaxed);
+    }
+
+    /// `set_live_floor_samples` in milliseconds.
+    pub fn live_floor_ms(&self) -> f64 {
+        self.samples_to_ms(self.live_floor_samples.load(Ordering::Relaxed))
+    }
+
+    /// Time left before capture starts, while `--capture-delay-ms` discards input.
+    pub fn capture_pending_ms(&self) -> Option<f64> {
+        let samples = self.capture_discard_samples.load(Ordering::Relaxed);
+        (samples > 0)
+            .then(|| samples as f64 / self.channels as f64 / self.sample_rate as f64 * 1000.0)
+    }
+
+    /// Unrequested read position jumps so far, and the size of the last in ms.
+    pub fn read_jumps(&self) -> (usize, f64) {
+        let frames = self.last_read_jump.load(Ordering::Relaxed) / self.channels as usize;
+        (
+            self.read_jumps.load(Ordering::Relaxed),
+            frames as f64 / self.sample_rate as f64 * 1000.0,
+        )
+    }
+
+    /// Callbacks seen with more than `MAX_CALLBACK_FRAMES` frames.
+    pub fn oversized_callbacks(&self) -> usize {
+        self.oversized_callbacks.load(Ordering::Relaxed)
+    }
+
+    /// Output callbacks that found too little audio to play (after the
+    /// first input; paused callbacks don't read).
+    pub fn output_underruns(&self) -> usize {
+        self.output_underrun_count.load(Ordering::Relaxed)
+    }
+
+    /// Clears the diagnostics counters and session peaks ("clear stats").
+    pub fn reset_stats(&self) {
+        self.meters.reset_session_peaks();
+        self.read_jumps.store(0, Ordering::Relaxed);
+        self.last_read_jump.store(0, Ordering::Relaxed);
+        self.oversized_callbacks.store(0, Ordering::Relaxed);
+        self.output_underrun_count.store(0, Ordering::Relaxed);
+        self.published_read_jumps.store(0, Ordering::Relaxed);
+        self.published_oversized.store(0, Ordering::Relaxed);
+        self.input_blocks.reset_dropped();
+        self.output_blocks.reset_dropped();
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Requested delay behind live, in samples (0 when live).
+    pub fn target_delay_samples(&self) -> usize {
+        self.load_target_delay()
+    }
+
+    /// Stream position the current target delay plays from.
+    pub fn target_position(&self) -> usize {
+        self.ring
+            .write_position()
+            .saturating_sub(self.target_delay_samples())
+    }
+
+    /// Copies the last `n_frames` frames (interleaved) ending at the write
+    /// position into a shared immutable slice, for analysis that needs a stable
+    /// window (FFT, onset detection). Fewer frames are returned if fewer have
+    /// been captured. Allocates, so call it from the TUI or analysis thread,
+    /// never from an audio callback.
+    #[allow(dead_code)] // API for analysis tools; nothing in the TUI uses it yet.
+    pub fn read_for_analysis(&self, n_frames: usize) -> Arc<[f32]> {
+        let channels = self.channels as usize;
+        let wp = self.ring.write_position();
+        // Leaves out what the producer could lap while it's being copied.
+        let available = wp - self.ring.safe_oldest();
+        let len = (n_frames * channels).min(available / channels * channels);
+        let mut window = vec![0.0; len];
+        if !self.ring.read_at(wp - len, &mut window) {
+            return Arc::from([]);
+        }
+        window.into()
+    }
+
+    /// Current raw positions and clamps, for the debug overlay.
+    pub fn debug_snapshot(&self) -> DebugSnapshot {
+        let target = self.load_target_delay();
+        let callback_frames = self.callback_frames.load(Ordering::Relaxed);
+        let max_delay = self.max_delay_samples();
+        DebugSnapshot {
+            write_position: self.ring.write_position(),
+            read_position: self.ring.read_position(),
+            target_delay_samples: target,
+            display_delay_samples: self.load_display_delay(),
+            callback_frames,
+            capacity: self.ring.capacity(),
+            max_delay_samples: max_delay,
+            clamped_delay_samples: (callback_frames * self.channels as usize
+                + self.live_floor_samples.load(Ordering::Relaxed)
+                + target)
+                .min(max_delay),
+            output_underruns: self.output_underruns(),
+            channels: self.channels,
+        }
+    }
+
+    /// How much of the buffer the delay takes, 0.0 - 1.0 (0.0 for an empty
+    /// buffer, which has nothing to use).
     pub fn buffer_usage(&self) -> f64 {
-        self.ring.usage_fraction()
+        if self.ring.capacity() == 0 {
+            return 0.0;
+        }
+        let usage = self.ring.usage_fraction();
+        if usage.is_finite() {
+            usage.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Seconds of audio captured since startup, from the ring's write position.
+    pub fn captured_seconds(&self) -> f64 {
+        let samples_per_second = self.sample_rate as f64 * self.channels as f64;
+        self.ring.write_position() as f64 / samples_per_second
+    }
+
+    /// Publishes what the audio callbacks flagged since the last call: buffer
+    /// fill thresholds (100: the buffer stops growing and the oldest audio is
+    /// overwritten), read jumps, oversized callbacks and auto bookmarks. Called
+    /// once per TUI frame; the callbacks never touch the event bus themselves.
+    pub fn publish_pending(&self) {
+        let wp = self.ring.write_position();
+        let previous = self.checked_write_position.swap(wp, Ordering::Relaxed);
+        let capacity = self.ring.capacity();
+        for level in BUFFER_THRESHOLDS {
+            let at = capacity * level as usize / 100;
+            if previous < at && wp >= at {
+                self.events.publish(ShifterEvent::BufferThreshold { level });
+            }
+        }
+
+        let (jumps, ms) = self.read_jumps();
+        if jumps > self.published_read_jumps.swap(jumps, Ordering::Relaxed) {
+            self.events.publish(ShifterEvent::XRun {
+                kind: XRunKind::ReadJump { ms },
+            });
+        }
+        let oversized = self.oversized_callbacks();
+        if oversized > self.published_oversized.swap(oversized, Ordering::Relaxed) {
+            self.events.publish(ShifterEvent::XRun {
+                kind: XRunKind::OversizedCallback,
+            });
+        }
+
+        let interval = self.auto_bookmark_interval_samples.load(Ordering::Acquire);
+        let crossed = self.last_auto_bookmark.load(Ordering::Relaxed);
+        // The callback pushes one mark per check, at the latest interval.
+        if crossed
+            > self
+                .published_auto_bookmarks
+                .swap(crossed, Ordering::Relaxed)
+        {
+            self.events.publish(ShifterEvent::Bookmarked {
+                position: crossed * interval,
+            });
+        }
+    }
+
+    pub fn record_wake(&self) {
+        self.wakes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Frames the input device has delivered so far.
+    pub fn input_frames(&self) -> usize {
+        self.input_frames.load(Ordering::Relaxed)
+    }
+
+    /// Frames the output device has played so far.
+    pub fn output_frames(&self) -> usize {
+        self.output_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn wake_count(&self) -> usize {
+        self.wakes.load(Ordering::Relaxed)
     }
 
     pub fn peak_levels(&self) -> (f32, f32) {
-        let l = self.peak_left.load(Ordering::Relaxed) as f32 / 1000.0;
-        let r = self.peak_right.load(Ordering::Relaxed) as f32 / 1000.0;
-        (l, r)
+        self.meters.peaks()
+    }
+
+    /// Bookmarked absolute positions still inside the buffer, oldest first.
+    pub fn bookmarks(&self) -> Vec<usize> {
+        let oldest = self
+            .ring
+            .write_position()
+            .saturating_sub(self.ring.capacity());
+        self.bookmarks
+            .lock()
+            .map(|marks| marks.iter().copied().filter(|&p| p >= oldest).collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a `--record-dir` recorder is running.
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+
+    /// Segment splits requested so far (see `request_split`).
+    pub fn split_requests(&self) -> usize {
+        self.split_requests.load(Ordering::Acquire)
+    }
+
+    /// Returns and resets the (input, output) envelope maxima since the last call.
+    /// Both are taken together so they cover the same wall-clock interval.
+    pub fn take_envelopes(&self) -> (f32, f32) {
+        self.meters.take_envelopes()
     }
 
     pub fn volume(&self) -> f32 {
-        self.volume.load(Ordering::Relaxed) as f32 / 1000.0
+        self.load_volume() as f32 / 1000.0
     }
 
     pub fn is_muted(&self) -> bool {
         self.muted_volume.load(Ordering::Relaxed) > 0
     }
 
+    /// Input gain in dB (0 = unity).
+    pub fn input_gain_db(&self) -> f32 {
+        self.input_gain_tenth_db.load(Ordering::Relaxed) as f32 / 10.0
+    }
+
     // -- Commands (called by TUI) --
 
+    /// Enables automatic bookmarks every `interval_s` seconds of captured audio
+    /// (0 disables). Call before the audio units start.
+    pub fn set_auto_bookmark_interval_s(&self, interval_s: u32) {
+        let interval = interval_s as usize * self.sample_rate as usize * self.channels as usize;
+        if interval > 0
+            && let Ok(mut marks) = self.bookmarks.lock()
+        {
+            // Preallocate so the output callback never reallocates.
+            marks.reserve(self.ring.capacity() / interval + 2);
+        }
+        self.auto_bookmark_interval_samples
+            .store(interval, Ordering::Release);
+    }
+
+    /// Discards the first `ms` of input, so the buffer starts after routing
+    /// settles. Call before the input unit starts.
+    pub fn set_capture_delay_ms(&self, ms: u32) {
+        let frames = ms as usize * self.sample_rate as usize / 1000;
+        self.capture_discard_samples
+            .store(frames * self.channels as usize, Ordering::Relaxed);
+    }
+
+    /// Limits how far the read position may move per callback when no seek
+    /// asked for it, so jumps after slow callbacks are spread out (0 = off).
+    pub fn set_max_read_step_ms(&self, ms: u32) {
+        let frames = ms as usize * self.sample_rate as usize / 1000;
+        self.max_read_step_samples
+            .store(frames * self.channels as usize, Ordering::Relaxed);
+    }
+
+    /// Stream position of the audio currently playing.
+    pub fn play_position(&self) -> usize {
+        let delay = self.load_display_delay();
+        self.ring.write_position().saturating_sub(delay)
+    }
+
+    /// Bookmarks the audio currently playing.
+    pub fn add_bookmark(&self) {
+        let pos = self.play_position();
+        if let Ok(mut marks) = self.bookmarks.lock() {
+            // Keep oldest-first order; auto bookmarks may be newer than `pos`.
+            let idx = marks.partition_point(|&p| p <= pos);
+            marks.insert(idx, pos);
+        }
+        self.events
+            .publish(ShifterEvent::Bookmarked { position: pos });
+    }
+
+    /// Asks the recorder to finish its current file and start the next.
+    /// Returns false if nothing is recording.
+    pub fn request_split(&self) -> bool {
+        if !self.is_recording() {
+            return false;
+        }
+        self.split_requests.fetch_add(1, Ordering::Release);
+        true
+    }
+
+    /// Called by the recorder thread when it starts and stops.
+    pub fn set_recording(&self, recording: bool) {
+        self.recording.store(recording, Ordering::Relaxed);
+    }
+
+    fn transport(&self) -> Transport {
+        Transport {
+            state: self.state(),
+            target_delay: self.target_delay_samples(),
+            position: self.play_position(),
+        }
+    }
+
+    fn samples_to_ms(&self, samples: usize) -> f64 {
+        let frames = samples / self.channels as usize;
+        frames as f64 / self.sample_rate as f64 * 1000.0
+    }
+
+    /// Publishes the state change since `before`, and a seek if the audio
+    /// jumped (`seeked` and the target delay moved).
+    fn publish_transport(&self, before: Transport, seeked: bool) {
+        let after = self.transport();
+        if after.state != before.state {
+            self.events.publish(ShifterEvent::StateChanged {
+                from: before.state,
+                to: after.state,
+                position: before.position,
+            });
+        }
+        if seeked && after.target_delay != before.target_delay {
+            self.events.publish(ShifterEvent::Seeked {
+                from_ms: self.samples_to_ms(before.target_delay),
+                to_ms: self.samples_to_ms(after.target_delay),
+                position: before.position,
+                to_position: self.target_position(),
+            });
+        }
+    }
+
+    /// Pauses, or resumes from where it paused. Resuming keeps the delay built
+    /// up while paused, so the audio doesn't jump (no `Seeked` event).
     pub fn toggle_pause(&self) {
+        let before = self.transport();
+        self.apply_toggle_pause();
+        self.publish_transport(before, false);
+    }
+
+    fn apply_toggle_pause(&self) {
         let current = self.state();
         match current {
             PlaybackState::Live | PlaybackState::TimeShifted => {
@@ -107,153 +676,1225 @@ impl PlaybackController {
                     self.state
                         .store(PlaybackState::TimeShifted as u8, Ordering::Release);
                 }
-                self.ramp_remaining
-                    .store(RAMP_LENGTH * self.channels as usize, Ordering::Release);
+                self.arm_ramp();
+            }
+        }
+    }
+
+    /// Moves to `target` through the same paths as the keys, so resuming and
+    /// going live fire the anti-click ramp. Live -> time-shifted has no such
+    /// path (it needs a delay; seek instead) and is rejected.
+    pub fn set_state(&self, target: PlaybackState) -> Result<()> {
+        let current = self.state();
+        if current == target {
+            return Ok(());
+        }
+        match target {
+            PlaybackState::Live => self.jump_to_live(),
+            PlaybackState::Paused => self.toggle_pause(),
+            PlaybackState::TimeShifted if current == PlaybackState::Paused => self.toggle_pause(),
+            PlaybackState::TimeShifted => {
+                return Err(anyhow!(
+                    "Invalid state transition from {} to {} (seek to a delay instead)",
+                    current.label(),
+                    target.label(),
+                ));
             }
         }
+        Ok(())
     }
 
-    pub fn seek_ms(&self, delta_ms: f64) {
-        let delta_samples =
-            (delta_ms / 1000.0 * self.sample_rate as f64) as i64 * self.channels as i64;
-        let cap = self.ring.capacity() as i64;
+    /// Moves the target delay by `delta_ms` (positive = further back).
+    /// Returns the edge hit if the seek had to be clamped.
+    pub fn seek_ms(&self, delta_ms: f64) -> Option<SeekLimit> {
+        // Float-to-int casts saturate; keep the rest from wrapping too.
+        let delta_samples = ((delta_ms / 1000.0 * self.sample_rate as f64) as i64)
+            .saturating_mul(self.channels as i64);
 
-        let current = self.target_delay_samples.load(Ordering::Relaxed) as i64;
-        // Don't seek further back than what's been written
-        let max_delay = (self.ring.write_position() as i64).min(cap);
-        let new_target = (current + delta_samples).clamp(0, max_delay);
+        let current = self.load_target_delay() as i64;
+        let max_delay = self.max_delay_samples() as i64;
+        let requested = current.saturating_add(delta_samples);
+        let toward = if delta_samples >= 0 {
+            Toward::MoreDelay
+        } else {
+            Toward::LessDelay
+        };
+        let snapped = self.snapped_delay(
+            requested.clamp(0, max_delay) as usize,
+            current as usize,
+            toward,
+        );
+        self.set_target_delay(snapped.min(max_delay as usize));
 
-        self.target_delay_samples
-            .store(new_target as usize, Ordering::Release);
-        self.ramp_remaining
-            .store(RAMP_LENGTH * self.channels as usize, Ordering::Release);
+        if requested < 0 {
+            Some(SeekLimit::Live)
+        } else if requested > max_delay {
+            Some(SeekLimit::BufferStart)
+        } else {
+            None
+        }
+    }
+
+    /// Seeks to an absolute delay behind live. Returns false, without seeking,
+    /// if that audio is no longer (or not yet) in the buffer.
+    pub fn seek_to_delay_ms(&self, delay_ms: f64) -> bool {
+        if !delay_ms.is_finite() || delay_ms < 0.0 {
+            return false;
+        }
+        let frames = (delay_ms / 1000.0 * self.sample_rate as f64) as usize;
+        let target = frames * self.channels as usize;
+        let max_delay = self.max_delay_samples();
+        if target > max_delay {
+            return false;
+        }
+        let current = self.load_target_delay();
+        let snapped = self.snapped_delay(target, current, Toward::Nearest);
+        self.set_target_delay(snapped.min(max_delay));
+        true
+    }
+
+    /// Sets where seeks land; anything but `Snap::Off` also turns snapping on.
+    pub fn set_snap(&self, snap: Snap) {
+        let mode = match snap {
+            Snap::Off => 0,
+            Snap::Grid(ms) => ms as usize,
+            Snap::Bookmarks => SNAP_BOOKMARKS,
+        };
+        self.snap_mode.store(mode, Ordering::Relaxed);
+        self.snap_enabled
+            .store(snap != Snap::Off, Ordering::Relaxed);
+    }
+
+    /// Turns snapping on or off, keeping the configured mode (a 1s grid if
+    /// none was set). Returns the snap now in effect.
+    pub fn toggle_snap(&self) -> Snap {
+        if self.snap_mode.load(Ordering::Relaxed) == 0 {
+            self.set_snap(Snap::Grid(snap::DEFAULT_GRID_MS));
+        } else {
+            self.snap_enabled.fetch_xor(true, Ordering::Relaxed);
+        }
+        self.snap()
+    }
+
+    /// The snap in effect (`Snap::Off` while toggled off).
+    pub fn snap(&self) -> Snap {
+        if !self.snap_enabled.load(Ordering::Relaxed) {
+            return Snap::Off;
+        }
+        match self.snap_mode.load(Ordering::Relaxed) {
+            0 => Snap::Off,
+            SNAP_BOOKMARKS => Snap::Bookmarks,
+            ms => Snap::Grid(ms as u32),
+        }
+    }
 
-        if new_target == 0 {
+    /// Applies the snap to a requested target delay. `current` is the delay
+    /// the seek started from.
+    fn snapped_delay(&self, delay: usize, current: usize, toward: Toward) -> usize {
+        let samples_per_ms = self.sample_rate as usize * self.channels as usize / 1000;
+        match self.snap() {
+            Snap::Off => delay,
+            Snap::Grid(ms) => {
+                let grid = ms as usize * self.sample_rate as usize / 1000 * self.channels as usize;
+                snap::snap_to_grid(delay, grid, toward)
+            }
+            Snap::Bookmarks => {
+                let wp = self.ring.write_position();
+                let delays: Vec<usize> = self
+                    .bookmarks()
+                    .iter()
+                    .map(|&pos| wp.saturating_sub(pos))
+                    .collect();
+                let tolerance = snap::BOOKMARK_TOLERANCE_MS * samples_per_ms;
+                snap::snap_to_bookmark(delay, current, &delays, tolerance, toward).unwrap_or(delay)
+            }
+        }
+    }
+
+    /// Seeks so that stream position `pos` plays next, if it's still buffered.
+    pub fn seek_to_position(&self, pos: usize) -> bool {
+        let delay = self.ring.write_position().saturating_sub(pos);
+        if delay > self.max_delay_samples() {
+            return false;
+        }
+        self.set_target_delay(delay);
+        true
+    }
+
+    fn set_target_delay(&self, target: usize) {
+        let before = self.transport();
+        self.target_delay_samples.store(target, Ordering::Release);
+        self.arm_ramp();
+
+        if target == 0 {
             self.state
                 .store(PlaybackState::Live as u8, Ordering::Release);
         } else {
             self.state
                 .store(PlaybackState::TimeShifted as u8, Ordering::Release);
         }
+        self.publish_transport(before, true);
+    }
+
+    /// Which seek edges the target delay currently sits at: (live, buffer start).
+    pub fn seek_edges(&self) -> (bool, bool) {
+        let target = self.load_target_delay();
+        (target == 0, target >= self.max_delay_samples())
+    }
+
+    /// Furthest back a seek can go: the oldest samples the writer won't
+    /// overwrite with its next write (see `AudioRingBuffer::safe_oldest`).
+    fn max_delay_samples(&self) -> usize {
+        self.ring.write_position() - self.ring.safe_oldest()
     }
 
     pub fn adjust_volume(&self, delta: i32) {
-        let current = self.volume.load(Ordering::Relaxed) as i32;
+        let current = self.load_volume() as i32;
         let new_vol = (current + delta).clamp(0, 1500) as usize;
-        self.volume.store(new_vol, Ordering::Relaxed);
-        // Unmute on manual volume change
+        self.store_volume(new_vol);
+    }
+
+    /// Sets the output volume (1.0 = 100%), clamped to 0-150%. Unmutes.
+    pub fn set_volume(&self, volume: f32) {
+        let new_vol = (volume * 1000.0).round().clamp(0.0, 1500.0) as usize;
+        self.store_volume(new_vol);
+    }
+
+    /// Stores a manual volume change (which unmutes) and publishes it.
+    fn store_volume(&self, volume: usize) {
+        let before = (self.volume(), self.is_muted());
+        self.store_raw_volume(volume);
         self.muted_volume.store(0, Ordering::Relaxed);
+        self.publish_volume(before);
+    }
+
+    /// Stores `volume` (value * 1000) and whether it is unity.
+    fn store_raw_volume(&self, volume: usize) {
+        self.volume.store(volume, Ordering::Relaxed);
+        self.volume_is_unity
+            .store(volume == 1000, Ordering::Relaxed);
+    }
+
+    fn publish_volume(&self, before: (f32, bool)) {
+        let after = (self.volume(), self.is_muted());
+        if after != before {
+            self.events.publish(ShifterEvent::VolumeChanged {
+                volume: after.0,
+                muted: after.1,
+            });
+        }
+    }
+
+    /// Sets the input gain in dB, clamped to +/-`INPUT_GAIN_MAX_DB`.
+    pub fn set_input_gain_db(&self, db: f32) {
+        let tenths = (db * 10.0).round() as isize;
+        let max = (INPUT_GAIN_MAX_DB * 10.0) as isize;
+        self.input_gain_tenth_db
+            .store(tenths.clamp(-max, max), Ordering::Relaxed);
+    }
+
+    pub fn adjust_input_gain_db(&self, delta_db: f32) {
+        self.set_input_gain_db(self.input_gain_db() + delta_db);
+    }
+
+    /// Sets the volume that puts the 95th percentile of the session's input
+    /// levels at `target_db`. Returns the volume and the percentile level.
+    pub fn auto_volume(&self, target_db: f32) -> Result<(f32, f32)> {
+        let counts = self.levels.counts();
+        if counts.iter().sum::<usize>() < loudness::AUTOVOL_MIN_WINDOWS {
+            return Err(anyhow!("Not enough audio yet for auto volume"));
+        }
+        let level = loudness::percentile_db(&counts, loudness::AUTOVOL_PERCENTILE)
+            .ok_or_else(|| anyhow!("Not enough audio yet for auto volume"))?;
+        let volume = loudness::gain_for(level, target_db);
+        self.set_volume(volume);
+        Ok((volume, level))
+    }
+
+    /// Solos a channel (1-based) to every output channel, or turns solo off
+    /// with `None`. Both fade in with the anti-click ramp.
+    pub fn set_solo(&self, channel: Option<u16>) -> Result<()> {
+        let index = match channel {
+            Some(ch) if ch == 0 || ch > self.channels => {
+                return Err(anyhow!(
+                    "Channel {ch} out of range (input has {})",
+                    self.channels
+                ));
+            }
+            Some(ch) => ch as isize - 1,
+            None => -1,
+        };
+        if self.solo_channel.swap(index, Ordering::Relaxed) != index {
+            self.arm_ramp();
+        }
+        Ok(())
+    }
+
+    /// The soloed channel (1-based), if any.
+    pub fn solo(&self) -> Option<u16> {
+        let index = self.solo_channel.load(Ordering::Relaxed);
+        (index >= 0).then(|| index as u16 + 1)
     }
 
     pub fn toggle_mute(&self) {
+        let before = (self.volume(), self.is_muted());
         let saved = self.muted_volume.load(Ordering::Relaxed);
         if saved > 0 {
             // Unmute: restore saved volume
-            self.volume.store(saved, Ordering::Relaxed);
+            self.store_raw_volume(saved);
             self.muted_volume.store(0, Ordering::Relaxed);
         } else {
             // Mute: save current volume, set to 0
-            let current = self.volume.load(Ordering::Relaxed);
+            let current = self.load_volume();
             self.muted_volume.store(current.max(1), Ordering::Relaxed);
-            self.volume.store(0, Ordering::Relaxed);
+            self.store_raw_volume(0);
         }
+        self.publish_volume(before);
     }
 
     pub fn jump_to_live(&self) {
+        let before = self.transport();
         self.target_delay_samples.store(0, Ordering::Release);
         self.state
             .store(PlaybackState::Live as u8, Ordering::Release);
-        self.ramp_remaining
-            .store(RAMP_LENGTH * self.channels as usize, Ordering::Release);
+        self.arm_ramp();
+        self.publish_transport(before, true);
+    }
+
+    // -- Called by input callback --
+
+    /// Writes an input buffer to the ring and the meter snapshots, minus any
+    /// part still inside the capture delay. The ring stays inactive (and the
+    /// write position at 0) until the first kept sample.
+    pub fn capture_input(&self, data: &[f32]) {
+        if data.is_empty() {
+            return;
+        }
+        self.input_frames
+            .fetch_add(data.len() / self.channels as usize, Ordering::Relaxed);
+        let discard = self.capture_discard_samples.load(Ordering::Relaxed);
+        let data = if discard > 0 {
+            let skipped = discard.min(data.len());
+            self.capture_discard_samples
+                .store(discard - skipped, Ordering::Relaxed);
+            &data[skipped..]
+        } else {
+            data
+        };
+        if data.is_empty() {
+            return;
+        }
+        self.ring.write(data);
+        self.input_blocks.push(data);
+    }
+
+    /// Applies the input gain in place, before `capture_input`. A change is
+    /// ramped across one callback buffer.
+    pub fn apply_input_gain(&self, data: &mut [f32]) {
+        if data.is_empty() {
+            return;
+        }
+        let target = 10f32.powf(self.input_gain_db() / 20.0);
+        let start = f32::from_bits(self.input_gain_applied.load(Ordering::Relaxed));
+        if start == target {
+            if target != 1.0 {
+                for s in data.iter_mut() {
+                    *s *= target;
+                }
+            }
+            return;
+        }
+        let frames = (data.len() / self.channels as usize).max(1);
+        for (i, frame) in data.chunks_mut(self.channels as usize).enumerate() {
+            let gain = start + (target - start) * ((i + 1) as f32 / frames as f32).min(1.0);
+            for s in frame {
+                *s *= gain;
+            }
+        }
+        self.input_gain_applied
+            .store(target.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Samples of a `len`-sample callback buffer to process: all of it, or
+    /// `MAX_CALLBACK_FRAMES` frames for an oversized one, which is counted.
+    pub fn callback_limit(&self, len: usize) -> usize {
+        let max = MAX_CALLBACK_FRAMES * self.channels as usize;
+        if len <= max {
+            return len;
+        }
+        self.oversized_callbacks.fetch_add(1, Ordering::Relaxed);
+        max
     }
 
     // -- Called by output callback --
 
+    /// Fills `data` from the read position, counting an underrun when there
+    /// isn't enough audio. Before the first input every read comes up
+    /// short, so those aren't counted.
+    pub fn read_output(&self, data: &mut [f32]) {
+        if self.ring.read(data) == ReadResult::Underrun && self.ring.write_position() > 0 {
+            self.output_underrun_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
     /// Positions the read head and returns the current state.
     ///
     /// The callback owns the read position. The TUI only sets `target_delay_samples`
     /// and this method translates that into `rp = wp - min_delay - target_delay`.
     pub fn pre_read(&self, frame_count: usize) -> PlaybackState {
+        self.record_auto_bookmark();
+        self.callback_frames.store(frame_count, Ordering::Relaxed);
+        self.output_frames.fetch_add(frame_count, Ordering::Relaxed);
+
         let state = self.state();
         if state == PlaybackState::Paused {
             self.display_delay_samples
                 .store(self.ring.delay_samples(), Ordering::Relaxed);
+            self.last_target_delay.store(usize::MAX, Ordering::Relaxed);
             return state;
         }
 
         let wp = self.ring.write_position();
         let callback_samples = frame_count * self.channels as usize;
-        let target = self.target_delay_samples.load(Ordering::Relaxed);
+        let target = self.load_target_delay();
 
-        // Total delay = one callback buffer (minimum) + user-requested extra delay
-        let total_delay = callback_samples + target;
+        // Total delay = one callback buffer (minimum) + the output's floor +
+        // user-requested extra delay
+        let floor = self.live_floor_samples.load(Ordering::Relaxed);
+        let total_delay = callback_samples + floor + target;
 
         // Don't go further back than the buffer allows or what's been written
         let clamped = total_delay.min(self.ring.capacity()).min(wp);
-        let target_rp = wp.saturating_sub(clamped);
+        // A floor change moves the read head on purpose, like a seek.
+        let target_rp = self.settle_read_position(floor + target, wp.saturating_sub(clamped));
         self.ring.set_read_position(target_rp);
 
         self.display_delay_samples.store(target, Ordering::Relaxed);
         state
     }
 
-    /// Applies software volume to the output buffer.
+    /// Checks the move from the current read position to `target_rp`. With an
+    /// unchanged target delay the read head should only advance by what the
+    /// last callback consumed; a larger move means a slow callback let the
+    /// writer run ahead. Counts it, and with a max step set, limits it.
+    fn settle_read_position(&self, target: usize, target_rp: usize) -> usize {
+        let last_target = self.last_target_delay.swap(target, Ordering::Relaxed);
+        let current = self.ring.read_position();
+        let jump = target_rp.abs_diff(current);
+        let threshold =
+            READ_JUMP_THRESHOLD_MS * self.sample_rate as usize / 1000 * self.channels as usize;
+        if last_target != target || jump <= threshold {
+            self.catching_up.store(false, Ordering::Relaxed);
+            return target_rp;
+        }
+
+        let step = self.max_read_step_samples.load(Ordering::Relaxed);
+        if !self.catching_up.load(Ordering::Relaxed) {
+            self.read_jumps.fetch_add(1, Ordering::Relaxed);
+            self.last_read_jump.store(jump, Ordering::Relaxed);
+        }
+        if step == 0 {
+            return target_rp;
+        }
+        // Stays between `current` and `target_rp`, both valid read positions.
+        let step = step.min(jump);
+        self.catching_up.store(step < jump, Ordering::Relaxed);
+        if target_rp > current {
+            current + step
+        } else {
+            current - step
+        }
+    }
+
+    /// Pushes a bookmark when the write position crosses the next interval.
+    fn record_auto_bookmark(&self) {
+        let interval = self.auto_bookmark_interval_samples.load(Ordering::Acquire);
+        if interval == 0 {
+            return;
+        }
+        let wp = self.ring.write_position();
+        let crossed = wp / interval;
+        if crossed <= self.last_auto_bookmark.load(Ordering::Relaxed) {
+            return;
+        }
+        // Never block the audio thread: if the TUI holds the lock, retry next callback.
+        let Ok(mut marks) = self.bookmarks.try_lock() else {
+            return;
+        };
+        if marks.len() == marks.capacity() {
+            let oldest = wp.saturating_sub(self.ring.capacity());
+            marks.retain(|&p| p >= oldest);
+        }
+        if marks.len() < marks.capacity() {
+            marks.push(crossed * interval);
+        }
+        self.last_auto_bookmark.store(crossed, Ordering::Relaxed);
+    }
+
+    /// Applies software volume to the output buffer. At 100% (the default)
+    /// the buffer is left alone without any arithmetic.
     pub fn apply_volume(&self, data: &mut [f32]) {
-        let vol = self.volume.load(Ordering::Relaxed) as f32 / 1000.0;
-        if (vol - 1.0).abs() > 0.001 {
-            for s in data.iter_mut() {
-                *s *= vol;
-            }
+        if self.volume_is_unity.load(Ordering::Relaxed) {
+            return;
+        }
+        let vol = self.load_volume() as f32 / 1000.0;
+        for s in data.iter_mut() {
+            *s *= vol;
         }
     }
 
-    /// Applies the anti-click ramp to the output buffer if needed.
-    pub fn apply_ramp(&self, data: &mut [f32]) {
-        let ramp = self.ramp_remaining.load(Ordering::Acquire);
-        if ramp == 0 {
+    /// Copies the soloed channel to every channel of each interleaved frame.
+    pub fn apply_solo(&self, data: &mut [f32]) {
+        let index = self.solo_channel.load(Ordering::Relaxed);
+        if index < 0 {
             return;
         }
-        let ramp_total = RAMP_LENGTH * self.channels as usize;
-        let elapsed = ramp_total.saturating_sub(ramp);
-        for (i, sample) in data.iter_mut().enumerate() {
-            let pos = elapsed + i;
-            if pos < ramp_total {
-                let gain = pos as f32 / ramp_total as f32;
-                *sample *= gain;
-            }
+        let index = index as usize;
+        for frame in data.chunks_exact_mut(self.channels as usize) {
+            let sample = frame[index];
+            frame.fill(sample);
         }
-        let consumed = data.len().min(ramp);
-        self.ramp_remaining.fetch_sub(consumed, Ordering::Release);
     }
 
-    /// Updates peak levels from the output buffer.
-    pub fn update_peaks(&self, data: &[f32]) {
-        if self.channels == 0 {
+    /// Starts the anti-click ramp over, from silence.
+    fn arm_ramp(&self) {
+        self.ramp_position.store(0, Ordering::Release);
+    }
+
+    /// Applies the anti-click ramp to the output buffer if needed. The gain
+    /// of each sample follows from how far into the ramp it is, frame by
+    /// frame (all channels of a frame get the same gain), so it rises the
+    /// same way whatever the callback sizes.
+    pub fn apply_ramp(&self, data: &mut [f32]) {
+        let position = self.ramp_position.load(Ordering::Acquire);
+        let channels = self.channels as usize;
+        let ramp_total = RAMP_LENGTH * channels;
+        if position >= ramp_total || data.is_empty() {
             return;
         }
-        let mut peak_l: f32 = 0.0;
-        let mut peak_r: f32 = 0.0;
-        let ch = self.channels as usize;
+        let ramped = data.len().min(ramp_total - position);
+        for (i, sample) in data[..ramped].iter_mut().enumerate() {
+            let frame = (position + i) / channels;
+            *sample *= frame as f32 / RAMP_LENGTH as f32;
+        }
+        // A seek from the TUI may have re-armed the ramp meanwhile; keep it whole.
+        let _ = self.ramp_position.compare_exchange(
+            position,
+            position + ramped,
+            Ordering::Release,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ring_buffer::AudioRingBuffer;
+
+    #[test]
+    fn seek_within_range_is_not_limited() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        assert_eq!(ctrl.seek_ms(2_000.0), None);
+        assert_eq!(ctrl.seek_ms(-1_000.0), None);
+        assert_eq!(ctrl.seek_edges(), (false, false));
+    }
+
+    #[test]
+    fn seek_past_written_audio_clamps_to_buffer_start() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        assert_eq!(ctrl.seek_ms(60_000.0), Some(SeekLimit::BufferStart));
+        assert_eq!(ctrl.state(), PlaybackState::TimeShifted);
+        assert_eq!(ctrl.seek_edges(), (false, true));
+    }
+
+    #[test]
+    fn seek_past_live_clamps_to_live() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.seek_ms(1_000.0);
+        assert_eq!(ctrl.seek_ms(-30_000.0), Some(SeekLimit::Live));
+        assert_eq!(ctrl.state(), PlaybackState::Live);
+        assert_eq!(ctrl.seek_edges(), (true, false));
+    }
+
+    #[test]
+    fn huge_seeks_clamp_instead_of_overflowing() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2 * 10));
+        ring.write(&vec![0.0; 48_000 * 2 * 5]);
+        let ctrl = PlaybackController::new(ring, 2, 48_000);
+
+        assert_eq!(ctrl.seek_ms(1e300), Some(SeekLimit::BufferStart));
+        assert_eq!(ctrl.load_target_delay(), 48_000 * 2 * 5);
+        assert_eq!(ctrl.seek_ms(-1e300), Some(SeekLimit::Live));
+        assert_eq!(ctrl.state(), PlaybackState::Live);
+        assert_eq!(ctrl.seek_ms(f64::MAX), Some(SeekLimit::BufferStart));
+    }
+
+    #[test]
+    fn seek_to_oldest_stays_readable_while_writes_wrap() {
+        let ring = Arc::new(AudioRingBuffer::new(1_000));
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        // Each 100-sample block holds its index, so a read shows where it came from.
+        for block in 0..30 {
+            ctrl.ring.write(&[block as f32; 100]);
+            ctrl.pre_read(100);
+            ctrl.ring.read(&mut [0.0; 100]);
+        }
+        // The oldest block (20) is left out: the next write overwrites it.
+        assert_eq!(ctrl.seek_ms(60_000.0), Some(SeekLimit::BufferStart));
+        assert_eq!(ctrl.load_target_delay(), 900);
+        for block in 30..45 {
+            let wp = ctrl.ring.write_position();
+            ctrl.ring.write(&[block as f32; 100]);
+            assert_eq!(
+                ctrl.ring.write_position(),
+                wp + 100,
+                "block {block} dropped"
+            );
+            ctrl.pre_read(100);
+            let mut out = [0.0; 100];
+            assert_eq!(ctrl.ring.read(&mut out), ReadResult::Ok);
+            assert_eq!(out, [(block - 9) as f32; 100]);
+        }
+        assert_eq!(ctrl.seek_edges(), (false, true));
+    }
 
-        for frame in data.chunks(ch) {
-            if let Some(&l) = frame.first() {
-                peak_l = peak_l.max(l.abs());
+    #[test]
+    fn seek_is_bounded_by_capacity() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 10_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.ring.read(&mut vec![0.0; 5_000]);
+        ctrl.ring.write(&vec![0.0; 5_000]);
+        assert_eq!(ctrl.seek_ms(12_000.0), Some(SeekLimit::BufferStart));
+        assert_eq!(ctrl.seek_edges(), (false, true));
+    }
+
+    #[test]
+    fn seek_to_delay_inside_buffer() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        assert!(ctrl.seek_to_delay_ms(3_000.0));
+        assert_eq!(ctrl.state(), PlaybackState::TimeShifted);
+        assert!(ctrl.seek_to_delay_ms(0.0));
+        assert_eq!(ctrl.state(), PlaybackState::Live);
+    }
+
+    #[test]
+    fn seek_to_delay_outside_buffer_does_nothing() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.seek_ms(1_000.0);
+        assert!(!ctrl.seek_to_delay_ms(6_000.0));
+        assert!(!ctrl.seek_to_delay_ms(-1.0));
+        assert_eq!(ctrl.seek_ms(4_000.0), None);
+    }
+
+    /// One output callback: position the read head, then consume a block.
+    #[test]
+    fn steady_callbacks_do_not_count_as_jumps() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 1_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        for _ in 0..20 {
+            ctrl.pre_read(10);
+            ctrl.ring.read(&mut [0.0; 10]);
+            ctrl.ring.write(&[0.0; 10]);
+        }
+        assert_eq!(ctrl.read_jumps().0, 0);
+        // Seeks move the read head on purpose.
+        ctrl.seek_ms(500.0);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        assert_eq!(ctrl.read_jumps().0, 0);
+    }
+
+    #[test]
+    fn debug_snapshot_shows_the_clamped_read_delay() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 1_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.seek_ms(995.0);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        let debug = ctrl.debug_snapshot();
+        assert_eq!(debug.write_position, 1_000);
+        assert_eq!(debug.target_delay_samples, 995);
+        assert_eq!(debug.callback_frames, 10);
+        // 10 + 995 is more than has been written.
+        assert_eq!(debug.max_delay_samples, 1_000);
+        assert_eq!(debug.clamped_delay_samples, 1_000);
+        assert_eq!(debug.read_position, 10);
+    }
+
+    #[test]
+    fn grid_snap_rounds_seeks() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 8_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.set_snap(Snap::Grid(1_000));
+        assert_eq!(ctrl.seek_ms(300.0), None);
+        assert_eq!(ctrl.target_delay_samples(), 1_000);
+        ctrl.seek_ms(300.0);
+        assert_eq!(ctrl.target_delay_samples(), 2_000);
+        ctrl.seek_ms(-300.0);
+        assert_eq!(ctrl.target_delay_samples(), 1_000);
+        assert!(ctrl.seek_to_delay_ms(3_600.0));
+        assert_eq!(ctrl.target_delay_samples(), 4_000);
+        // Toggled off, seeks are exact again.
+        assert_eq!(ctrl.toggle_snap(), Snap::Off);
+        ctrl.seek_ms(300.0);
+        assert_eq!(ctrl.target_delay_samples(), 4_300);
+    }
+
+    #[test]
+    fn bookmark_snap_lands_on_nearby_marks() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 8_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        // Bookmark the audio 5s behind live.
+        ctrl.seek_ms(5_000.0);
+        ctrl.pre_read(0);
+        ctrl.add_bookmark();
+        ctrl.jump_to_live();
+        ctrl.set_snap(Snap::Bookmarks);
+        ctrl.seek_ms(4_500.0);
+        assert_eq!(ctrl.target_delay_samples(), 5_000);
+        ctrl.seek_ms(-3_000.0);
+        assert_eq!(ctrl.target_delay_samples(), 2_000);
+    }
+
+    #[test]
+    fn toggling_snap_without_a_mode_uses_the_default_grid() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 1_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        assert_eq!(ctrl.snap(), Snap::Off);
+        assert_eq!(ctrl.toggle_snap(), Snap::Grid(snap::DEFAULT_GRID_MS));
+    }
+
+    #[test]
+    fn writer_running_ahead_is_a_jump() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 1_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        // A slow callback: 200ms written while nothing was read.
+        ctrl.ring.write(&[0.0; 200]);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        let (count, ms) = ctrl.read_jumps();
+        assert_eq!(count, 1);
+        assert!((ms - 190.0).abs() < 1.0, "{ms}");
+    }
+
+    #[test]
+    fn max_read_step_spreads_a_jump_out() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 1_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.set_max_read_step_ms(40);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        let before = ctrl.ring.read_position();
+        ctrl.ring.write(&[0.0; 200]);
+
+        ctrl.pre_read(10);
+        assert_eq!(ctrl.ring.read_position(), before + 40);
+        ctrl.ring.read(&mut [0.0; 10]);
+        // Keeps closing the gap without counting the same jump again.
+        for _ in 0..10 {
+            ctrl.pre_read(10);
+            ctrl.ring.read(&mut [0.0; 10]);
+        }
+        let live_rp = ctrl.ring.write_position() - 10;
+        ctrl.pre_read(10);
+        assert_eq!(ctrl.ring.read_position(), live_rp);
+        assert_eq!(ctrl.read_jumps().0, 1);
+    }
+
+    #[test]
+    fn read_steps_longer_than_the_jump_land_on_the_target() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 1_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        // 500-sample steps against 100 to 200-sample jumps.
+        ctrl.set_max_read_step_ms(500);
+        ctrl.ring.set_read_position(500);
+        assert_eq!(ctrl.settle_read_position(10, 500), 500);
+
+        assert_eq!(ctrl.settle_read_position(10, 700), 700);
+        assert!(!ctrl.catching_up.load(Ordering::Relaxed));
+        ctrl.ring.set_read_position(100);
+        assert_eq!(ctrl.settle_read_position(10, 0), 0);
+        assert!(!ctrl.catching_up.load(Ordering::Relaxed));
+        assert_eq!(ctrl.read_jumps().0, 2);
+    }
+
+    #[test]
+    fn capture_delay_discards_the_first_samples() {
+        let ring = Arc::new(AudioRingBuffer::new(1000));
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.set_capture_delay_ms(100);
+        assert_eq!(ctrl.capture_pending_ms(), Some(100.0));
+
+        let input: Vec<f32> = (0..150).map(|i| i as f32).collect();
+        for block in input.chunks(30).take(3) {
+            ctrl.capture_input(block);
+        }
+        // Nothing readable yet, and the output gets silence.
+        assert_eq!(ctrl.ring.write_position(), 0);
+        assert_eq!(ctrl.capture_pending_ms(), Some(10.0));
+        let mut out = [1.0_f32; 10];
+        assert_eq!(ctrl.ring.read(&mut out), ReadResult::Underrun);
+        assert_eq!(out, [0.0; 10]);
+
+        // The window ends inside this block: only its tail is kept.
+        for block in input.chunks(30).skip(3) {
+            ctrl.capture_input(block);
+        }
+        assert_eq!(ctrl.capture_pending_ms(), None);
+        assert_eq!(ctrl.ring.write_position(), 50);
+        let mut captured = [0.0_f32; 50];
+        assert!(ctrl.ring.read_at(0, &mut captured));
+        assert_eq!(captured.to_vec(), input[100..].to_vec());
+    }
+
+    #[test]
+    fn read_for_analysis_copies_the_latest_frames() {
+        let ring = Arc::new(AudioRingBuffer::new(100));
+        let ctrl = PlaybackController::new(ring, 2, 1000);
+        assert!(ctrl.read_for_analysis(4).is_empty());
+
+        let input: Vec<f32> = (0..130).map(|i| i as f32).collect();
+        // Play along live, so the writer is free to lap the buffer.
+        for block in input.chunks(10) {
+            ctrl.ring.write(block);
+            ctrl.ring.set_read_position(ctrl.ring.write_position());
+        }
+        // The window wraps around the end of the buffer.
+        let window = ctrl.read_for_analysis(20);
+        assert_eq!(&window[..], &input[90..]);
+
+        // Asking for more than the buffer holds returns what is left, minus
+        // the next write's worth kept from the writer, in whole frames.
+        let all = ctrl.read_for_analysis(1000);
+        assert_eq!(all.len(), 90);
+        assert_eq!(&all[..], &input[40..]);
+    }
+
+    #[test]
+    fn ramp_is_the_same_across_block_sizes() {
+        let ring = Arc::new(AudioRingBuffer::new(1000));
+        let ctrl = PlaybackController::new(ring, 2, 1000);
+        let total = RAMP_LENGTH * 2;
+        ctrl.jump_to_live();
+        let mut whole = vec![1.0; total + 10];
+        ctrl.apply_ramp(&mut whole);
+        assert!(ctrl.ramp_position.load(Ordering::Relaxed) >= total);
+        assert_eq!(whole[0], 0.0);
+        // Both channels of a frame get the same gain.
+        assert_eq!(whole[2], whole[3]);
+        assert!(whole[total - 1] < 1.0);
+        assert_eq!(&whole[total..], &[1.0; 10]);
+
+        // Empty and one-sample blocks, and a block crossing the ramp's end.
+        ctrl.jump_to_live();
+        let mut pieces = Vec::new();
+        for len in [0, 1, 1, 0, 99, total - 101 - 5, 15] {
+            let mut data = vec![1.0; len];
+            ctrl.apply_ramp(&mut data);
+            pieces.extend(data);
+        }
+        assert_eq!(pieces, whole);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "target delay is 5000, outside 0..=1000")]
+    fn check_mode_panics_on_impossible_values() {
+        let ring = Arc::new(AudioRingBuffer::new(1000));
+        let ctrl = PlaybackController::new(ring, 2, 1000).with_unsafe_check_mode();
+        ctrl.ring.write(&[0.0; 800]);
+        assert!(ctrl.seek_to_delay_ms(200.0));
+        ctrl.set_volume(1.5);
+        assert_eq!(ctrl.target_delay_samples(), 400);
+        assert_eq!(ctrl.volume(), 1.5);
+        // No method stores this; a racing write could.
+        ctrl.target_delay_samples.store(5_000, Ordering::Relaxed);
+        ctrl.target_delay_samples();
+    }
+
+    #[test]
+    fn degenerate_controllers_stay_finite_and_never_panic() {
+        for (capacity, channels, sample_rate) in [
+            (0, 0, 0),
+            (0, 2, 48_000),
+            (1000, 0, 1000),
+            (1000, 1, 0),
+            (1, 1, 1),
+        ] {
+            let ring = Arc::new(AudioRingBuffer::new(capacity));
+            let ctrl = PlaybackController::new(ring, channels, sample_rate);
+            let case = format!("capacity {capacity}, {channels}ch, {sample_rate}Hz");
+            for frames in [0, 1, 128] {
+                ctrl.capture_input(&vec![0.5; frames * 2]);
+                ctrl.pre_read(frames);
+                let mut out = vec![0.0; frames * 2];
+                ctrl.ring.read(&mut out);
+                ctrl.apply_solo(&mut out);
+                ctrl.apply_ramp(&mut out);
+                ctrl.apply_volume(&mut out);
+            }
+            ctrl.seek_ms(500.0);
+            ctrl.toggle_pause();
+            ctrl.toggle_pause();
+            ctrl.jump_to_live();
+            ctrl.publish_pending();
+            for value in [
+                ctrl.delay_ms(),
+                ctrl.callback_ms(),
+                ctrl.captured_seconds(),
+                ctrl.read_jumps().1,
+            ] {
+                assert!(value.is_finite() && value >= 0.0, "{case}: {value}");
             }
-            if let Some(&r) = frame.get(1).filter(|_| ch >= 2) {
-                peak_r = peak_r.max(r.abs());
+            let usage = ctrl.buffer_usage();
+            assert!((0.0..=1.0).contains(&usage), "{case}: {usage}");
+        }
+    }
+
+    #[test]
+    fn ramp_rises_evenly_across_callback_sizes() {
+        let channels = 2;
+        let total = RAMP_LENGTH * channels;
+        // Frame `f` of the ramp plays at `f / RAMP_LENGTH`, then full gain.
+        let reference: Vec<f32> = (0..total + 5_000)
+            .map(|i| (i / channels).min(RAMP_LENGTH) as f32 / RAMP_LENGTH as f32)
+            .collect();
+        for frames in [32, 256, 300, 4096] {
+            let ring = Arc::new(AudioRingBuffer::new(1000));
+            let ctrl = PlaybackController::new(ring, channels as u16, 1000);
+            ctrl.jump_to_live();
+            let mut gains = Vec::new();
+            for _ in 0..reference.len().div_ceil(frames * channels) {
+                let mut data = vec![1.0; frames * channels];
+                ctrl.apply_ramp(&mut data);
+                gains.extend(data);
             }
+            assert_eq!(&gains[..reference.len()], &reference[..], "{frames} frames");
+            assert!(
+                gains
+                    .windows(2)
+                    .all(|w| w[1] >= w[0] && w[1] - w[0] <= 1.0 / RAMP_LENGTH as f32 + 1e-6),
+                "{frames} frames"
+            );
+            assert!(ctrl.ramp_position.load(Ordering::Relaxed) >= total);
         }
+    }
+
+    #[test]
+    fn huge_buffers_finish_the_ramp_and_are_clamped() {
+        let ring = Arc::new(AudioRingBuffer::new(1000));
+        let ctrl = PlaybackController::new(ring, 2, 1000);
+        ctrl.jump_to_live();
+        let mut gains = vec![1.0; 65536];
+        ctrl.apply_ramp(&mut gains);
+        assert!(ctrl.ramp_position.load(Ordering::Relaxed) >= RAMP_LENGTH * 2);
+        assert!(gains[RAMP_LENGTH * 2..].iter().all(|&g| g == 1.0));
+
+        assert_eq!(ctrl.callback_limit(0), 0);
+        assert_eq!(ctrl.callback_limit(256), 256);
+        assert_eq!(ctrl.oversized_callbacks(), 0);
+        assert_eq!(ctrl.callback_limit(65536), 2 * MAX_CALLBACK_FRAMES);
+        assert_eq!(ctrl.oversized_callbacks(), 1);
+    }
+
+    #[test]
+    fn live_floor_holds_playback_behind_the_input() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        // The callback reads up to the newest sample.
+        assert_eq!(ctrl.ring.read_position(), 5_000);
+
+        ctrl.set_live_floor_samples(2_000);
+        assert_eq!(ctrl.live_floor_ms(), 2_000.0);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        assert_eq!(ctrl.ring.read_position(), 5_000 - 2_000);
+        // On purpose, so not a jump; seeks stack on top of it.
+        assert_eq!(ctrl.read_jumps().0, 0);
+        ctrl.seek_ms(1_000.0);
+        ctrl.pre_read(10);
+        ctrl.ring.read(&mut [0.0; 10]);
+        assert_eq!(ctrl.ring.read_position(), 5_000 - 3_000);
+        assert_eq!(ctrl.debug_snapshot().clamped_delay_samples, 3_010);
+        assert_eq!(ctrl.delay_ms(), 1_000.0);
+
+        // Never more than the buffer holds.
+        ctrl.set_live_floor_samples(usize::MAX);
+        assert_eq!(ctrl.live_floor_ms(), 10_000.0);
+    }
+
+    #[test]
+    fn output_underruns_count_short_reads_after_the_first_input() {
+        let ring = Arc::new(AudioRingBuffer::new(1000));
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        // Nothing captured yet: silence, but not an underrun.
+        ctrl.read_output(&mut [0.0; 10]);
+        assert_eq!(ctrl.output_underruns(), 0);
+
+        ctrl.ring.write(&[0.5; 15]);
+        ctrl.read_output(&mut [0.0; 10]);
+        assert_eq!(ctrl.output_underruns(), 0);
+        let mut out = [1.0; 10];
+        ctrl.read_output(&mut out);
+        assert_eq!(out, [0.0; 10]);
+        assert_eq!(ctrl.output_underruns(), 1);
+        assert_eq!(ctrl.debug_snapshot().output_underruns, 1);
+
+        ctrl.reset_stats();
+        assert_eq!(ctrl.output_underruns(), 0);
+    }
+
+    #[test]
+    fn empty_input_buffers_are_ignored() {
+        let ring = Arc::new(AudioRingBuffer::new(1000));
+        let ctrl = PlaybackController::new(ring, 2, 1000);
+        ctrl.set_input_gain_db(6.0);
+        ctrl.apply_input_gain(&mut []);
+        // The gain change still ramps over the next real buffer.
+        let mut data = [1.0; 4];
+        ctrl.apply_input_gain(&mut data);
+        assert!(data[0] < data[2]);
+        ctrl.capture_input(&[]);
+        assert_eq!(ctrl.ring.write_position(), 0);
+    }
+
+    #[test]
+    fn input_gain_ramps_to_the_target() {
+        let ring = Arc::new(AudioRingBuffer::new(100));
+        let ctrl = PlaybackController::new(ring, 2, 1000);
+        let mut data = [1.0; 8];
+        ctrl.apply_input_gain(&mut data);
+        assert_eq!(data, [1.0; 8]);
 
-        // Exponential decay for smooth meter movement
-        let decay = 0.85;
-        let prev_l = self.peak_left.load(Ordering::Relaxed) as f32 / 1000.0;
-        let prev_r = self.peak_right.load(Ordering::Relaxed) as f32 / 1000.0;
-        let new_l = peak_l.max(prev_l * decay);
-        let new_r = peak_r.max(prev_r * decay);
+        ctrl.set_input_gain_db(-6.0);
+        assert_eq!(ctrl.input_gain_db(), -6.0);
+        let target = 10f32.powf(-6.0 / 20.0);
+        ctrl.apply_input_gain(&mut data);
+        // Both channels of a frame get the same gain, easing down to the target.
+        assert_eq!(data[0], data[1]);
+        assert!(data[0] < 1.0 && data[0] > data[2]);
+        assert!((data[6] - target).abs() < 1e-6);
+
+        let mut data = [1.0; 4];
+        ctrl.apply_input_gain(&mut data);
+        assert!(data.iter().all(|s| (s - target).abs() < 1e-6));
+
+        ctrl.adjust_input_gain_db(100.0);
+        assert_eq!(ctrl.input_gain_db(), INPUT_GAIN_MAX_DB);
+    }
+
+    #[test]
+    fn solo_copies_one_channel_to_both_ears() {
+        let ring = Arc::new(AudioRingBuffer::new(100));
+        let ctrl = PlaybackController::new(ring, 2, 1000);
+        let mut data = [1.0, 2.0, 3.0, 4.0];
+        ctrl.apply_solo(&mut data);
+        assert_eq!(data, [1.0, 2.0, 3.0, 4.0]);
+
+        ctrl.set_solo(Some(2)).unwrap();
+        assert_eq!(ctrl.solo(), Some(2));
+        assert!(ctrl.ramp_position.load(Ordering::Relaxed) < RAMP_LENGTH * ctrl.channels as usize);
+        ctrl.apply_solo(&mut data);
+        assert_eq!(data, [2.0, 2.0, 4.0, 4.0]);
+
+        ctrl.set_solo(None).unwrap();
+        assert_eq!(ctrl.solo(), None);
+        assert!(ctrl.set_solo(Some(3)).is_err());
+        assert!(ctrl.set_solo(Some(0)).is_err());
+    }
+
+    #[test]
+    fn solo_maps_eight_channel_frames() {
+        let ring = Arc::new(AudioRingBuffer::new(100));
+        let ctrl = PlaybackController::new(ring, 8, 1000);
+        ctrl.set_solo(Some(3)).unwrap();
+        let mut data: Vec<f32> = (0..16).map(|i| i as f32).collect();
+        ctrl.apply_solo(&mut data);
+        assert_eq!(&data[..8], &[2.0; 8]);
+        assert_eq!(&data[8..], &[10.0; 8]);
+    }
+
+    #[test]
+    fn set_state_goes_through_pause_and_live() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.set_state(PlaybackState::Paused).unwrap();
+        assert_eq!(ctrl.state(), PlaybackState::Paused);
+        ctrl.ring.write(&[0.0; 500]);
+        // Resuming keeps the delay accumulated while paused, with a ramp.
+        ctrl.set_state(PlaybackState::TimeShifted).unwrap();
+        assert_eq!(ctrl.state(), PlaybackState::TimeShifted);
+        assert!(ctrl.ramp_position.load(Ordering::Relaxed) < RAMP_LENGTH * ctrl.channels as usize);
+        ctrl.set_state(PlaybackState::Live).unwrap();
+        assert_eq!(ctrl.state(), PlaybackState::Live);
+        ctrl.set_state(PlaybackState::Live).unwrap();
+    }
+
+    #[test]
+    fn set_state_rejects_live_to_time_shifted() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        assert!(ctrl.set_state(PlaybackState::TimeShifted).is_err());
+        assert_eq!(ctrl.state(), PlaybackState::Live);
+    }
+
+    #[test]
+    fn buffer_thresholds_fire_once_when_crossed() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 8_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        let events = ctrl.events.subscribe();
+        ctrl.publish_pending();
+        ctrl.ring.write(&vec![0.0; 1000]);
+        ctrl.publish_pending();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [ShifterEvent::BufferThreshold { level: 90 }]
+        );
+        ctrl.ring.write(&vec![0.0; 1000]);
+        ctrl.publish_pending();
+        ctrl.publish_pending();
+        ctrl.ring.read(&mut vec![0.0; 5000]);
+        ctrl.ring.write(&vec![0.0; 1000]);
+        ctrl.publish_pending();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [ShifterEvent::BufferThreshold { level: 100 }]
+        );
+    }
+
+    #[test]
+    fn scripted_commands_publish_transport_and_volume_events() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        let events = ctrl.events.subscribe();
+        ctrl.seek_ms(2_000.0);
+        ctrl.seek_ms(-5_000.0); // clamped at live
+        ctrl.seek_ms(-1_000.0); // already live: nothing moves
+        ctrl.set_state(PlaybackState::Paused).unwrap();
+        ctrl.set_state(PlaybackState::Live).unwrap();
+        ctrl.set_volume(0.5);
+        ctrl.set_volume(0.5);
+        ctrl.toggle_mute();
+        ctrl.add_bookmark();
+
+        use PlaybackState::*;
+        let (live, back) = (5_000, 3_000);
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                ShifterEvent::StateChanged {
+                    from: Live,
+                    to: TimeShifted,
+                    position: live,
+                },
+                ShifterEvent::Seeked {
+                    from_ms: 0.0,
+                    to_ms: 2_000.0,
+                    position: live,
+                    to_position: back,
+                },
+                ShifterEvent::StateChanged {
+                    from: TimeShifted,
+                    to: Live,
+                    position: live,
+                },
+                ShifterEvent::Seeked {
+                    from_ms: 2_000.0,
+                    to_ms: 0.0,
+                    position: live,
+                    to_position: live,
+                },
+                ShifterEvent::StateChanged {
+                    from: Live,
+                    to: Paused,
+                    position: live,
+                },
+                ShifterEvent::StateChanged {
+                    from: Paused,
+                    to: Live,
+                    position: live,
+                },
+                ShifterEvent::VolumeChanged {
+                    volume: 0.5,
+                    muted: false,
+                },
+                ShifterEvent::VolumeChanged {
+                    volume: 0.0,
+                    muted: true,
+                },
+                ShifterEvent::Bookmarked { position: live },
+            ]
+        );
+    }
+
+    #[test]
+    fn callback_flags_are_published_once() {
+        let ring = Arc::new(AudioRingBuffer::new(10_000));
+        ring.write(&vec![0.0; 5_000]);
+        let ctrl = PlaybackController::new(ring, 1, 1000);
+        ctrl.set_auto_bookmark_interval_s(2);
+        let events = ctrl.events.subscribe();
+        ctrl.ring.write(&[0.0; 2]);
+        ctrl.record_auto_bookmark();
+        ctrl.callback_limit(MAX_CALLBACK_FRAMES * 2 + 2);
+        ctrl.publish_pending();
+        ctrl.publish_pending();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                ShifterEvent::XRun {
+                    kind: XRunKind::OversizedCallback
+                },
+                ShifterEvent::Bookmarked { position: 4_000 },
+            ]
+        );
+    }
 
-        self.peak_left
-            .store((new_l * 1000.0) as usize, Ordering::Relaxed);
-        self.peak_right
-            .store((new_r * 1000.0) as usize, Ordering::Relaxed);
+    #[test]
+    fn volume_skips_the_buffer_only_at_unity() {
+        let ctrl = PlaybackController::new(Arc::new(AudioRingBuffer::new(100)), 1, 1000);
+        let apply = |ctrl: &PlaybackController| {
+            let mut data = [0.5, -0.25];
+            ctrl.apply_volume(&mut data);
+            data
+        };
+        assert_eq!(apply(&ctrl), [0.5, -0.25]);
+        ctrl.adjust_volume(-500);
+        assert_eq!(apply(&ctrl), [0.25, -0.125]);
+        ctrl.adjust_volume(500);
+        assert_eq!(apply(&ctrl), [0.5, -0.25]);
+        ctrl.toggle_mute();
+        assert_eq!(apply(&ctrl), [0.0, -0.0]);
+        ctrl.toggle_mute();
+        assert_eq!(apply(&ctrl), [0.5, -0.25]);
     }
 }
@@ -0,0 +1,276 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::playback::controller::PlaybackController;
+
+/// Envelope sampling period.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the (comparatively expensive) correlation is recomputed.
+const ESTIMATE_INTERVAL: Duration = Duration::from_secs(1);
+/// Envelope history kept per side, in samples (~100s at 50ms).
+const HISTORY_LEN: usize = 2000;
+/// Correlation window, in samples (~10s at 50ms).
+const WINDOW_LEN: usize = 200;
+/// Below this standard deviation an envelope is too flat to correlate.
+const MIN_STD_DEV: f32 = 0.005;
+/// Minimum normalized correlation for an estimate to be shown.
+pub const MIN_CONFIDENCE: f32 = 0.6;
+
+/// Result of correlating the output envelope against the input envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LagEstimate {
+    /// Lag in envelope samples (output trails input by this many samples).
+    pub lag: usize,
+    /// Normalized cross-correlation at `lag` (-1.0 - 1.0).
+    pub confidence: f32,
+}
+
+/// Estimates how far `output` trails `input`.
+///
+/// Both slices hold envelope values sampled at the same instants, oldest first.
+/// The newest `window` output values are compared against every input segment
+/// shifted back by `0..=max_lag`, and the lag with the highest normalized
+/// cross-correlation wins. Returns `None` if there isn't enough history or
+/// either side is too flat to correlate.
+pub fn estimate_lag(
+    input: &[f32],
+    output: &[f32],
+    window: usize,
+    max_lag: usize,
+) -> Option<LagEstimate> {
+    let n = input.len().min(output.len());
+    if window < 2 || n < window {
+        return None;
+    }
+    let max_lag = max_lag.min(n - window);
+    let out = &output[output.len() - window..];
+    let (out_mean, out_std) = mean_std(out);
+    if out_std < MIN_STD_DEV {
+        return None;
+    }
+
+    let end = input.len();
+    let mut best: Option<LagEstimate> = None;
+    for lag in 0..=max_lag {
+        let seg = &input[end - window - lag..end - lag];
+        let (in_mean, in_std) = mean_std(seg);
+        if in_std < MIN_STD_DEV {
+            continue;
+        }
+        let cov = seg
+            .iter()
+            .zip(out)
+            .map(|(&a, &b)| (a - in_mean) * (b - out_mean))
+            .sum::<f32>()
+            / window as f32;
+        let corr = cov / (in_std * out_std);
+        if best.is_none_or(|b| corr > b.confidence) {
+            best = Some(LagEstimate {
+                lag,
+                confidence: corr,
+            });
+        }
+    }
+    best
+}
+
+fn mean_std(data: &[f32]) -> (f32, f32) {
+    let len = data.len() as f32;
+    let mean = data.iter().sum::<f32>() / len;
+    let var = data.iter().map(|&x| (x - mean) * (x - mean)).sum::<f32>() / len;
+    (mean, var.sqrt())
+}
+
+/// Samples input/output envelopes on the TUI thread and periodically
+/// estimates the real end-to-end delay from their cross-correlation.
+pub struct DelayProbe {
+    input: VecDeque<f32>,
+    output: VecDeque<f32>,
+    /// When each envelope sample was taken (frames are not exactly 50ms apart).
+    times: VecDeque<Instant>,
+    last_sample: Instant,
+    last_estimate: Instant,
+    /// Measured delay in ms, if the last estimate was confident enough.
+    measured_ms: Option<f64>,
+    confidence: f32,
+}
+
+impl DelayProbe {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            input: VecDeque::with_capacity(HISTORY_LEN),
+            output: VecDeque::with_capacity(HISTORY_LEN),
+            times: VecDeque::with_capacity(HISTORY_LEN),
+            last_sample: now,
+            last_estimate: now,
+            measured_ms: None,
+            confidence: 0.0,
+        }
+    }
+
+    /// Called every TUI frame. Cheap unless a sample or estimate is due.
+    pub fn tick(&mut self, controller: &PlaybackController) {
+        let now = Instant::now();
+        if now.duration_since(self.last_sample) < SAMPLE_INTERVAL {
+            return;
+        }
+        self.last_sample = now;
+
+        let (input, output) = controller.take_envelopes();
+        if self.input.len() == HISTORY_LEN {
+            self.input.pop_front();
+            self.output.pop_front();
+            self.times.pop_front();
+        }
+        self.input.push_back(input);
+        self.output.push_back(output);
+        self.times.push_back(now);
+
+        if now.duration_since(self.last_estimate) >= ESTIMATE_INTERVAL {
+            self.last_estimate = now;
+            self.estimate();
+        }
+    }
+
+    fn estimate(&mut self) {
+        // Convert the lag to time using the average sample spacing.
+        let spacing_ms = match (self.times.front(), self.times.back()) {
+            (Some(&first), Some(&last)) if self.times.len() > 1 => {
+                last.duration_since(first).as_secs_f64() * 1000.0 / (self.times.len() - 1) as f64
+            }
+            _ => return,
+        };
+
+        let input = self.input.make_contiguous();
+        let output = self.output.make_contiguous();
+        match estimate_lag(input, output, WINDOW_LEN, HISTORY_LEN) {
+            Some(est) if est.confidence >= MIN_CONFIDENCE => {
+                self.measured_ms = Some(est.lag as f64 * spacing_ms);
+                self.confidence = est.confidence;
+            }
+            Some(est) => {
+                self.measured_ms = None;
+                self.confidence = est.confidence;
+            }
+            None => {
+                self.measured_ms = None;
+                self.confidence = 0.0;
+            }
+        }
+    }
+
+    pub fn measured_ms(&self) -> Option<f64> {
+        self.measured_ms
+    }
+
+    pub fn confidence(&self) -> f32 {
+        self.confidence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_known_lag() {
+        // A deterministic pseudo-random envelope in 0.0..1.0, and the same
+        // envelope 37 samples late.
+        let input: Vec<f32> = (0..600)
+            .scan(1_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        let output = [vec![0.0; 37], input[..input.len() - 37].to_vec()].concat();
+        let est = estimate_lag(&input, &output, 200, 300).unwrap();
+        assert_eq!(est.lag, 37);
+        assert!(est.confidence > 0.99);
+    }
+
+    #[test]
+    fn finds_zero_lag() {
+        let input: Vec<f32> = (0..400)
+            .scan(2_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        let est = estimate_lag(&input, &input, 200, 100).unwrap();
+        assert_eq!(est.lag, 0);
+    }
+
+    #[test]
+    fn finds_lag_with_noise_and_gain() {
+        let input: Vec<f32> = (0..800)
+            .scan(3_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        let noise: Vec<f32> = (0..800)
+            .scan(99_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        let output: Vec<f32> = [vec![0.0; 240], input[..input.len() - 240].to_vec()]
+            .concat()
+            .iter()
+            .zip(&noise)
+            .map(|(&s, &n)| s * 0.5 + (n - 0.5) * 0.1)
+            .collect();
+        let est = estimate_lag(&input, &output, 200, 400).unwrap();
+        assert_eq!(est.lag, 240);
+        assert!(est.confidence > MIN_CONFIDENCE);
+    }
+
+    #[test]
+    fn flat_output_is_skipped() {
+        let input: Vec<f32> = (0..400)
+            .scan(4_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        let output = vec![0.3_f32; 400];
+        assert_eq!(estimate_lag(&input, &output, 200, 100), None);
+    }
+
+    #[test]
+    fn flat_input_yields_no_estimate() {
+        let input = vec![0.0_f32; 400];
+        let output: Vec<f32> = (0..400)
+            .scan(5_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        assert_eq!(estimate_lag(&input, &output, 200, 100), None);
+    }
+
+    #[test]
+    fn short_history_yields_no_estimate() {
+        let input: Vec<f32> = (0..100)
+            .scan(6_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        assert_eq!(estimate_lag(&input, &input, 200, 100), None);
+    }
+
+    #[test]
+    fn max_lag_is_bounded_by_history() {
+        let input: Vec<f32> = (0..300)
+            .scan(7_u32, |state, _| {
+                *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                Some((*state >> 8) as f32 / (1 << 24) as f32)
+            })
+            .collect();
+        let output = [vec![0.0; 50], input[..input.len() - 50].to_vec()].concat();
+        let est = estimate_lag(&input, &output, 200, 10_000).unwrap();
+        assert_eq!(est.lag, 50);
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::playback::state::PlaybackState;
+
+/// Audio glitch kinds reported by the callbacks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XRunKind {
+    /// The read position jumped after a slow output callback.
+    ReadJump { ms: f64 },
+    /// A callback larger than `MAX_CALLBACK_FRAMES` was clamped.
+    OversizedCallback,
+}
+
+/// Something that changed in the controller. Positions are stream positions
+/// in interleaved samples, as the ring counts them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShifterEvent {
+    /// `position` is what was playing when it changed.
+    StateChanged {
+        from: PlaybackState,
+        to: PlaybackState,
+        position: usize,
+    },
+    /// The audio jumped: a seek or a jump to live. Delays are behind live;
+    /// `to_position` is where the new target plays from.
+    Seeked {
+        from_ms: f64,
+        to_ms: f64,
+        position: usize,
+        to_position: usize,
+    },
+    VolumeChanged {
+        volume: f32,
+        muted: bool,
+    },
+    /// A bookmark (manual or automatic) was added at `position`.
+    Bookmarked {
+        position: usize,
+    },
+    XRun {
+        kind: XRunKind,
+    },
+    /// The buffer filled past `level` percent for the first time.
+    BufferThreshold {
+        level: u8,
+    },
+    /// Playback moved to another output device.
+    DeviceChanged {
+        output: String,
+    },
+}
+
+/// Fans `ShifterEvent`s out to every subscriber. Published from the TUI,
+/// script, notification and power threads, never from the audio callbacks:
+/// what they flag is translated by `PlaybackController::publish_pending`.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<ShifterEvent>>>,
+}
+
+impl EventBus {
+    /// A receiver for every event published from now on.
+    pub fn subscribe(&self) -> Receiver<ShifterEvent> {
+        let (tx, rx) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+
+    /// Sends `event` to every subscriber, dropping the ones that hung up.
+    pub fn publish(&self, event: ShifterEvent) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_subscriber_gets_events_published_after_it_joined() {
+        let bus = EventBus::default();
+        let early = bus.subscribe();
+        bus.publish(ShifterEvent::BufferThreshold { level: 90 });
+        let late = bus.subscribe();
+        bus.publish(ShifterEvent::BufferThreshold { level: 100 });
+
+        let received =
+            |rx: &Receiver<ShifterEvent>| -> Vec<ShifterEvent> { rx.try_iter().collect() };
+        assert_eq!(
+            received(&early),
+            [
+                ShifterEvent::BufferThreshold { level: 90 },
+                ShifterEvent::BufferThreshold { level: 100 },
+            ]
+        );
+        assert_eq!(
+            received(&late),
+            [ShifterEvent::BufferThreshold { level: 100 }]
+        );
+
+        drop(early);
+        bus.publish(ShifterEvent::BufferThreshold { level: 50 });
+        assert_eq!(bus.subscribers.lock().unwrap().len(), 1);
+    }
+}
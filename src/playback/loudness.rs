@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Length of one short-term RMS window.
+pub const WINDOW_MS: usize = 400;
+
+/// Lower edge of the histogram; quieter windows (silence, pauses) are not counted.
+pub const FLOOR_DB: f32 = -72.0;
+
+/// Histogram bin width.
+pub const BIN_DB: f32 = 1.0;
+
+/// Number of bins, `FLOOR_DB` .. 0 dBFS.
+pub const BINS: usize = 72;
+
+/// Default level `autovol` places the 95th percentile at.
+pub const AUTOVOL_TARGET_DB: f32 = -14.0;
+
+/// Percentile of the level distribution `autovol` aligns to the target.
+pub const AUTOVOL_PERCENTILE: f32 = 0.95;
+
+/// Windows needed before `autovol` trusts the distribution (10 seconds).
+pub const AUTOVOL_MIN_WINDOWS: usize = 25;
+
+/// Coarse histogram of short-term RMS levels of the captured audio over the
+/// whole session. Written by the analysis thread, read by the TUI.
+pub struct LevelHistogram {
+    window_samples: usize,
+    counts: Box<[AtomicUsize]>,
+    /// Sum of squares of the window in progress, as `f64` bits. Analysis thread only.
+    window_sum: AtomicU64,
+    /// Samples in the window in progress. Analysis thread only.
+    window_len: AtomicUsize,
+}
+
+impl LevelHistogram {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        Self {
+            window_samples: (sample_rate as usize * WINDOW_MS / 1000 * channels as usize).max(1),
+            counts: (0..BINS).map(|_| AtomicUsize::new(0)).collect(),
+            window_sum: AtomicU64::new(0),
+            window_len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds captured samples; every completed window is counted in its bin.
+    pub fn record(&self, samples: &[f32]) {
+        let mut sum = f64::from_bits(self.window_sum.load(Ordering::Relaxed));
+        let mut len = self.window_len.load(Ordering::Relaxed);
+        for &s in samples {
+            sum += s as f64 * s as f64;
+            len += 1;
+            if len == self.window_samples {
+                let rms = (sum / len as f64).sqrt() as f32;
+                if let Some(bin) = bin_of(20.0 * rms.log10()) {
+                    self.counts[bin].fetch_add(1, Ordering::Relaxed);
+                }
+                sum = 0.0;
+                len = 0;
+            }
+        }
+        self.window_sum.store(sum.to_bits(), Ordering::Relaxed);
+        self.window_len.store(len, Ordering::Relaxed);
+    }
+
+    /// Window counts per bin, quietest first.
+    pub fn counts(&self) -> Vec<usize> {
+        self.counts
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed))
+            .collect()
+    }
+}
+
+/// Bin for a level, or `None` below the floor (or for silence, -inf / NaN).
+fn bin_of(db: f32) -> Option<usize> {
+    if db.is_nan() || db < FLOOR_DB {
+        return None;
+    }
+    Some((((db - FLOOR_DB) / BIN_DB) as usize).min(BINS - 1))
+}
+
+/// Level below which fraction `p` of the counted windows lie (upper edge of
+/// the bin where the cumulative count reaches it). `None` without data.
+pub fn percentile_db(counts: &[usize], p: f32) -> Option<f32> {
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let rank = ((total as f32 * p).ceil() as usize).clamp(1, total);
+    let mut seen = 0;
+    for (bin, &count) in counts.iter().enumerate() {
+        seen += count;
+        if seen >= rank {
+            return Some(FLOOR_DB + (bin + 1) as f32 * BIN_DB);
+        }
+    }
+    None
+}
+
+/// Volume (fraction, capped at 150%) that moves `level_db` to `target_db`.
+pub fn gain_for(level_db: f32, target_db: f32) -> f32 {
+    10f32.powf((target_db - level_db) / 20.0).min(1.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_land_in_their_bins() {
+        // 1 kHz mono, 400-sample windows; a constant window's RMS is its amplitude.
+        let histogram = LevelHistogram::new(1, 1000);
+        for db in [-20.5, -20.5, -6.5, -100.0_f32] {
+            histogram.record(&[10f32.powf(db / 20.0); 400]);
+        }
+        let counts = histogram.counts();
+        assert_eq!(counts.iter().sum::<usize>(), 3);
+        assert_eq!(counts[bin_of(-20.5).unwrap()], 2);
+        assert_eq!(counts[bin_of(-6.5).unwrap()], 1);
+    }
+
+    #[test]
+    fn partial_windows_carry_over() {
+        let histogram = LevelHistogram::new(1, 1000);
+        histogram.record(&[0.5; 300]);
+        assert_eq!(histogram.counts().iter().sum::<usize>(), 0);
+        histogram.record(&[0.5; 100]);
+        assert_eq!(histogram.counts()[bin_of(-6.02).unwrap()], 1);
+    }
+
+    #[test]
+    fn percentile_of_a_synthetic_program() {
+        // 90 quiet windows around -30 dB, 10 loud ones at -10.5 dB.
+        let histogram = LevelHistogram::new(1, 1000);
+        for db in std::iter::repeat_n(-30.5_f32, 90).chain([-10.5; 10]) {
+            histogram.record(&[10f32.powf(db / 20.0); 400]);
+        }
+        let counts = histogram.counts();
+        assert_eq!(percentile_db(&counts, 0.5), Some(-30.0));
+        assert_eq!(percentile_db(&counts, 0.95), Some(-10.0));
+        assert_eq!(percentile_db(&counts, 0.9), Some(-30.0));
+        assert_eq!(percentile_db(&[0; BINS], 0.95), None);
+    }
+
+    #[test]
+    fn gain_moves_the_level_to_the_target() {
+        assert!((gain_for(-14.0, -14.0) - 1.0).abs() < 1e-6);
+        assert!((gain_for(-8.0, -14.0) - 0.501).abs() < 0.001);
+        // Quiet programs are capped at 150%.
+        assert_eq!(gain_for(-40.0, -14.0), 1.5);
+    }
+}
@@ -1,2 +1,9 @@
+pub mod analysis;
+pub mod clock_drift;
 pub mod controller;
+pub mod delay_probe;
+pub mod events;
+pub mod loudness;
+pub mod silence;
+pub mod snap;
 pub mod state;
@@ -0,0 +1,312 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Length of one peak history segment.
+pub const SEGMENT_MS: usize = 10;
+
+/// Segments quieter than this (-40 dBFS) count as silence.
+pub const SILENCE_THRESHOLD: f32 = 0.01;
+
+/// Silence shorter than this (in segments, 250ms) is a pause inside speech,
+/// not a boundary between segments.
+pub const MIN_GAP_SEGMENTS: usize = 25;
+
+/// Presses within this many segments (1s) after a segment start jump to the
+/// one before it, so repeated presses keep going back.
+pub const REPEAT_GRACE_SEGMENTS: usize = 100;
+
+/// Peak level of the captured audio per `SEGMENT_MS` segment, covering the
+/// ring buffer. Written by the analysis thread, scanned by the TUI.
+///
+/// Segment `k` covers stream positions `k * segment_samples ..`; its peak is
+/// stored at `k % len` as value * 1000. Coarser levels of a peak pyramid are
+/// kept up to date as segments complete: bucket `b` of level `l` holds the
+/// peak of segments `b << l .. (b + 1) << l`, so the peak of any range takes
+/// a handful of reads whatever its length (the zoomed-out timeline).
+pub struct PeakHistory {
+    segment_samples: usize,
+    /// Level 0 holds one peak per segment, level `l` one per `2^l` segments.
+    levels: Box<[Box<[AtomicUsize]>]>,
+    /// Segments completed (absolute count).
+    completed: AtomicUsize,
+    /// Peak of the segment in progress. Analysis thread only.
+    current_peak: AtomicUsize,
+}
+
+impl PeakHistory {
+    /// History for a ring of `capacity` samples.
+    pub fn new(capacity: usize, channels: u16, sample_rate: u32) -> Self {
+        let segment_samples = (sample_rate as usize * SEGMENT_MS / 1000 * channels as usize).max(1);
+        let len = capacity / segment_samples + 2;
+        // Two spare buckets per coarser level, like level 0, so a whole
+        // buffer's worth stays readable while the newest bucket fills.
+        let levels = (0..)
+            .take_while(|&level| 1usize << level <= len)
+            .map(|level| {
+                let buckets = if level == 0 { len } else { (len >> level) + 2 };
+                (0..buckets).map(|_| AtomicUsize::new(0)).collect()
+            })
+            .collect();
+        Self {
+            segment_samples,
+            levels,
+            completed: AtomicUsize::new(0),
+            current_peak: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn segment_samples(&self) -> usize {
+        self.segment_samples
+    }
+
+    /// Segments completed so far (absolute count): the live edge.
+    pub fn completed(&self) -> usize {
+        self.completed.load(Ordering::Acquire)
+    }
+
+    /// Records captured samples starting at stream `position`. Called by the
+    /// analysis thread with consecutive input blocks.
+    pub fn record(&self, position: usize, samples: &[f32]) {
+        let mut completed = self.completed.load(Ordering::Relaxed);
+        let mut peak = self.current_peak.load(Ordering::Relaxed);
+        for (i, s) in samples.iter().enumerate() {
+            let segment = (position + i) / self.segment_samples;
+            while segment > completed {
+                self.push(completed, peak);
+                completed += 1;
+                peak = 0;
+            }
+            peak = peak.max((s.abs() * 1000.0) as usize);
+        }
+        self.current_peak.store(peak, Ordering::Relaxed);
+        self.completed.store(completed, Ordering::Release);
+    }
+
+    /// Stores the peak of segment `k` in every level. The first segment of a
+    /// bucket overwrites what the slot held before; the others raise it.
+    fn push(&self, k: usize, peak: usize) {
+        for (level, ring) in self.levels.iter().enumerate() {
+            let slot = &ring[(k >> level) % ring.len()];
+            if k.is_multiple_of(1 << level) {
+                slot.store(peak, Ordering::Relaxed);
+            } else {
+                slot.fetch_max(peak, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// First segment still retained.
+    fn oldest(&self, completed: usize) -> usize {
+        completed.saturating_sub(self.levels[0].len() - 1)
+    }
+
+    /// Peaks of completed segments `from..to` (absolute indices), oldest first.
+    /// Segments no longer retained read as silence.
+    pub fn peaks(&self, from: usize, to: usize) -> Vec<f32> {
+        let completed = self.completed.load(Ordering::Acquire);
+        let oldest = self.oldest(completed);
+        let ring = &self.levels[0];
+        (from..to.min(completed))
+            .map(|k| {
+                if k < oldest {
+                    0.0
+                } else {
+                    ring[k % ring.len()].load(Ordering::Relaxed) as f32 / 1000.0
+                }
+            })
+            .collect()
+    }
+
+    /// Peak of completed segments `from..to` (absolute indices), from the
+    /// largest pyramid buckets that fit the range. Segments no longer
+    /// retained read as silence.
+    pub fn max_peak(&self, from: usize, to: usize) -> f32 {
+        let completed = self.completed.load(Ordering::Acquire);
+        let to = to.min(completed);
+        let mut k = from.max(self.oldest(completed));
+        let mut peak = 0;
+        while k < to {
+            // Grow the bucket while it stays aligned and inside the range.
+            let mut level = 0;
+            while level + 1 < self.levels.len()
+                && k.is_multiple_of(2 << level)
+                && k + (2 << level) <= to
+            {
+                level += 1;
+            }
+            let ring = &self.levels[level];
+            peak = peak.max(ring[(k >> level) % ring.len()].load(Ordering::Relaxed));
+            k += 1 << level;
+        }
+        peak as f32 / 1000.0
+    }
+}
+
+/// Finds where the non-silent stretch around `current` starts, for re-listening.
+///
+/// `peaks` are consecutive segment peaks with `current` the playing segment.
+/// Inside silence, goes to the start of the stretch before it. Within
+/// `REPEAT_GRACE_SEGMENTS` of a start, goes one stretch further back. Returns 0
+/// (the oldest segment) if no earlier silence-to-sound transition exists.
+pub fn previous_segment_start(peaks: &[f32], current: usize) -> usize {
+    if peaks.is_empty() {
+        return 0;
+    }
+    let silent = silence_runs(peaks);
+    let mut i = current.min(peaks.len() - 1);
+
+    // At (or just after) a start: skip past it to search the stretch before.
+    let start = sound_start(&silent, i);
+    if i - start < REPEAT_GRACE_SEGMENTS && !silent[i] {
+        i = start.saturating_sub(1);
+    }
+    // In silence: back to the end of the previous sound.
+    while i > 0 && silent[i] {
+        i -= 1;
+    }
+    sound_start(&silent, i)
+}
+
+/// First segment of the sound stretch containing `i` (walks back to silence).
+fn sound_start(silent: &[bool], mut i: usize) -> usize {
+    while i > 0 && !silent[i - 1] {
+        i -= 1;
+    }
+    i
+}
+
+/// Per-segment silence flags, ignoring quiet runs shorter than `MIN_GAP_SEGMENTS`.
+fn silence_runs(peaks: &[f32]) -> Vec<bool> {
+    let mut silent: Vec<bool> = peaks.iter().map(|&p| p < SILENCE_THRESHOLD).collect();
+    let mut i = 0;
+    while i < silent.len() {
+        if !silent[i] {
+            i += 1;
+            continue;
+        }
+        let run_start = i;
+        while i < silent.len() && silent[i] {
+            i += 1;
+        }
+        // A short pause between sounds isn't a boundary. (Runs touching the
+        // oldest end are kept: nothing earlier is known.)
+        if i - run_start < MIN_GAP_SEGMENTS && run_start > 0 && i < silent.len() {
+            silent[run_start..i].fill(false);
+        }
+    }
+    silent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inside_sound_jumps_to_its_start() {
+        // silence 100, sound 300 (starts at 100), playing at 350
+        let p = [vec![0.0; 100], vec![0.5; 300]].concat();
+        assert_eq!(previous_segment_start(&p, 350), 100);
+    }
+
+    #[test]
+    fn repeated_press_goes_to_the_previous_start() {
+        // sound 200, silence 100, sound 300 (starts at 300)
+        let p = [vec![0.5; 200], vec![0.0; 100], vec![0.5; 300]].concat();
+        assert_eq!(previous_segment_start(&p, 500), 300);
+        // Just after that start: go back another stretch, to 0.
+        assert_eq!(previous_segment_start(&p, 310), 0);
+    }
+
+    #[test]
+    fn inside_silence_jumps_to_previous_stretch_start() {
+        // silence 50, sound 200 (starts at 50), silence 200, playing in silence at 400
+        let p = [vec![0.0; 50], vec![0.5; 200], vec![0.0; 200]].concat();
+        assert_eq!(previous_segment_start(&p, 400), 50);
+    }
+
+    #[test]
+    fn short_pauses_are_not_boundaries() {
+        // sound with a 10-segment dip inside: one stretch starting at 100
+        let p = [
+            vec![0.0; 100],
+            vec![0.5; 200],
+            vec![0.0; 10],
+            vec![0.5; 200],
+        ]
+        .concat();
+        assert_eq!(previous_segment_start(&p, 480), 100);
+    }
+
+    #[test]
+    fn no_earlier_transition_goes_to_oldest() {
+        let p = vec![0.5; 500];
+        assert_eq!(previous_segment_start(&p, 400), 0);
+        let p = vec![0.0; 500];
+        assert_eq!(previous_segment_start(&p, 400), 0);
+        assert_eq!(previous_segment_start(&[], 0), 0);
+    }
+
+    #[test]
+    fn history_records_segment_peaks() {
+        // 1 kHz mono: 10 samples per segment.
+        let history = PeakHistory::new(1000, 1, 1000);
+        assert_eq!(history.segment_samples(), 10);
+        let mut block = vec![0.0; 25];
+        block[3] = 0.5;
+        block[14] = -0.25;
+        history.record(0, &block);
+        // Segment 2 is still in progress.
+        assert_eq!(history.peaks(0, 3), vec![0.5, 0.25]);
+        history.record(25, &[0.1; 6]);
+        assert_eq!(history.peaks(2, 3), vec![0.1]);
+    }
+
+    #[test]
+    fn history_forgets_segments_older_than_the_buffer() {
+        let history = PeakHistory::new(100, 1, 1000);
+        history.record(0, &[0.5; 1000]);
+        let retained = history.peaks(0, 99);
+        assert_eq!(retained[0], 0.0);
+        assert_eq!(retained[98], 0.5);
+    }
+
+    #[test]
+    fn pyramid_matches_brute_force_peaks() {
+        // 1 kHz mono, 66 segments kept: recording 5000 samples wraps every level.
+        let history = PeakHistory::new(640, 1, 1000);
+        let mut seed = 12_345u32;
+        let samples: Vec<f32> = (0..5_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) as f32 / 65_536.0 * if seed.is_multiple_of(7) { 1.0 } else { 0.2 }
+            })
+            .collect();
+        let segment_peak = |k: usize| {
+            samples[k * 10..(k + 1) * 10]
+                .iter()
+                .map(|s| (s.abs() * 1000.0) as usize)
+                .max()
+                .unwrap_or(0)
+        };
+
+        let mut position = 0;
+        for block in samples.chunks(37) {
+            history.record(position, block);
+            position += block.len();
+            let completed = history.completed();
+            let oldest = completed.saturating_sub(65);
+            for from in oldest.saturating_sub(3)..completed {
+                for to in from + 1..=completed + 2 {
+                    let want = (from.max(oldest)..to.min(completed))
+                        .map(segment_peak)
+                        .max()
+                        .unwrap_or(0);
+                    assert_eq!(
+                        history.max_peak(from, to),
+                        want as f32 / 1000.0,
+                        "{from}..{to} with {completed} completed"
+                    );
+                }
+            }
+        }
+    }
+}
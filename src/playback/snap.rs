@@ -0,0 +1,122 @@
+/// How close (in ms of delay) a seek has to land to a bookmark to snap to it.
+pub const BOOKMARK_TOLERANCE_MS: usize = 1_000;
+
+/// Grid used when snapping is toggled on without `--snap`.
+pub const DEFAULT_GRID_MS: u32 = 1_000;
+
+/// Where seeks land, chosen with `--snap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Snap {
+    #[default]
+    Off,
+    /// Delays are rounded to multiples of this many ms.
+    Grid(u32),
+    /// Seeks landing within `BOOKMARK_TOLERANCE_MS` of a bookmark go to it.
+    Bookmarks,
+}
+
+impl Snap {
+    /// Short label for the status line, e.g. "1s" or "marks".
+    pub fn label(self) -> String {
+        match self {
+            Self::Off => "off".to_string(),
+            Self::Grid(ms) if ms % 1000 == 0 => format!("{}s", ms / 1000),
+            Self::Grid(ms) => format!("{ms}ms"),
+            Self::Bookmarks => "marks".to_string(),
+        }
+    }
+}
+
+/// Which way a snapped delay may round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toward {
+    /// Absolute seeks: the nearest grid point.
+    Nearest,
+    /// Seeking back: the next grid point at or beyond the request, so steps
+    /// smaller than the grid still move.
+    MoreDelay,
+    /// Seeking toward live.
+    LessDelay,
+}
+
+/// Rounds a delay (in samples) to a multiple of `grid` samples.
+pub fn snap_to_grid(delay: usize, grid: usize, toward: Toward) -> usize {
+    if grid == 0 {
+        return delay;
+    }
+    let below = delay / grid * grid;
+    match toward {
+        Toward::LessDelay => below,
+        Toward::MoreDelay if below == delay => delay,
+        Toward::MoreDelay => below + grid,
+        Toward::Nearest if delay - below >= grid.div_ceil(2) => below + grid,
+        Toward::Nearest => below,
+    }
+}
+
+/// The bookmark delay within `tolerance` of `delay` closest to it, if any.
+/// For relative seeks only bookmarks on the far side of `current` count, so
+/// a seek never snaps back to where it started.
+pub fn snap_to_bookmark(
+    delay: usize,
+    current: usize,
+    bookmark_delays: &[usize],
+    tolerance: usize,
+    toward: Toward,
+) -> Option<usize> {
+    bookmark_delays
+        .iter()
+        .copied()
+        .filter(|&mark| match toward {
+            Toward::Nearest => true,
+            Toward::MoreDelay => mark > current,
+            Toward::LessDelay => mark < current,
+        })
+        .filter(|&mark| mark.abs_diff(delay) <= tolerance)
+        .min_by_key(|&mark| mark.abs_diff(delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        assert_eq!(Snap::Grid(2000).label(), "2s");
+        assert_eq!(Snap::Grid(250).label(), "250ms");
+        assert_eq!(Snap::Bookmarks.label(), "marks");
+    }
+
+    #[test]
+    fn grid_rounds_in_the_seek_direction() {
+        assert_eq!(snap_to_grid(1_400, 1_000, Toward::Nearest), 1_000);
+        assert_eq!(snap_to_grid(1_500, 1_000, Toward::Nearest), 2_000);
+        // A 100-sample step from a grid point still moves a whole grid step.
+        assert_eq!(snap_to_grid(2_100, 1_000, Toward::MoreDelay), 3_000);
+        assert_eq!(snap_to_grid(1_900, 1_000, Toward::LessDelay), 1_000);
+        assert_eq!(snap_to_grid(2_000, 1_000, Toward::MoreDelay), 2_000);
+        assert_eq!(snap_to_grid(1_234, 0, Toward::Nearest), 1_234);
+    }
+
+    #[test]
+    fn bookmarks_snap_within_tolerance_ahead_of_the_seek() {
+        let marks = [5_000, 9_000];
+        assert_eq!(
+            snap_to_bookmark(5_600, 0, &marks, 1_000, Toward::Nearest),
+            Some(5_000)
+        );
+        assert_eq!(
+            snap_to_bookmark(7_000, 0, &marks, 1_000, Toward::Nearest),
+            None
+        );
+        // Seeking back from the 5000 mark doesn't snap to it again.
+        assert_eq!(
+            snap_to_bookmark(5_500, 5_000, &marks, 1_000, Toward::MoreDelay),
+            None
+        );
+        assert_eq!(
+            snap_to_bookmark(8_500, 5_000, &marks, 1_000, Toward::MoreDelay),
+            Some(9_000)
+        );
+    }
+}
@@ -1,3 +1,8 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Error, anyhow};
+
 /// Playback state for the time-shift engine.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -20,6 +25,16 @@ impl PlaybackState {
         }
     }
 
+    /// Strict counterpart of `from_u8` for external input: `None` if unknown.
+    pub fn try_from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::Live),
+            1 => Some(Self::Paused),
+            2 => Some(Self::TimeShifted),
+            _ => None,
+        }
+    }
+
     pub fn label(self) -> &'static str {
         match self {
             Self::Live => "LIVE",
@@ -36,3 +51,42 @@ impl PlaybackState {
         }
     }
 }
+
+/// Symbol and label, as in the status line: ">> LIVE".
+impl fmt::Display for PlaybackState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.symbol(), self.label())
+    }
+}
+
+/// Parses a state as its number (discriminant) or label, case-insensitively.
+impl FromStr for PlaybackState {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let state = match value.to_lowercase().as_str() {
+            "live" => Some(Self::Live),
+            "paused" => Some(Self::Paused),
+            "time-shifted" => Some(Self::TimeShifted),
+            number => number.parse().ok().and_then(Self::try_from_u8),
+        };
+        state.ok_or_else(|| anyhow!("Invalid state '{value}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_and_parse() {
+        assert_eq!(PlaybackState::Paused.to_string(), "|| PAUSED");
+        assert_eq!(
+            "TIME-SHIFTED".parse::<PlaybackState>().unwrap(),
+            PlaybackState::TimeShifted
+        );
+        assert_eq!("1".parse::<PlaybackState>().unwrap(), PlaybackState::Paused);
+        assert!("3".parse::<PlaybackState>().is_err());
+        assert!("stopped".parse::<PlaybackState>().is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+
+/// Settings changed from the TUI that are kept between runs, stored as
+/// `key=value` lines. Unknown keys and bad values are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prefs {
+    /// Added to the meters' dB readout so it matches a reference meter
+    /// (`Shift+C`). 0 = uncalibrated.
+    pub meter_calibration_db: f32,
+}
+
+impl Prefs {
+    /// `~/.config/shifter/prefs`, if `$HOME` is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".config/shifter/prefs"))
+    }
+
+    /// Reads the preferences at `path`; defaults if the file doesn't exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+        Ok(Self::parse(&contents))
+    }
+
+    /// Writes the preferences to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| anyhow!("Failed to create {}: {e}", dir.display()))?;
+        }
+        fs::write(path, self.to_file())
+            .map_err(|e| anyhow!("Failed to write {}: {e}", path.display()))
+    }
+
+    fn to_file(&self) -> String {
+        format!("meter_calibration_db={}\n", self.meter_calibration_db)
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut prefs = Self::default();
+        for (key, value) in contents.lines().filter_map(|line| line.split_once('=')) {
+            if key.trim() == "meter_calibration_db"
+                && let Ok(db) = value.trim().parse::<f32>()
+                && db.is_finite()
+            {
+                prefs.meter_calibration_db = db;
+            }
+        }
+        prefs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let dir = std::env::temp_dir().join(format!("shifter-test-{}-prefs", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("nested/prefs");
+        assert_eq!(Prefs::load(&path).unwrap(), Prefs::default());
+
+        let prefs = Prefs {
+            meter_calibration_db: -2.5,
+        };
+        prefs.save(&path).unwrap();
+        assert_eq!(Prefs::load(&path).unwrap(), prefs);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn unknown_keys_and_bad_values_are_ignored() {
+        let prefs = Prefs::parse("theme=dark\nmeter_calibration_db=loud\n");
+        assert_eq!(prefs, Prefs::default());
+        let prefs = Prefs::parse("# comment\nmeter_calibration_db = 1.5\n");
+        assert_eq!(prefs.meter_calibration_db, 1.5);
+    }
+}
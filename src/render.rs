@@ -0,0 +1,277 @@
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::audio::dc_block::DcBlocker;
+use crate::audio::raw_sink::RawFormat;
+use crate::console::Console;
+use crate::playback::loudness::{self, LevelHistogram};
+use crate::wav::{WavReader, WavSpec, WavWriter};
+
+/// Frames per block read from the input file.
+const BLOCK_FRAMES: usize = 4096;
+
+/// Processing settings, the offline counterparts of the live options.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    /// `--dc-block`.
+    pub dc_block: bool,
+    /// `--input-gain-db`.
+    pub input_gain_db: f32,
+    /// Output volume as a fraction (the `volume` command / `+`/`-` keys).
+    pub volume: f32,
+}
+
+/// The live processing chain in capture order (DC blocker, input gain, volume)
+/// without a controller. Filter state carries over from one block to the next.
+pub struct RenderPipeline {
+    dc_blocker: Option<DcBlocker>,
+    gain: f32,
+}
+
+impl RenderPipeline {
+    pub fn new(settings: RenderSettings, channels: u16, sample_rate: u32) -> Self {
+        Self {
+            dc_blocker: settings
+                .dc_block
+                .then(|| DcBlocker::new(channels, sample_rate)),
+            gain: 10f32.powf(settings.input_gain_db / 20.0) * settings.volume,
+        }
+    }
+
+    /// Processes interleaved samples in place.
+    pub fn process(&mut self, data: &mut [f32]) {
+        if let Some(blocker) = self.dc_blocker.as_mut() {
+            blocker.process(data);
+        }
+        if (self.gain - 1.0).abs() > f32::EPSILON {
+            for s in data.iter_mut() {
+                *s *= self.gain;
+            }
+        }
+    }
+}
+
+/// What `render_file` produced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSummary {
+    pub frames: u64,
+    pub sample_rate: u32,
+    /// Short-term level the loudest 5% of the output reaches (what `autovol`
+    /// aligns), `None` if the output is too short or silent.
+    pub level_db: Option<f32>,
+    /// Sample peak of the output in dBFS.
+    pub peak_db: f32,
+}
+
+impl RenderSummary {
+    pub fn describe(&self, elapsed_s: f64) -> String {
+        let seconds = self.frames as f64 / self.sample_rate.max(1) as f64;
+        let level = self
+            .level_db
+            .map_or_else(|| "n/a".to_string(), |db| format!("{db:.0} dBFS"));
+        format!(
+            "Rendered {seconds:.1}s in {elapsed_s:.1}s ({:.0}x): level (95th pct) {level}, peak {:.1} dBFS",
+            seconds / elapsed_s.max(1e-6),
+            self.peak_db,
+        )
+    }
+}
+
+/// Runs `input` through the pipeline into `output` (in `format`, or the input's
+/// own format), as fast as it can be read and written. `progress` gets the
+/// percentage done after each block.
+pub fn render_file(
+    input: &Path,
+    output: &Path,
+    format: Option<RawFormat>,
+    settings: RenderSettings,
+    mut progress: impl FnMut(u32),
+) -> Result<RenderSummary> {
+    let mut reader = WavReader::open(input)?;
+    let spec = reader.spec();
+    let mut writer = WavWriter::create(
+        output,
+        WavSpec {
+            format: format.unwrap_or(spec.format),
+            ..spec
+        },
+    )?;
+    let mut pipeline = RenderPipeline::new(settings, spec.channels, spec.sample_rate);
+    let levels = LevelHistogram::new(spec.channels, spec.sample_rate);
+    let total_frames = reader.frames().max(1);
+
+    let mut block = vec![0.0f32; BLOCK_FRAMES * spec.channels as usize];
+    let mut frames = 0u64;
+    let mut peak = 0.0f32;
+    loop {
+        let n = reader.read(&mut block)?;
+        if n == 0 {
+            break;
+        }
+        let data = &mut block[..n];
+        pipeline.process(data);
+        levels.record(data);
+        peak = data.iter().fold(peak, |peak, s| peak.max(s.abs()));
+        writer.write(data)?;
+        frames += (n / spec.channels as usize) as u64;
+        progress((frames * 100 / total_frames) as u32);
+    }
+    writer.finish()?;
+
+    Ok(RenderSummary {
+        frames,
+        sample_rate: spec.sample_rate,
+        level_db: loudness::percentile_db(&levels.counts(), loudness::AUTOVOL_PERCENTILE),
+        peak_db: 20.0 * peak.log10(),
+    })
+}
+
+/// `shifter render`: renders with progress every 10% and prints the summary.
+pub fn run(
+    input: &Path,
+    output: &Path,
+    format: Option<RawFormat>,
+    settings: RenderSettings,
+    console: &Console,
+) -> Result<()> {
+    let started = Instant::now();
+    let mut reported = 0;
+    let summary = render_file(input, output, format, settings, |percent| {
+        if percent >= reported + 10 {
+            reported = percent / 10 * 10;
+            console.info(&format!("Rendering... {reported}%"));
+        }
+    })?;
+    console.out(&summary.describe(started.elapsed().as_secs_f64()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    const RATE: u32 = 8_000;
+
+    #[test]
+    fn dc_blocked_and_attenuated_render_matches_the_reference() {
+        let (dir, pid) = (std::env::temp_dir(), std::process::id());
+        let input = dir.join(format!("shifter-test-{pid}-render-in.wav"));
+        let output = dir.join(format!("shifter-test-{pid}-render-out.wav"));
+        // A 0.5 amplitude 440 Hz sine on a 0.25 DC offset, left channel only;
+        // the right channel is silent.
+        let source: Vec<f32> = (0..RATE as usize * 3)
+            .flat_map(|i| {
+                let t = i as f32 / RATE as f32;
+                [0.25 + 0.5 * (2.0 * PI * 440.0 * t).sin(), 0.0]
+            })
+            .collect();
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: RATE,
+            format: RawFormat::S16,
+        };
+        let mut writer = WavWriter::create(&input, spec).unwrap();
+        writer.write(&source).unwrap();
+        writer.finish().unwrap();
+        let settings = RenderSettings {
+            dc_block: true,
+            input_gain_db: -6.0,
+            volume: 1.0,
+        };
+        let mut last = 0;
+        let summary = render_file(&input, &output, None, settings, |p| last = p).unwrap();
+        assert_eq!((summary.frames, last), (3 * RATE as u64, 100));
+
+        // Reference: the sine alone at -6 dB, once the blocker has settled (1s).
+        let gain = 10f32.powf(-6.0 / 20.0);
+        let mut reader = WavReader::open(&output).unwrap();
+        let mut rendered = vec![0.0; reader.frames() as usize * 2];
+        reader.read(&mut rendered).unwrap();
+        assert_eq!(rendered.len(), source.len());
+        for (frame, (got, src)) in rendered.chunks(2).zip(source.chunks(2)).enumerate() {
+            if frame < RATE as usize {
+                continue;
+            }
+            let want = (src[0] - 0.25) * gain;
+            // 10 Hz high-pass phase shift at 440 Hz plus s16 quantization.
+            assert!(
+                (got[0] - want).abs() < 0.02,
+                "frame {frame}: {} vs {want}",
+                got[0]
+            );
+            assert!(got[1].abs() < 1e-3);
+        }
+        // The first samples still carry the offset (the blocker starts from
+        // rest), so the peak is near 0.75 * gain: about -8.5 dBFS.
+        let expected_peak = 20.0 * (0.75 * gain).log10();
+        assert!(
+            (summary.peak_db - expected_peak).abs() < 0.5,
+            "{}",
+            summary.peak_db
+        );
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn block_size_does_not_change_the_result() {
+        let settings = RenderSettings {
+            dc_block: true,
+            input_gain_db: 3.0,
+            volume: 0.8,
+        };
+        let source: Vec<f32> = (0..2_000)
+            .map(|i| 0.1 + (i as f32 * 0.05).sin() * 0.3)
+            .collect();
+        let mut whole = source.clone();
+        RenderPipeline::new(settings, 2, RATE).process(&mut whole);
+        let mut blocks = source;
+        let mut pipeline = RenderPipeline::new(settings, 2, RATE);
+        for block in blocks.chunks_mut(14) {
+            pipeline.process(block);
+        }
+        assert_eq!(whole, blocks);
+    }
+
+    #[test]
+    fn float_render_can_change_the_format() {
+        let (dir, pid) = (std::env::temp_dir(), std::process::id());
+        let input = dir.join(format!("shifter-test-{pid}-render-f32-in.wav"));
+        let output = dir.join(format!("shifter-test-{pid}-render-s24-out.wav"));
+        // The same sine on an offset, stored exactly as f32.
+        let source: Vec<f32> = (0..RATE as usize)
+            .flat_map(|i| {
+                let t = i as f32 / RATE as f32;
+                [0.25 + 0.5 * (2.0 * PI * 440.0 * t).sin(), 0.0]
+            })
+            .collect();
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: RATE,
+            format: RawFormat::F32,
+        };
+        let mut writer = WavWriter::create(&input, spec).unwrap();
+        writer.write(&source).unwrap();
+        writer.finish().unwrap();
+        let settings = RenderSettings {
+            dc_block: false,
+            input_gain_db: 0.0,
+            volume: 1.0,
+        };
+        render_file(&input, &output, Some(RawFormat::S24), settings, |_| {}).unwrap();
+        let mut reader = WavReader::open(&output).unwrap();
+        assert_eq!(reader.spec().format, RawFormat::S24);
+        let mut rendered = vec![0.0; reader.frames() as usize * 2];
+        reader.read(&mut rendered).unwrap();
+        assert_eq!(rendered.len(), source.len());
+        for (got, want) in rendered.iter().zip(&source) {
+            assert!((got - want).abs() < 1e-6);
+        }
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+}
@@ -1,11 +1,40 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen};
 use ratatui::DefaultTerminal;
 
-use crate::playback::controller::PlaybackController;
+use crate::audio::engine::{AudioDeviceID, AudioEngine};
+use crate::audio::latency::HardwareLatency;
+use crate::audio::recorder::Recorder;
+use crate::control::command::Command;
+use crate::control::script::{self, Script, ScriptAction, Status};
+use crate::control::status_file::StatusFile;
+use crate::control::wallclock;
+use crate::event_log::EventLog;
+use crate::platform::MemoryLock;
+use crate::playback::clock_drift::ClockDrift;
+use crate::playback::controller::{PlaybackController, SeekLimit};
+use crate::playback::delay_probe::DelayProbe;
+use crate::playback::events::{ShifterEvent, XRunKind};
+use crate::playback::loudness;
+use crate::playback::silence;
+use crate::playback::snap::Snap;
+use crate::prefs::Prefs;
+use crate::tui::format::format_delay_ms;
+use crate::tui::idle::IdleMode;
+use crate::tui::keymap::{self, Action, ConfirmQuit, NumberKeys};
+use crate::tui::readout::{self, SmoothedDb, peak_to_db};
+use crate::tui::repeat::SeekAccelerator;
+use crate::tui::session::SessionClock;
+use crate::tui::theme::Palette;
+use crate::tui::timeline::{Extent, TimelineView};
 use crate::tui::ui;
 
 /// Seek scales indexed 0..8 corresponding to keys 1..9.
@@ -21,16 +50,122 @@ pub const SEEK_SCALES: [(f64, &str); 9] = [
     (30_000.0, "30s"),
 ];
 
+/// Time constant of the meter dB readout smoothing.
+const DEFAULT_METER_SMOOTHING: Duration = Duration::from_millis(250);
+
+/// How long a toast notice stays on screen.
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Frames averaged for `avg_frame_ms` (two seconds at ~30 FPS).
+const FRAME_TIME_WINDOW: usize = 60;
+
+/// Average frame time above which the status bar shows `[slow]` (20 FPS).
+pub const SLOW_FRAME_MS: f64 = 50.0;
+
+/// Search box at the top of the help overlay.
+#[derive(Debug, Default)]
+pub struct HelpSearchState {
+    /// Filters the help to bindings whose description contains it.
+    pub query: String,
+    /// Whether typed characters go to `query` (true while the overlay is open).
+    pub active: bool,
+}
+
 pub struct App {
     pub controller: Arc<PlaybackController>,
     pub should_quit: bool,
     pub input_device_name: String,
     pub output_device_name: String,
+    /// Why the output device was chosen, shown in the device details.
+    pub output_reason: Option<String>,
+    /// The extra live delay for an AirPlay output ("AirPlay +1.8s").
+    pub airplay: Option<String>,
+    /// Shown in the title when set.
+    pub instance_name: Option<String>,
     pub buffer_seconds: u32,
     /// Current seek scale index (0..8, default 4 = 1s).
     pub seek_scale_index: usize,
+    /// What the number keys do.
+    pub number_keys: NumberKeys,
+    /// Step multiplier for held seek keys.
+    pub seek_accel: SeekAccelerator,
     /// Whether the help overlay is shown.
     pub show_help: bool,
+    /// Current help page (index into `keymap::help_pages()`).
+    pub help_page: usize,
+    /// Help page scroll offset in lines; clamped when drawn.
+    pub help_scroll: u16,
+    /// Terminal height at the last draw, for paging the help overlay.
+    terminal_height: u16,
+    /// Help overlay search input.
+    pub help_search: HelpSearchState,
+    /// Wall-clock time being typed after `T`, while the prompt is open.
+    pub time_prompt: Option<String>,
+    /// Which quit keys ask first.
+    confirm_quit: ConfirmQuit,
+    /// Whether the quit confirmation is open.
+    pub quit_prompt: bool,
+    /// Whether the diagnostics overlay is shown.
+    pub show_diagnostics: bool,
+    /// Whether the latency budget overlay is shown.
+    pub show_latency_budget: bool,
+    /// Whether the device details overlay (full names) is shown.
+    pub show_device_details: bool,
+    /// Whether the debug overlay (raw positions) is shown.
+    pub show_debug: bool,
+    /// Whether the recorded files overlay is shown.
+    pub show_recordings: bool,
+    /// Device latencies and IO buffer sizes, read once at startup.
+    pub hardware_latency: HardwareLatency,
+    /// Whether the ring buffer is pinned in RAM (`None` without an engine).
+    pub memory_lock: Option<MemoryLock>,
+    /// Measures the real end-to-end delay from input/output envelopes.
+    pub delay_probe: DelayProbe,
+    /// Input vs. output device clock, for the debug overlay.
+    pub clock_drift: ClockDrift,
+    /// Colors used when drawing.
+    pub palette: Palette,
+    /// Zoom and pan of the timeline panel.
+    pub timeline: TimelineView,
+    /// Whether drawing the full layout panicked; cleared on the next resize.
+    pub layout_error: bool,
+    /// When the UI dims for lack of key presses.
+    pub idle: IdleMode,
+    /// Whether the UI is drawn dimmed (idle); it is then only redrawn when
+    /// `redraw` is set.
+    pub dimmed: bool,
+    /// Something changed that the next frame must show, even while dimmed.
+    redraw: bool,
+    /// Time of each of the last `FRAME_TIME_WINDOW` run loop iterations.
+    frame_times: VecDeque<Duration>,
+    last_frame_start: Option<Instant>,
+    /// Running time and captured-audio drift tracking.
+    pub session: SessionClock,
+    /// Controller events, handled once per frame.
+    events: Receiver<ShifterEvent>,
+    /// Displayed (left, right) meter dB numbers, smoothed against frame jitter.
+    pub meter_readout: [SmoothedDb; 2],
+    /// Level a calibration tone should read, in dB.
+    meter_reference_db: f32,
+    /// Saved preferences (the meter calibration) and where they live.
+    pub prefs: Prefs,
+    prefs_path: Option<PathBuf>,
+    /// Notices from background watchers (e.g. screen lock), shown as toasts.
+    notices: Receiver<String>,
+    /// Current toast and when it was shown.
+    pub toast: Option<(String, Instant)>,
+    /// `--event-log` writer, if enabled.
+    event_log: Option<EventLog>,
+    /// `--status-file` writer, if enabled. Dropping it removes the file.
+    status_file: Option<StatusFile>,
+    /// `--record-dir` recorder, if enabled. Dropping it finishes the last file.
+    pub recorder: Option<Recorder>,
+    /// User automation script, if one was loaded.
+    script: Option<Script>,
+    /// Owns the audio units; needed to switch the output device.
+    engine: Option<AudioEngine>,
+    /// New system output device IDs, when following the system output.
+    system_output: Option<Receiver<AudioDeviceID>>,
 }
 
 impl App {
@@ -39,74 +174,790 @@ impl App {
         input_device_name: String,
         output_device_name: String,
         buffer_seconds: u32,
+        hardware_latency: HardwareLatency,
+        palette: Palette,
+        notices: Receiver<String>,
     ) -> Self {
+        let events = controller.events.subscribe();
+        let clock_drift = ClockDrift::new(controller.sample_rate());
         Self {
             controller,
             should_quit: false,
             input_device_name,
             output_device_name,
+            output_reason: None,
+            airplay: None,
+            instance_name: None,
             buffer_seconds,
             seek_scale_index: 4, // default: 1s
+            number_keys: NumberKeys::default(),
+            seek_accel: SeekAccelerator::new(true),
             show_help: false,
+            help_page: 0,
+            help_scroll: 0,
+            terminal_height: 0,
+            help_search: HelpSearchState::default(),
+            time_prompt: None,
+            confirm_quit: ConfirmQuit::default(),
+            quit_prompt: false,
+            show_diagnostics: false,
+            show_latency_budget: false,
+            show_device_details: false,
+            show_debug: false,
+            show_recordings: false,
+            hardware_latency,
+            memory_lock: None,
+            delay_probe: DelayProbe::new(),
+            clock_drift,
+            palette,
+            timeline: TimelineView::default(),
+            layout_error: false,
+            idle: IdleMode::new(None, Instant::now()),
+            dimmed: false,
+            redraw: true,
+            frame_times: VecDeque::with_capacity(FRAME_TIME_WINDOW),
+            last_frame_start: None,
+            session: SessionClock::new(),
+            events,
+            meter_readout: std::array::from_fn(|_| SmoothedDb::new(DEFAULT_METER_SMOOTHING)),
+            meter_reference_db: -18.0,
+            prefs: Prefs::default(),
+            prefs_path: None,
+            notices,
+            toast: None,
+            event_log: None,
+            status_file: None,
+            recorder: None,
+            script: None,
+            engine: None,
+            system_output: None,
+        }
+    }
+
+    pub fn with_instance_name(mut self, name: String) -> Self {
+        self.instance_name = Some(name);
+        self
+    }
+
+    pub fn with_output_reason(mut self, reason: String) -> Self {
+        self.output_reason = Some(reason);
+        self
+    }
+
+    /// Attaches the automation script. A load error is shown as a toast.
+    pub fn with_script(mut self, script: Result<Option<Script>>) -> Self {
+        match script {
+            Ok(script) => self.script = script,
+            Err(e) => self.flash(e.to_string()),
         }
+        self
+    }
+
+    pub fn with_number_keys(mut self, number_keys: NumberKeys) -> Self {
+        self.number_keys = number_keys;
+        self
+    }
+
+    /// Turns seek key acceleration on or off (on by default).
+    pub fn with_seek_acceleration(mut self, enabled: bool) -> Self {
+        self.seek_accel = SeekAccelerator::new(enabled);
+        self
+    }
+
+    pub fn with_event_log(mut self, event_log: Option<EventLog>) -> Self {
+        self.event_log = event_log;
+        self
+    }
+
+    pub fn with_status_file(mut self, status_file: Option<StatusFile>) -> Self {
+        self.status_file = status_file;
+        self
+    }
+
+    pub fn with_recorder(mut self, recorder: Option<Recorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    /// Opens the debug overlay at startup (`--debug`).
+    pub fn with_debug(mut self, show: bool) -> Self {
+        self.show_debug = show;
+        self
+    }
+
+    pub fn with_confirm_quit(mut self, confirm_quit: ConfirmQuit) -> Self {
+        self.confirm_quit = confirm_quit;
+        self
+    }
+
+    /// Dims the UI after `timeout` without a key press (`--idle-timeout-s`).
+    pub fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle = IdleMode::new(timeout, Instant::now());
+        self
+    }
+
+    /// Sets the meter dB readout smoothing time constant (zero disables it).
+    pub fn with_meter_smoothing(mut self, tau: Duration) -> Self {
+        self.meter_readout = std::array::from_fn(|_| SmoothedDb::new(tau));
+        self
+    }
+
+    /// Sets the level a calibration tone should read (`--meter-reference-db`).
+    pub fn with_meter_reference(mut self, reference_db: f32) -> Self {
+        self.meter_reference_db = reference_db;
+        self
+    }
+
+    /// Loads the preferences at `path`; errors are shown as a toast.
+    pub fn with_prefs(mut self, path: Option<PathBuf>) -> Self {
+        if let Some(path) = &path {
+            match Prefs::load(path) {
+                Ok(prefs) => self.prefs = prefs,
+                Err(e) => self.flash(e.to_string()),
+            }
+        }
+        self.prefs_path = path;
+        self
+    }
+
+    /// Hands over the audio engine. With `system_output`, playback follows the
+    /// system output device when it changes (e.g. headphones plugged in).
+    pub fn with_engine(
+        mut self,
+        engine: AudioEngine,
+        system_output: Option<Receiver<AudioDeviceID>>,
+    ) -> Self {
+        self.memory_lock = Some(engine.memory_lock);
+        self.airplay = engine.airplay.map(|airplay| airplay.label());
+        self.engine = Some(engine);
+        self.system_output = system_output;
+        self
     }
 
     pub fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
         while !self.should_quit {
-            terminal.draw(|frame| ui::draw(frame, self))?;
+            self.record_frame(Instant::now());
+            self.update();
+            // While idle the dimmed frame stays up until something changes.
+            if self.redraw || !self.dimmed {
+                self.draw(terminal)?;
+                self.redraw = false;
+            }
 
             // Poll at ~30 FPS for smooth meter updates
-            if event::poll(Duration::from_millis(33))?
-                && let Event::Key(key) = event::read()?
-            {
-                if key.kind != crossterm::event::KeyEventKind::Press {
-                    continue;
+            if event::poll(Duration::from_millis(33))? {
+                match event::read()? {
+                    Event::Key(key) if key.kind == crossterm::event::KeyEventKind::Press => {
+                        self.handle_key(key.code, key.modifiers);
+                    }
+                    // A new size may fit the layout again.
+                    Event::Resize(..) => {
+                        self.layout_error = false;
+                        self.redraw = true;
+                    }
+                    _ => {}
                 }
-                self.handle_key(key.code, key.modifiers);
             }
         }
         Ok(())
     }
 
-    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
-        match code {
-            KeyCode::Char('q') | KeyCode::Char('Q') => {
-                self.should_quit = true;
+    /// Records the time since the previous run loop iteration started;
+    /// `run` calls it at the top of each one.
+    pub fn record_frame(&mut self, now: Instant) {
+        if let Some(last) = self.last_frame_start.replace(now) {
+            if self.frame_times.len() == FRAME_TIME_WINDOW {
+                self.frame_times.pop_front();
             }
-            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
+            self.frame_times
+                .push_back(now.saturating_duration_since(last));
+        }
+    }
+
+    /// Average time of the last `FRAME_TIME_WINDOW` frames, in ms (0 before
+    /// the second frame). The poll wait is part of it: ~33ms when idle.
+    pub fn avg_frame_ms(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        let total: Duration = self.frame_times.iter().sum();
+        total.as_secs_f64() * 1000.0 / self.frame_times.len() as f64
+    }
+
+    /// Whether the UI runs below 20 FPS, e.g. with the CPU overloaded. Only
+    /// judged over a full window, so a slow first frame doesn't flag it.
+    pub fn is_slow(&self) -> bool {
+        self.frame_times.len() == FRAME_TIME_WINDOW && self.avg_frame_ms() > SLOW_FRAME_MS
+    }
+
+    /// One frame's work before drawing: meters, script, device changes,
+    /// controller events, toasts and idle mode.
+    pub fn update(&mut self) {
+        self.delay_probe.tick(&self.controller);
+        if self.clock_drift.tick(&self.controller)
+            && let Some(ppm) = self.clock_drift.ppm()
+        {
+            self.flash(format!(
+                "Output clock drifting {ppm:+.0}ppm from the input: the delay will creep"
+            ));
+        }
+        let (elapsed, captured) = (
+            self.session.elapsed_seconds(),
+            self.controller.captured_seconds(),
+        );
+        if self.controller.capture_pending_ms().is_some() {
+            // Drift counts from when capture really starts.
+            self.session.rebase(elapsed, captured);
+        }
+        self.session
+            .observe(self.controller.wake_count(), elapsed, captured);
+        let (peak_l, peak_r) = self.controller.peak_levels();
+        self.meter_readout[0].tick(peak_to_db(peak_l));
+        self.meter_readout[1].tick(peak_to_db(peak_r));
+        self.run_script_tick();
+        self.follow_system_output();
+        self.follow_airplay_latency();
+        self.controller.publish_pending();
+        self.handle_events();
+        self.write_status_file();
+        self.update_toast();
+        let dimmed = self.idle.is_idle(Instant::now());
+        if dimmed != self.dimmed {
+            self.dimmed = dimmed;
+            self.redraw = true;
+        }
+    }
+
+    /// Draws the layout (`ui::draw` handles small terminals). A panic while
+    /// rendering (widgets can panic in very small areas) is caught and shown
+    /// as too small until the next resize.
+    fn draw(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let size = terminal.size()?;
+        self.terminal_height = size.height;
+        if self.layout_error {
+            terminal.draw(ui::draw_too_small)?;
+            return Ok(());
+        }
+        let app = &*self;
+        match panic::catch_unwind(AssertUnwindSafe(|| {
+            terminal.draw(|frame| ui::draw(frame, app)).map(|_| ())
+        })) {
+            Ok(result) => result?,
+            Err(_) => {
+                self.layout_error = true;
+                // The panic hook restored the terminal; take it over again.
+                terminal::enable_raw_mode()?;
+                crossterm::execute!(std::io::stdout(), EnterAlternateScreen)?;
+                terminal.clear()?;
+                terminal.draw(ui::draw_too_small)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles this frame's controller events: toasts for read jumps, a full
+    /// buffer and device changes, and marks, pauses and seeks for the event
+    /// log. Xruns and buffer thresholds wake idle mode. A log write error is
+    /// shown once and stops logging.
+    fn handle_events(&mut self) {
+        let events: Vec<ShifterEvent> = self.events.try_iter().collect();
+        for event in events {
+            if matches!(
+                event,
+                ShifterEvent::XRun { .. } | ShifterEvent::BufferThreshold { .. }
+            ) {
+                self.idle.wake(Instant::now());
+            }
+            match &event {
+                ShifterEvent::XRun {
+                    kind: XRunKind::ReadJump { ms },
+                } => self.flash(format!(
+                    "Playback jumped {} (slow output callback)",
+                    format_delay_ms(*ms)
+                )),
+                ShifterEvent::BufferThreshold { level: 100 } => self.flash(format!(
+                    "Buffer full \u{2014} now a rolling {}s window",
+                    self.buffer_seconds
+                )),
+                ShifterEvent::DeviceChanged { output } => self.flash(format!("Output: {output}")),
+                _ => {}
+            }
+            if let Some(log) = self.event_log.as_mut()
+                && let Err(e) = log.record(&event, wallclock::local_seconds_of_day())
+            {
+                self.event_log = None;
+                self.flash(e.to_string());
+            }
+        }
+    }
+
+    /// Rewrites `--status-file` once per second. A write error is shown once
+    /// and stops it.
+    fn write_status_file(&mut self) {
+        if let Some(file) = self.status_file.as_mut()
+            && let Err(e) = file.tick(
+                Instant::now(),
+                &self.controller,
+                &self.input_device_name,
+                &self.output_device_name,
+            )
+        {
+            self.status_file = None;
+            self.flash(e.to_string());
+        }
+    }
+
+    fn update_toast(&mut self) {
+        while let Ok(notice) = self.notices.try_recv() {
+            self.flash(notice);
+        }
+        if self
+            .toast
+            .as_ref()
+            .is_some_and(|(_, shown)| shown.elapsed() >= TOAST_DURATION)
+        {
+            self.toast = None;
+            self.redraw = true;
+        }
+    }
+
+    fn follow_system_output(&mut self) {
+        let (Some(engine), Some(rx)) = (self.engine.as_mut(), self.system_output.as_ref()) else {
+            return;
+        };
+        // Only the latest change matters; intermediate devices may already be gone.
+        let Some(new_id) = rx.try_iter().last() else {
+            return;
+        };
+        match engine.switch_output_device_by_id(new_id) {
+            Ok(()) => {
+                self.output_device_name = engine.output_device_name.clone();
+                self.output_reason = Some("followed the system output".to_string());
+                self.hardware_latency = engine.hardware_latency;
+                self.airplay = engine.airplay.map(|airplay| airplay.label());
+                self.controller.events.publish(ShifterEvent::DeviceChanged {
+                    output: self.output_device_name.clone(),
+                });
+            }
+            Err(e) => self.flash(e.to_string()),
+        }
+    }
+
+    fn follow_airplay_latency(&mut self) {
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+        if !engine.poll_airplay_latency() {
+            return;
+        }
+        self.hardware_latency = engine.hardware_latency;
+        self.airplay = engine.airplay.map(|airplay| airplay.label());
+        if let Some(label) = &self.airplay {
+            self.flash(format!("AirPlay latency changed: {label}"));
+        }
+    }
+
+    fn run_script_tick(&mut self) {
+        let Some(script) = self.script.as_mut() else {
+            return;
+        };
+        let controller = &self.controller;
+        let result = script.tick(|| Status::from_controller(controller));
+        self.apply_script_result(result);
+    }
+
+    /// Offers the key to the script's `on_key`. Returns true if it was consumed.
+    fn run_script_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        let (Some(script), Some(key)) = (self.script.as_mut(), script::key_name(code, modifiers))
+        else {
+            return false;
+        };
+        let status = Status::from_controller(&self.controller);
+        match script.on_key(&key, status) {
+            Ok((consumed, actions)) => {
+                self.apply_script_result(Ok(actions));
+                consumed
+            }
+            Err(e) => {
+                self.flash(e.to_string());
+                false
+            }
+        }
+    }
+
+    fn apply_script_result(&mut self, result: Result<Vec<ScriptAction>>) {
+        let actions = match result {
+            Ok(actions) => actions,
+            Err(e) => return self.flash(e.to_string()),
+        };
+        for action in actions {
+            match action {
+                ScriptAction::Command(command) => {
+                    if let Err(e) = command.apply(&self.controller) {
+                        self.flash(e.to_string());
+                    }
+                }
+                ScriptAction::Mark => self.controller.add_bookmark(),
+                ScriptAction::Toast(message) => self.flash(message),
+            }
+        }
+    }
+
+    /// Shows `message` as a toast for `TOAST_DURATION`.
+    pub fn flash(&mut self, message: impl Into<String>) {
+        self.toast = Some((message.into(), Instant::now()));
+        self.redraw = true;
+    }
+
+    fn seek(&mut self, delta_ms: f64) {
+        match self.controller.seek_ms(delta_ms) {
+            Some(SeekLimit::Live) => self.flash("Already live"),
+            Some(SeekLimit::BufferStart) => self.flash("Buffer limit reached"),
+            None => self.flash_snapped_bookmark(),
+        }
+    }
+
+    /// Names the bookmark a seek snapped to.
+    fn flash_snapped_bookmark(&mut self) {
+        if self.controller.snap() != Snap::Bookmarks {
+            return;
+        }
+        let target = self.controller.target_position();
+        let marks = self.controller.bookmarks();
+        if let Some(n) = marks.iter().position(|&pos| pos == target) {
+            self.flash(format!("Snapped to bookmark {}", n + 1));
+        }
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        // While idle, a key only wakes the UI.
+        if self.idle.wake(Instant::now()) {
+            self.dimmed = false;
+            self.redraw = true;
+            return;
+        }
+        // Prompts and the help overlay are modal: they get keys before the script.
+        if self.quit_prompt {
+            self.handle_quit_prompt_key(code, modifiers);
+            return;
+        }
+        if self.time_prompt.is_some() {
+            self.handle_time_prompt_key(code);
+            return;
+        }
+        if self.show_help && self.handle_help_key(code, modifiers) {
+            return;
+        }
+        if self.run_script_key(code, modifiers) {
+            return;
+        }
+        let Some(action) = keymap::lookup(code, modifiers) else {
+            return;
+        };
+        let multiplier = match action {
+            Action::SeekTowardLive | Action::SeekBack => {
+                self.seek_accel.press(action, Instant::now())
+            }
+            _ => {
+                self.seek_accel.reset();
+                1
             }
-            KeyCode::Char(' ') => {
+        };
+        match action {
+            Action::Quit => {
+                let ctrl_c = modifiers.contains(KeyModifiers::CONTROL);
+                if self.confirm_quit.asks(ctrl_c) {
+                    self.quit_prompt = true;
+                } else {
+                    self.should_quit = true;
+                }
+            }
+            Action::TogglePause => {
                 self.controller.toggle_pause();
             }
-            KeyCode::Char('l') | KeyCode::Char('L') => {
+            Action::JumpToLive => {
                 self.controller.jump_to_live();
             }
-            KeyCode::Char('m') | KeyCode::Char('M') => {
+            Action::ToggleMute => {
                 self.controller.toggle_mute();
             }
-            KeyCode::Char('h') | KeyCode::Char('H') => {
+            Action::AutoVolume => {
+                let target = loudness::AUTOVOL_TARGET_DB;
+                match self.controller.auto_volume(target) {
+                    Ok((volume, _)) => self.flash(format!(
+                        "Set volume to {:.0}% so peaks sit at {target:.0} dBFS",
+                        volume * 100.0
+                    )),
+                    Err(e) => self.flash(e.to_string()),
+                }
+            }
+            Action::ClearStats => {
+                self.controller.reset_stats();
+                self.flash("Stats cleared");
+            }
+            Action::CalibrateMeters => self.calibrate_meters(),
+            Action::ZoomTimelineIn => {
+                if !self.timeline.zoom_in(self.timeline_extent().capacity) {
+                    self.flash("Timeline fully zoomed in");
+                }
+            }
+            Action::ZoomTimelineOut => {
+                if !self.timeline.zoom_out() {
+                    self.flash("Timeline shows the whole buffer");
+                }
+            }
+            Action::PanTimelineTowardLive => {
+                let extent = self.timeline_extent();
+                self.timeline.pan(-1, extent);
+            }
+            Action::PanTimelineBack => {
+                let extent = self.timeline_extent();
+                self.timeline.pan(1, extent);
+            }
+            Action::ToggleHelp => {
                 self.show_help = !self.show_help;
+                self.help_search = HelpSearchState {
+                    query: String::new(),
+                    active: self.show_help,
+                };
+                self.help_scroll = 0;
             }
-            KeyCode::Left => {
-                let step_ms = SEEK_SCALES[self.seek_scale_index].0;
-                self.controller.seek_ms(-step_ms);
+            Action::ReplaySegment => {
+                self.replay_segment();
             }
-            KeyCode::Right => {
-                let step_ms = SEEK_SCALES[self.seek_scale_index].0;
-                self.controller.seek_ms(step_ms);
+            Action::GoToTime => {
+                self.time_prompt = Some(String::new());
             }
-            KeyCode::Up => {
+            Action::ToggleDiagnostics => {
+                self.show_diagnostics = !self.show_diagnostics;
+            }
+            Action::DimScreen => self.idle.dim(),
+            Action::ToggleLatencyBudget => {
+                self.show_latency_budget = !self.show_latency_budget;
+            }
+            Action::ToggleDeviceDetails => {
+                self.show_device_details = !self.show_device_details;
+            }
+            Action::ToggleSnap => match self.controller.toggle_snap() {
+                Snap::Off => self.flash("Snap off"),
+                snap => self.flash(format!("Snap: {}", snap.label())),
+            },
+            Action::ToggleDebug => {
+                self.show_debug = !self.show_debug;
+            }
+            Action::SplitRecording => {
+                if self.controller.request_split() {
+                    self.flash("Starting the next recorded file");
+                } else {
+                    self.flash("Not recording (see --record-dir)");
+                }
+            }
+            Action::ToggleRecordings => {
+                self.show_recordings = !self.show_recordings;
+            }
+            Action::SeekTowardLive => {
+                let step_ms = SEEK_SCALES[self.seek_scale_index].0 * multiplier as f64;
+                self.seek(-step_ms);
+            }
+            Action::SeekBack => {
+                let step_ms = SEEK_SCALES[self.seek_scale_index].0 * multiplier as f64;
+                self.seek(step_ms);
+            }
+            Action::VolumeUp => {
                 self.controller.adjust_volume(50);
             }
-            KeyCode::Down => {
+            Action::VolumeDown => {
                 self.controller.adjust_volume(-50);
             }
-            // Number keys 1-9 select seek scale
-            KeyCode::Char(c @ '1'..='9') => {
-                self.seek_scale_index = (c as usize - '1' as usize).min(8);
+            Action::InputGainUp => {
+                self.controller.adjust_input_gain_db(1.0);
+            }
+            Action::InputGainDown => {
+                self.controller.adjust_input_gain_db(-1.0);
+            }
+            Action::NumberKey => {
+                if let KeyCode::Char(c @ '1'..='9') = code {
+                    self.number_key(c as usize - '0' as usize);
+                }
+            }
+        }
+    }
+
+    /// Number key `n` (1-9), per the `--number-keys` mode.
+    fn number_key(&mut self, n: usize) {
+        match self.number_keys {
+            NumberKeys::SeekScale => {
+                self.seek_scale_index = (n - 1).min(8);
+            }
+            NumberKeys::Bookmarks => {
+                let marks = self.controller.bookmarks();
+                match marks.get(n - 1) {
+                    Some(&pos) if self.controller.seek_to_position(pos) => {
+                        self.flash(format!("Bookmark {n}"));
+                    }
+                    _ => self.flash(format!("No bookmark {n} ({} in buffer)", marks.len())),
+                }
+            }
+            NumberKeys::Volume => {
+                self.controller.set_volume(NumberKeys::volume_preset(n));
+            }
+        }
+    }
+
+    /// The buffer in peak history segments, for the timeline panel.
+    pub fn timeline_extent(&self) -> Extent {
+        let history = &self.controller.peak_history;
+        let seg = history.segment_samples();
+        Extent {
+            live: history.completed() as i64,
+            capacity: (self.controller.ring.capacity() / seg) as i64,
+            playhead: (self.controller.play_position() / seg) as i64,
+        }
+    }
+
+    /// Seeks back to where the sound playing now began (after silence), using
+    /// the analysis thread's peak history. Repeated presses go further back.
+    fn replay_segment(&mut self) {
+        let ring = &self.controller.ring;
+        let history = &self.controller.peak_history;
+        let seg = history.segment_samples();
+        let wp = ring.write_position();
+        // First segment that is still fully buffered, and the last complete one.
+        let oldest = wp.saturating_sub(ring.capacity()).div_ceil(seg);
+        let newest = wp / seg;
+        let peaks = history.peaks(oldest, newest);
+        let current = (self.controller.play_position() / seg).saturating_sub(oldest);
+        let start = oldest + silence::previous_segment_start(&peaks, current);
+        if !self.controller.seek_to_position(start * seg) {
+            self.flash("Buffer limit reached");
+        }
+    }
+
+    /// Calibrates the meter readout so the tone playing now reads as the
+    /// reference level; with no signal, clears the calibration. Saved to prefs.
+    fn calibrate_meters(&mut self) {
+        let measured = self.meter_readout[0]
+            .value()
+            .max(self.meter_readout[1].value());
+        let (offset, message) = match readout::calibration_offset(measured, self.meter_reference_db)
+        {
+            Some(offset) => (
+                offset,
+                format!(
+                    "Meters calibrated: {measured:.1} dB reads {:.1} dB",
+                    self.meter_reference_db
+                ),
+            ),
+            None => (0.0, "No signal: meter calibration cleared".to_string()),
+        };
+        self.prefs.meter_calibration_db = offset;
+        match &self.prefs_path {
+            Some(path) => match self.prefs.save(path) {
+                Ok(()) => self.flash(message),
+                Err(e) => self.flash(e.to_string()),
+            },
+            None => self.flash(message),
+        }
+    }
+
+    /// Y / Enter quits, N / Esc cancels. Ctrl+C quits from the prompt too,
+    /// so pressing it twice always gets out.
+    fn handle_quit_prompt_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Char('y' | 'Y') | KeyCode::Enter => self.should_quit = true,
+            KeyCode::Char('n' | 'N') | KeyCode::Esc => self.quit_prompt = false,
+            _ => {}
+        }
+    }
+
+    /// Editing keys for the go-to-time prompt. Enter seeks, Esc cancels.
+    fn handle_time_prompt_key(&mut self, code: KeyCode) {
+        let Some(input) = self.time_prompt.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Char(c) if c.is_ascii_digit() || c == ':' || c == '.' => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.time_prompt = None,
+            KeyCode::Enter => {
+                let input = self.time_prompt.take().unwrap_or_default();
+                let result = wallclock::parse_time_of_day(&input)
+                    .and_then(|time| Command::At(time).apply(&self.controller));
+                match result {
+                    Ok(()) => self.flash(format!("At {input}")),
+                    Err(e) => self.flash(e.to_string()),
+                }
             }
             _ => {}
         }
     }
+
+    /// Search, page and scroll keys while the help overlay is open.
+    /// Returns true if consumed.
+    fn handle_help_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        let help_pages = keymap::help_pages();
+        let pages = help_pages.len().max(1);
+        let searching = !self.help_search.query.is_empty();
+        // Never scroll past a full last screen of bindings (plus the blank
+        // padding lines, or the "No matching keys" line).
+        let shown = if searching {
+            keymap::search(&self.help_search.query).len()
+        } else {
+            help_pages
+                .get(self.help_page)
+                .map_or(0, |(_, bindings)| bindings.len())
+        };
+        let lines = shown.max(1) as u16 + 2;
+        let visible = ui::help_visible_rows(self.terminal_height, lines);
+        let max_scroll = lines.saturating_sub(visible);
+        let page_rows = visible.max(1);
+        self.help_scroll = self.help_scroll.min(max_scroll);
+        match code {
+            KeyCode::Char(c)
+                if self.help_search.active
+                    && !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.help_search.query.push(c);
+                self.help_scroll = 0;
+            }
+            KeyCode::Backspace if self.help_search.active => {
+                self.help_search.query.pop();
+                self.help_scroll = 0;
+            }
+            // PgDn/PgUp scroll a long page first, then turn it.
+            KeyCode::PageDown if self.help_scroll < max_scroll => {
+                self.help_scroll = (self.help_scroll + page_rows).min(max_scroll);
+            }
+            KeyCode::PageUp if self.help_scroll > 0 => {
+                self.help_scroll = self.help_scroll.saturating_sub(page_rows);
+            }
+            KeyCode::Tab | KeyCode::PageDown if searching => {}
+            KeyCode::BackTab | KeyCode::PageUp if searching => {}
+            KeyCode::Tab | KeyCode::PageDown => {
+                self.help_page = (self.help_page + 1) % pages;
+                self.help_scroll = 0;
+            }
+            KeyCode::BackTab | KeyCode::PageUp => {
+                self.help_page = (self.help_page + pages - 1) % pages;
+                self.help_scroll = 0;
+            }
+            KeyCode::Down => {
+                self.help_scroll = (self.help_scroll + 1).min(max_scroll);
+            }
+            KeyCode::Up => {
+                self.help_scroll = self.help_scroll.saturating_sub(1);
+            }
+            KeyCode::Esc => {
+                self.show_help = false;
+                self.help_search = HelpSearchState::default();
+            }
+            _ => return false,
+        }
+        true
+    }
 }
@@ -0,0 +1,221 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::audio::driver_check;
+use crate::audio::engine::{self, DeviceCache, DeviceInfo};
+use crate::tui::theme::Palette;
+
+/// What a key did in the picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    /// The device at this index was chosen.
+    Picked(usize),
+    Quit,
+}
+
+/// Startup list of input devices to choose from when none was given.
+pub struct DevicePicker {
+    labels: Vec<String>,
+    selected: usize,
+}
+
+impl DevicePicker {
+    pub fn new(labels: Vec<String>) -> Self {
+        Self {
+            labels,
+            selected: 0,
+        }
+    }
+
+    /// Moves the selection or makes a choice. ↑/↓ (or k/j) move, Enter
+    /// picks, Esc, q and Ctrl+C quit.
+    pub fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Option<Choice> {
+        let last = self.labels.len().saturating_sub(1);
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Enter if !self.labels.is_empty() => {
+                return Some(Choice::Picked(self.selected));
+            }
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(Choice::Quit);
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Some(Choice::Quit),
+            _ => {}
+        }
+        None
+    }
+
+    /// Draws the list centered in the frame, the selection highlighted.
+    pub fn draw(&self, frame: &mut Frame, palette: &Palette) {
+        let title = " Select input device ";
+        let footer = " \u{2191}/\u{2193} select \u{00b7} Enter start \u{00b7} Esc quit ";
+        let lines: Vec<Line> = self
+            .labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                if i == self.selected {
+                    Line::styled(
+                        format!("\u{25b6} {label} "),
+                        Style::default()
+                            .fg(palette.overlay)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    Line::from(format!("  {label} "))
+                }
+            })
+            .collect();
+        let content_width = lines
+            .iter()
+            .map(|l| l.width())
+            .chain([title.chars().count(), footer.chars().count()])
+            .max()
+            .unwrap_or(0) as u16;
+
+        let area = frame.area();
+        let width = (content_width + 2).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let x = area.x + area.width.saturating_sub(width) / 2;
+        let y = area.y + area.height.saturating_sub(height) / 2;
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .title_bottom(footer)
+            .border_style(Style::default().fg(palette.overlay));
+        // Keep the selection in view when the list is taller than the screen.
+        let visible = height.saturating_sub(2) as usize;
+        let scroll = (self.selected + 1).saturating_sub(visible) as u16;
+        frame.render_widget(
+            Paragraph::new(lines).block(block).scroll((scroll, 0)),
+            Rect::new(x, y, width, height),
+        );
+    }
+
+    /// Runs the picker until a device is picked (`Some(index)`) or it is quit.
+    fn run(mut self, terminal: &mut DefaultTerminal, palette: &Palette) -> Result<Option<usize>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame, palette))?;
+            if !event::poll(Duration::from_millis(250))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match self.handle_key(key.code, key.modifiers) {
+                    Some(Choice::Picked(index)) => return Ok(Some(index)),
+                    Some(Choice::Quit) => return Ok(None),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+/// Picker line for a device: "BlackHole 2ch  [2ch 48000Hz]", plus who is using it.
+fn label(dev: &DeviceInfo) -> String {
+    let tag = dev
+        .usage
+        .tag()
+        .map_or_else(String::new, |tag| format!(" ({tag})"));
+    format!(
+        "{}  [{}ch {}Hz]{tag}",
+        dev.name, dev.input_channels, dev.sample_rate
+    )
+}
+
+/// The input device to use when none was named: the only virtual input
+/// device, or one picked from a list if there are several. `None` if the
+/// picker was quit. With none, the error tells a driver that isn't loaded
+/// from one that isn't installed.
+pub fn choose_input(palette: &Palette, cache: &Mutex<DeviceCache>) -> Result<Option<String>> {
+    let devices = engine::virtual_input_devices(cache);
+    match devices.as_slice() {
+        [] => {
+            let installed =
+                driver_check::blackhole_drivers(Path::new(driver_check::HAL_PLUGIN_DIR));
+            Err(anyhow!(driver_check::missing_device_message(&installed)))
+        }
+        [only] => Ok(Some(only.name.clone())),
+        _ => {
+            let picker = DevicePicker::new(devices.iter().map(label).collect());
+            let mut terminal = ratatui::init();
+            let picked = picker.run(&mut terminal, palette);
+            ratatui::restore();
+            Ok(picked?.map(|index| devices[index].name.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn arrows_move_within_the_list_and_enter_picks() {
+        let mut picker = DevicePicker::new(vec![
+            "BlackHole 2ch  [2ch 48000Hz]".to_string(),
+            "BlackHole 16ch  [16ch 48000Hz]".to_string(),
+            "Loopback Audio  [2ch 44100Hz] (running)".to_string(),
+        ]);
+        let none = KeyModifiers::NONE;
+        assert_eq!(picker.handle_key(KeyCode::Up, none), None);
+        assert_eq!(picker.selected, 0);
+        for _ in 0..5 {
+            picker.handle_key(KeyCode::Down, none);
+        }
+        assert_eq!(picker.selected, 2);
+        picker.handle_key(KeyCode::Char('k'), none);
+        assert_eq!(
+            picker.handle_key(KeyCode::Enter, none),
+            Some(Choice::Picked(1))
+        );
+        assert_eq!(picker.handle_key(KeyCode::Esc, none), Some(Choice::Quit));
+        assert_eq!(
+            picker.handle_key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(Choice::Quit)
+        );
+        assert_eq!(
+            DevicePicker::new(vec![]).handle_key(KeyCode::Enter, none),
+            None
+        );
+    }
+
+    #[test]
+    fn the_selected_device_is_marked() {
+        let mut picker = DevicePicker::new(vec![
+            "BlackHole 2ch  [2ch 48000Hz]".to_string(),
+            "BlackHole 16ch  [16ch 48000Hz]".to_string(),
+            "Loopback Audio  [2ch 44100Hz] (running)".to_string(),
+        ]);
+        picker.handle_key(KeyCode::End, KeyModifiers::NONE);
+        let mut terminal = Terminal::new(TestBackend::new(60, 10)).unwrap();
+        terminal
+            .draw(|frame| picker.draw(frame, &Palette::standard()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..10)
+            .map(|y| (0..60).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect();
+        let row = |text: &str| rows.iter().position(|row| row.contains(text));
+        assert!(row(" Select input device ").is_some());
+        assert!(row("\u{25b6} Loopback Audio").is_some());
+        assert!(row("  BlackHole 2ch").is_some());
+        assert!(row("\u{25b6} BlackHole").is_none());
+    }
+}
@@ -0,0 +1,199 @@
+/// Compact duration for labels: "750ms", "2.5s", "1m05.3s", "1h02m05s".
+/// Trailing ".0" is dropped ("2s", "1m00s"). Non-finite values render as "--".
+pub fn format_duration_ms(ms: f64) -> String {
+    if !ms.is_finite() {
+        return "--".to_string();
+    }
+    let ms = ms.max(0.0);
+    if ms.round() < 1000.0 {
+        return format!("{:.0}ms", ms.round());
+    }
+
+    let tenths = (ms / 100.0).round() as u64;
+    if tenths < 600 {
+        return format!("{}s", tenths_label(tenths));
+    }
+    if tenths < 36_000 {
+        let minutes = tenths / 600;
+        let rem = tenths % 600;
+        let pad = if rem < 100 { "0" } else { "" };
+        return format!("{minutes}m{pad}{}s", tenths_label(rem));
+    }
+
+    let total_s = (ms / 1000.0).round() as u64;
+    format!(
+        "{}h{:02}m{:02}s",
+        total_s / 3600,
+        total_s % 3600 / 60,
+        total_s % 60
+    )
+}
+
+/// Millisecond-precision duration for the delay readout:
+/// "0.150s", "12.345s", "1m05.300s", "1h02m05.300s".
+pub fn format_delay_ms(ms: f64) -> String {
+    if !ms.is_finite() {
+        return "--".to_string();
+    }
+    let millis = ms.max(0.0).round() as u64;
+    let frac = millis % 1000;
+    let total_s = millis / 1000;
+    if total_s < 60 {
+        format!("{total_s}.{frac:03}s")
+    } else if total_s < 3600 {
+        format!("{}m{:02}.{frac:03}s", total_s / 60, total_s % 60)
+    } else {
+        format!(
+            "{}h{:02}m{:02}.{frac:03}s",
+            total_s / 3600,
+            total_s % 3600 / 60,
+            total_s % 60
+        )
+    }
+}
+
+/// Whole-second clock for long-running counters: "0:00:05", "1:02:05", "27:00:00".
+pub fn format_clock(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        return "--".to_string();
+    }
+    let total_s = seconds.max(0.0) as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        total_s / 3600,
+        total_s % 3600 / 60,
+        total_s % 60
+    )
+}
+
+/// Whole count with thousands separators: "950", "12,450,000".
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// `text` cut to `width` characters by replacing its middle with "…", so
+/// both ends stay readable: "Elgato Wa…face #2". Text that fits is returned
+/// as is.
+pub fn ellipsize_middle(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let head = width / 2;
+    let tail = width - 1 - head;
+    let mut out: String = text.chars().take(head).collect();
+    out.push('\u{2026}');
+    out.extend(text.chars().skip(len - tail));
+    out
+}
+
+fn tenths_label(tenths: u64) -> String {
+    match tenths % 10 {
+        0 => format!("{}", tenths / 10),
+        d => format!("{}.{d}", tenths / 10),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_group_thousands() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(950), "950");
+        assert_eq!(format_count(1_000), "1,000");
+        assert_eq!(format_count(12_450_000), "12,450,000");
+        assert_eq!(format_count(u64::MAX), "18,446,744,073,709,551,615");
+    }
+
+    #[test]
+    fn compact_milliseconds() {
+        assert_eq!(format_duration_ms(0.0), "0ms");
+        assert_eq!(format_duration_ms(750.0), "750ms");
+        assert_eq!(format_duration_ms(999.4), "999ms");
+    }
+
+    #[test]
+    fn compact_rounds_up_into_seconds() {
+        assert_eq!(format_duration_ms(999.6), "1s");
+        assert_eq!(format_duration_ms(1000.0), "1s");
+    }
+
+    #[test]
+    fn compact_seconds() {
+        assert_eq!(format_duration_ms(2500.0), "2.5s");
+        assert_eq!(format_duration_ms(2000.0), "2s");
+        assert_eq!(format_duration_ms(59_940.0), "59.9s");
+    }
+
+    #[test]
+    fn compact_rounds_up_into_minutes() {
+        assert_eq!(format_duration_ms(59_960.0), "1m00s");
+    }
+
+    #[test]
+    fn compact_minutes() {
+        assert_eq!(format_duration_ms(65_300.0), "1m05.3s");
+        assert_eq!(format_duration_ms(60_000.0), "1m00s");
+        assert_eq!(format_duration_ms(3_599_900.0), "59m59.9s");
+    }
+
+    #[test]
+    fn compact_hours() {
+        assert_eq!(format_duration_ms(3_600_000.0), "1h00m00s");
+        assert_eq!(format_duration_ms(3_725_000.0), "1h02m05s");
+    }
+
+    #[test]
+    fn compact_degenerate_values() {
+        assert_eq!(format_duration_ms(-5.0), "0ms");
+        assert_eq!(format_duration_ms(f64::NAN), "--");
+        assert_eq!(format_duration_ms(f64::INFINITY), "--");
+    }
+
+    #[test]
+    fn delay_keeps_millisecond_precision() {
+        assert_eq!(format_delay_ms(0.0), "0.000s");
+        assert_eq!(format_delay_ms(150.0), "0.150s");
+        assert_eq!(format_delay_ms(12_345.0), "12.345s");
+        assert_eq!(format_delay_ms(59_999.6), "1m00.000s");
+        assert_eq!(format_delay_ms(65_300.0), "1m05.300s");
+        assert_eq!(format_delay_ms(3_725_300.0), "1h02m05.300s");
+        assert_eq!(format_delay_ms(f64::NAN), "--");
+    }
+
+    #[test]
+    fn long_names_lose_their_middle() {
+        let name = "Elgato Wave:3 (Digital Audio Interface) #2";
+        assert_eq!(ellipsize_middle(name, 18), "Elgato Wa\u{2026}face) #2");
+        assert_eq!(ellipsize_middle(name, 42), name);
+        assert_eq!(ellipsize_middle(name, 41).chars().count(), 41);
+        assert_eq!(
+            ellipsize_middle("\u{00dc}ber-Ger\u{00e4}t", 5),
+            "\u{00dc}b\u{2026}\u{00e4}t"
+        );
+        assert_eq!(ellipsize_middle(name, 1), "\u{2026}");
+        assert_eq!(ellipsize_middle(name, 0), "");
+    }
+
+    #[test]
+    fn clock_counts_hours_past_a_day() {
+        assert_eq!(format_clock(5.9), "0:00:05");
+        assert_eq!(format_clock(3725.0), "1:02:05");
+        assert_eq!(format_clock(97_200.0), "27:00:00");
+        assert_eq!(format_clock(-1.0), "0:00:00");
+        assert_eq!(format_clock(f64::NAN), "--");
+    }
+}
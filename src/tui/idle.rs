@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+/// Idle mode: after `timeout` without a key press, or right away with `I`,
+/// the UI is drawn dimmed and no longer redrawn every frame. Any key or an
+/// alert (xrun, buffer threshold) wakes it.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleMode {
+    /// `--idle-timeout-s`; `None` only dims on `I`.
+    timeout: Option<Duration>,
+    last_activity: Instant,
+    forced: bool,
+}
+
+impl IdleMode {
+    pub fn new(timeout: Option<Duration>, now: Instant) -> Self {
+        Self {
+            timeout,
+            last_activity: now,
+            forced: false,
+        }
+    }
+
+    pub fn is_idle(&self, now: Instant) -> bool {
+        self.forced
+            || self
+                .timeout
+                .is_some_and(|timeout| now.saturating_duration_since(self.last_activity) >= timeout)
+    }
+
+    /// Counts as activity (a key press or an alert). Returns whether it was
+    /// idle, i.e. whether this woke it.
+    pub fn wake(&mut self, now: Instant) -> bool {
+        let was_idle = self.is_idle(now);
+        self.last_activity = now;
+        self.forced = false;
+        was_idle
+    }
+
+    /// Dims right away, until the next key or alert.
+    pub fn dim(&mut self) {
+        self.forced = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dims_after_the_timeout_and_wakes_on_activity() {
+        let start = Instant::now();
+        let at = |s: u64| start + Duration::from_secs(s);
+        let mut idle = IdleMode::new(Some(Duration::from_secs(60)), start);
+        assert!(!idle.is_idle(at(59)));
+        assert!(idle.is_idle(at(60)));
+        assert!(idle.wake(at(61)));
+        assert!(!idle.is_idle(at(120)));
+        assert!(!idle.wake(at(120)));
+        assert!(idle.is_idle(at(180)));
+    }
+
+    #[test]
+    fn dim_holds_until_woken_even_without_a_timeout() {
+        let start = Instant::now();
+        let mut idle = IdleMode::new(None, start);
+        assert!(!idle.is_idle(start + Duration::from_secs(3600)));
+        idle.dim();
+        assert!(idle.is_idle(start));
+        assert!(idle.wake(start));
+        assert!(!idle.is_idle(start + Duration::from_secs(3600)));
+    }
+}
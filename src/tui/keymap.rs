@@ -0,0 +1,501 @@
+use clap::ValueEnum;
+use crossterm::event::{KeyCode, KeyModifiers};
+
+/// Everything a key press can do. `App::handle_key` dispatches on these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    TogglePause,
+    JumpToLive,
+    SeekTowardLive,
+    SeekBack,
+    NumberKey,
+    GoToTime,
+    ToggleSnap,
+    ReplaySegment,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    AutoVolume,
+    InputGainUp,
+    InputGainDown,
+    ToggleDiagnostics,
+    ToggleLatencyBudget,
+    ToggleDeviceDetails,
+    ToggleDebug,
+    ClearStats,
+    CalibrateMeters,
+    ZoomTimelineIn,
+    ZoomTimelineOut,
+    PanTimelineTowardLive,
+    PanTimelineBack,
+    DimScreen,
+    ToggleHelp,
+    SplitRecording,
+    ToggleRecordings,
+    Quit,
+}
+
+/// Help overlay sections, in page order. Empty sections are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Transport,
+    Seeking,
+    Volume,
+    MarkersLoops,
+    Views,
+    System,
+}
+
+impl Section {
+    pub const ALL: [Section; 6] = [
+        Self::Transport,
+        Self::Seeking,
+        Self::Volume,
+        Self::MarkersLoops,
+        Self::Views,
+        Self::System,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Transport => "Transport",
+            Self::Seeking => "Seeking",
+            Self::Volume => "Volume",
+            Self::MarkersLoops => "Markers & Loops",
+            Self::Views => "Views",
+            Self::System => "System",
+        }
+    }
+}
+
+/// What the number keys 1-9 do, chosen with `--number-keys`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum NumberKeys {
+    /// Select the seek step (1ms .. 30s).
+    #[default]
+    SeekScale,
+    /// Jump to bookmark N, oldest first.
+    Bookmarks,
+    /// Volume presets 20% .. 100%.
+    Volume,
+}
+
+impl NumberKeys {
+    /// Short label for the keys bar.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::SeekScale => "scale",
+            Self::Bookmarks => "mark",
+            Self::Volume => "vol%",
+        }
+    }
+
+    /// Volume for digit `n` (1-9) in `Volume` mode: 20% for 1 up to 100% for 9.
+    pub fn volume_preset(n: usize) -> f32 {
+        (n.clamp(1, 9) as f32 + 1.0) * 0.1
+    }
+}
+
+/// Which quit keys ask for confirmation first, chosen with `--confirm-quit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ConfirmQuit {
+    /// Quit immediately.
+    #[default]
+    Never,
+    /// Q asks; Ctrl+C still quits immediately.
+    Q,
+    /// Both Q and Ctrl+C ask.
+    Always,
+}
+
+impl ConfirmQuit {
+    /// Whether the quit key (Ctrl+C if `ctrl_c`, else Q) should ask first.
+    pub fn asks(self, ctrl_c: bool) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Q => !ctrl_c,
+            Self::Always => true,
+        }
+    }
+}
+
+/// A key pattern. `Char` matches case-insensitively and without Ctrl or Alt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    /// The upper-case letter, i.e. with Shift.
+    Shift(char),
+    /// The key without Alt (Option).
+    Code(KeyCode),
+    /// The key with Alt (Option) held.
+    Alt(KeyCode),
+    /// Any of the digits 1-9.
+    Digit,
+}
+
+impl Key {
+    fn matches(self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        let alt = modifiers.contains(KeyModifiers::ALT);
+        match (self, code) {
+            (Self::Char(k), KeyCode::Char(c)) => !ctrl && !alt && k.eq_ignore_ascii_case(&c),
+            (Self::Ctrl(k), KeyCode::Char(c)) => ctrl && k.eq_ignore_ascii_case(&c),
+            (Self::Shift(k), KeyCode::Char(c)) => !ctrl && c == k.to_ascii_uppercase(),
+            (Self::Digit, KeyCode::Char('1'..='9')) => !ctrl,
+            (Self::Code(k), code) => !alt && k == code,
+            (Self::Alt(k), code) => alt && k == code,
+            _ => false,
+        }
+    }
+}
+
+/// One row of the keymap: the keys bound to an action and how help shows it.
+pub struct Binding {
+    pub action: Action,
+    pub section: Section,
+    pub keys: &'static [Key],
+    /// Key column in the help overlay.
+    pub label: &'static str,
+    pub description: &'static str,
+}
+
+/// The keymap. Drives both key dispatch and the help overlay.
+pub const BINDINGS: &[Binding] = &[
+    Binding {
+        action: Action::TogglePause,
+        section: Section::Transport,
+        keys: &[Key::Char(' ')],
+        label: "Space",
+        description: "Pause / Resume playback",
+    },
+    Binding {
+        action: Action::JumpToLive,
+        section: Section::Transport,
+        keys: &[Key::Char('l')],
+        label: "L",
+        description: "Jump to live",
+    },
+    Binding {
+        action: Action::SeekTowardLive,
+        section: Section::Seeking,
+        keys: &[Key::Code(KeyCode::Left)],
+        label: "\u{2190}",
+        description: "Seek forward (toward live) by current step",
+    },
+    Binding {
+        action: Action::SeekBack,
+        section: Section::Seeking,
+        keys: &[Key::Code(KeyCode::Right)],
+        label: "\u{2192}",
+        description: "Seek backward (increase delay) by current step",
+    },
+    Binding {
+        action: Action::NumberKey,
+        section: Section::Seeking,
+        keys: &[Key::Digit],
+        label: "1-9",
+        description: "Seek step 1ms..30s (or as set by --number-keys)",
+    },
+    Binding {
+        action: Action::ReplaySegment,
+        section: Section::Seeking,
+        keys: &[Key::Shift('n')],
+        label: "Shift+N",
+        description: "Replay from start of sound segment",
+    },
+    Binding {
+        action: Action::GoToTime,
+        section: Section::Seeking,
+        keys: &[Key::Char('t')],
+        label: "T",
+        description: "Go to wall-clock time (HH:MM[:SS])",
+    },
+    Binding {
+        action: Action::ToggleSnap,
+        section: Section::Seeking,
+        keys: &[Key::Char('s')],
+        label: "S",
+        description: "Toggle seek snapping (grid or bookmarks)",
+    },
+    Binding {
+        action: Action::VolumeUp,
+        section: Section::Volume,
+        keys: &[Key::Code(KeyCode::Up)],
+        label: "\u{2191}",
+        description: "Volume up (5% steps, max 150%)",
+    },
+    Binding {
+        action: Action::VolumeDown,
+        section: Section::Volume,
+        keys: &[Key::Code(KeyCode::Down)],
+        label: "\u{2193}",
+        description: "Volume down (5% steps)",
+    },
+    Binding {
+        action: Action::ToggleMute,
+        section: Section::Volume,
+        keys: &[Key::Char('m')],
+        label: "M",
+        description: "Toggle mute",
+    },
+    Binding {
+        action: Action::AutoVolume,
+        section: Section::Volume,
+        keys: &[Key::Char('a')],
+        label: "A",
+        description: "Auto volume (loud parts at -14 dBFS)",
+    },
+    Binding {
+        action: Action::InputGainUp,
+        section: Section::Volume,
+        keys: &[Key::Char(']')],
+        label: "]",
+        description: "Input gain up (1 dB, before the buffer)",
+    },
+    Binding {
+        action: Action::InputGainDown,
+        section: Section::Volume,
+        keys: &[Key::Char('[')],
+        label: "[",
+        description: "Input gain down (1 dB)",
+    },
+    Binding {
+        action: Action::ToggleDiagnostics,
+        section: Section::Views,
+        keys: &[Key::Char('d')],
+        label: "D",
+        description: "Toggle diagnostics",
+    },
+    Binding {
+        action: Action::ToggleLatencyBudget,
+        section: Section::Views,
+        keys: &[Key::Char('b')],
+        label: "B",
+        description: "Toggle latency budget",
+    },
+    Binding {
+        action: Action::ToggleDeviceDetails,
+        section: Section::Views,
+        keys: &[Key::Char('v')],
+        label: "V",
+        description: "Toggle device details (full names)",
+    },
+    Binding {
+        action: Action::ToggleDebug,
+        section: Section::Views,
+        keys: &[Key::Ctrl('d')],
+        label: "Ctrl+D",
+        description: "Toggle debug (raw read/write positions)",
+    },
+    Binding {
+        action: Action::ClearStats,
+        section: Section::Views,
+        keys: &[Key::Ctrl('p')],
+        label: "Ctrl+P",
+        description: "Clear stats (peak flags, drops, jumps)",
+    },
+    Binding {
+        action: Action::CalibrateMeters,
+        section: Section::Views,
+        keys: &[Key::Shift('c')],
+        label: "Shift+C",
+        description: "Calibrate meter dB to the reference tone",
+    },
+    // Before `ZoomTimelineIn`: its `z` also matches Shift+Z.
+    Binding {
+        action: Action::ZoomTimelineOut,
+        section: Section::Views,
+        keys: &[Key::Shift('z')],
+        label: "Shift+Z",
+        description: "Zoom timeline out (up to the whole buffer)",
+    },
+    Binding {
+        action: Action::ZoomTimelineIn,
+        section: Section::Views,
+        keys: &[Key::Char('z')],
+        label: "Z",
+        description: "Zoom timeline in (down to 5s per screen)",
+    },
+    // Terminals sending Option+arrows as word moves report Alt+B / Alt+F.
+    Binding {
+        action: Action::PanTimelineTowardLive,
+        section: Section::Views,
+        keys: &[Key::Alt(KeyCode::Left), Key::Alt(KeyCode::Char('b'))],
+        label: "Alt+\u{2190}",
+        description: "Pan timeline toward live",
+    },
+    Binding {
+        action: Action::PanTimelineBack,
+        section: Section::Views,
+        keys: &[Key::Alt(KeyCode::Right), Key::Alt(KeyCode::Char('f'))],
+        label: "Alt+\u{2192}",
+        description: "Pan timeline back (older audio)",
+    },
+    Binding {
+        action: Action::DimScreen,
+        section: Section::Views,
+        keys: &[Key::Char('i')],
+        label: "I",
+        description: "Dim the screen (idle mode) until the next key",
+    },
+    Binding {
+        action: Action::ToggleHelp,
+        section: Section::Views,
+        keys: &[Key::Char('h')],
+        label: "H",
+        description: "Toggle this help",
+    },
+    // Before `SplitRecording`: its `r` also matches Shift+R.
+    Binding {
+        action: Action::ToggleRecordings,
+        section: Section::System,
+        keys: &[Key::Shift('r')],
+        label: "Shift+R",
+        description: "Toggle the list of recorded files (--record-dir)",
+    },
+    Binding {
+        action: Action::SplitRecording,
+        section: Section::System,
+        keys: &[Key::Char('r')],
+        label: "R",
+        description: "Start the next recorded file (--record-dir)",
+    },
+    Binding {
+        action: Action::Quit,
+        section: Section::System,
+        keys: &[Key::Char('q'), Key::Ctrl('c')],
+        label: "Q / Ctrl+C",
+        description: "Quit",
+    },
+];
+
+/// Returns the action bound to a key press, if any.
+pub fn lookup(code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+    BINDINGS
+        .iter()
+        .find(|b| b.keys.iter().any(|k| k.matches(code, modifiers)))
+        .map(|b| b.action)
+}
+
+/// Help pages: one per non-empty section, in section order.
+pub fn help_pages() -> Vec<(Section, Vec<&'static Binding>)> {
+    Section::ALL
+        .iter()
+        .map(|&section| {
+            let bindings = BINDINGS.iter().filter(|b| b.section == section).collect();
+            (section, bindings)
+        })
+        .filter(|(_, bindings): &(Section, Vec<&Binding>)| !bindings.is_empty())
+        .collect()
+}
+
+/// Bindings whose description contains `query` (case-insensitive), in keymap order.
+pub fn search(query: &str) -> Vec<&'static Binding> {
+    let query = query.to_lowercase();
+    BINDINGS
+        .iter()
+        .filter(|b| b.description.to_lowercase().contains(&query))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_binding_key_dispatches_to_its_action() {
+        for binding in BINDINGS {
+            for key in binding.keys {
+                let (code, modifiers) = match *key {
+                    Key::Char(c) => (KeyCode::Char(c), KeyModifiers::NONE),
+                    Key::Ctrl(c) => (KeyCode::Char(c), KeyModifiers::CONTROL),
+                    Key::Shift(c) => (KeyCode::Char(c.to_ascii_uppercase()), KeyModifiers::SHIFT),
+                    Key::Code(code) => (code, KeyModifiers::NONE),
+                    Key::Alt(code) => (code, KeyModifiers::ALT),
+                    Key::Digit => (KeyCode::Char('5'), KeyModifiers::NONE),
+                };
+                assert_eq!(lookup(code, modifiers), Some(binding.action));
+            }
+        }
+    }
+
+    #[test]
+    fn char_keys_ignore_case() {
+        assert_eq!(
+            lookup(KeyCode::Char('L'), KeyModifiers::SHIFT),
+            Some(Action::JumpToLive)
+        );
+    }
+
+    #[test]
+    fn volume_presets_span_20_to_100_percent() {
+        assert!((NumberKeys::volume_preset(1) - 0.2).abs() < 1e-6);
+        assert!((NumberKeys::volume_preset(9) - 1.0).abs() < 1e-6);
+        assert!((NumberKeys::volume_preset(5) - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn confirm_quit_modes() {
+        assert!(!ConfirmQuit::Never.asks(false));
+        assert!(!ConfirmQuit::Never.asks(true));
+        assert!(ConfirmQuit::Q.asks(false));
+        assert!(!ConfirmQuit::Q.asks(true));
+        assert!(ConfirmQuit::Always.asks(true));
+    }
+
+    #[test]
+    fn shift_bindings_need_the_upper_case_letter() {
+        assert_eq!(
+            lookup(KeyCode::Char('N'), KeyModifiers::SHIFT),
+            Some(Action::ReplaySegment)
+        );
+        assert_eq!(lookup(KeyCode::Char('n'), KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn ctrl_does_not_trigger_plain_char_bindings() {
+        assert_eq!(lookup(KeyCode::Char('m'), KeyModifiers::CONTROL), None);
+    }
+
+    #[test]
+    fn alt_arrows_pan_instead_of_seeking() {
+        assert_eq!(
+            lookup(KeyCode::Left, KeyModifiers::ALT),
+            Some(Action::PanTimelineTowardLive)
+        );
+        assert_eq!(
+            lookup(KeyCode::Char('f'), KeyModifiers::ALT),
+            Some(Action::PanTimelineBack)
+        );
+        assert_eq!(
+            lookup(KeyCode::Left, KeyModifiers::NONE),
+            Some(Action::SeekTowardLive)
+        );
+        assert_eq!(
+            lookup(KeyCode::Char('Z'), KeyModifiers::SHIFT),
+            Some(Action::ZoomTimelineOut)
+        );
+    }
+
+    #[test]
+    fn help_pages_skip_empty_sections() {
+        let pages = help_pages();
+        assert!(pages.iter().all(|(_, bindings)| !bindings.is_empty()));
+        let total: usize = pages.iter().map(|(_, b)| b.len()).sum();
+        assert_eq!(total, BINDINGS.len());
+    }
+
+    #[test]
+    fn search_matches_descriptions_case_insensitively() {
+        let found = search("VOLUME");
+        assert!(!found.is_empty());
+        assert!(
+            found
+                .iter()
+                .all(|b| b.description.to_lowercase().contains("volume"))
+        );
+        assert_eq!(search("").len(), BINDINGS.len());
+        assert!(search("no such binding").is_empty());
+    }
+}
@@ -1,2 +1,11 @@
 pub mod app;
+pub mod device_picker;
+pub mod format;
+pub mod idle;
+pub mod keymap;
+pub mod readout;
+pub mod repeat;
+pub mod session;
+pub mod theme;
+pub mod timeline;
 pub mod ui;
@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+/// Floor of the meter dB readout, shown for silence.
+pub const SILENCE_DB: f32 = -96.0;
+
+/// Peak level (linear) to dB, with `SILENCE_DB` for (near) silence.
+pub fn peak_to_db(peak: f32) -> f32 {
+    if peak > 0.0001 {
+        20.0 * peak.log10()
+    } else {
+        SILENCE_DB
+    }
+}
+
+/// Quietest readout a meter calibration can be taken from, in dB.
+pub const CALIBRATION_MIN_DB: f32 = -60.0;
+
+/// Offset that makes a `measured_db` readout show `reference_db`, or `None`
+/// if there is too little signal to calibrate against.
+pub fn calibration_offset(measured_db: f32, reference_db: f32) -> Option<f32> {
+    (measured_db >= CALIBRATION_MIN_DB).then_some(reference_db - measured_db)
+}
+
+/// A readout with the calibration `offset_db` applied. Silence stays at the floor.
+pub fn calibrated(readout_db: f32, offset_db: f32) -> f32 {
+    if readout_db <= SILENCE_DB {
+        readout_db
+    } else {
+        readout_db + offset_db
+    }
+}
+
+/// Smooths the numeric dB readout next to a level meter so it doesn't jitter
+/// every frame. Only the displayed number is smoothed; the bar still follows
+/// the analysis thread's peak directly.
+///
+/// Exponential moving average with time constant `tau`: after `tau` the
+/// readout has covered ~63% of a step, after 3×`tau` ~95%.
+#[derive(Debug, Clone)]
+pub struct SmoothedDb {
+    tau: Duration,
+    value: f32,
+    last_update: Option<Instant>,
+}
+
+impl SmoothedDb {
+    pub fn new(tau: Duration) -> Self {
+        Self {
+            tau,
+            value: SILENCE_DB,
+            last_update: None,
+        }
+    }
+
+    /// Moves the readout toward `target_db` by the time elapsed since the last call.
+    pub fn tick(&mut self, target_db: f32) -> f32 {
+        let now = Instant::now();
+        let dt = self.last_update.map_or(Duration::ZERO, |t| now - t);
+        self.last_update = Some(now);
+        self.step(target_db, dt)
+    }
+
+    /// Moves the readout toward `target_db` as if `dt` had elapsed.
+    pub fn step(&mut self, target_db: f32, dt: Duration) -> f32 {
+        if self.tau.is_zero() {
+            self.value = target_db;
+        } else {
+            let alpha = 1.0 - (-dt.as_secs_f32() / self.tau.as_secs_f32()).exp();
+            self.value += (target_db - self.value) * alpha;
+        }
+        self.value
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(33);
+
+    #[test]
+    fn zero_tau_follows_immediately() {
+        let mut db = SmoothedDb::new(Duration::ZERO);
+        assert_eq!(db.step(-12.0, FRAME), -12.0);
+        assert_eq!(db.step(-3.0, FRAME), -3.0);
+    }
+
+    #[test]
+    fn one_tau_covers_about_63_percent() {
+        let mut db = SmoothedDb {
+            value: -20.0,
+            ..SmoothedDb::new(Duration::from_millis(200))
+        };
+        let v = db.step(-10.0, Duration::from_millis(200));
+        assert!((v - (-20.0 + 10.0 * 0.632)).abs() < 0.01, "{v}");
+    }
+
+    #[test]
+    fn frame_jitter_is_damped() {
+        // Peaks alternating by ±1.5 dB every frame move the readout far less.
+        let mut db = SmoothedDb::new(Duration::from_millis(300));
+        for _ in 0..200 {
+            db.step(-18.0, FRAME);
+        }
+        let mut min = f32::MAX;
+        let mut max = f32::MIN;
+        for i in 0..60 {
+            let target = if i & 1 == 0 { -16.5 } else { -19.5 };
+            let v = db.step(target, FRAME);
+            min = min.min(v);
+            max = max.max(v);
+        }
+        assert!(max - min < 0.5, "swing {}", max - min);
+    }
+
+    #[test]
+    fn steps_settle_within_a_second() {
+        let mut db = SmoothedDb::new(Duration::from_millis(300));
+        for _ in 0..30 {
+            db.step(-6.0, FRAME);
+        }
+        assert!((db.value() - -6.0).abs() < 4.0);
+    }
+
+    #[test]
+    fn calibration_matches_the_reference() {
+        let offset = calibration_offset(-20.5, -18.0).unwrap();
+        assert!((calibrated(-20.5, offset) - -18.0).abs() < 1e-6);
+        assert_eq!(calibrated(SILENCE_DB, offset), SILENCE_DB);
+        assert_eq!(calibration_offset(-70.0, -18.0), None);
+    }
+
+    #[test]
+    fn silence_maps_to_floor() {
+        assert_eq!(peak_to_db(0.0), SILENCE_DB);
+        assert!((peak_to_db(0.5) - -6.02).abs() < 0.01);
+    }
+}
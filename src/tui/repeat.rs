@@ -0,0 +1,169 @@
+use std::time::{Duration, Instant};
+
+use crate::tui::keymap::Action;
+
+/// Presses closer together than this count as a held (auto-repeating) key.
+pub const REPEAT_INTERVAL: Duration = Duration::from_millis(120);
+
+/// A gap this long ends the run and drops back to ×1.
+pub const RESET_GAP: Duration = Duration::from_millis(300);
+
+/// Rapid repeats needed to double the step.
+const REPEATS_PER_LEVEL: u32 = 5;
+
+/// Largest step multiplier.
+pub const MAX_MULTIPLIER: u32 = 8;
+
+/// Seek key acceleration: holding a seek key scales the step ×1, ×2, ×4, ×8.
+///
+/// Every `REPEATS_PER_LEVEL` presses less than `REPEAT_INTERVAL` apart double
+/// the multiplier. Slower presses keep it; a `RESET_GAP` pause, another seek
+/// key or any other key start over at ×1.
+pub struct SeekAccelerator {
+    enabled: bool,
+    /// Key and time of the previous press in the run.
+    last: Option<(Action, Instant)>,
+    /// Rapid repeats in the current run.
+    repeats: u32,
+}
+
+impl SeekAccelerator {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            last: None,
+            repeats: 0,
+        }
+    }
+
+    /// Records a press of seek key `key` at `now`; returns the step multiplier.
+    pub fn press(&mut self, key: Action, now: Instant) -> u32 {
+        if !self.enabled {
+            return 1;
+        }
+        match self.last {
+            Some((last_key, at)) if last_key == key => {
+                let gap = now.saturating_duration_since(at);
+                if gap >= RESET_GAP {
+                    self.repeats = 0;
+                } else if gap < REPEAT_INTERVAL {
+                    self.repeats += 1;
+                }
+            }
+            _ => self.repeats = 0,
+        }
+        self.last = Some((key, now));
+        self.multiplier()
+    }
+
+    /// Ends the run (a non-seek key was pressed).
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.repeats = 0;
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        let level = (self.repeats / REPEATS_PER_LEVEL).min(MAX_MULTIPLIER.ilog2());
+        1 << level
+    }
+
+    /// The multiplier while a run is accelerated and still going, for the status line.
+    pub fn active_multiplier(&self, now: Instant) -> Option<u32> {
+        let (_, at) = self.last?;
+        let multiplier = self.multiplier();
+        (multiplier > 1 && now.saturating_duration_since(at) < RESET_GAP).then_some(multiplier)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_key_doubles_up_to_the_cap() {
+        let mut accel = SeekAccelerator::new(true);
+        // A key held down repeats every 50 ms.
+        let start = Instant::now();
+        let multipliers: Vec<u32> = (0..25)
+            .map(|i| accel.press(Action::SeekBack, start + Duration::from_millis(i * 50)))
+            .collect();
+        assert_eq!(multipliers[..5], [1; 5]);
+        assert_eq!(multipliers[5..10], [2; 5]);
+        assert_eq!(multipliers[10..15], [4; 5]);
+        assert_eq!(multipliers[15..], [8; 10]);
+    }
+
+    #[test]
+    fn slow_presses_hold_and_long_gaps_reset() {
+        let start = Instant::now();
+        let mut accel = SeekAccelerator::new(true);
+        for i in 0..5 {
+            accel.press(Action::SeekBack, start + Duration::from_millis(i * 50));
+        }
+        assert_eq!(
+            accel.press(Action::SeekBack, start + Duration::from_millis(250)),
+            2
+        );
+        // 200 ms later: not a repeat, but the run continues.
+        assert_eq!(
+            accel.press(Action::SeekBack, start + Duration::from_millis(450)),
+            2
+        );
+        // 300 ms gap: back to ×1.
+        assert_eq!(
+            accel.press(Action::SeekBack, start + Duration::from_millis(750)),
+            1
+        );
+    }
+
+    #[test]
+    fn other_keys_reset() {
+        let start = Instant::now();
+        let mut accel = SeekAccelerator::new(true);
+        for i in 0..6 {
+            accel.press(Action::SeekBack, start + Duration::from_millis(i * 50));
+        }
+        assert_eq!(
+            accel.press(Action::SeekTowardLive, start + Duration::from_millis(300)),
+            1
+        );
+
+        for i in 0..6 {
+            accel.press(Action::SeekBack, start + Duration::from_millis(i * 50));
+        }
+        accel.reset();
+        assert_eq!(
+            accel.press(Action::SeekBack, start + Duration::from_millis(300)),
+            1
+        );
+    }
+
+    #[test]
+    fn active_multiplier_fades_after_the_gap() {
+        let start = Instant::now();
+        let mut accel = SeekAccelerator::new(true);
+        for i in 0..6 {
+            accel.press(Action::SeekBack, start + Duration::from_millis(i * 50));
+        }
+        assert_eq!(
+            accel.active_multiplier(start + Duration::from_millis(300)),
+            Some(2)
+        );
+        assert_eq!(
+            accel.active_multiplier(start + Duration::from_millis(600)),
+            None
+        );
+    }
+
+    #[test]
+    fn disabled_never_accelerates() {
+        let mut accel = SeekAccelerator::new(false);
+        // A key held down repeats every 50 ms.
+        let start = Instant::now();
+        let multipliers: Vec<u32> = (0..25)
+            .map(|i| accel.press(Action::SeekBack, start + Duration::from_millis(i * 50)))
+            .collect();
+        assert!(multipliers.iter().all(|&m| m == 1));
+        assert_eq!(accel.active_multiplier(Instant::now()), None);
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Instant;
+
+/// Captured audio lagging wall time by more than this (beyond what sleep
+/// explains) is shown as a warning.
+pub const DRIFT_WARNING_SECONDS: f64 = 60.0;
+
+/// Running time vs. audio captured, for the status counters.
+///
+/// Captured time should track wall time. Capture stops while the Mac sleeps,
+/// so on each wake the current gap becomes the new baseline and only lag
+/// accumulated after that counts as drift.
+pub struct SessionClock {
+    started: Instant,
+    /// Gap (wall - captured) accepted at the last wake.
+    baseline_gap: f64,
+    /// Controller wake count at the last rebase.
+    wakes_seen: usize,
+}
+
+impl SessionClock {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            baseline_gap: 0.0,
+            wakes_seen: 0,
+        }
+    }
+
+    /// Seconds since startup.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    /// Rebases the drift baseline if the system woke since the last call.
+    pub fn observe(&mut self, wakes: usize, elapsed: f64, captured: f64) {
+        if wakes != self.wakes_seen {
+            self.wakes_seen = wakes;
+            self.rebase(elapsed, captured);
+        }
+    }
+
+    /// Accepts the current gap, e.g. while capture hasn't started yet.
+    pub fn rebase(&mut self, elapsed: f64, captured: f64) {
+        self.baseline_gap = elapsed - captured;
+    }
+
+    /// Seconds captured audio lags wall time, excluding sleep.
+    pub fn drift(&self, elapsed: f64, captured: f64) -> f64 {
+        elapsed - captured - self.baseline_gap
+    }
+
+    /// Whether the drift is large enough to flag.
+    pub fn is_drifting(&self, elapsed: f64, captured: f64) -> bool {
+        self.drift(elapsed, captured) > DRIFT_WARNING_SECONDS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_lag_is_not_flagged() {
+        let clock = SessionClock::new();
+        assert!(!clock.is_drifting(3600.0, 3599.0));
+        assert!(!clock.is_drifting(3600.0, 3600.0 - DRIFT_WARNING_SECONDS));
+    }
+
+    #[test]
+    fn lag_past_threshold_is_flagged() {
+        let clock = SessionClock::new();
+        assert!(clock.is_drifting(3600.0, 3600.0 - DRIFT_WARNING_SECONDS - 1.0));
+    }
+
+    #[test]
+    fn sleep_gap_is_forgiven_after_wake() {
+        let mut clock = SessionClock::new();
+        // Slept for an hour: wall 2h, captured 1h.
+        clock.observe(1, 7200.0, 3600.0);
+        assert!(!clock.is_drifting(7200.0, 3600.0));
+        // Later lag still counts.
+        assert!(clock.is_drifting(9000.0, 5300.0));
+    }
+
+    #[test]
+    fn without_a_new_wake_the_baseline_stays() {
+        let mut clock = SessionClock::new();
+        clock.observe(0, 7200.0, 3600.0);
+        assert!(clock.is_drifting(7200.0, 3600.0));
+    }
+}
@@ -0,0 +1,128 @@
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+
+/// Orange from the Okabe-Ito palette, distinguishable under the common color deficiencies.
+const ORANGE: Color = Color::Rgb(230, 159, 0);
+/// Sky blue from the Okabe-Ito palette.
+const SKY_BLUE: Color = Color::Rgb(86, 180, 233);
+
+/// Colors used by the `draw_*` functions. Nothing in the UI hard-codes a color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Level meter below -12 dB.
+    pub meter_low: Color,
+    /// Level meter from -12 dB to -3 dB.
+    pub meter_mid: Color,
+    /// Level meter above -3 dB.
+    pub meter_high: Color,
+    /// Buffer gauge up to 70% full.
+    pub buffer_low: Color,
+    /// Buffer gauge from 70% to 90% full.
+    pub buffer_mid: Color,
+    /// Buffer gauge above 90% full.
+    pub buffer_high: Color,
+    /// Unfilled part of gauges and meters.
+    pub track: Color,
+    pub state_live: Color,
+    pub state_paused: Color,
+    pub state_shifted: Color,
+    /// Keys that currently do nothing (e.g. seeking past a range edge).
+    pub disabled: Color,
+    /// Read position (delay) line under the buffer gauge fill.
+    pub delay: Color,
+    /// Bookmark ticks on the buffer gauge.
+    pub marker: Color,
+    /// Session peak flag on the level meters.
+    pub session_peak: Color,
+    /// Overlay borders.
+    pub overlay: Color,
+    /// Toast border.
+    pub toast: Color,
+    /// Values that suggest something is wrong (e.g. capture drifting from wall time).
+    pub warning: Color,
+}
+
+impl Palette {
+    pub fn standard() -> Self {
+        Self {
+            meter_low: Color::Green,
+            meter_mid: Color::Yellow,
+            meter_high: Color::Red,
+            buffer_low: Color::Blue,
+            buffer_mid: Color::Yellow,
+            buffer_high: Color::Red,
+            track: Color::DarkGray,
+            disabled: Color::DarkGray,
+            state_live: Color::Green,
+            state_paused: Color::Yellow,
+            state_shifted: Color::Cyan,
+            delay: Color::Cyan,
+            marker: Color::Magenta,
+            session_peak: Color::LightRed,
+            overlay: Color::Cyan,
+            toast: Color::Yellow,
+            warning: Color::Yellow,
+        }
+    }
+
+    /// Blue/orange/white scheme, safe for deuteranopia and protanopia.
+    pub fn colorblind() -> Self {
+        Self {
+            meter_low: Color::Blue,
+            meter_mid: ORANGE,
+            meter_high: Color::White,
+            buffer_low: Color::Blue,
+            buffer_mid: ORANGE,
+            buffer_high: Color::White,
+            state_live: SKY_BLUE,
+            state_paused: ORANGE,
+            state_shifted: Color::White,
+            delay: SKY_BLUE,
+            marker: ORANGE,
+            session_peak: SKY_BLUE,
+            overlay: SKY_BLUE,
+            toast: ORANGE,
+            warning: ORANGE,
+            ..Self::standard()
+        }
+    }
+}
+
+/// Idle mode over a drawn frame: dark gray text on black (where a background
+/// was set), nothing bold. Applied to every cell after drawing, so it covers
+/// palette and default colors alike.
+pub fn dim(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        cell.fg = Color::DarkGray;
+        if cell.bg != Color::Reset {
+            cell.bg = Color::Black;
+        }
+        cell.modifier.remove(Modifier::BOLD);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    use super::*;
+
+    #[test]
+    fn dimming_grays_text_and_keeps_unset_backgrounds() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 2, 1));
+        let gauge = Style::default()
+            .fg(Color::Green)
+            .bg(Color::DarkGray)
+            .add_modifier(Modifier::BOLD);
+        buffer.set_string(0, 0, "\u{2588}", gauge);
+        buffer.set_string(1, 0, "x", Style::default());
+        dim(&mut buffer);
+        let (bar, text) = (&buffer[(0, 0)], &buffer[(1, 0)]);
+        assert_eq!(
+            (bar.fg, bar.bg, bar.modifier),
+            (Color::DarkGray, Color::Black, Modifier::empty())
+        );
+        assert_eq!((text.fg, text.bg), (Color::DarkGray, Color::Reset));
+    }
+}
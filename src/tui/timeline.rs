@@ -0,0 +1,194 @@
+use crate::playback::silence::SEGMENT_MS;
+
+/// Narrowest window the timeline zooms in to (5s per screen), in segments.
+pub const MIN_SPAN_SEGMENTS: i64 = 5_000 / SEGMENT_MS as i64;
+
+/// Where the buffer stands, in peak history segments (`SEGMENT_MS` each).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    /// The live edge (segments captured so far).
+    pub live: i64,
+    /// Segments the buffer holds when full.
+    pub capacity: i64,
+    /// The playing segment.
+    pub playhead: i64,
+}
+
+/// Zoom and pan of the timeline panel. Zoom level `z` shows `1 / 2^z` of the
+/// buffer per screen, down to `MIN_SPAN_SEGMENTS`. The window is centered on
+/// the playhead plus the pan offset and never leaves the buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimelineView {
+    zoom: u32,
+    /// Segments the window center sits behind the playhead (negative: toward live).
+    pan: i64,
+}
+
+impl TimelineView {
+    /// Deepest zoom level: the first whose span reaches `MIN_SPAN_SEGMENTS`.
+    fn max_zoom(capacity: i64) -> u32 {
+        let mut zoom = 0;
+        while capacity >> zoom > MIN_SPAN_SEGMENTS && zoom < 62 {
+            zoom += 1;
+        }
+        zoom
+    }
+
+    /// Segments per screen at the current zoom.
+    pub fn span(&self, capacity: i64) -> i64 {
+        let capacity = capacity.max(1);
+        (capacity >> self.zoom).max(MIN_SPAN_SEGMENTS.min(capacity))
+    }
+
+    /// Zooms in one level around the playhead; false at the deepest level.
+    pub fn zoom_in(&mut self, capacity: i64) -> bool {
+        if self.zoom >= Self::max_zoom(capacity) {
+            return false;
+        }
+        self.zoom += 1;
+        self.pan = 0;
+        true
+    }
+
+    /// Zooms out one level around the playhead; false at the whole buffer.
+    pub fn zoom_out(&mut self) -> bool {
+        if self.zoom == 0 {
+            return false;
+        }
+        self.zoom -= 1;
+        self.pan = 0;
+        true
+    }
+
+    /// Moves the window by `steps` half screens (positive: back in time),
+    /// stopping at the buffer ends. False if it couldn't move.
+    pub fn pan(&mut self, steps: i64, extent: Extent) -> bool {
+        let before = self.window(extent).0;
+        self.pan += steps * (self.span(extent.capacity) / 2).max(1);
+        // Keep the offset in range so panning back from an end is immediate.
+        let newest = self.window(extent).0;
+        self.pan = self.center_offset(newest, extent);
+        newest != before
+    }
+
+    /// The visible window as (newest segment, span): the window covers
+    /// `newest - span .. newest`, drawn with `newest` in column 0 like the
+    /// buffer gauge. `newest` is at most the live edge.
+    pub fn window(&self, extent: Extent) -> (i64, i64) {
+        let span = self.span(extent.capacity);
+        let newest_allowed = extent.live;
+        let oldest_allowed = extent.live - extent.capacity.max(span) + span;
+        let newest = (extent.playhead - self.pan + span / 2).clamp(oldest_allowed, newest_allowed);
+        (newest, span)
+    }
+
+    fn center_offset(&self, newest: i64, extent: Extent) -> i64 {
+        extent.playhead + self.span(extent.capacity) / 2 - newest
+    }
+}
+
+/// Segments `from..to` drawn in `column` of `width` for a window ending at
+/// `newest`: column 0 holds the newest audio.
+pub fn column_segments(column: usize, width: usize, newest: i64, span: i64) -> (i64, i64) {
+    let width = width.max(1) as i64;
+    let column = column as i64;
+    let to = newest - column * span / width;
+    let from = newest - (column + 1) * span / width;
+    (from, to)
+}
+
+/// Column of `width` that shows `segment`, if it is in the window.
+pub fn segment_column(segment: i64, width: usize, newest: i64, span: i64) -> Option<usize> {
+    // The inverse of `column_segments`: the column whose range holds it.
+    let behind = newest - segment;
+    if behind < 1 || behind > span {
+        return None;
+    }
+    Some(((behind * width as i64 + span - 1) / span - 1) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ten minutes of buffer, full, playing 30s behind live.
+    const EXTENT: Extent = Extent {
+        live: 100_000,
+        capacity: 60_000,
+        playhead: 97_000,
+    };
+
+    #[test]
+    fn zoom_halves_the_span_down_to_five_seconds() {
+        let mut view = TimelineView::default();
+        assert_eq!(view.span(EXTENT.capacity), 60_000);
+        let mut spans = vec![];
+        while view.zoom_in(EXTENT.capacity) {
+            spans.push(view.span(EXTENT.capacity));
+        }
+        assert_eq!(spans, [30_000, 15_000, 7_500, 3_750, 1_875, 937, 500]);
+        assert!(view.zoom_out());
+        assert_eq!(view.span(EXTENT.capacity), 937);
+        // A buffer shorter than the minimum never zooms.
+        assert!(!TimelineView::default().zoom_in(300));
+    }
+
+    #[test]
+    fn window_follows_the_playhead_and_stays_in_the_buffer() {
+        let mut view = TimelineView::default();
+        assert_eq!(view.window(EXTENT), (100_000, 60_000));
+        view.zoom_in(EXTENT.capacity);
+        view.zoom_in(EXTENT.capacity);
+        // 15_000 wide, centered on the playhead would pass live: pinned to it.
+        assert_eq!(view.window(EXTENT), (100_000, 15_000));
+        let far_back = Extent {
+            playhead: 60_000,
+            ..EXTENT
+        };
+        assert_eq!(view.window(far_back), (67_500, 15_000));
+        let oldest = Extent {
+            playhead: 40_000,
+            ..EXTENT
+        };
+        assert_eq!(view.window(oldest), (55_000, 15_000));
+    }
+
+    #[test]
+    fn panning_moves_half_screens_and_stops_at_the_ends() {
+        let mut view = TimelineView::default();
+        view.zoom_in(EXTENT.capacity);
+        view.zoom_in(EXTENT.capacity);
+        assert!(!view.pan(-1, EXTENT));
+        assert!(view.pan(1, EXTENT));
+        assert_eq!(view.window(EXTENT).0, 92_500);
+        for _ in 0..10 {
+            view.pan(1, EXTENT);
+        }
+        assert_eq!(view.window(EXTENT).0, 55_000);
+        assert!(!view.pan(1, EXTENT));
+        // Coming back starts moving right away.
+        assert!(view.pan(-1, EXTENT));
+        assert_eq!(view.window(EXTENT).0, 62_500);
+        // Zooming recenters on the playhead.
+        view.zoom_out();
+        assert_eq!(view.window(EXTENT), (100_000, 30_000));
+    }
+
+    #[test]
+    fn columns_cover_the_window_and_find_the_playhead() {
+        let (newest, span) = (1_000, 400);
+        assert_eq!(column_segments(0, 80, newest, span), (995, 1_000));
+        assert_eq!(column_segments(79, 80, newest, span), (600, 605));
+        for width in [80, 7, 333] {
+            for segment in 600..1_000 {
+                let column = segment_column(segment, width, newest, span).unwrap();
+                assert!(column < width);
+                let (from, to) = column_segments(column, width, newest, span);
+                assert!((from..to).contains(&segment), "{segment} in {column}");
+            }
+        }
+        assert_eq!(segment_column(999, 80, newest, span), Some(0));
+        assert_eq!(segment_column(1_000, 80, newest, span), None);
+        assert_eq!(segment_column(599, 80, newest, span), None);
+    }
+}
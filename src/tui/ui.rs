@@ -1,61 +1,190 @@
+use std::time::Instant;
+
+use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Block, Borders, Clear, Gauge, Paragraph};
-use ratatui::Frame;
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
 
+use crate::audio::latency::LatencyBudget;
+use crate::playback::controller::MAX_CALLBACK_FRAMES;
+use crate::playback::loudness;
+use crate::playback::silence::{PeakHistory, SEGMENT_MS};
+use crate::playback::snap::Snap;
 use crate::playback::state::PlaybackState;
 use crate::tui::app::{App, SEEK_SCALES};
+use crate::tui::format::{
+    ellipsize_middle, format_clock, format_count, format_delay_ms, format_duration_ms,
+};
+use crate::tui::keymap;
+use crate::tui::readout::{calibrated, peak_to_db};
+use crate::tui::theme::{self, Palette};
+use crate::tui::timeline::{self, Extent};
 
-pub fn draw(frame: &mut Frame, app: &App) {
+/// Smallest terminal the full layout is drawn in.
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 10;
+
+/// Whether a `width` x `height` terminal is big enough for [`draw`].
+pub fn fits(width: u16, height: u16) -> bool {
+    width >= MIN_WIDTH && height >= MIN_HEIGHT
+}
+
+/// A panel of the main layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Panel {
+    Status,
+    Buffer,
+    Timeline,
+    Levels,
+    Devices,
+    /// Only while `--record-dir` is recording.
+    Session,
+    Keys,
+}
+
+impl Panel {
+    /// Panels in the order they're dropped last to first as the terminal
+    /// gets shorter. `MIN_HEIGHT` fits the first three.
+    const PRIORITY: [Panel; 7] = [
+        Self::Status,
+        Self::Levels,
+        Self::Buffer,
+        Self::Devices,
+        Self::Keys,
+        Self::Timeline,
+        Self::Session,
+    ];
+
+    fn height(self) -> u16 {
+        match self {
+            Self::Levels | Self::Timeline => 4,
+            _ => 3,
+        }
+    }
+}
+
+/// Panels that fit in `height` rows, taken in priority order and returned
+/// in display (top to bottom) order. The Session panel only counts while
+/// `recording`.
+pub fn visible_panels(height: u16, recording: bool) -> Vec<Panel> {
+    let mut remaining = height;
+    let mut panels: Vec<Panel> = Panel::PRIORITY
+        .into_iter()
+        .filter(|&panel| recording || panel != Panel::Session)
+        .take_while(|panel| {
+            let fits = panel.height() <= remaining;
+            remaining = remaining.saturating_sub(panel.height());
+            fits
+        })
+        .collect();
+    panels.sort();
+    panels
+}
+
+/// Centered fallback message for terminals too small for the layout.
+pub fn draw_too_small(frame: &mut Frame) {
     let area = frame.area();
+    let message = format!("Terminal too small \u{2014} need {MIN_WIDTH}\u{00d7}{MIN_HEIGHT}");
+    let y = area.y + area.height.saturating_sub(1) / 2;
+    let line = Rect::new(area.x, y, area.width, area.height.min(1));
+    frame.render_widget(Paragraph::new(message).centered(), line);
+}
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3), // Status
-            Constraint::Length(3), // Buffer gauge
-            Constraint::Length(4), // Level meters
-            Constraint::Length(3), // Device info
-            Constraint::Length(3), // Keys
-            Constraint::Min(0),    // Spacer
-        ])
-        .split(area);
+/// Draws the main layout, dropping panels the terminal has no room for, or
+/// just a message below `MIN_WIDTH` x `MIN_HEIGHT`.
+pub fn draw(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    if !fits(area.width, area.height) {
+        draw_too_small(frame);
+        return;
+    }
 
-    draw_status(frame, chunks[0], app);
-    draw_buffer_gauge(frame, chunks[1], app);
-    draw_levels(frame, chunks[2], app);
-    draw_device_info(frame, chunks[3], app);
-    draw_keys(frame, chunks[4], app);
+    let recording = app.recorder.is_some() && app.controller.is_recording();
+    let panels = visible_panels(area.height, recording);
+    let constraints = panels
+        .iter()
+        .map(|panel| Constraint::Length(panel.height()))
+        .chain([Constraint::Min(0)]); // Spacer
+    let chunks = Layout::vertical(constraints).split(area);
+    for (&panel, &chunk) in panels.iter().zip(chunks.iter()) {
+        match panel {
+            Panel::Status => draw_status(frame, chunk, app),
+            Panel::Buffer => {
+                draw_buffer_gauge(frame, chunk, app);
+                draw_delay_line(frame, chunk, app);
+                draw_timeline(frame, chunk, app);
+            }
+            Panel::Timeline => draw_timeline_panel(frame, chunk, app),
+            Panel::Levels => draw_levels(frame, chunk, app),
+            Panel::Devices => draw_device_info(frame, chunk, app),
+            Panel::Session => draw_session_info(frame, chunk, app),
+            Panel::Keys => draw_keys(frame, chunk, app),
+        }
+    }
 
+    if let Some((toast, _)) = &app.toast {
+        draw_toast(frame, area, toast, &app.palette);
+    }
+    if app.show_diagnostics {
+        draw_diagnostics_overlay(frame, area, app);
+    }
+    if app.show_latency_budget {
+        draw_latency_budget(frame, area, app);
+    }
+    if app.show_device_details {
+        draw_device_details(frame, area, app);
+    }
+    if app.show_recordings {
+        draw_recordings(frame, area, app);
+    }
+    if app.show_debug {
+        draw_debug(frame, area, app);
+    }
+    if let Some(input) = &app.time_prompt {
+        draw_time_prompt(frame, area, input, &app.palette);
+    }
+    if app.quit_prompt {
+        draw_quit_prompt(frame, area, &app.palette);
+    }
     if app.show_help {
-        draw_help_overlay(frame, area);
+        draw_help_overlay(
+            frame,
+            area,
+            app.help_page,
+            app.help_scroll,
+            &app.help_search.query,
+            &app.palette,
+        );
+    }
+    if app.dimmed {
+        theme::dim(frame.buffer_mut());
     }
 }
 
 fn draw_status(frame: &mut Frame, area: Rect, app: &App) {
     let state = app.controller.state();
-    let delay_s = app.controller.delay_ms() / 1000.0;
+    let delay = format_delay_ms(app.controller.delay_ms());
     let usage = app.controller.buffer_usage() * 100.0;
     let scale_label = SEEK_SCALES[app.seek_scale_index].1;
 
     let state_style = match state {
         PlaybackState::Live => Style::default()
-            .fg(Color::Green)
+            .fg(app.palette.state_live)
             .add_modifier(Modifier::BOLD),
         PlaybackState::Paused => Style::default()
-            .fg(Color::Yellow)
+            .fg(app.palette.state_paused)
             .add_modifier(Modifier::BOLD),
         PlaybackState::TimeShifted => Style::default()
-            .fg(Color::Cyan)
+            .fg(app.palette.state_shifted)
             .add_modifier(Modifier::BOLD),
     };
 
-    let line = Line::from(vec![
+    let mut line = Line::from(vec![
         Span::raw("  State: "),
-        Span::styled(format!("{} {}", state.symbol(), state.label()), state_style),
+        Span::styled(state.to_string(), state_style),
         Span::raw(format!(
-            "{:width$}Delay: {delay_s:>6.3}s",
+            "{:width$}Delay: {delay:>9}",
             "",
             width = 14 - state.label().len()
         )),
@@ -67,38 +196,326 @@ fn draw_status(frame: &mut Frame, area: Rect, app: &App) {
         }),
         Span::raw(format!("   Step: {scale_label:>4}")),
     ]);
+    let input_gain = app.controller.input_gain_db();
+    if input_gain != 0.0 {
+        line.push_span(Span::raw(format!("   In: {input_gain:+.1}dB")));
+    }
+    if let Snap::Grid(_) | Snap::Bookmarks = app.controller.snap() {
+        line.push_span(Span::raw(format!(
+            "   Snap: {}",
+            app.controller.snap().label()
+        )));
+    }
+    if let Some(airplay) = &app.airplay {
+        line.push_span(Span::styled(
+            format!("   {airplay}"),
+            Style::default().fg(app.palette.overlay),
+        ));
+    }
+    if let Some(channel) = app.controller.solo() {
+        line.push_span(Span::styled(
+            format!("   SOLO ch{channel}"),
+            Style::default()
+                .fg(app.palette.warning)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    if let Some(multiplier) = app.seek_accel.active_multiplier(Instant::now()) {
+        line.push_span(Span::styled(
+            format!("   seeking \u{00d7}{multiplier}"),
+            Style::default().fg(app.palette.overlay),
+        ));
+    }
+
+    // Running time and audio captured; captured lagging far behind means gaps.
+    let elapsed = app.session.elapsed_seconds();
+    let captured = app.controller.captured_seconds();
+    let captured_style = if app.session.is_drifting(elapsed, captured) {
+        Style::default().fg(app.palette.warning)
+    } else {
+        Style::default()
+    };
+    let captured_span = match app.controller.capture_pending_ms() {
+        Some(ms) => Span::styled(
+            format!("Starting capture in {:.1}s", ms / 1000.0),
+            Style::default().fg(app.palette.warning),
+        ),
+        None => Span::styled(
+            format!("Captured {}", format_clock(captured)),
+            captured_style,
+        ),
+    };
+    let mut counters = Line::from(vec![
+        Span::raw(format!(" Up {} \u{00b7} ", format_clock(elapsed))),
+        captured_span,
+        Span::raw(" "),
+    ])
+    .right_aligned();
+    if app.is_slow() {
+        counters.push_span(Span::styled(
+            "[slow] ",
+            Style::default().fg(app.palette.disabled),
+        ));
+    }
 
-    let block = Block::default().borders(Borders::ALL).title(" Shifter ");
+    let title = match &app.instance_name {
+        Some(name) => format!(" Shifter \u{00b7} {name} "),
+        None => " Shifter ".to_string(),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .title(counters);
     let paragraph = Paragraph::new(line).block(block);
     frame.render_widget(paragraph, area);
 }
 
-fn draw_buffer_gauge(frame: &mut Frame, area: Rect, app: &App) {
-    let usage = app.controller.buffer_usage();
-    let buf_max = app.buffer_seconds as f64;
-    let delay_s = (app.controller.delay_ms() / 1000.0).min(buf_max);
-
-    let color = if usage > 0.9 {
-        Color::Red
+/// Buffer gauge color for `usage` (0.0..1.0 of the buffer filled).
+fn buffer_color(usage: f64, palette: &Palette) -> Color {
+    if usage > 0.9 {
+        palette.buffer_high
     } else if usage > 0.7 {
-        Color::Yellow
+        palette.buffer_mid
     } else {
-        Color::Blue
-    };
+        palette.buffer_low
+    }
+}
+
+/// Draws the buffer gauge as a waveform thumbnail of the whole buffer, newest
+/// on the left like the delay line: a bar per column for the peak of its
+/// slice, in the usage color, the playhead column marked and the delay /
+/// total label on top. Columns not captured yet show the bare track.
+fn draw_buffer_gauge(frame: &mut Frame, area: Rect, app: &App) {
+    // The memory cost of `--buffer-seconds`.
+    let title = format!(" Buffer ({:.1} MB) ", app.controller.ring.capacity_mb());
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    if inner.width == 0 || inner.height == 0 {
+        return;
+    }
+
+    let palette = &app.palette;
+    let buf_max_ms = app.buffer_seconds as f64 * 1000.0;
+    let delay_ms = app.controller.delay_ms().min(buf_max_ms);
+    let label = format!(
+        "{} / {}",
+        format_duration_ms(delay_ms),
+        format_duration_ms(buf_max_ms)
+    );
+    let width = inner.width as usize;
+    let label_start = width.saturating_sub(label.chars().count()) / 2;
+    let mut label_chars = label.chars();
+
+    let track = Style::default().bg(palette.track);
+    let wave_style = track.fg(buffer_color(app.controller.buffer_usage(), palette));
+    let playhead_style = track.fg(palette.delay).add_modifier(Modifier::BOLD);
+    let thumbnail = buffer_thumbnail(&app.controller.peak_history, app.timeline_extent(), width);
+    let spans: Vec<Span> = thumbnail
+        .into_iter()
+        .enumerate()
+        .map(|(column, cell)| {
+            if column >= label_start
+                && let Some(c) = label_chars.next()
+            {
+                return Span::styled(c.to_string(), track);
+            }
+            match cell {
+                ThumbnailCell::Empty => Span::styled(" ", track),
+                ThumbnailCell::Peak(peak) => Span::styled(thumbnail_glyph(peak), wave_style),
+                ThumbnailCell::Playhead => Span::styled("\u{2503}", playhead_style),
+            }
+        })
+        .collect();
+
+    let y = inner.y + (inner.height - 1) / 2;
+    for row in inner.y..inner.y + inner.height {
+        let line = if row == y {
+            Line::from(spans.clone())
+        } else {
+            Line::styled(" ".repeat(width), track)
+        };
+        frame.render_widget(
+            Paragraph::new(line),
+            Rect::new(inner.x, row, inner.width, 1),
+        );
+    }
+}
+
+/// One column of the buffer gauge's waveform thumbnail.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ThumbnailCell {
+    /// Nothing captured this far back yet.
+    Empty,
+    /// The loudest peak in the column's slice.
+    Peak(f32),
+    Playhead,
+}
+
+/// Columns of the buffer gauge thumbnail: the whole buffer across `width`,
+/// anchored at the live edge so older audio slides right as the ring wraps.
+/// Each column reads the peak pyramid, so a frame costs a few loads per
+/// column whatever the buffer length.
+fn buffer_thumbnail(history: &PeakHistory, extent: Extent, width: usize) -> Vec<ThumbnailCell> {
+    let oldest = (extent.live - extent.capacity).max(0);
+    let playhead = timeline::segment_column(extent.playhead, width, extent.live, extent.capacity);
+    (0..width)
+        .map(|column| {
+            if Some(column) == playhead {
+                return ThumbnailCell::Playhead;
+            }
+            let (from, to) = timeline::column_segments(column, width, extent.live, extent.capacity);
+            if to <= oldest {
+                ThumbnailCell::Empty
+            } else {
+                ThumbnailCell::Peak(history.max_peak(from.max(0) as usize, to as usize))
+            }
+        })
+        .collect()
+}
+
+/// Like `waveform_glyph`, but silence still shows the lowest bar, so the
+/// captured part of the buffer reads apart from the empty rest.
+fn thumbnail_glyph(peak: f32) -> &'static str {
+    match waveform_glyph(peak) {
+        " " => "\u{2581}",
+        bar => bar,
+    }
+}
+
+/// Draws the current delay as a line along the bottom border of the buffer
+/// gauge, so the read position reads apart from the occupancy fill above it.
+fn draw_delay_line(frame: &mut Frame, area: Rect, app: &App) {
+    if area.width < 3 || area.height < 3 {
+        return;
+    }
+    let buf_max_ms = app.buffer_seconds as f64 * 1000.0;
+    let inner_width = (area.width - 2) as usize;
+    let columns = delay_columns(app.controller.delay_ms(), buf_max_ms, inner_width);
+    let y = area.y + area.height - 1;
+    let style = Style::default().fg(app.palette.delay);
+    for col in 0..columns {
+        if let Some(cell) = frame.buffer_mut().cell_mut((area.x + 1 + col as u16, y)) {
+            cell.set_symbol("\u{2501}").set_style(style);
+        }
+    }
+}
+
+/// Columns of the delay line: `delay_ms / buf_max_ms` of `width`, at least
+/// one whenever there is any delay.
+fn delay_columns(delay_ms: f64, buf_max_ms: f64, width: usize) -> usize {
+    if delay_ms <= 0.0 || buf_max_ms <= 0.0 {
+        return 0;
+    }
+    let ratio = (delay_ms / buf_max_ms).min(1.0);
+    ((ratio * width as f64).round() as usize).clamp(1, width)
+}
 
-    let gauge = Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title(" Buffer "))
-        .gauge_style(Style::default().fg(color).bg(Color::DarkGray))
-        .ratio(usage.clamp(0.0, 1.0))
-        .label(format!("{delay_s:.1}s / {buf_max:.0}s"));
+/// Draws bookmarks as tick marks on the bottom border of the buffer gauge.
+/// Columns map delay behind live to the gauge width, like the fill itself.
+fn draw_timeline(frame: &mut Frame, area: Rect, app: &App) {
+    if area.width < 3 || area.height < 3 {
+        return;
+    }
+    let ring = &app.controller.ring;
+    let wp = ring.write_position();
+    let capacity = ring.capacity().max(1);
+    let inner_width = (area.width - 2) as usize;
+    let y = area.y + area.height - 1;
+    let tick_style = Style::default().fg(app.palette.marker);
 
-    frame.render_widget(gauge, area);
+    for pos in app.controller.bookmarks() {
+        let delay = wp.saturating_sub(pos);
+        let col = (delay * inner_width / capacity).min(inner_width - 1);
+        if let Some(cell) = frame.buffer_mut().cell_mut((area.x + 1 + col as u16, y)) {
+            cell.set_symbol("\u{2534}").set_style(tick_style);
+        }
+    }
+}
+
+/// Draws the zoomable timeline: a waveform of the window the zoom and pan
+/// show, live on the left like the buffer gauge, over a minimap of the whole
+/// buffer with that window highlighted. Both mark the playhead.
+fn draw_timeline_panel(frame: &mut Frame, area: Rect, app: &App) {
+    let extent = app.timeline_extent();
+    let (newest, span) = app.timeline.window(extent);
+    let title = format!(
+        " Timeline  {}/screen ",
+        format_duration_ms((span * SEGMENT_MS as i64) as f64)
+    );
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    if inner.width == 0 || inner.height < 2 {
+        return;
+    }
+
+    let width = inner.width as usize;
+    let history = &app.controller.peak_history;
+    let oldest = (extent.live - extent.capacity).max(0);
+    let wave_style = Style::default().fg(app.palette.meter_low);
+    let playhead_style = Style::default()
+        .fg(app.palette.delay)
+        .add_modifier(Modifier::BOLD);
+    let playhead = timeline::segment_column(extent.playhead, width, newest, span);
+    let wave: Vec<Span> = (0..width)
+        .map(|column| {
+            if Some(column) == playhead {
+                return Span::styled("\u{2503}", playhead_style);
+            }
+            let (from, to) = timeline::column_segments(column, width, newest, span);
+            if to <= oldest {
+                return Span::raw(" ");
+            }
+            let peak = history.max_peak(from.max(0) as usize, to as usize);
+            Span::styled(waveform_glyph(peak), wave_style)
+        })
+        .collect();
+
+    // The minimap spans the whole buffer, newest on the left.
+    let window_style = Style::default().fg(app.palette.overlay);
+    let outside_style = Style::default().fg(app.palette.disabled);
+    let minimap_playhead =
+        timeline::segment_column(extent.playhead, width, extent.live, extent.capacity);
+    let minimap: Vec<Span> = (0..width)
+        .map(|column| {
+            if Some(column) == minimap_playhead {
+                return Span::styled("\u{2503}", playhead_style);
+            }
+            let (from, to) = timeline::column_segments(column, width, extent.live, extent.capacity);
+            if from < newest && to > newest - span {
+                Span::styled("\u{2501}", window_style)
+            } else {
+                Span::styled("\u{2500}", outside_style)
+            }
+        })
+        .collect();
+
+    let lines = vec![Line::from(wave), Line::from(minimap)];
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Bar for a timeline column's peak, on a 48 dB scale; blank below it.
+fn waveform_glyph(peak: f32) -> &'static str {
+    const BARS: [&str; 9] = [
+        " ", "\u{2581}", "\u{2582}", "\u{2583}", "\u{2584}", "\u{2585}", "\u{2586}", "\u{2587}",
+        "\u{2588}",
+    ];
+    let db = peak_to_db(peak);
+    let level = ((db + 48.0) / 48.0 * 8.0).ceil().clamp(0.0, 8.0);
+    BARS[level as usize]
 }
 
 fn draw_levels(frame: &mut Frame, area: Rect, app: &App) {
     let (peak_l, peak_r) = app.controller.peak_levels();
 
-    let block = Block::default().borders(Borders::ALL).title(" Levels ");
+    let calibration = app.prefs.meter_calibration_db;
+    let title = if calibration == 0.0 {
+        " Levels ".to_string()
+    } else {
+        format!(" Levels (cal {calibration:+.1} dB) ")
+    };
+    let block = Block::default().borders(Borders::ALL).title(title);
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
@@ -108,26 +525,39 @@ fn draw_levels(frame: &mut Frame, area: Rect, app: &App) {
         .constraints([Constraint::Length(1), Constraint::Length(1)])
         .split(inner);
 
-    draw_meter(frame, rows[0], "L", peak_l);
-    draw_meter(frame, rows[1], "R", peak_r);
+    let readout_l = calibrated(app.meter_readout[0].value(), calibration);
+    let readout_r = calibrated(app.meter_readout[1].value(), calibration);
+    let (session_l, session_r) = app.controller.meters.session_peaks();
+    let palette = &app.palette;
+    draw_meter(frame, rows[0], "L", peak_l, session_l, readout_l, palette);
+    draw_meter(frame, rows[1], "R", peak_r, session_r, readout_r, palette);
 }
 
-fn draw_meter(frame: &mut Frame, area: Rect, label: &str, peak: f32) {
-    let db = if peak > 0.0001 {
-        20.0 * peak.log10()
-    } else {
-        -96.0
-    };
+/// Meter position of a peak: -60dB..0dB mapped to 0.0..1.0.
+fn meter_ratio(peak: f32) -> f64 {
+    ((peak_to_db(peak) + 60.0) / 60.0).clamp(0.0, 1.0) as f64
+}
 
-    // Map -60dB..0dB to 0.0..1.0
-    let ratio = ((db + 60.0) / 60.0).clamp(0.0, 1.0) as f64;
+/// Draws one meter row. The bar follows `peak`, with a flag at `session_peak`
+/// (if any); the number shows `readout_db`, the smoothed value held by `App`.
+fn draw_meter(
+    frame: &mut Frame,
+    area: Rect,
+    label: &str,
+    peak: f32,
+    session_peak: f32,
+    readout_db: f32,
+    palette: &Palette,
+) {
+    let db = peak_to_db(peak);
+    let ratio = meter_ratio(peak);
 
     let color = if db > -3.0 {
-        Color::Red
+        palette.meter_high
     } else if db > -12.0 {
-        Color::Yellow
+        palette.meter_mid
     } else {
-        Color::Green
+        palette.meter_low
     };
 
     let cols = Layout::default()
@@ -142,37 +572,228 @@ fn draw_meter(frame: &mut Frame, area: Rect, label: &str, peak: f32) {
     let lbl = Paragraph::new(format!(" {label}"));
     frame.render_widget(lbl, cols[0]);
 
-    let gauge = Gauge::default()
-        .gauge_style(Style::default().fg(color).bg(Color::DarkGray))
-        .ratio(ratio);
-    frame.render_widget(gauge, cols[1]);
+    draw_precise_meter(frame, cols[1], ratio, color, palette.track);
+
+    if session_peak > 0.0 && cols[1].width > 0 {
+        let col = (meter_ratio(session_peak) * (cols[1].width - 1) as f64).round() as u16;
+        // Keep the cell's background so the flag sits on the bar or the track.
+        if let Some(cell) = frame.buffer_mut().cell_mut((cols[1].x + col, cols[1].y)) {
+            cell.set_symbol("\u{25b6}").set_fg(palette.session_peak);
+        }
+    }
 
-    let db_text = Paragraph::new(format!(" {db:>5.0} dB"));
+    let db_text = Paragraph::new(format!(" {readout_db:>5.0} dB"));
     frame.render_widget(db_text, cols[2]);
 }
 
+/// Block elements from one eighth of a cell to a full one.
+const EIGHTHS: [char; 8] = [
+    '\u{258f}', '\u{258e}', '\u{258d}', '\u{258c}', '\u{258b}', '\u{258a}', '\u{2589}', '\u{2588}',
+];
+
+/// A `width`-cell bar filled to `ratio` in eighths of a cell: full blocks,
+/// one partial block, then spaces.
+fn precise_bar(ratio: f64, width: usize) -> String {
+    let eighths = (ratio.clamp(0.0, 1.0) * width as f64 * 8.0).round() as usize;
+    let (full, partial) = (eighths / 8, eighths % 8);
+    let mut bar = EIGHTHS[7].to_string().repeat(full);
+    if let Some(i) = partial.checked_sub(1) {
+        bar.push(EIGHTHS[i]);
+    }
+    let fill = width - bar.chars().count();
+    bar.push_str(&" ".repeat(fill));
+    bar
+}
+
+/// Draws a meter bar at eight times a `Gauge`'s resolution: the partial
+/// block's unfilled part shows the track color behind it.
+fn draw_precise_meter(frame: &mut Frame, area: Rect, ratio: f64, color: Color, track: Color) {
+    let bar = precise_bar(ratio, area.width as usize);
+    let style = Style::default().fg(color).bg(track);
+    frame.render_widget(Paragraph::new(Line::styled(bar, style)), area);
+}
+
 fn draw_device_info(frame: &mut Frame, area: Rect, app: &App) {
-    let line = Line::from(format!(
-        "  In: {}    Out: {}",
-        app.input_device_name, app.output_device_name
-    ));
+    // Two spaces, "In: ", four spaces and "Out: " around the names.
+    let room = (area.width as usize).saturating_sub(2 + 15);
+    let (input, output) = fit_names(&app.input_device_name, &app.output_device_name, room);
+    let line = Line::from(format!("  In: {input}    Out: {output}"));
 
     let block = Block::default().borders(Borders::ALL).title(" Devices ");
     let paragraph = Paragraph::new(line).block(block);
     frame.render_widget(paragraph, area);
 }
 
+/// What `--record-dir` has written so far and the file being written, e.g.
+/// "REC ● | Samples: 12,450,000 | Size: 47.5 MB | File: segment-001-….wav".
+/// The dot flashes once a second.
+fn draw_session_info(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(recorder) = &app.recorder else {
+        return;
+    };
+    let samples = recorder.samples_written();
+    let megabytes = recorder.bytes_written() as f64 / (1024.0 * 1024.0);
+    let file = recorder
+        .segments()
+        .last()
+        .and_then(|segment| segment.path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "\u{2014}".to_string());
+    let dot = if (app.session.elapsed_seconds() as u64).is_multiple_of(2) {
+        "\u{25CF}"
+    } else {
+        " "
+    };
+    let info = format!(
+        " | Samples: {} | Size: {megabytes:.1} MB | File: ",
+        format_count(samples)
+    );
+    // Two spaces, "REC ", the dot and the info before the file name.
+    let room = (area.width as usize).saturating_sub(2 + 2 + 5 + info.chars().count());
+    let line = Line::from(vec![
+        Span::raw("  "),
+        Span::styled(
+            format!("REC {dot}"),
+            Style::default()
+                .fg(app.palette.warning)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!("{info}{}", ellipsize_middle(&file, room))),
+    ]);
+
+    let block = Block::default().borders(Borders::ALL).title(" Session ");
+    frame.render_widget(Paragraph::new(line).block(block), area);
+}
+
+/// The two device names in `width` characters, shortened in the middle when
+/// they don't fit. A name shorter than its half leaves the rest to the other.
+fn fit_names(input: &str, output: &str, width: usize) -> (String, String) {
+    let (input_len, output_len) = (input.chars().count(), output.chars().count());
+    if input_len + output_len <= width {
+        return (input.to_string(), output.to_string());
+    }
+    let half = width / 2;
+    let input_width = if output_len < width - half {
+        width - output_len
+    } else {
+        half.min(input_len)
+    };
+    (
+        ellipsize_middle(input, input_width),
+        ellipsize_middle(output, width - input_width),
+    )
+}
+
+/// Full device names, for when the Devices panel shortens them.
+fn draw_device_details(frame: &mut Frame, area: Rect, app: &App) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rows = [
+        ("In", Some(&app.input_device_name)),
+        ("Out", Some(&app.output_device_name)),
+        ("Why", app.output_reason.as_ref()),
+    ];
+    let lines: Vec<Line> = rows
+        .into_iter()
+        .filter_map(|(label, name)| name.map(|name| (label, name)))
+        .map(|(label, name)| {
+            Line::from(vec![
+                Span::styled(format!(" {label:<4}"), bold),
+                Span::raw(format!("{name} ")),
+            ])
+        })
+        .collect();
+
+    // Wide enough for the longest name, wrapping only past the screen width.
+    let content = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16;
+    let width = (content + 2).max(24).min(area.width);
+    let inner_width = width.saturating_sub(2).max(1);
+    let height = lines
+        .iter()
+        .map(|l| (l.width() as u16).div_ceil(inner_width).max(1))
+        .sum::<u16>()
+        + 2;
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+    let popup = Rect::new(x, y, width, height.min(area.height));
+
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Device Details ")
+        .border_style(Style::default().fg(app.palette.overlay));
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, popup);
+}
+
+/// The `--record-dir` files, newest last; older ones scroll off the top when
+/// they don't fit. The file still being written is marked.
+fn draw_recordings(frame: &mut Frame, area: Rect, app: &App) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let lines: Vec<Line> = match &app.recorder {
+        None => vec![Line::from(" Not recording (start with --record-dir) ")],
+        Some(recorder) => {
+            let segments = recorder.segments();
+            if segments.is_empty() {
+                vec![Line::from(" Nothing recorded yet ")]
+            } else {
+                let rate = recorder.sample_rate().max(1) as f64;
+                let rows = area.height.saturating_sub(2) as usize;
+                segments[segments.len().saturating_sub(rows)..]
+                    .iter()
+                    .map(|segment| {
+                        let marker = if segment.finished { "  " } else { "\u{25CF} " };
+                        Line::from(vec![
+                            Span::styled(format!(" {marker}{:>3}  ", segment.index), bold),
+                            Span::raw(format!(
+                                "{}  {} ",
+                                format_clock(segment.frames as f64 / rate),
+                                segment.path.display()
+                            )),
+                        ])
+                    })
+                    .collect()
+            }
+        }
+    };
+
+    let content = lines.iter().map(|l| l.width()).max().unwrap_or(0) as u16;
+    let width = (content + 2).max(24).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Recordings ")
+        .border_style(Style::default().fg(app.palette.overlay));
+    frame.render_widget(Paragraph::new(lines).block(block), popup);
+}
+
 fn draw_keys(frame: &mut Frame, area: Rect, app: &App) {
-    let _ = app;
     let bold = Style::default().add_modifier(Modifier::BOLD);
+    // Grey out a seek direction that is already at its range edge.
+    let (at_live, at_buffer_start) = app.controller.seek_edges();
+    let seek_style = |limited: bool| {
+        if limited {
+            bold.fg(app.palette.disabled)
+        } else {
+            bold
+        }
+    };
     let line = Line::from(vec![
         Span::raw("  "),
         Span::styled("Space", bold),
         Span::raw(":pause  "),
-        Span::styled("\u{2190}/\u{2192}", bold),
+        Span::styled("\u{2190}", seek_style(at_live)),
+        Span::styled("/", bold),
+        Span::styled("\u{2192}", seek_style(at_buffer_start)),
         Span::raw(":seek  "),
         Span::styled("1-9", bold),
-        Span::raw(":scale  "),
+        Span::raw(format!(":{}  ", app.number_keys.label())),
         Span::styled("\u{2191}/\u{2193}", bold),
         Span::raw(":vol  "),
         Span::styled("M", bold),
@@ -190,47 +811,373 @@ fn draw_keys(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_widget(paragraph, area);
 }
 
-fn draw_help_overlay(frame: &mut Frame, area: Rect) {
+/// Draws a one-line notice in the bottom-right corner.
+fn draw_toast(frame: &mut Frame, area: Rect, text: &str, palette: &Palette) {
+    let width = (text.chars().count() as u16 + 4).min(area.width); // +4 for border and padding
+    let height = 3.min(area.height);
+    let popup = Rect::new(
+        area.x + area.width - width,
+        area.y + area.height - height,
+        width,
+        height,
+    );
+
+    frame.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(palette.toast));
+    let paragraph = Paragraph::new(format!(" {text}")).block(block);
+    frame.render_widget(paragraph, popup);
+}
+
+/// Small centered input box for the go-to-time prompt.
+fn draw_time_prompt(frame: &mut Frame, area: Rect, input: &str, palette: &Palette) {
+    let width = 36.min(area.width);
+    let height = 3.min(area.height);
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Go to time (HH:MM[:SS]) ")
+        .title_bottom(" Enter go \u{00b7} Esc cancel ")
+        .border_style(Style::default().fg(palette.overlay));
+    let line = Line::from(vec![
+        Span::raw(format!(" {input}")),
+        Span::styled("_", Style::default().fg(palette.overlay)),
+    ]);
+    frame.render_widget(Paragraph::new(line).block(block), popup);
+}
+
+/// Small centered yes/no box for `--confirm-quit`.
+fn draw_quit_prompt(frame: &mut Frame, area: Rect, palette: &Palette) {
+    let width = 36.min(area.width);
+    let height = 3.min(area.height);
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Quit ")
+        .title_bottom(" Y / Enter quit \u{00b7} N / Esc stay ")
+        .border_style(Style::default().fg(palette.overlay));
+    let paragraph = Paragraph::new(" Quit Shifter and lose the buffer?").block(block);
+    frame.render_widget(paragraph, popup);
+}
+
+/// Draws one help page per keymap section, or the bindings matching `query`
+/// across all sections. The popup sizes itself to the content, up to 80% of
+/// the terminal, and scrolls when it doesn't fit. The search line stays put.
+fn draw_help_overlay(
+    frame: &mut Frame,
+    area: Rect,
+    page: usize,
+    scroll: u16,
+    query: &str,
+    palette: &Palette,
+) {
+    let pages = keymap::help_pages();
+    let page = page.min(pages.len().saturating_sub(1));
+    let Some((section, page_bindings)) = pages.get(page) else {
+        return;
+    };
+    let searching = !query.is_empty();
+    let bindings = if searching {
+        keymap::search(query)
+    } else {
+        page_bindings.clone()
+    };
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let search_line = Line::from(vec![
+        Span::styled("  Search: ", bold),
+        Span::raw(query.to_string()),
+        Span::styled("_", Style::default().fg(palette.overlay)),
+    ]);
+    let mut lines = vec![Line::from("")];
+    for binding in &bindings {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:<12}", binding.label), bold),
+            Span::raw(binding.description),
+        ]));
+    }
+    if bindings.is_empty() {
+        lines.push(Line::from("  No matching keys"));
+    }
+    lines.push(Line::from(""));
+
+    let title = if searching {
+        format!(" Help \u{2014} {} matching ", bindings.len())
+    } else {
+        format!(
+            " Help \u{2014} {} ({}/{}) ",
+            section.title(),
+            page + 1,
+            pages.len()
+        )
+    };
+    let footer = if searching {
+        " Type to filter \u{00b7} \u{2191}/\u{2193}/PgUp/PgDn scroll \u{00b7} Esc close "
+    } else {
+        " Type to filter \u{00b7} Tab pages \u{00b7} \u{2191}/\u{2193}/PgUp/PgDn scroll \u{00b7} Esc close "
+    };
+    let content_width = lines
+        .iter()
+        .chain([&search_line])
+        .map(|l| l.width())
+        .chain([title.chars().count(), footer.chars().count()])
+        .max()
+        .unwrap_or(0) as u16;
+
+    let max_width = (area.width as u32 * 4 / 5) as u16;
+    let total = lines.len() as u16;
+    let visible = help_visible_rows(area.height, total);
+    let width = (content_width + 2).min(max_width); // +2 for border
+    let height = visible + 3; // border and search line
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+    let popup = Rect::new(x, y, width, height);
+
+    let scroll = scroll.min(total.saturating_sub(visible));
+
+    frame.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .title_bottom(footer)
+        .border_style(Style::default().fg(palette.overlay));
+    let inner = block.inner(popup);
+    frame.render_widget(block, popup);
+    let [search_area, list_area] =
+        Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+    frame.render_widget(Paragraph::new(search_line), search_area);
+    frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), list_area);
+
+    // Arrows on the right edge when lines are hidden above or below.
+    let arrow_style = Style::default().fg(palette.overlay);
+    let right = list_area.x + list_area.width.saturating_sub(1);
+    if scroll > 0 && list_area.height > 0 {
+        let top = Rect::new(right, list_area.y, 1, 1);
+        frame.render_widget(Paragraph::new("\u{25b2}").style(arrow_style), top);
+    }
+    if scroll + visible < total && list_area.height > 0 {
+        let bottom = Rect::new(right, list_area.y + list_area.height - 1, 1, 1);
+        frame.render_widget(Paragraph::new("\u{25bc}").style(arrow_style), bottom);
+    }
+}
+
+/// Rows of the help list visible for `lines` lines in a terminal `height`
+/// rows tall. The popup takes at most 4/5 of the height, including its
+/// border and search line.
+pub fn help_visible_rows(height: u16, lines: u16) -> u16 {
+    let max_height = (height as u32 * 4 / 5) as u16;
+    (lines + 3).min(max_height).saturating_sub(3)
+}
+
+fn draw_diagnostics_overlay(frame: &mut Frame, area: Rect, app: &App) {
     let bold = Style::default().add_modifier(Modifier::BOLD);
+    let counts = app.controller.levels.counts();
+    let nominal = format_delay_ms(app.controller.delay_ms());
+    let measured = match app.delay_probe.measured_ms() {
+        Some(ms) => format_delay_ms(ms),
+        None => "--".to_string(),
+    };
     let lines = vec![
         Line::from(""),
         Line::from(vec![
-            Span::styled("  Space       ", bold),
-            Span::raw("Pause / Resume playback"),
+            Span::styled("  Delay       ", bold),
+            Span::raw(format!("nominal {nominal}, measured {measured}")),
         ]),
         Line::from(vec![
-            Span::styled("  \u{2190} / \u{2192}       ", bold),
-            Span::raw("Seek backward / forward by current step"),
+            Span::styled("  Confidence  ", bold),
+            Span::raw(format!("{:.2}", app.delay_probe.confidence())),
         ]),
         Line::from(vec![
-            Span::styled("  1-9         ", bold),
-            Span::raw("Seek step: 1ms 10ms 100ms 500ms 1s 2s 5s 10s 30s"),
+            Span::styled("  Memory      ", bold),
+            Span::raw(match app.memory_lock {
+                Some(lock) => format!("ring buffer {}", lock.describe()),
+                None => "--".to_string(),
+            }),
         ]),
         Line::from(vec![
-            Span::styled("  \u{2191} / \u{2193}       ", bold),
-            Span::raw("Volume up / down (5% steps, max 150%)"),
+            Span::styled("  Meter drops ", bold),
+            Span::raw(format!(
+                "in {}, out {} blocks",
+                app.controller.input_blocks.dropped(),
+                app.controller.output_blocks.dropped()
+            )),
         ]),
         Line::from(vec![
-            Span::styled("  M           ", bold),
-            Span::raw("Toggle mute"),
+            Span::styled("  Levels      ", bold),
+            Span::raw(level_bars(&counts, LEVEL_BAR_GROUP)),
+            Span::raw(
+                match loudness::percentile_db(&counts, loudness::AUTOVOL_PERCENTILE) {
+                    Some(db) => format!(" p95 {db:.0} dB"),
+                    None => " no data".to_string(),
+                },
+            ),
         ]),
         Line::from(vec![
-            Span::styled("  L           ", bold),
-            Span::raw("Jump to live"),
+            Span::styled("  Read jumps  ", bold),
+            Span::raw(match app.controller.read_jumps() {
+                (0, _) => "none".to_string(),
+                (count, ms) => format!("{count} (last {})", format_delay_ms(ms)),
+            }),
         ]),
         Line::from(vec![
-            Span::styled("  H           ", bold),
-            Span::raw("Toggle this help"),
+            Span::styled("  Oversized   ", bold),
+            Span::raw(format!(
+                "{} callbacks over {} frames",
+                app.controller.oversized_callbacks(),
+                MAX_CALLBACK_FRAMES
+            )),
+        ]),
+        Line::from(""),
+    ];
+
+    let height = lines.len() as u16 + 2; // +2 for border
+    let width = 60;
+    let x = area.x + area.width.saturating_sub(width) / 2;
+    let y = area.y + area.height.saturating_sub(height) / 2;
+    let popup = Rect::new(x, y, width.min(area.width), height.min(area.height));
+
+    frame.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Diagnostics ")
+        .border_style(Style::default().fg(app.palette.overlay));
+    let paragraph = Paragraph::new(lines).block(block);
+    frame.render_widget(paragraph, popup);
+}
+
+/// Raw positions and clamps from `PlaybackController::debug_snapshot`, in
+/// samples and as time, for bug reports about seeking and latency.
+fn draw_debug(frame: &mut Frame, area: Rect, app: &App) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let debug = app.controller.debug_snapshot();
+    let samples_per_ms = app.controller.sample_rate() as f64 * debug.channels as f64 / 1000.0;
+    let row = |label: &str, samples: usize| {
+        Line::from(vec![
+            Span::styled(format!("  {label:<16}"), bold),
+            Span::raw(format!(
+                "{samples:>12}  {}",
+                format_delay_ms(samples as f64 / samples_per_ms)
+            )),
+        ])
+    };
+    let drift_style = if app.clock_drift.is_drifting() {
+        Style::default().fg(app.palette.warning)
+    } else {
+        Style::default()
+    };
+    let lines = vec![
+        Line::from(""),
+        row("write_position", debug.write_position),
+        row("read_position", debug.read_position),
+        row(
+            "write - read",
+            debug.write_position.saturating_sub(debug.read_position),
+        ),
+        row("target_delay", debug.target_delay_samples),
+        row("display_delay", debug.display_delay_samples),
+        row("callback", debug.callback_frames * debug.channels as usize),
+        row("capacity", debug.capacity),
+        row("max_delay", debug.max_delay_samples),
+        row("clamped_delay", debug.clamped_delay_samples),
+        Line::from(vec![
+            Span::styled(format!("  {:<16}", "drift"), bold),
+            match app.clock_drift.ppm() {
+                Some(ppm) => Span::styled(format!("{ppm:>+9.0}ppm"), drift_style),
+                None => Span::raw(format!("{:>12}", "measuring")),
+            },
         ]),
         Line::from(vec![
-            Span::styled("  Q           ", bold),
-            Span::raw("Quit"),
+            Span::styled(format!("  {:<16}", "OUT-XRUN"), bold),
+            Span::styled(
+                format!("{:>12}", debug.output_underruns),
+                if debug.output_underruns > 0 {
+                    Style::default().fg(app.palette.warning)
+                } else {
+                    Style::default()
+                },
+            ),
         ]),
         Line::from(""),
     ];
 
     let height = lines.len() as u16 + 2; // +2 for border
-    let width = 78;
+    let width = 50;
+    let x = area.x + area.width.saturating_sub(width);
+    let popup = Rect::new(x, area.y, width.min(area.width), height.min(area.height));
+
+    frame.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(
+            " Debug (samples, {}ch interleaved) ",
+            debug.channels
+        ))
+        .border_style(Style::default().fg(app.palette.overlay));
+    frame.render_widget(Paragraph::new(lines).block(block), popup);
+}
+
+/// Histogram bins per character of the diagnostics level chart (3 dB).
+const LEVEL_BAR_GROUP: usize = 3;
+
+/// One-line bar chart of the level histogram, `group` bins per character,
+/// scaled to the fullest column: quiet on the left, 0 dBFS on the right.
+fn level_bars(counts: &[usize], group: usize) -> String {
+    const BARS: [char; 8] = [
+        '\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}', '\u{2587}',
+        '\u{2588}',
+    ];
+    let columns: Vec<usize> = counts.chunks(group).map(|c| c.iter().sum()).collect();
+    let max = columns.iter().copied().max().unwrap_or(0);
+    columns
+        .iter()
+        .map(|&n| match n {
+            0 => ' ',
+            n => BARS[((n * BARS.len()).div_ceil(max) - 1).min(BARS.len() - 1)],
+        })
+        .collect()
+}
+
+/// Breaks the end-to-end delay down into device, buffer and user components.
+fn draw_latency_budget(frame: &mut Frame, area: Rect, app: &App) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let budget = LatencyBudget::new(
+        &app.hardware_latency,
+        app.controller.sample_rate(),
+        app.controller.callback_ms() + app.controller.live_floor_ms(),
+        app.controller.delay_ms(),
+    );
+
+    let mut lines = vec![Line::from("")];
+    for (name, ms) in &budget.components {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {name:<16}"), bold),
+            Span::raw(format!("{:>12}", format_delay_ms(*ms))),
+        ]));
+    }
+    lines.push(Line::from(format!("  {}", "\u{2500}".repeat(28))));
+    lines.push(Line::from(vec![
+        Span::styled(format!("  {:<16}", "Total"), bold),
+        Span::styled(format!("{:>12}", format_delay_ms(budget.total_ms())), bold),
+    ]));
+    lines.push(Line::from(""));
+
+    let height = lines.len() as u16 + 2; // +2 for border
+    let width = 34;
     let x = area.x + area.width.saturating_sub(width) / 2;
     let y = area.y + area.height.saturating_sub(height) / 2;
     let popup = Rect::new(x, y, width.min(area.width), height.min(area.height));
@@ -239,8 +1186,764 @@ fn draw_help_overlay(frame: &mut Frame, area: Rect) {
 
     let block = Block::default()
         .borders(Borders::ALL)
-        .title(" Help ")
-        .border_style(Style::default().fg(Color::Cyan));
+        .title(" Latency Budget ")
+        .border_style(Style::default().fg(app.palette.overlay));
     let paragraph = Paragraph::new(lines).block(block);
     frame.render_widget(paragraph, popup);
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, mpsc};
+    use std::time::Duration;
+
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+    use crate::audio::latency::HardwareLatency;
+    use crate::audio::raw_sink::RawFormat;
+    use crate::audio::recorder::{Recorder, SplitTriggers};
+    use crate::audio::ring_buffer::AudioRingBuffer;
+    use crate::playback::controller::PlaybackController;
+    use crate::playback::events::ShifterEvent;
+    use crate::wav::WavSpec;
+
+    #[test]
+    fn too_small_fallback_renders_in_a_tiny_terminal() {
+        assert!(!fits(5, 24));
+        assert!(!fits(80, 9));
+        assert!(fits(MIN_WIDTH, MIN_HEIGHT));
+        let mut terminal = Terminal::new(TestBackend::new(5, 2)).unwrap();
+        terminal.draw(draw_too_small).unwrap();
+        let buffer = terminal.backend().buffer();
+        let first: String = (0..5).map(|x| buffer[(x, 0)].symbol()).collect();
+        assert_eq!(first, "Termi");
+    }
+
+    #[test]
+    fn delay_line_tracks_the_read_position() {
+        assert_eq!(delay_columns(0.0, 60_000.0, 40), 0);
+        assert_eq!(delay_columns(30_000.0, 60_000.0, 40), 20);
+        assert_eq!(delay_columns(10.0, 60_000.0, 40), 1);
+        assert_eq!(delay_columns(90_000.0, 60_000.0, 40), 40);
+        assert_eq!(delay_columns(1_000.0, 0.0, 40), 0);
+    }
+
+    #[test]
+    fn every_size_renders_and_drops_panels_in_order() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        let titles = [
+            (Panel::Status, " Shifter "),
+            (Panel::Buffer, " Buffer ("),
+            (Panel::Timeline, " Timeline "),
+            (Panel::Levels, " Levels "),
+            (Panel::Devices, " Devices "),
+            (Panel::Keys, " Keys "),
+        ];
+        for width in (5..=120).step_by(5) {
+            for height in (5..=40).step_by(5).chain([MIN_HEIGHT, 13, 16, 19]) {
+                let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+                terminal.draw(|frame| draw(frame, &app)).unwrap();
+                let buffer = terminal.backend().buffer();
+                let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+                if !fits(width, height) {
+                    if width >= 31 {
+                        assert!(text.contains("Terminal too small \u{2014} need 40\u{00d7}10"));
+                    }
+                    assert!(!text.contains(" Shifter "), "{width}x{height}");
+                    continue;
+                }
+                let shown = visible_panels(height, false);
+                for (panel, title) in titles {
+                    assert_eq!(
+                        text.contains(title),
+                        shown.contains(&panel),
+                        "{panel:?} at {width}x{height}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn panels_drop_in_priority_order() {
+        use Panel::*;
+        assert_eq!(
+            visible_panels(MIN_HEIGHT, false),
+            vec![Status, Buffer, Levels]
+        );
+        assert_eq!(
+            visible_panels(15, false),
+            vec![Status, Buffer, Levels, Devices]
+        );
+        assert_eq!(
+            visible_panels(16, false),
+            vec![Status, Buffer, Levels, Devices, Keys]
+        );
+        assert_eq!(
+            visible_panels(20, false),
+            vec![Status, Buffer, Timeline, Levels, Devices, Keys]
+        );
+        assert_eq!(visible_panels(7, false), vec![Status, Levels]);
+        // While recording, the Session panel is the first to go.
+        assert_eq!(
+            visible_panels(20, true),
+            vec![Status, Buffer, Timeline, Levels, Devices, Keys]
+        );
+        assert_eq!(
+            visible_panels(23, true),
+            vec![Status, Buffer, Timeline, Levels, Devices, Session, Keys]
+        );
+    }
+
+    #[test]
+    fn long_device_names_shorten_in_the_middle() {
+        assert_eq!(fit_names("In", "Out", 10), ("In".into(), "Out".into()));
+        let long = "Elgato Wave:3 (Digital Audio Interface) #2";
+        assert_eq!(
+            fit_names(long, "Speakers", 26),
+            ("Elgato Wa\u{2026}face) #2".into(), "Speakers".into())
+        );
+        let (input, output) = fit_names(long, long, 31);
+        assert_eq!((input.chars().count(), output.chars().count()), (15, 16));
+
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let mut app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        app.input_device_name = long.to_string();
+        let mut terminal = Terminal::new(TestBackend::new(40, 16)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("In: Elgat\u{2026}"));
+        assert!(!text.contains(long));
+        app.handle_key(KeyCode::Char('v'), KeyModifiers::NONE);
+        let mut terminal = Terminal::new(TestBackend::new(80, 16)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains(" Device Details "));
+        assert!(text.contains(long));
+        assert!(!text.contains(" Why "));
+
+        let app =
+            app.with_output_reason("system output (default 'BlackHole 2ch' is virtual)".into());
+        let mut terminal = Terminal::new(TestBackend::new(80, 16)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains(" Why system output (default 'BlackHole 2ch' is virtual)"));
+    }
+
+    #[test]
+    fn recordings_overlay_lists_segments_or_says_why_not() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let mut app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        app.handle_key(KeyCode::Char('r'), KeyModifiers::NONE);
+        assert!(app.toast.as_ref().unwrap().0.starts_with("Not recording"));
+        app.handle_key(KeyCode::Char('R'), KeyModifiers::SHIFT);
+        assert!(app.show_recordings);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains(" Recordings "));
+        assert!(text.contains("Not recording (start with --record-dir)"));
+
+        let dir = std::env::temp_dir().join(format!("shifter-test-{}-ui", std::process::id()));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            format: RawFormat::F32,
+        };
+        let (notice_tx, _notices) = mpsc::channel();
+        let recorder = Recorder::spawn(
+            app.controller.clone(),
+            &dir,
+            spec,
+            SplitTriggers::default(),
+            notice_tx,
+        )
+        .unwrap();
+        app.recorder = Some(recorder);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("Nothing recorded yet"));
+        app.handle_key(KeyCode::Char('r'), KeyModifiers::NONE);
+        assert_eq!(
+            app.toast.as_ref().unwrap().0,
+            "Starting the next recorded file"
+        );
+        app.recorder = None;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn session_panel_shows_what_is_recorded_while_recording() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000).with_sink());
+        let (_, notices) = mpsc::channel();
+        let mut app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(!text.contains(" Session "));
+
+        let dir = std::env::temp_dir().join(format!("shifter-test-{}-session", std::process::id()));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            format: RawFormat::F32,
+        };
+        let (notice_tx, _notices) = mpsc::channel();
+        let recorder = Recorder::spawn(
+            app.controller.clone(),
+            &dir,
+            spec,
+            SplitTriggers::default(),
+            notice_tx,
+        )
+        .unwrap();
+        app.recorder = Some(recorder);
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains(" Session "));
+        assert!(text.contains("| Samples: 0 | Size: 0.0 MB | File: \u{2014}"));
+        // Below the device info.
+        assert!(text.find(" Session ").unwrap() > text.find(" Devices ").unwrap());
+
+        let sink = app.controller.sink_blocks.as_ref().unwrap();
+        for _ in 0..4 {
+            sink.push(&[0.5; 512]);
+        }
+        let recorder = app.recorder.as_ref().unwrap();
+        while recorder.samples_written() < 2_048 {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("| Samples: 2,048 | Size: 0.0 MB | File: segment-001-"));
+        app.recorder = None;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn airplay_outputs_show_their_extra_delay() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let mut app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        let mut terminal = Terminal::new(TestBackend::new(120, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(!text.contains("AirPlay"));
+        app.airplay = Some("AirPlay +1.8s".to_string());
+        app.controller.set_live_floor_samples(48_000 * 2);
+        let mut terminal = Terminal::new(TestBackend::new(120, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("   AirPlay +1.8s"));
+        app.show_latency_budget = true;
+        // One second of floor on top of the (not yet known) callback.
+        let mut terminal = Terminal::new(TestBackend::new(120, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("Minimum delay"));
+        assert_eq!(app.controller.live_floor_ms(), 1_000.0);
+    }
+
+    #[test]
+    fn debug_overlay_counts_output_underruns() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        )
+        .with_debug(true);
+        app.controller.ring.write(&[0.0; 64]);
+        app.controller.read_output(&mut [0.0; 128]);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains(" Debug (samples, 2ch interleaved) "));
+        assert!(text.contains("OUT-XRUN                   1"));
+    }
+
+    #[test]
+    fn degenerate_controllers_render_sane_values() {
+        let ring = Arc::new(AudioRingBuffer::new(0));
+        let controller = Arc::new(PlaybackController::new(ring, 0, 0));
+        let (_, notices) = mpsc::channel();
+        let mut app = App::new(
+            controller,
+            "In".to_string(),
+            "Out".to_string(),
+            0,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        app.controller.capture_input(&[0.5; 256]);
+        app.controller.pre_read(128);
+        app.update();
+        for (width, height) in [(80, 24), (120, 40), (40, 16)] {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|frame| draw(frame, &app)).unwrap();
+            let buffer = terminal.backend().buffer();
+            let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+            for bad in ["NaN", "inf"] {
+                assert!(!text.contains(bad), "{bad} at {width}x{height}:\n{text}");
+            }
+        }
+        app.handle_key(KeyCode::Char('d'), KeyModifiers::NONE);
+        app.handle_key(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(!text.contains("NaN") && !text.contains("inf"), "{text}");
+    }
+
+    #[test]
+    fn slow_frames_show_in_the_status_bar() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let mut app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        let start = Instant::now();
+        let frames = |app: &mut App, from: u64, ms: u64| {
+            for i in 0..=60 {
+                app.record_frame(start + Duration::from_millis(from + i * ms));
+            }
+        };
+        assert_eq!(app.avg_frame_ms(), 0.0);
+        frames(&mut app, 0, 33);
+        assert!((app.avg_frame_ms() - 33.0).abs() < 0.01);
+        assert!(!app.is_slow());
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(!text.contains("[slow]"));
+
+        // Only the last 60 frames count.
+        frames(&mut app, 10_000, 70);
+        assert!((app.avg_frame_ms() - 70.0).abs() < 0.01);
+        assert!(app.is_slow());
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("[slow]"));
+    }
+
+    #[test]
+    fn waveform_bars_follow_the_peak_in_db() {
+        assert_eq!(waveform_glyph(0.0), " ");
+        assert_eq!(waveform_glyph(0.001), " ");
+        assert_eq!(waveform_glyph(1.0), "\u{2588}");
+        assert_eq!(waveform_glyph(0.5), "\u{2587}");
+        assert_eq!(waveform_glyph(0.1), "\u{2585}");
+    }
+
+    #[test]
+    fn buffer_gauge_draws_a_waveform_anchored_at_live() {
+        // 1s of stereo at 48kHz: 100 segments over 78 columns.
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        // 96,000 f32 samples.
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains(" Buffer (0.4 MB) "));
+        // Symbols and foreground colors of the buffer gauge's inner row.
+        let buffer_row = |app: &App| -> Vec<(String, Color)> {
+            let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+            terminal.draw(|frame| draw(frame, app)).unwrap();
+            let buffer = terminal.backend().buffer();
+            let title = (0..24)
+                .find(|&y| {
+                    let row: String = (0..80).map(|x| buffer[(x, y)].symbol()).collect();
+                    row.contains(" Buffer (")
+                })
+                .unwrap();
+            (1..79)
+                .map(|x| {
+                    let cell = &buffer[(x, title + 1)];
+                    (cell.symbol().to_string(), cell.fg)
+                })
+                .collect()
+        };
+        let ctrl = &app.controller;
+        let record = |from_s: f64, seconds: f64, level: f32| {
+            let start = (from_s * 96_000.0) as usize;
+            let samples = vec![level; (seconds * 96_000.0) as usize];
+            ctrl.peak_history.record(start, &samples);
+            for block in samples.chunks(960) {
+                ctrl.ring.write(block);
+                ctrl.pre_read(480);
+            }
+        };
+
+        // 0.3s captured: the columns past it are empty.
+        record(0.0, 0.3, 0.0);
+        let row = buffer_row(&app);
+        assert_eq!(row[22].0, "\u{2581}");
+        assert_eq!(row[23].0, " ");
+        assert_eq!(row[70].0, " ");
+
+        // A burst that wraps out of the ring is gone; the newest 0.1s fills
+        // the leftmost columns.
+        record(0.3, 0.1, 1.0);
+        record(0.4, 1.1, 0.0);
+        record(1.5, 0.1, 1.0);
+        assert!(ctrl.seek_to_delay_ms(200.0));
+        ctrl.pre_read(0);
+        let row = buffer_row(&app);
+        let wave = buffer_color(ctrl.buffer_usage(), &app.palette);
+        assert!(
+            row[..8]
+                .iter()
+                .all(|cell| *cell == ("\u{2588}".to_string(), wave))
+        );
+        assert_eq!(row[8].0, "\u{2581}");
+        assert_eq!(row[14], ("\u{2503}".to_string(), app.palette.delay));
+        assert!(row[46..].iter().all(|(symbol, _)| symbol == "\u{2581}"));
+        let text: String = row.iter().map(|(symbol, _)| symbol.as_str()).collect();
+        assert!(text.contains("200ms / 1m00s"), "{text}");
+    }
+
+    #[test]
+    fn idle_mode_dims_the_layout_until_a_key_or_an_alert() {
+        let ring = Arc::new(AudioRingBuffer::new(48_000 * 2));
+        let controller = Arc::new(PlaybackController::new(ring, 2, 48_000));
+        let (_, notices) = mpsc::channel();
+        let mut app = App::new(
+            controller,
+            "BlackHole 2ch".to_string(),
+            "MacBook Pro Speakers".to_string(),
+            60,
+            HardwareLatency::default(),
+            Palette::standard(),
+            notices,
+        );
+        let all_dimmed = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+            terminal.draw(|frame| draw(frame, app)).unwrap();
+            let buffer = terminal.backend().buffer();
+            buffer
+                .content()
+                .iter()
+                .all(|cell| cell.fg == Color::DarkGray)
+        };
+        app.update();
+        assert!(!all_dimmed(&app));
+        app.handle_key(KeyCode::Char('i'), KeyModifiers::NONE);
+        app.update();
+        assert!(app.dimmed && all_dimmed(&app));
+
+        // The key that wakes it does nothing else.
+        app.handle_key(KeyCode::Char(' '), KeyModifiers::NONE);
+        assert_eq!(app.controller.state(), PlaybackState::Live);
+        app.update();
+        assert!(!all_dimmed(&app));
+
+        app.idle.dim();
+        app.update();
+        app.controller
+            .events
+            .publish(ShifterEvent::BufferThreshold { level: 90 });
+        app.update();
+        assert!(!app.dimmed);
+    }
+
+    #[test]
+    fn every_binding_appears_exactly_once_across_pages() {
+        let render_help = |width: u16, height: u16, page: usize, scroll: u16| -> String {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal
+                .draw(|frame| {
+                    let palette = Palette::standard();
+                    draw_help_overlay(frame, frame.area(), page, scroll, "", &palette)
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                        + "\n"
+                })
+                .collect()
+        };
+        let pages: Vec<String> = (0..keymap::help_pages().len())
+            .map(|page| render_help(80, 24, page, 0))
+            .collect();
+        for binding in keymap::BINDINGS {
+            let count: usize = pages
+                .iter()
+                .map(|text| text.matches(binding.description).count())
+                .sum();
+            assert_eq!(count, 1, "{:?} shown {count} times", binding.action);
+        }
+    }
+
+    #[test]
+    fn page_title_shows_section_and_position() {
+        let render_help = |width: u16, height: u16, page: usize, scroll: u16| -> String {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal
+                .draw(|frame| {
+                    let palette = Palette::standard();
+                    draw_help_overlay(frame, frame.area(), page, scroll, "", &palette)
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                        + "\n"
+                })
+                .collect()
+        };
+        let pages = keymap::help_pages();
+        let text = render_help(80, 24, 1, 0);
+        let expected = format!("{} (2/{})", pages[1].0.title(), pages.len());
+        assert!(text.contains(&expected));
+    }
+
+    #[test]
+    fn small_terminal_scrolls_to_last_binding() {
+        let render_help = |width: u16, height: u16, page: usize, scroll: u16| -> String {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal
+                .draw(|frame| {
+                    let palette = Palette::standard();
+                    draw_help_overlay(frame, frame.area(), page, scroll, "", &palette)
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                        + "\n"
+                })
+                .collect()
+        };
+        let pages = keymap::help_pages();
+        let (page, bindings) = pages
+            .iter()
+            .enumerate()
+            .map(|(i, (_, bindings))| (i, bindings))
+            .max_by_key(|(_, bindings)| bindings.len())
+            .unwrap();
+        let last = bindings.last().unwrap().description;
+        assert!(!render_help(80, 7, page, 0).contains(last));
+        assert!(render_help(80, 7, page, u16::MAX).contains(last));
+    }
+
+    #[test]
+    fn scroll_arrows_show_hidden_lines() {
+        let render_help = |width: u16, height: u16, page: usize, scroll: u16| -> String {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal
+                .draw(|frame| {
+                    let palette = Palette::standard();
+                    draw_help_overlay(frame, frame.area(), page, scroll, "", &palette)
+                })
+                .unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                        + "\n"
+                })
+                .collect()
+        };
+        let (page, _) = keymap::help_pages()
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (_, bindings))| bindings.len())
+            .unwrap();
+        let top = render_help(80, 12, page, 0);
+        assert!(!top.contains('\u{25b2}'));
+        assert!(top.contains('\u{25bc}'));
+        let bottom = render_help(80, 12, page, u16::MAX);
+        assert!(bottom.contains('\u{25b2}'));
+        assert!(!bottom.contains('\u{25bc}'));
+        // Nothing hidden, no arrows.
+        let all = render_help(120, 80, page, 0);
+        assert!(!all.contains('\u{25b2}') && !all.contains('\u{25bc}'));
+    }
+
+    #[test]
+    fn visible_rows_fit_four_fifths_of_the_terminal() {
+        assert_eq!(help_visible_rows(50, 10), 10);
+        assert_eq!(help_visible_rows(20, 30), 13);
+        assert_eq!(help_visible_rows(2, 30), 0);
+    }
+
+    #[test]
+    fn search_filters_across_pages() {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal
+            .draw(|frame| {
+                let palette = Palette::standard();
+                draw_help_overlay(frame, frame.area(), 0, 0, "toggle", &palette)
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect();
+        assert!(text.contains("Search: toggle"));
+        for binding in keymap::BINDINGS {
+            let matches = binding.description.to_lowercase().contains("toggle");
+            assert_eq!(
+                text.contains(binding.description),
+                matches,
+                "{:?}",
+                binding.action
+            );
+        }
+    }
+
+    #[test]
+    fn search_without_matches_says_so() {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal
+            .draw(|frame| {
+                let palette = Palette::standard();
+                draw_help_overlay(frame, frame.area(), 0, 0, "zzz", &palette)
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect();
+        assert!(text.contains("No matching keys"));
+        assert!(text.contains("0 matching"));
+    }
+
+    #[test]
+    fn precise_bars_fill_in_eighths_of_a_cell() {
+        assert_eq!(precise_bar(0.0, 4), "    ");
+        assert_eq!(precise_bar(1.0 / 32.0, 4), "\u{258f}   ");
+        assert_eq!(precise_bar(0.5, 4), "\u{2588}\u{2588}  ");
+        assert_eq!(precise_bar(0.6, 4), "\u{2588}\u{2588}\u{258d} ");
+        assert_eq!(precise_bar(1.5, 4), "\u{2588}".repeat(4));
+        assert_eq!(precise_bar(0.5, 0), "");
+    }
+
+    #[test]
+    fn level_bars_scale_to_the_fullest_column() {
+        let mut counts = vec![0; 9];
+        counts[0] = 1;
+        counts[4] = 4;
+        counts[5] = 4;
+        assert_eq!(level_bars(&counts, 3), "\u{2581}\u{2588} ");
+        assert_eq!(level_bars(&[0; 6], 3), "  ");
+    }
+}
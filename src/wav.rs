@@ -0,0 +1,309 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+
+use crate::audio::raw_sink::{RawEncoder, RawFormat};
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Size of the header `WavWriter` writes (RIFF, `fmt ` and `data` chunk headers).
+const HEADER_BYTES: u64 = 44;
+
+/// Stream layout of a WAV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavSpec {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub format: RawFormat,
+}
+
+impl WavSpec {
+    fn block_align(self) -> usize {
+        self.channels as usize * self.format.bytes_per_sample()
+    }
+}
+
+/// Reads the samples of a 16/24-bit integer or 32-bit float WAV file as f32,
+/// a block at a time.
+pub struct WavReader {
+    reader: BufReader<File>,
+    spec: WavSpec,
+    /// Bytes of sample data not read yet.
+    remaining: u64,
+    bytes: Vec<u8>,
+}
+
+impl WavReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).map_err(|e| anyhow!("Failed to open {}: {e}", path.display()))?;
+        let mut reader = BufReader::new(file);
+        let (spec, data_len) =
+            read_header(&mut reader).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        Ok(Self {
+            reader,
+            spec,
+            remaining: data_len,
+            bytes: Vec::new(),
+        })
+    }
+
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Frames of sample data in the file.
+    pub fn frames(&self) -> u64 {
+        self.remaining / self.spec.block_align() as u64
+    }
+
+    /// Fills `out` with the next samples (whole frames) and returns how many
+    /// were read; 0 at the end of the data.
+    pub fn read(&mut self, out: &mut [f32]) -> Result<usize> {
+        let align = self.spec.block_align();
+        let frames =
+            (out.len() / self.spec.channels as usize).min((self.remaining / align as u64) as usize);
+        let bytes_per_sample = self.spec.format.bytes_per_sample();
+        self.bytes.resize(frames * align, 0);
+        self.reader
+            .read_exact(&mut self.bytes)
+            .map_err(|e| anyhow!("Failed to read samples: {e}"))?;
+        self.remaining -= self.bytes.len() as u64;
+        for (sample, bytes) in out
+            .iter_mut()
+            .zip(self.bytes.chunks_exact(bytes_per_sample))
+        {
+            *sample = decode(self.spec.format, bytes);
+        }
+        Ok(frames * self.spec.channels as usize)
+    }
+}
+
+/// Writes a WAV file, encoding f32 samples with `RawEncoder` (dithered for the
+/// integer formats). The chunk sizes are filled in by `finish`.
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    encoder: RawEncoder,
+    spec: WavSpec,
+    /// Samples written so far.
+    samples: usize,
+    bytes: Vec<u8>,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, spec: WavSpec) -> Result<Self> {
+        let file =
+            File::create(path).map_err(|e| anyhow!("Failed to create {}: {e}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(&header(spec, 0))
+            .map_err(|e| anyhow!("Failed to write {}: {e}", path.display()))?;
+        Ok(Self {
+            writer,
+            encoder: RawEncoder::new(spec.format),
+            spec,
+            samples: 0,
+            bytes: Vec::new(),
+        })
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<()> {
+        self.bytes.clear();
+        self.encoder.encode(self.samples, samples, &mut self.bytes);
+        self.samples += samples.len();
+        self.writer
+            .write_all(&self.bytes)
+            .map_err(|e| anyhow!("Failed to write samples: {e}"))
+    }
+
+    /// Writes the final chunk sizes and flushes.
+    pub fn finish(mut self) -> Result<()> {
+        let data_len = (self.samples * self.spec.format.bytes_per_sample()) as u32;
+        if data_len % 2 == 1 {
+            // Chunks are padded to an even length.
+            self.writer
+                .write_all(&[0])
+                .map_err(|e| anyhow!("Failed to write samples: {e}"))?;
+        }
+        self.writer
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.writer.write_all(&header(self.spec, data_len)))
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| anyhow!("Failed to finish WAV file: {e}"))
+    }
+}
+
+/// The 44-byte header for `data_len` bytes of samples.
+fn header(spec: WavSpec, data_len: u32) -> Vec<u8> {
+    let format_tag = match spec.format {
+        RawFormat::F32 => WAVE_FORMAT_IEEE_FLOAT,
+        RawFormat::S16 | RawFormat::S24 => WAVE_FORMAT_PCM,
+    };
+    let align = spec.block_align() as u32;
+    let riff_len = HEADER_BYTES as u32 - 8 + data_len + data_len % 2;
+    let mut out = Vec::with_capacity(HEADER_BYTES as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&riff_len.to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&format_tag.to_le_bytes());
+    out.extend_from_slice(&spec.channels.to_le_bytes());
+    out.extend_from_slice(&spec.sample_rate.to_le_bytes());
+    out.extend_from_slice(&(spec.sample_rate * align).to_le_bytes());
+    out.extend_from_slice(&(align as u16).to_le_bytes());
+    out.extend_from_slice(&(spec.format.bytes_per_sample() as u16 * 8).to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    out
+}
+
+/// Reads chunks up to the `data` chunk; returns the format and the data length.
+fn read_header(reader: &mut impl Read) -> Result<(WavSpec, u64)> {
+    let mut riff = [0u8; 12];
+    reader
+        .read_exact(&mut riff)
+        .map_err(|_| anyhow!("not a WAV file"))?;
+    if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(anyhow!("not a WAV file"));
+    }
+    let mut spec = None;
+    loop {
+        let mut chunk = [0u8; 8];
+        reader
+            .read_exact(&mut chunk)
+            .map_err(|_| anyhow!("no data chunk"))?;
+        let len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+        match &chunk[..4] {
+            b"fmt " => {
+                let mut fmt = vec![0u8; len as usize + (len % 2) as usize];
+                reader
+                    .read_exact(&mut fmt)
+                    .map_err(|_| anyhow!("truncated fmt chunk"))?;
+                spec = Some(parse_fmt(&fmt)?);
+            }
+            b"data" => {
+                let spec = spec.ok_or_else(|| anyhow!("data chunk before fmt chunk"))?;
+                return Ok((spec, len));
+            }
+            _ => {
+                // Skip other chunks (LIST, fact, ...), padded to an even length.
+                std::io::copy(&mut reader.take(len + len % 2), &mut std::io::sink())
+                    .map_err(|e| anyhow!("Failed to read: {e}"))?;
+            }
+        }
+    }
+}
+
+fn parse_fmt(fmt: &[u8]) -> Result<WavSpec> {
+    if fmt.len() < 16 {
+        return Err(anyhow!("truncated fmt chunk"));
+    }
+    let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+    let mut format_tag = u16_at(0);
+    if format_tag == WAVE_FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+        // The sub-format GUID starts with the format tag.
+        format_tag = u16_at(24);
+    }
+    let channels = u16_at(2);
+    let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+    let bits = u16_at(14);
+    let format = match (format_tag, bits) {
+        (WAVE_FORMAT_PCM, 16) => RawFormat::S16,
+        (WAVE_FORMAT_PCM, 24) => RawFormat::S24,
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => RawFormat::F32,
+        _ => {
+            return Err(anyhow!(
+                "unsupported sample format (tag {format_tag}, {bits} bits); \
+                 16/24-bit PCM and 32-bit float are supported"
+            ));
+        }
+    };
+    if channels == 0 || sample_rate == 0 {
+        return Err(anyhow!("no channels or sample rate in fmt chunk"));
+    }
+    Ok(WavSpec {
+        channels,
+        sample_rate,
+        format,
+    })
+}
+
+fn decode(format: RawFormat, bytes: &[u8]) -> f32 {
+    match format {
+        RawFormat::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        RawFormat::S16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32_768.0,
+        RawFormat::S24 => {
+            // Sign-extend through the top byte of an i32.
+            let v = i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8;
+            v as f32 / 8_388_608.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_round_trip_within_a_step() {
+        // Float is exact; the integer formats are within dither of a step.
+        for (format, step) in [
+            (RawFormat::F32, 0.0),
+            (RawFormat::S16, 1.0 / 32_768.0),
+            (RawFormat::S24, 1.0 / 8_388_608.0),
+        ] {
+            let path = std::env::temp_dir().join(format!(
+                "shifter-test-{}-{}.wav",
+                std::process::id(),
+                format.name()
+            ));
+            let spec = WavSpec {
+                channels: 2,
+                sample_rate: 44_100,
+                format,
+            };
+            let mut writer = WavWriter::create(&path, spec).unwrap();
+            writer.write(&[0.5, -0.5, 0.25]).unwrap();
+            writer.write(&[-1.0]).unwrap();
+            writer.finish().unwrap();
+
+            let mut reader = WavReader::open(&path).unwrap();
+            assert_eq!(reader.spec(), spec);
+            assert_eq!(reader.frames(), 2);
+            let mut samples = vec![0.0; 8];
+            let n = reader.read(&mut samples).unwrap();
+            assert_eq!(reader.read(&mut samples[n..]).unwrap(), 0);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(n, 4, "{format:?}");
+            for (got, want) in samples.iter().zip([0.5, -0.5, 0.25, -1.0]) {
+                assert!(
+                    (got - want).abs() <= 2.0 * step,
+                    "{format:?}: {got} vs {want}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_chunks_are_skipped_and_odd_formats_refused() {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8_000,
+            format: RawFormat::S16,
+        };
+        let mut file = header(spec, 2);
+        // Splice a LIST chunk (odd length, padded) in front of `data`.
+        file.splice(36..36, b"LIST\x03\x00\x00\x00abc\x00".iter().copied());
+        file.extend_from_slice(&16_384i16.to_le_bytes());
+        let (read_spec, len) = read_header(&mut file.as_slice()).unwrap();
+        assert_eq!((read_spec, len), (spec, 2));
+
+        let mut fmt = header(spec, 0)[20..36].to_vec();
+        fmt[14] = 8; // 8-bit
+        assert!(parse_fmt(&fmt).is_err());
+    }
+}